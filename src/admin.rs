@@ -0,0 +1,376 @@
+//! Page to administer the server while it runs, served with --admin
+//!
+//! It's at `__admin` under the route prefix, behind authentication, and only the user given with
+//! --admin may see it. It shows the transfers in progress and what happened recently, and can
+//! pause uploads and clear the caches.
+use std::collections::{HashMap, VecDeque};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+
+use actix_web::{
+    HttpMessage, HttpRequest, HttpResponse,
+    body::{BodySize, BoxBody, EitherBody, MessageBody},
+    dev::{Payload, ServiceRequest, ServiceResponse},
+    error::PayloadError,
+    http::{Method, StatusCode, header},
+    middleware::Next,
+    web::{self, Bytes},
+};
+use bytesize::ByteSize;
+use chrono::{DateTime, Local};
+use futures::{Stream, StreamExt};
+use serde::Deserialize;
+
+use crate::{
+    MiniserveConfig,
+    auth::CurrentUser,
+    checksum::ChecksumCache,
+    errors::RuntimeError,
+    file_op::{DirSizeCache, FileOpQueryParameters},
+    i18n, renderer,
+    resize::ResizeCache,
+    torrent::TorrentCache,
+};
+
+/// Number of events kept for the admin page at most
+const MAX_EVENTS: usize = 100;
+
+/// A transfer in progress, as shown on the admin page
+#[derive(Clone)]
+pub struct Transfer {
+    /// Whether a file is received from the client, rather than sent to it
+    pub upload: bool,
+    pub path: String,
+    pub user: Option<String>,
+    pub client: String,
+    /// Number of bytes to transfer, if known
+    pub length: Option<u64>,
+    pub transferred: u64,
+    pub elapsed: Duration,
+}
+
+/// Something that happened while serving, as shown on the admin page
+#[derive(Clone)]
+pub struct Event {
+    pub time: DateTime<Local>,
+    pub user: Option<String>,
+    pub description: String,
+}
+
+/// A transfer in progress, when it started and how many bytes it transferred so far
+type TransferEntry = (Transfer, Instant, Arc<AtomicU64>);
+
+/// Transfers in progress and recent events, shared by all workers
+#[derive(Default)]
+pub struct Activity {
+    transfers: Mutex<HashMap<u64, TransferEntry>>,
+    next_id: AtomicU64,
+    events: Mutex<VecDeque<Event>>,
+}
+
+impl Activity {
+    /// Transfers in progress, the oldest first
+    pub fn transfers(&self) -> Vec<Transfer> {
+        let transfers = self.transfers.lock().expect("Transfers poisoned");
+        let mut transfers: Vec<_> = transfers
+            .values()
+            .map(|(transfer, started, transferred)| Transfer {
+                transferred: transferred.load(Ordering::Relaxed),
+                elapsed: started.elapsed(),
+                ..transfer.clone()
+            })
+            .collect();
+        transfers.sort_by_key(|transfer| std::cmp::Reverse(transfer.elapsed));
+        transfers
+    }
+
+    /// Recent events, the latest first
+    pub fn events(&self) -> Vec<Event> {
+        let events = self.events.lock().expect("Events poisoned");
+        events.iter().rev().cloned().collect()
+    }
+
+    /// Records an event, forgetting the oldest ones
+    fn record(&self, user: Option<String>, description: String) {
+        let mut events = self.events.lock().expect("Events poisoned");
+        if events.len() >= MAX_EVENTS {
+            events.pop_front();
+        }
+        events.push_back(Event {
+            time: Local::now(),
+            user,
+            description,
+        });
+    }
+
+    /// Starts showing a transfer, until the returned guard is dropped
+    fn start(self: Arc<Self>, transfer: Transfer) -> TransferGuard {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let transferred = Arc::new(AtomicU64::new(0));
+        self.transfers
+            .lock()
+            .expect("Transfers poisoned")
+            .insert(id, (transfer, Instant::now(), transferred.clone()));
+        TransferGuard {
+            activity: self,
+            id,
+            transferred,
+            complete: false,
+        }
+    }
+}
+
+/// Transfer shown on the admin page while it lives, and recorded as an event once dropped
+struct TransferGuard {
+    activity: Arc<Activity>,
+    id: u64,
+    transferred: Arc<AtomicU64>,
+    complete: bool,
+}
+
+impl Drop for TransferGuard {
+    fn drop(&mut self) {
+        let removed = self
+            .activity
+            .transfers
+            .lock()
+            .expect("Transfers poisoned")
+            .remove(&self.id);
+        let Some((transfer, ..)) = removed else {
+            return;
+        };
+        let size = ByteSize::b(self.transferred.load(Ordering::Relaxed));
+        let description = match (transfer.upload, self.complete) {
+            (false, true) => format!("Downloaded {} ({size})", transfer.path),
+            (false, false) => format!("Interrupted download of {} after {size}", transfer.path),
+            (true, true) => format!("Uploaded to {} ({size})", transfer.path),
+            (true, false) => format!("Failed upload to {} after {size}", transfer.path),
+        };
+        self.activity.record(transfer.user, description);
+    }
+}
+
+/// Shows downloads of files and uploads on the admin page while they're in progress
+///
+/// Like with --max-downloads, files are told apart from listings by their Content-Disposition
+/// header.
+pub async fn transfers_middleware(
+    mut req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<EitherBody<TrackedBody, impl MessageBody>>, actix_web::Error> {
+    let conf = req
+        .app_data::<web::Data<MiniserveConfig>>()
+        .expect("Could not get miniserve config")
+        .clone();
+    if conf.admin.is_none() {
+        return Ok(next.call(req).await?.map_into_right_body());
+    }
+    let activity = req
+        .app_data::<web::Data<Activity>>()
+        .expect("Could not get activity")
+        .clone()
+        .into_inner();
+    let user = req
+        .extensions()
+        .get::<CurrentUser>()
+        .map(|user| user.name.clone());
+    let client = req
+        .connection_info()
+        .realip_remote_addr()
+        .unwrap_or_default()
+        .to_owned();
+
+    if req.method() == Method::POST && req.match_info().unprocessed() == "/upload" {
+        let path = web::Query::<FileOpQueryParameters>::from_query(req.query_string())
+            .map(|query| query.into_inner().path.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let length = req
+            .headers()
+            .get(header::CONTENT_LENGTH)
+            .and_then(|length| length.to_str().ok()?.parse().ok());
+        let mut guard = activity.start(Transfer {
+            upload: true,
+            path: format!("/{}", path.trim_start_matches('/')),
+            user,
+            client,
+            length,
+            transferred: 0,
+            elapsed: Duration::ZERO,
+        });
+        let transferred = guard.transferred.clone();
+        let payload: Pin<Box<dyn Stream<Item = Result<Bytes, PayloadError>>>> =
+            Box::pin(req.take_payload().map(move |chunk| {
+                if let Ok(bytes) = &chunk {
+                    transferred.fetch_add(bytes.len() as u64, Ordering::Relaxed);
+                }
+                chunk
+            }));
+        req.set_payload(Payload::from(payload));
+        let res = next.call(req).await?;
+        guard.complete = res.status().is_success();
+        return Ok(res.map_into_right_body());
+    }
+
+    let res = next.call(req).await?;
+    let is_download = res.request().method() == Method::GET
+        && matches!(res.status(), StatusCode::OK | StatusCode::PARTIAL_CONTENT)
+        && res.headers().contains_key(header::CONTENT_DISPOSITION);
+    if !is_download {
+        return Ok(res.map_into_right_body());
+    }
+    let length = match res.response().body().size() {
+        BodySize::Sized(length) => Some(length),
+        _ => None,
+    };
+    let guard = activity.start(Transfer {
+        upload: false,
+        path: res.request().path().to_owned(),
+        user,
+        client,
+        length,
+        transferred: 0,
+        elapsed: Duration::ZERO,
+    });
+    Ok(res
+        .map_body(|_, body| TrackedBody {
+            body: body.boxed(),
+            guard,
+        })
+        .map_into_left_body())
+}
+
+/// Body of a download, counting what has been sent of it
+pub struct TrackedBody {
+    body: BoxBody,
+    guard: TransferGuard,
+}
+
+impl MessageBody for TrackedBody {
+    type Error = <BoxBody as MessageBody>::Error;
+
+    fn size(&self) -> BodySize {
+        self.body.size()
+    }
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Self::Error>>> {
+        let poll = Pin::new(&mut self.body).poll_next(cx);
+        match &poll {
+            Poll::Ready(Some(Ok(bytes))) => {
+                self.guard
+                    .transferred
+                    .fetch_add(bytes.len() as u64, Ordering::Relaxed);
+            }
+            Poll::Ready(None) => self.guard.complete = true,
+            _ => {}
+        }
+        poll
+    }
+}
+
+/// Checks that the user asking for the admin page is the one given with --admin
+fn admin_user(req: &HttpRequest, conf: &MiniserveConfig) -> Result<String, RuntimeError> {
+    let user = req
+        .extensions()
+        .get::<CurrentUser>()
+        .map(|user| user.name.clone());
+    match user {
+        Some(user) if conf.admin.as_ref() == Some(&user) => Ok(user),
+        _ => Err(RuntimeError::AdminForbiddenError),
+    }
+}
+
+/// Serves the admin page
+pub async fn admin_page(req: HttpRequest) -> Result<HttpResponse, RuntimeError> {
+    let conf = req.app_data::<web::Data<MiniserveConfig>>().unwrap();
+    let activity = req.app_data::<web::Data<Activity>>().unwrap();
+    admin_user(&req, conf)?;
+
+    Ok(HttpResponse::Ok()
+        .content_type(mime::TEXT_HTML_UTF_8)
+        .insert_header((header::CACHE_CONTROL, "no-store"))
+        .body(
+            renderer::admin(
+                conf,
+                &activity.transfers(),
+                &activity.events(),
+                i18n::texts(&req),
+            )
+            .into_string(),
+        ))
+}
+
+/// What the admin page can do
+#[derive(Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum Action {
+    PauseUploads,
+    ResumeUploads,
+    ClearCaches,
+}
+
+#[derive(Deserialize)]
+pub struct ActionForm {
+    action: Action,
+}
+
+/// Does what the admin page asks for, then shows it again
+///
+/// Forms of other sites could otherwise make the browser of the administrator post to it, so
+/// requests from other origins are refused.
+pub async fn admin_action(
+    req: HttpRequest,
+    form: web::Form<ActionForm>,
+) -> Result<HttpResponse, RuntimeError> {
+    let conf = req.app_data::<web::Data<MiniserveConfig>>().unwrap();
+    let activity = req.app_data::<web::Data<Activity>>().unwrap();
+    let user = admin_user(&req, conf)?;
+
+    let same_origin = match req.headers().get(header::ORIGIN) {
+        Some(origin) => {
+            let info = req.connection_info();
+            origin.as_bytes() == format!("{}://{}", info.scheme(), info.host()).as_bytes()
+        }
+        None => true,
+    };
+    if !same_origin {
+        return Err(RuntimeError::AdminForbiddenError);
+    }
+
+    let description = match form.action {
+        Action::PauseUploads => {
+            conf.uploads_paused.set(true);
+            "Paused uploads"
+        }
+        Action::ResumeUploads => {
+            conf.uploads_paused.set(false);
+            "Resumed uploads"
+        }
+        Action::ClearCaches => {
+            if let Some(cache) = req.app_data::<web::Data<DirSizeCache>>() {
+                cache.clear();
+            }
+            if let Some(cache) = req.app_data::<web::Data<ChecksumCache>>() {
+                cache.clear();
+            }
+            if let Some(cache) = req.app_data::<web::Data<TorrentCache>>() {
+                cache.clear();
+            }
+            if let Some(cache) = req.app_data::<web::Data<ResizeCache>>() {
+                cache.clear();
+            }
+            "Cleared the caches"
+        }
+    };
+    log::info!("{user} {}", description.to_lowercase());
+    activity.record(Some(user), description.to_owned());
+
+    Ok(HttpResponse::SeeOther()
+        .insert_header((header::LOCATION, req.path()))
+        .finish())
+}
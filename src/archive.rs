@@ -9,6 +9,7 @@ use tar::Builder;
 use zip::{ZipWriter, write};
 
 use crate::errors::RuntimeError;
+use crate::file_utils::IgnoredPaths;
 
 /// Available archive methods
 #[derive(Deserialize, Clone, Copy, EnumIter, EnumString, Display)]
@@ -57,10 +58,12 @@ impl ArchiveMethod {
     /// Recursively includes all files and subdirectories.
     ///
     /// If `skip_symlinks` is `true`, symlinks fill not be followed and will just be ignored.
+    /// Entries matched by `ignored` are left out.
     pub fn create_archive<T, W>(
         self,
         dir: T,
         skip_symlinks: bool,
+        ignored: &IgnoredPaths,
         out: W,
     ) -> Result<(), RuntimeError>
     where
@@ -69,21 +72,26 @@ impl ArchiveMethod {
     {
         let dir = dir.as_ref();
         match self {
-            Self::TarGz => tar_gz(dir, skip_symlinks, out),
-            Self::Tar => tar_dir(dir, skip_symlinks, out),
-            Self::Zip => zip_dir(dir, skip_symlinks, out),
+            Self::TarGz => tar_gz(dir, skip_symlinks, ignored, out),
+            Self::Tar => tar_dir(dir, skip_symlinks, ignored, out),
+            Self::Zip => zip_dir(dir, skip_symlinks, ignored, out),
         }
     }
 }
 
 /// Write a gzipped tarball of `dir` in `out`.
-fn tar_gz<W>(dir: &Path, skip_symlinks: bool, out: W) -> Result<(), RuntimeError>
+fn tar_gz<W>(
+    dir: &Path,
+    skip_symlinks: bool,
+    ignored: &IgnoredPaths,
+    out: W,
+) -> Result<(), RuntimeError>
 where
     W: std::io::Write,
 {
     let mut out = Encoder::new(out).map_err(|e| RuntimeError::IoError("GZIP".to_string(), e))?;
 
-    tar_dir(dir, skip_symlinks, &mut out)?;
+    tar_dir(dir, skip_symlinks, ignored, &mut out)?;
 
     out.finish()
         .into_result()
@@ -115,7 +123,12 @@ where
 /// ├── f
 /// └── g
 /// ```
-fn tar_dir<W>(dir: &Path, skip_symlinks: bool, out: W) -> Result<(), RuntimeError>
+fn tar_dir<W>(
+    dir: &Path,
+    skip_symlinks: bool,
+    ignored: &IgnoredPaths,
+    out: W,
+) -> Result<(), RuntimeError>
 where
     W: std::io::Write,
 {
@@ -129,7 +142,7 @@ where
         )
     })?;
 
-    tar(dir, directory.to_string(), skip_symlinks, out)
+    tar(dir, directory.to_string(), skip_symlinks, ignored, out)
         .map_err(|e| RuntimeError::ArchiveCreationError("tarball".to_string(), Box::new(e)))
 }
 
@@ -140,6 +153,7 @@ fn tar<W>(
     src_dir: &Path,
     inner_folder: String,
     skip_symlinks: bool,
+    ignored: &IgnoredPaths,
     out: W,
 ) -> Result<(), RuntimeError>
 where
@@ -150,17 +164,22 @@ where
     tar_builder.follow_symlinks(!skip_symlinks);
 
     // Recursively adds the content of src_dir into the archive stream
-    tar_builder
-        .append_dir_all(inner_folder, src_dir)
-        .map_err(|e| {
-            RuntimeError::IoError(
-                format!(
-                    "Failed to append the content of {} to the TAR archive",
-                    src_dir.to_str().unwrap_or("file")
-                ),
-                e,
-            )
-        })?;
+    append_dir_contents(
+        &mut tar_builder,
+        src_dir,
+        Path::new(&inner_folder),
+        skip_symlinks,
+        ignored,
+    )
+    .map_err(|e| {
+        RuntimeError::IoError(
+            format!(
+                "Failed to append the content of {} to the TAR archive",
+                src_dir.to_str().unwrap_or("file")
+            ),
+            e,
+        )
+    })?;
 
     // Finish the archive
     tar_builder.into_inner().map_err(|e| {
@@ -170,6 +189,43 @@ where
     Ok(())
 }
 
+/// Adds a directory and what it contains to a tarball, except for the ignored entries
+///
+/// Like [`Builder::append_dir_all`], symlinks to directories are only followed when the builder
+/// follows symlinks.
+fn append_dir_contents<W>(
+    tar_builder: &mut Builder<W>,
+    src_dir: &Path,
+    name: &Path,
+    skip_symlinks: bool,
+    ignored: &IgnoredPaths,
+) -> std::io::Result<()>
+where
+    W: std::io::Write,
+{
+    tar_builder.append_dir(name, src_dir)?;
+    for entry in std::fs::read_dir(src_dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let is_dir = if skip_symlinks {
+            entry.file_type()?.is_dir()
+        } else {
+            path.is_dir()
+        };
+        if ignored.is_ignored(&path, is_dir) {
+            continue;
+        }
+
+        let entry_name = name.join(entry.file_name());
+        if is_dir {
+            append_dir_contents(tar_builder, &path, &entry_name, skip_symlinks, ignored)?;
+        } else {
+            tar_builder.append_path_with_name(&path, &entry_name)?;
+        }
+    }
+    Ok(())
+}
+
 /// Write a zip of `dir` in `out`.
 ///
 /// The target directory will be saved as a top-level directory in the archive.
@@ -197,6 +253,7 @@ fn create_zip_from_directory<W>(
     out: W,
     directory: &Path,
     skip_symlinks: bool,
+    ignored: &IgnoredPaths,
 ) -> Result<(), RuntimeError>
 where
     W: std::io::Write + std::io::Seek,
@@ -240,6 +297,9 @@ where
             if entry_metadata.file_type().is_symlink() && skip_symlinks {
                 continue;
             }
+            if ignored.is_ignored(&entry_path, entry_metadata.is_dir()) {
+                continue;
+            }
             let current_entry_name = entry_path.file_name().ok_or_else(|| {
                 RuntimeError::InvalidPathError("Invalid file or directory name".to_string())
             })?;
@@ -286,13 +346,18 @@ where
 /// Writes a zip of `dir` in `out`.
 ///
 /// The content of `src_dir` will be saved in the archive as the  folder named .
-fn zip_data<W>(src_dir: &Path, skip_symlinks: bool, mut out: W) -> Result<(), RuntimeError>
+fn zip_data<W>(
+    src_dir: &Path,
+    skip_symlinks: bool,
+    ignored: &IgnoredPaths,
+    mut out: W,
+) -> Result<(), RuntimeError>
 where
     W: std::io::Write,
 {
     let mut data = Vec::new();
     let memory_file = Cursor::new(&mut data);
-    create_zip_from_directory(memory_file, src_dir, skip_symlinks).map_err(|e| {
+    create_zip_from_directory(memory_file, src_dir, skip_symlinks, ignored).map_err(|e| {
         RuntimeError::ArchiveCreationError(
             "Failed to create the ZIP archive".to_string(),
             Box::new(e),
@@ -305,7 +370,12 @@ where
     Ok(())
 }
 
-fn zip_dir<W>(dir: &Path, skip_symlinks: bool, out: W) -> Result<(), RuntimeError>
+fn zip_dir<W>(
+    dir: &Path,
+    skip_symlinks: bool,
+    ignored: &IgnoredPaths,
+    out: W,
+) -> Result<(), RuntimeError>
 where
     W: std::io::Write,
 {
@@ -319,6 +389,6 @@ where
         )
     })?;
 
-    zip_data(dir, skip_symlinks, out)
+    zip_data(dir, skip_symlinks, ignored, out)
         .map_err(|e| RuntimeError::ArchiveCreationError("zip".to_string(), Box::new(e)))
 }
@@ -0,0 +1,207 @@
+//! Browse the contents of a zip archive as if it were a regular directory.
+//!
+//! Nothing gets extracted to disk: listings are computed from the archive's central directory and
+//! members are decompressed on the fly when they are requested.
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io;
+use std::path::Path;
+use std::time::SystemTime;
+
+use actix_web::{HttpRequest, HttpResponse, http::header, web};
+use bytesize::ByteSize;
+use chrono::{NaiveDate, TimeZone, Utc};
+use percent_encoding::utf8_percent_encode;
+use zip::ZipArchive;
+
+use crate::errors::RuntimeError;
+use crate::listing::{self, Entry, EntryType, percent_encode_sets::COMPONENT};
+
+/// Open the zip archive at `path`, reading its central directory
+pub fn open_archive(path: &Path) -> Result<ZipArchive<File>, RuntimeError> {
+    let file = File::open(path).map_err(|e| {
+        RuntimeError::IoError(format!("Failed to open archive {}", path.display()), e)
+    })?;
+    ZipArchive::new(file)
+        .map_err(|e| RuntimeError::ParseError(format!("archive {}", path.display()), e.to_string()))
+}
+
+/// What a request path inside the archive points to
+enum Target {
+    Listing(Vec<Entry>),
+    /// Directory requested without a trailing slash
+    Redirect,
+    Member(ZipArchive<File>, u64),
+}
+
+/// Serve either a listing of a directory inside the archive, or the content of one of its members
+pub async fn archive_handler(req: HttpRequest) -> Result<HttpResponse, RuntimeError> {
+    let conf = req.app_data::<web::Data<crate::MiniserveConfig>>().unwrap();
+    let tail = req.match_info().query("tail").to_string();
+
+    let hidden = tail.split('/').any(|component| component.starts_with('.'));
+    if hidden && !conf.show_hidden {
+        return Err(RuntimeError::RouteNotFoundError(tail));
+    }
+
+    // Reading the central directory is blocking I/O, which would otherwise stall the worker
+    let target = web::block({
+        let path = conf.path.clone();
+        let tail = tail.clone();
+        let base = req.path().to_owned();
+        let show_hidden = conf.show_hidden;
+        move || resolve(&path, &tail, &base, show_hidden)
+    })
+    .await
+    .map_err(|e| {
+        RuntimeError::IoError(
+            "Failed to read the archive".to_string(),
+            io::Error::other(e),
+        )
+    })??;
+
+    let (mut archive, size) = match target {
+        Target::Listing(entries) => {
+            return Ok(listing::render_listing(&req, entries, None).into_parts().1);
+        }
+        Target::Redirect => {
+            let location = format!("{}/", req.path());
+            return Ok(HttpResponse::Found()
+                .append_header((header::LOCATION, location))
+                .finish());
+        }
+        Target::Member(archive, size) => (archive, size),
+    };
+    let mime = Path::new(&tail)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map_or(
+            mime::APPLICATION_OCTET_STREAM,
+            actix_files::file_extension_to_mime,
+        );
+
+    // Members are decompressed in a separate thread and streamed through a pipe, the same way
+    // archives of directories are generated.
+    let (tx, rx) = futures::channel::mpsc::channel::<io::Result<actix_web::web::Bytes>>(10);
+    let mut pipe = crate::pipe::Pipe::new(tx);
+    std::thread::spawn(move || {
+        let result = archive
+            .by_name(&tail)
+            .map_err(io::Error::other)
+            .and_then(|mut member| io::copy(&mut member, &mut pipe));
+        if let Err(err) = result {
+            log::error!("Error while streaming archive member {tail}: {err:?}");
+        }
+    });
+
+    Ok(HttpResponse::Ok()
+        .content_type(mime)
+        .no_chunking(size)
+        .body(actix_web::body::BodyStream::new(rx)))
+}
+
+/// Find what `tail` points to in the archive at `path`, which is served under `base`
+fn resolve(path: &Path, tail: &str, base: &str, show_hidden: bool) -> Result<Target, RuntimeError> {
+    let mut archive = open_archive(path)?;
+
+    if tail.is_empty() || tail.ends_with('/') {
+        if !tail.is_empty() && !archive.file_names().any(|name| name.starts_with(tail)) {
+            return Err(RuntimeError::RouteNotFoundError(tail.to_string()));
+        }
+        let entries = directory_entries(&mut archive, tail, base, show_hidden);
+        return Ok(Target::Listing(entries));
+    }
+
+    if archive.index_for_name(tail).is_none() {
+        // Archives don't always contain explicit directory members
+        let dir_prefix = format!("{tail}/");
+        if archive
+            .file_names()
+            .any(|name| name.starts_with(&dir_prefix))
+        {
+            return Ok(Target::Redirect);
+        }
+        return Err(RuntimeError::RouteNotFoundError(tail.to_string()));
+    }
+
+    let size = archive
+        .by_name(tail)
+        .map(|member| member.size())
+        .map_err(|e| RuntimeError::ParseError(format!("archive member {tail}"), e.to_string()))?;
+    Ok(Target::Member(archive, size))
+}
+
+/// List the direct children of `dir` inside the archive
+///
+/// `dir` is either empty for the root of the archive or ends with a `/`. Directories that only
+/// exist implicitly as part of a member's path are listed as well.
+fn directory_entries(
+    archive: &mut ZipArchive<File>,
+    dir: &str,
+    base: &str,
+    show_hidden: bool,
+) -> Vec<Entry> {
+    let mut children: BTreeMap<String, Entry> = BTreeMap::new();
+
+    for index in 0..archive.len() {
+        let Ok(member) = archive.by_index_raw(index) else {
+            continue;
+        };
+        let Some(rest) = member.name().strip_prefix(dir) else {
+            continue;
+        };
+        let (name, is_dir) = match rest.split_once('/') {
+            Some((name, _)) => (name.to_string(), true),
+            None => (rest.to_string(), false),
+        };
+        if name.is_empty() || (name.starts_with('.') && !show_hidden) {
+            continue;
+        }
+
+        let link = Path::new(base)
+            .join(utf8_percent_encode(&name, COMPONENT).to_string())
+            .to_string_lossy()
+            .to_string();
+        let last_modification_date = member.last_modified().and_then(to_system_time);
+        let entry = if is_dir {
+            Entry::new(
+                name.clone(),
+                EntryType::Directory,
+                link,
+                None,
+                last_modification_date,
+                None,
+            )
+        } else {
+            Entry::new(
+                name.clone(),
+                EntryType::File,
+                link,
+                Some(ByteSize::b(member.size())),
+                last_modification_date,
+                None,
+            )
+        };
+        // Explicit directory members take precedence over the ones deduced from nested paths
+        if !children.contains_key(&name) || member.is_dir() {
+            children.insert(name, entry);
+        }
+    }
+
+    children.into_values().collect()
+}
+
+/// Convert a zip timestamp, which has no notion of time zones, to a `SystemTime`
+fn to_system_time(datetime: zip::DateTime) -> Option<SystemTime> {
+    let naive = NaiveDate::from_ymd_opt(
+        datetime.year().into(),
+        datetime.month().into(),
+        datetime.day().into(),
+    )?
+    .and_hms_opt(
+        datetime.hour().into(),
+        datetime.minute().into(),
+        datetime.second().into(),
+    )?;
+    Some(Utc.from_utc_datetime(&naive).into())
+}
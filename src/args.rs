@@ -1,13 +1,21 @@
 use std::fmt::Display;
 use std::net::IpAddr;
 use std::path::PathBuf;
+use std::time::Duration;
 
 use actix_web::http::header::{HeaderMap, HeaderName, HeaderValue};
+use bytesize::ByteSize;
+use chrono::FixedOffset;
+use chrono::format::StrftimeItems;
 use clap::{Parser, ValueEnum, ValueHint};
 
 use crate::auth;
-use crate::listing::{SortingMethod, SortingOrder};
-use crate::renderer::ThemeSlug;
+use crate::content_type;
+use crate::external_ip::{self, ExternalIpSource};
+use crate::file_utils;
+use crate::i18n::Lang;
+use crate::listing::{SortingMethod, SortingOrder, TimeFormat, Timezone};
+use crate::renderer::{CustomColorScheme, ThemeSlug};
 
 #[derive(ValueEnum, Clone)]
 pub enum MediaType {
@@ -16,6 +24,18 @@ pub enum MediaType {
     Video,
 }
 
+/// What to do with the Windows service running miniserve
+#[cfg(windows)]
+#[derive(ValueEnum, Clone, Copy)]
+pub enum ServiceAction {
+    /// Register the service, starting it now and along with Windows
+    Install,
+    /// Stop and remove the service
+    Uninstall,
+    /// Serve as the service, which is how the service control manager starts miniserve
+    Run,
+}
+
 #[derive(ValueEnum, Clone)]
 pub enum SizeDisplay {
     Human,
@@ -39,9 +59,50 @@ pub struct CliArgs {
     pub verbose: bool,
 
     /// Which path to serve
+    ///
+    /// Use '-' to serve whatever is piped to standard input as a single file.
+    ///
+    /// Use 's3://bucket/prefix' to serve objects from an S3-compatible object storage. The region,
+    /// endpoint and credentials are then read from the AWS_REGION, AWS_ENDPOINT_URL,
+    /// AWS_ACCESS_KEY_ID, AWS_SECRET_ACCESS_KEY and AWS_SESSION_TOKEN environment variables.
     #[arg(value_hint = ValueHint::AnyPath, env = "MINISERVE_PATH")]
     pub path: Option<PathBuf>,
 
+    /// File name to offer for download when serving a single file
+    ///
+    /// This is mostly useful when serving standard input, which has no name of its own.
+    /// For example: tar c somedir | miniserve - --filename somedir.tar
+    #[arg(long = "filename", requires = "path", env = "MINISERVE_FILENAME")]
+    pub filename: Option<String>,
+
+    /// Browse the contents of a zip archive instead of serving it as a single file
+    ///
+    /// Directories inside the archive are listed like regular ones and members are decompressed
+    /// on demand, without ever extracting the archive to disk.
+    #[arg(
+        long = "browse-archive",
+        requires = "path",
+        conflicts_with_all = ["allowed_upload_dir", "enable_webdav"],
+        env = "MINISERVE_BROWSE_ARCHIVE"
+    )]
+    pub browse_archive: bool,
+
+    /// Serve the tree of a commit of this git repository instead of a path
+    ///
+    /// Files are read from the repository's object database, so nothing gets checked out and the
+    /// working tree is ignored. Use --ref to choose the commit.
+    #[arg(
+        long = "git",
+        value_hint = ValueHint::DirPath,
+        conflicts_with_all = ["path", "allowed_upload_dir", "enable_webdav"],
+        env = "MINISERVE_GIT"
+    )]
+    pub git: Option<PathBuf>,
+
+    /// Branch, tag or commit to serve with --git [default: HEAD]
+    #[arg(long = "ref", requires = "git", env = "MINISERVE_REF")]
+    pub git_ref: Option<String>,
+
     /// The path to where file uploads will be written to before being moved to their
     /// correct location. It's wise to make sure that this directory will be written to
     /// disk and not into memory.
@@ -53,7 +114,7 @@ pub struct CliArgs {
         value_hint = ValueHint::FilePath,
         requires = "allowed_upload_dir",
         value_parser(validate_is_dir_and_exists),
-        env = "MINISERVER_TEMP_UPLOAD_DIRECTORY")
+        env = "MINISERVE_TEMP_UPLOAD_DIRECTORY")
     ]
     pub temp_upload_directory: Option<PathBuf>,
 
@@ -76,11 +137,59 @@ pub struct CliArgs {
     ///
     /// This will cause the server to serve the equivalent `.html` file indicated by the path.
     ///
-    /// `/about` will try to find `about.html` and serve it.
+    /// `/about` will try to find `about.html` and serve it. Unless --index is given, directories
+    /// are also served by their `index.html`, so `/about/` serves `about/index.html`.
     #[arg(long, env = "MINISERVE_PRETTY_URLS")]
     pub pretty_urls: bool,
 
+    /// Serve this HTML file instead of the built-in error page when a path can't be found
+    ///
+    /// The response keeps its 404 status code. The file is read again for every response, so it
+    /// can be edited while miniserve is running. It's used rather than the templates of
+    /// --error-pages and --template-dir.
+    #[arg(long = "error-404", value_hint = ValueHint::FilePath, env = "MINISERVE_ERROR_404")]
+    pub error_404: Option<PathBuf>,
+
+    /// Directory of HTML templates to use instead of the built-in error pages
+    ///
+    /// Templates are named after the status code they are used for, like '403.html' or
+    /// '500.html'. Statuses without a template fall back to the 'error.html' template of
+    /// --template-dir, then to the built-in page. Templates can contain the {{status_code}},
+    /// {{reason}}, {{message}} and {{return_address}} placeholders.
+    #[arg(long = "error-pages", value_hint = ValueHint::DirPath, env = "MINISERVE_ERROR_PAGES")]
+    pub error_pages: Option<PathBuf>,
+
+    /// Directory of HTML templates to use instead of the built-in listing and error pages
+    ///
+    /// 'listing.html' replaces the listing page. It can contain the {{lang}}, {{title}}, {{head}},
+    /// {{body_attributes}}, {{navigation}}, {{header}}, {{breadcrumbs}}, {{toolbar}}, {{listing}},
+    /// {{totals}}, {{readme}}, {{footer}}, {{uploads}} and {{scripts}} placeholders, which are
+    /// filled with the parts of the built-in page. 'error.html' replaces the error pages that
+    /// neither --error-404 nor a template of --error-pages replace, with the same placeholders as
+    /// those. Pages without a template keep using the built-in ones. Placeholders are all there is
+    /// to templates, which have no conditions or loops.
+    #[arg(long = "template-dir", value_hint = ValueHint::DirPath, env = "MINISERVE_TEMPLATE_DIR")]
+    pub template_dir: Option<PathBuf>,
+
+    /// Expose a single file at a custom route
+    ///
+    /// Specify each alias as a 'route=path' pair. The route is relative to the route prefix and the
+    /// file doesn't need to be inside the served directory. This parameter can be used multiple
+    /// times to add multiple aliases.
+    ///
+    /// Example:
+    /// --alias /latest.iso=/data/builds/nightly.iso
+    #[arg(
+        long = "alias",
+        value_parser(parse_alias),
+        num_args(1),
+        env = "MINISERVE_ALIAS"
+    )]
+    pub aliases: Vec<(String, PathBuf)>,
+
     /// Port to use
+    ///
+    /// Use 0 to pick any free port.
     #[arg(
         short = 'p',
         long = "port",
@@ -89,7 +198,23 @@ pub struct CliArgs {
     )]
     pub port: u16,
 
+    /// Write the port miniserve listens on to this file descriptor once it is bound
+    ///
+    /// This is mostly useful with '-p 0', which picks a free port, so that scripts and test
+    /// harnesses can find out which one.
+    #[cfg(unix)]
+    #[arg(
+        long = "print-bound-port-fd",
+        value_name = "FD",
+        conflicts_with = "listen_unix",
+        env = "MINISERVE_PRINT_BOUND_PORT_FD"
+    )]
+    pub print_bound_port_fd: Option<u32>,
+
     /// Interface to listen on
+    ///
+    /// This is either an IP address or the name of a network interface, like 'eth0', in which
+    /// case miniserve listens on the addresses that interface has when starting.
     #[arg(
         short = 'i',
         long = "interfaces",
@@ -97,7 +222,170 @@ pub struct CliArgs {
         num_args(1),
         env = "MINISERVE_INTERFACE"
     )]
-    pub interfaces: Vec<IpAddr>,
+    pub interfaces: Vec<Interface>,
+
+    /// Listen on a Unix domain socket at this path instead of TCP ports
+    ///
+    /// This is meant for running behind a reverse proxy on the same machine. A stale socket left
+    /// at that path is replaced.
+    #[cfg(unix)]
+    #[arg(
+        long = "listen-unix",
+        value_name = "PATH",
+        value_hint = ValueHint::FilePath,
+        conflicts_with_all = [
+            "port",
+            "interfaces",
+            "announce",
+            "public",
+            "external_ip",
+            "open",
+            "copy_url",
+        ],
+        env = "MINISERVE_LISTEN_UNIX"
+    )]
+    pub listen_unix: Option<PathBuf>,
+
+    /// Permissions of the Unix domain socket, in octal (e.g. 660)
+    #[cfg(unix)]
+    #[arg(
+        long = "listen-unix-mode",
+        value_name = "MODE",
+        value_parser(parse_socket_mode),
+        requires = "listen_unix",
+        env = "MINISERVE_LISTEN_UNIX_MODE"
+    )]
+    pub listen_unix_mode: Option<u32>,
+
+    /// Number of worker threads handling requests
+    ///
+    /// Defaults to the number of physical CPU cores.
+    #[arg(
+        long = "workers",
+        value_name = "N",
+        value_parser = clap::value_parser!(u16).range(1..),
+        env = "MINISERVE_WORKERS"
+    )]
+    pub workers: Option<u16>,
+
+    /// Seconds an idle connection is kept open for further requests, 0 disables keep-alive
+    #[arg(
+        long = "keep-alive",
+        value_name = "SECONDS",
+        default_value = "5",
+        env = "MINISERVE_KEEP_ALIVE"
+    )]
+    pub keep_alive: u64,
+
+    /// Seconds a client has to send the headers of a request before it is answered with a 408
+    ///
+    /// 0 disables the timeout. This doesn't limit how long transfers may take.
+    #[arg(
+        long = "client-request-timeout",
+        value_name = "SECONDS",
+        default_value = "5",
+        env = "MINISERVE_CLIENT_REQUEST_TIMEOUT"
+    )]
+    pub client_request_timeout: u64,
+
+    /// Seconds to let running transfers finish when shutting down, e.g. on Ctrl-C
+    #[arg(
+        long = "shutdown-timeout",
+        value_name = "SECONDS",
+        default_value = "0",
+        env = "MINISERVE_SHUTDOWN_TIMEOUT"
+    )]
+    pub shutdown_timeout: u64,
+
+    /// Stop after this long, e.g. '2h' or '1h30m'
+    ///
+    /// New connections are refused from then on, and running transfers get --shutdown-timeout to
+    /// finish. Plain numbers are seconds, and the units s, m, h and d are understood.
+    #[arg(
+        long = "duration",
+        value_name = "DURATION",
+        value_parser(parse_duration),
+        env = "MINISERVE_DURATION"
+    )]
+    pub duration: Option<Duration>,
+
+    /// Stop once files have been downloaded this many times
+    ///
+    /// Only complete downloads count, not interrupted or partial ones, nor listings. With '1',
+    /// this hands a single file over to a single person.
+    #[arg(
+        long = "max-downloads",
+        value_name = "N",
+        value_parser = clap::value_parser!(u64).range(1..),
+        env = "MINISERVE_MAX_DOWNLOADS"
+    )]
+    pub max_downloads: Option<u64>,
+
+    /// Only serve an upload page, and stop once a file has been received
+    ///
+    /// The file is written to DEST, or into it under the name it was uploaded with if DEST is a
+    /// directory. Its path is printed once the upload is complete.
+    #[arg(
+        long = "receive",
+        value_name = "DEST",
+        conflicts_with = "precompressed",
+        env = "MINISERVE_RECEIVE"
+    )]
+    pub receive: Option<PathBuf>,
+
+    /// Run in the background once the server is bound
+    ///
+    /// Errors happening before that are still reported on the terminal. Output and logs go to
+    /// --log-file, or are discarded if it isn't set.
+    #[cfg(unix)]
+    #[arg(long = "daemon", env = "MINISERVE_DAEMON")]
+    pub daemon: bool,
+
+    /// Write the process ID to this file, which is removed on exit
+    #[cfg(unix)]
+    #[arg(
+        long = "pid-file",
+        value_name = "PATH",
+        value_hint = ValueHint::FilePath,
+        env = "MINISERVE_PID_FILE"
+    )]
+    pub pid_file: Option<PathBuf>,
+
+    /// Append output and logs to this file when running with --daemon
+    #[cfg(unix)]
+    #[arg(
+        long = "log-file",
+        value_name = "PATH",
+        value_hint = ValueHint::FilePath,
+        requires = "daemon",
+        env = "MINISERVE_LOG_FILE"
+    )]
+    pub log_file: Option<PathBuf>,
+
+    /// Switch to this user once bound, given by name or ID
+    ///
+    /// This allows binding privileged ports like 80 as root without serving files as root. The
+    /// group defaults to the primary group of the user. The user needs write access to the
+    /// directories of --pid-file and --listen-unix for those to be removed on exit.
+    #[cfg(unix)]
+    #[arg(long = "user", value_name = "USER", env = "MINISERVE_USER")]
+    pub user: Option<String>,
+
+    /// Switch to this group once bound, given by name or ID
+    #[cfg(unix)]
+    #[arg(long = "group", value_name = "GROUP", env = "MINISERVE_GROUP")]
+    pub group: Option<String>,
+
+    /// Only allow access to the files being served, using Landlock
+    ///
+    /// Even if a bug let requests escape the served path, no other files could be accessed, which
+    /// includes symlinks pointing outside of it. Writing is only allowed within the served path
+    /// and the temporary upload directory, when uploads or directory creation are enabled. As the
+    /// latter becomes accessible as a whole, consider setting --temp-directory along with uploads.
+    /// Requires Linux 5.13 or later.
+    #[cfg(target_os = "linux")]
+    #[arg(long = "sandbox", env = "MINISERVE_SANDBOX")]
+    pub sandbox: bool,
 
     /// Set authentication
     ///
@@ -121,9 +409,18 @@ pub struct CliArgs {
     /// joe:123
     /// bob:sha256:a665a45920422f9d417e4867efdc4fb8a04a1f3fff1fa07e998e86f7f7a27ae3
     /// bill:
+    ///
+    /// The file is read again when miniserve receives SIGHUP.
     #[arg(long, value_hint = ValueHint::FilePath, env = "MINISERVE_AUTH_FILE", verbatim_doc_comment)]
     pub auth_file: Option<PathBuf>,
 
+    /// Serve a page to administer the server at '__admin' under the route prefix, for this user
+    ///
+    /// The page shows the transfers in progress and recent events, and can pause uploads and
+    /// clear the caches. It requires --auth or --auth-file, and only this user may see it.
+    #[arg(long, value_name = "USER", env = "MINISERVE_ADMIN")]
+    pub admin: Option<String>,
+
     /// Use a specific route prefix
     #[arg(long = "route-prefix", env = "MINISERVE_ROUTE_PREFIX")]
     pub route_prefix: Option<String>,
@@ -141,9 +438,29 @@ pub struct CliArgs {
     pub no_symlinks: bool,
 
     /// Show hidden files
+    ///
+    /// Listings then offer to hide them again, which is remembered while browsing.
+    /// Without this flag, hidden files are never listed.
     #[arg(short = 'H', long = "hidden", env = "MINISERVE_HIDDEN")]
     pub hidden: bool,
 
+    /// Hide entries matching this gitignore-style pattern
+    ///
+    /// Matching entries are left out of listings and archives, and requests for them are
+    /// answered with 404, like for entries matched by a .miniserveignore file in the served
+    /// directory. This parameter can be used multiple times.
+    ///
+    /// Example:
+    /// --ignore '*.tmp' --ignore 'node_modules'
+    #[arg(
+        long = "ignore",
+        value_name = "PATTERN",
+        value_parser(file_utils::parse_ignore_pattern),
+        num_args(1),
+        env = "MINISERVE_IGNORE"
+    )]
+    pub ignore_patterns: Vec<String>,
+
     /// Default sorting method for file list
     #[arg(
         short = 'S',
@@ -164,6 +481,54 @@ pub struct CliArgs {
     )]
     pub default_sorting_order: SortingOrder,
 
+    /// How listings show when entries were last modified
+    ///
+    /// Listings offer to switch to the other formats, which is remembered while browsing.
+    #[arg(
+        long = "time-format",
+        default_value = "both",
+        ignore_case = true,
+        env = "MINISERVE_TIME_FORMAT"
+    )]
+    pub time_format: TimeFormat,
+
+    /// Show the times in listings in this time zone
+    ///
+    /// Either 'local' for the time zone of the server, 'UTC' or a fixed offset from UTC like
+    /// '+05:30'.
+    #[arg(
+        long = "timezone",
+        value_name = "TIMEZONE",
+        default_value = "local",
+        value_parser(parse_timezone),
+        env = "MINISERVE_TIMEZONE"
+    )]
+    pub timezone: Timezone,
+
+    /// Format the times in listings like this, with strftime-style specifiers
+    ///
+    /// The specifiers are listed at https://docs.rs/chrono/latest/chrono/format/strftime/.
+    #[arg(
+        long = "date-format",
+        value_name = "FORMAT",
+        default_value = "%Y-%m-%d %H:%M:%S %:z",
+        value_parser(parse_date_format),
+        env = "MINISERVE_DATE_FORMAT"
+    )]
+    pub date_format: String,
+
+    /// Show pages in this language
+    ///
+    /// Without it, pages are in the language the browser prefers, if it's one of these, and
+    /// otherwise in English.
+    #[arg(
+        long = "lang",
+        value_name = "LANG",
+        ignore_case = true,
+        env = "MINISERVE_LANG"
+    )]
+    pub lang: Option<Lang>,
+
     /// Default color scheme
     #[arg(
         short = 'c',
@@ -184,10 +549,88 @@ pub struct CliArgs {
     )]
     pub color_scheme_dark: ThemeSlug,
 
+    /// Override colors of the default color schemes, e.g. "bg=#101010,fg=#eeeeee,accent=#00aaff"
+    ///
+    /// 'bg' is the background of pages, 'fg' their text and 'accent' the color of directory
+    /// links and buttons. Colors are hex values, and those left out keep the ones of the schemes
+    /// set by --color-scheme and --color-scheme-dark. Themes picked in the theme menu are left
+    /// as they are.
+    #[arg(
+        long = "color-scheme-custom",
+        value_parser(parse_color_scheme_custom),
+        env = "MINISERVE_COLOR_SCHEME_CUSTOM"
+    )]
+    pub color_scheme_custom: Option<CustomColorScheme>,
+
+    /// Add the CSS of this file to the stylesheet of pages, after the styles of the themes
+    ///
+    /// The file is read once, when miniserve starts.
+    #[arg(long = "custom-css", value_hint = ValueHint::FilePath, env = "MINISERVE_CUSTOM_CSS")]
+    pub custom_css: Option<PathBuf>,
+
+    /// Add the JavaScript of this file at the end of pages, like an analytics snippet
+    ///
+    /// The file is read once, when miniserve starts. It's inlined in a script element, so it
+    /// mustn't contain '</script>'.
+    #[arg(long = "custom-js", value_hint = ValueHint::FilePath, env = "MINISERVE_CUSTOM_JS")]
+    pub custom_js: Option<PathBuf>,
+
+    /// Serve this image as the favicon instead of the built-in logo
+    ///
+    /// Its type is told by its extension, like '.ico', '.png' or '.svg'. The file is read once,
+    /// when miniserve starts.
+    #[arg(long = "favicon", value_hint = ValueHint::FilePath, env = "MINISERVE_FAVICON")]
+    pub favicon: Option<PathBuf>,
+
     /// Enable QR code display
     #[arg(short = 'q', long = "qrcode", env = "MINISERVE_QRCODE")]
     pub qrcode: bool,
 
+    /// Open the served URL in the default browser once listening
+    ///
+    /// The browser named by the BROWSER environment variable is used if it is set.
+    #[arg(long = "open", env = "MINISERVE_OPEN")]
+    pub open: bool,
+
+    /// Copy the served URL to the clipboard on startup
+    ///
+    /// The public URL is copied if there is one, see --public, and an address on the local
+    /// network otherwise.
+    #[arg(long = "copy-url", env = "MINISERVE_COPY_URL")]
+    pub copy_url: bool,
+
+    /// Announce the server on the local network through mDNS, also known as Bonjour
+    ///
+    /// Devices on the network can then find it by name as an '_http._tcp' service, or
+    /// '_https._tcp' with TLS. It is named after --title if given, and after the host otherwise.
+    #[arg(long = "announce", env = "MINISERVE_ANNOUNCE")]
+    pub announce: bool,
+
+    /// Ask the router to forward the port through UPnP or NAT-PMP
+    ///
+    /// This makes the server reachable beyond the local network, and the resulting public URL is
+    /// printed along with a QR code. The forwarding is renewed while
+    /// miniserve runs and removed when it exits. Anyone on the internet can then reach the
+    /// server, so consider enabling authentication.
+    #[arg(long = "public", env = "MINISERVE_PUBLIC")]
+    pub public: bool,
+
+    /// Find out the public IP address of this machine and print URLs using it
+    ///
+    /// The address is asked to a STUN server, like 'stun:stun.example.com:3478', or to a plain
+    /// HTTP endpoint answering with the address alone, like 'http://ip.example.com/'. Defaults to
+    /// a public STUN server. The resulting URLs only work if the port is forwarded to this
+    /// machine, see --public.
+    #[arg(
+        long = "external-ip",
+        value_name = "SOURCE",
+        value_parser(external_ip::parse_external_ip_source),
+        num_args(0..=1),
+        default_missing_value = external_ip::DEFAULT_SOURCE,
+        env = "MINISERVE_EXTERNAL_IP"
+    )]
+    pub external_ip: Option<ExternalIpSource>,
+
     /// Enable file uploading (and optionally specify for which directory)
     ///
     /// The provided path is not a physical file system path. Instead, it's relative to the serve
@@ -220,7 +663,13 @@ pub struct CliArgs {
     /// Enable recursive directory size calculation
     ///
     /// This is disabled by default because it is a potentially fairly IO intensive operation.
-    #[arg(long = "directory-size", env = "MINISERVE_DIRECTORY_SIZE")]
+    /// Listings show placeholders until the sizes are calculated, and sizes are reused for a
+    /// minute.
+    #[arg(
+        long = "directory-size",
+        visible_alias = "dir-size",
+        env = "MINISERVE_DIRECTORY_SIZE"
+    )]
     pub directory_size: bool,
 
     /// Enable creating directories
@@ -276,6 +725,10 @@ pub struct CliArgs {
 
     /// Compress response
     ///
+    /// Responses are compressed with gzip, brotli or zstd, depending on what the client accepts.
+    /// Content that is already compressed, like images, videos and archive downloads, is sent as
+    /// is.
+    ///
     /// WARNING: Enabling this option may slow down transfers due to CPU overhead, so it is
     /// disabled by default.
     ///
@@ -284,24 +737,121 @@ pub struct CliArgs {
     #[arg(
         short = 'C',
         long = "compress-response",
+        visible_alias = "compress",
         env = "MINISERVE_COMPRESS_RESPONSE"
     )]
     pub compress_response: bool,
 
+    /// Serve precompressed variants of files to clients accepting them
+    ///
+    /// When a client requests 'app.js' and accepts Brotli or gzip encoding, 'app.js.br' or
+    /// 'app.js.gz' is served instead if it exists next to it, with the matching Content-Encoding.
+    #[arg(long = "precompressed", env = "MINISERVE_PRECOMPRESSED")]
+    pub precompressed: bool,
+
     /// List directories first
+    ///
+    /// This is the default of listings, which have a link to switch between directories first
+    /// and directories among files.
     #[arg(short = 'D', long = "dirs-first", env = "MINISERVE_DIRS_FIRST")]
     pub dirs_first: bool,
 
+    /// Maximum number of entries shown in a directory listing
+    ///
+    /// Larger directories are truncated with a notice, a link to show all entries and a form
+    /// listing only those whose name contains a text. This protects browsers from pathologically
+    /// large listings, but not the server, which still reads the whole directory. Raw, minimal
+    /// and JSON listings, which are meant for tools, are never truncated.
+    #[arg(
+        long = "max-listing-entries",
+        env = "MINISERVE_MAX_LISTING_ENTRIES",
+        value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..)
+    )]
+    pub max_listing_entries: Option<usize>,
+
+    /// Refresh open listings when entries of their directory are added, removed or modified
+    ///
+    /// Pages are told of changes through server-sent events, for which the directories of open
+    /// listings are checked every second.
+    #[arg(long = "live-refresh", env = "MINISERVE_LIVE_REFRESH")]
+    pub live_refresh: bool,
+
+    /// Serve an Atom feed of the files recently added or modified below each directory
+    ///
+    /// Feeds are at 'feed.xml' in directories, e.g. '/reports/feed.xml', unless a file of that
+    /// name is there. They list the 50 files modified last.
+    #[arg(long = "feed", env = "MINISERVE_FEED")]
+    pub feed: bool,
+
+    /// Serve a sitemap of the served files and directories, for search engines to index them
+    ///
+    /// The sitemap is at 'sitemap.xml' under the route prefix, unless the served directory has
+    /// a file of that name. Hidden and ignored entries are left out of it.
+    #[arg(long = "sitemap", env = "MINISERVE_SITEMAP")]
+    pub sitemap: bool,
+
+    /// Serve checksums of files, computed when they're first asked for
+    ///
+    /// They're served for queries like '/file.iso?hash=sha256' and as sidecars like
+    /// '/file.iso.sha256', unless a file of that name exists. 'sha256' and 'sha512' are
+    /// supported. Directories also have manifests of the checksums of the files below them, at
+    /// 'SHA256SUMS' and 'SHA512SUMS', which listings link to. Checksums are kept until files
+    /// change.
+    #[arg(long = "checksums", env = "MINISERVE_CHECKSUMS")]
+    pub checksums: bool,
+
+    /// Serve Metalink descriptions of files, for download managers to fetch them in pieces
+    ///
+    /// They're sidecars like '/file.iso.meta4', unless a file of that name exists, with the
+    /// SHA-256 checksums of files and of their pieces. These are computed when first asked for,
+    /// and kept until files change.
+    #[arg(long = "metalink", env = "MINISERVE_METALINK")]
+    pub metalink: bool,
+
+    /// Serve torrents of files and directories, with miniserve as their web seed
+    ///
+    /// They're served for queries like '/file.iso?torrent=true', which listings link to, and
+    /// have no tracker. The served directory itself has no torrent, as clients find the files
+    /// of web seeds by the name of the torrent.
+    #[arg(long = "torrent", env = "MINISERVE_TORRENT")]
+    pub torrent: bool,
+
+    /// Serve a robots.txt at the root, either 'allow', 'deny' or 'file:<path>'
+    ///
+    /// 'allow' lets crawlers index everything, 'deny' asks them to index nothing and
+    /// 'file:<path>' serves the given file, which is read once, when miniserve starts. It's
+    /// served at '/robots.txt' whatever the route prefix, where crawlers look for it, and takes
+    /// the place of a robots.txt in the served directory.
+    #[arg(long = "robots", value_parser(parse_robots), env = "MINISERVE_ROBOTS")]
+    pub robots: Option<Robots>,
+
     /// Shown instead of host in page title and heading
     #[arg(short = 't', long = "title", env = "MINISERVE_TITLE")]
     pub title: Option<String>,
 
+    /// Text shown above listings, like 'ACME build artifacts'
+    ///
+    /// It's HTML-escaped unless --html-text is given.
+    #[arg(long = "header-text", env = "MINISERVE_HEADER_TEXT")]
+    pub header_text: Option<String>,
+
+    /// Text shown in the footer of listings
+    ///
+    /// It's HTML-escaped unless --html-text is given.
+    #[arg(long = "footer-text", env = "MINISERVE_FOOTER_TEXT")]
+    pub footer_text: Option<String>,
+
+    /// Insert --header-text and --footer-text as HTML instead of escaping them
+    #[arg(long = "html-text", env = "MINISERVE_HTML_TEXT")]
+    pub html_text: bool,
+
     /// Inserts custom headers into the responses. Specify each header as a 'Header:Value' pair.
     /// This parameter can be used multiple times to add multiple headers.
     ///
     /// Example:
     /// --header "Header1:Value1" --header "Header2:Value2"
-    /// (If a header is already set or previously inserted, it will not be overwritten.)
+    /// (If a header is already set by miniserve, it will not be overwritten. A header given several
+    /// times is sent with all of its values.)
     #[arg(
         long = "header",
         value_parser(parse_header),
@@ -310,7 +860,106 @@ pub struct CliArgs {
     )]
     pub header: Vec<HeaderMap>,
 
+    /// Serve files with this extension using the given content type
+    ///
+    /// Specify each mapping as a '.ext=type/subtype' pair. This overrides the content type guessed
+    /// from the extension, which can be wrong or missing for less common file types. This parameter
+    /// can be used multiple times to add multiple mappings.
+    ///
+    /// Example:
+    /// --mime-map .wasm=application/wasm --mime-map .mjs=text/javascript
+    #[arg(
+        long = "mime-map",
+        value_name = "MAPPING",
+        value_parser(content_type::parse_mime_mapping),
+        num_args(1),
+        env = "MINISERVE_MIME_MAP"
+    )]
+    pub mime_map: Vec<(String, mime::Mime)>,
+
+    /// Read content type mappings from a file, one '.ext=type/subtype' pair per line
+    ///
+    /// Lines starting with '#' are ignored. Mappings given with --mime-map take precedence. The
+    /// file is read again when miniserve receives SIGHUP.
+    #[arg(long = "mime-map-file", value_hint = ValueHint::FilePath, env = "MINISERVE_MIME_MAP_FILE")]
+    pub mime_map_file: Option<PathBuf>,
+
+    /// Declare this charset for text files
+    ///
+    /// Browsers guess the encoding of text files that don't declare one, which often goes wrong
+    /// for older documents. Prefix the charset with a glob and '=' to only use it for matching
+    /// paths. Globbed charsets are tried in order before the one without a glob. This parameter
+    /// can be used multiple times.
+    ///
+    /// Example:
+    /// --charset utf-8 --charset '*.txt=windows-1252'
+    #[arg(
+        long = "charset",
+        value_parser(content_type::parse_charset_rule),
+        num_args(1),
+        env = "MINISERVE_CHARSET"
+    )]
+    pub charsets: Vec<content_type::CharsetRule>,
+
+    /// Make browsers download files matching this glob instead of displaying them
+    ///
+    /// Globs are matched against paths relative to the served directory, and '*' also matches
+    /// slashes. This takes precedence over --inline. This parameter can be used multiple times.
+    ///
+    /// Example:
+    /// --download '*.pdf' --download 'exports/**'
+    #[arg(
+        long = "download",
+        value_name = "GLOB",
+        value_parser(content_type::parse_glob),
+        num_args(1),
+        env = "MINISERVE_DOWNLOAD"
+    )]
+    pub download_globs: Vec<globset::GlobMatcher>,
+
+    /// Make browsers display files matching this glob instead of downloading them, if they can
+    ///
+    /// Globs are matched like for --download. This parameter can be used multiple times.
+    #[arg(
+        long = "inline",
+        value_name = "GLOB",
+        value_parser(content_type::parse_glob),
+        num_args(1),
+        env = "MINISERVE_INLINE"
+    )]
+    pub inline_globs: Vec<globset::GlobMatcher>,
+
+    /// Send a preset of security headers with every response
+    ///
+    /// This adds X-Content-Type-Options, Referrer-Policy and a Content-Security-Policy that the
+    /// built-in pages work with, as well as Strict-Transport-Security when TLS is enabled. Served
+    /// HTML files are subject to the same policy. Any of these headers can be replaced with
+    /// --header.
+    #[arg(long = "hardened-headers", env = "MINISERVE_HARDENED_HEADERS")]
+    pub hardened_headers: bool,
+
+    /// Allow web apps from this origin to fetch files and listings cross-origin
+    ///
+    /// Use '*' to allow any origin. This parameter can be used multiple times to allow multiple
+    /// origins. Preflight requests are answered without requiring authentication.
+    ///
+    /// When authentication is enabled, browsers are allowed to send credentials to the listed
+    /// origins. This isn't possible with '*', so authenticated requests will then fail in browsers.
+    ///
+    /// Example:
+    /// --cors https://app.example.com
+    #[arg(
+        long = "cors",
+        value_name = "ORIGIN",
+        value_parser(parse_cors_origin),
+        num_args(1),
+        env = "MINISERVE_CORS"
+    )]
+    pub cors_origins: Vec<String>,
+
     /// Visualize symlinks in directory listing
+    ///
+    /// Symlinks are shown with the paths they point to, and broken ones are listed as well.
     #[arg(
         short = 'l',
         long = "show-symlink-info",
@@ -318,10 +967,22 @@ pub struct CliArgs {
     )]
     pub show_symlink_info: bool,
 
+    /// Show the mode, owner and group of entries in listings
+    ///
+    /// This is meant for inspecting directories of a server remotely, as it tells who can read
+    /// and change its files.
+    #[cfg(unix)]
+    #[arg(long = "show-permissions", env = "MINISERVE_SHOW_PERMISSIONS")]
+    pub show_permissions: bool,
+
     /// Hide version footer
+    ///
+    /// Pages then don't link to miniserve or show its version, as kiosks or white-label shares
+    /// may want.
     #[arg(
         short = 'F',
         long = "hide-version-footer",
+        visible_alias = "no-footer",
         env = "MINISERVE_HIDE_VERSION_FOOTER"
     )]
     pub hide_version_footer: bool,
@@ -330,6 +991,14 @@ pub struct CliArgs {
     #[arg(long = "hide-theme-selector", env = "MINISERVE_HIDE_THEME_SELECTOR")]
     pub hide_theme_selector: bool,
 
+    /// Render listings as bare indexes like those of nginx, without styles, scripts or forms
+    ///
+    /// Each entry is a line of a '<pre>' block with its link, last modification in UTC and size
+    /// in bytes, so that pages stay tiny and easy to parse, as package mirrors want. Listings
+    /// asked for as JSON are unchanged.
+    #[arg(long = "minimal-ui", env = "MINISERVE_MINIMAL_UI")]
+    pub minimal_ui: bool,
+
     /// If enabled, display a wget command to recursively download the current directory
     #[arg(
         short = 'W',
@@ -338,14 +1007,42 @@ pub struct CliArgs {
     )]
     pub show_wget_footer: bool,
 
+    /// Start the servers described in a file, one per line, instead of a single one
+    ///
+    /// Each line has the options and path of a server, as they'd be given on the command line,
+    /// with quotes around words containing spaces. Empty lines and lines starting with '#' are
+    /// skipped. Options affecting the whole process, like --daemon, --user or --sandbox, can't be
+    /// given for a single server, and no other option can be given along with this one.
+    #[arg(
+        long = "instances",
+        value_name = "FILE",
+        value_hint = ValueHint::FilePath,
+        exclusive = true,
+        env = "MINISERVE_INSTANCES"
+    )]
+    pub instances: Option<PathBuf>,
+
     /// Generate completion file for a shell
     #[arg(long = "print-completions", value_name = "shell")]
     pub print_completions: Option<clap_complete::Shell>,
 
     /// Generate man page
+    ///
+    /// The roff page is rendered from these options, so it never gets out of date. For example:
+    /// miniserve --print-manpage > /usr/share/man/man1/miniserve.1
     #[arg(long = "print-manpage")]
     pub print_manpage: bool,
 
+    /// Manage a Windows service serving with the other arguments given
+    ///
+    /// The service logs to the Windows event log and reloads --auth-file and --mime-map-file when
+    /// asked to with 'sc control miniserve paramchange'. Services don't start in the current
+    /// directory and don't see the environment of the user installing them, so paths should be
+    /// absolute and options given as arguments.
+    #[cfg(windows)]
+    #[arg(long = "service", value_name = "ACTION")]
+    pub service: Option<ServiceAction>,
+
     /// TLS certificate to use
     #[cfg(feature = "tls")]
     #[arg(long = "tls-cert", requires = "tls_key", value_hint = ValueHint::FilePath, env = "MINISERVE_TLS_CERT")]
@@ -356,10 +1053,98 @@ pub struct CliArgs {
     #[arg(long = "tls-key", requires = "tls_cert", value_hint = ValueHint::FilePath, env = "MINISERVE_TLS_KEY")]
     pub tls_key: Option<PathBuf>,
 
+    /// Also accept HTTP/3 connections over QUIC (experimental)
+    ///
+    /// QUIC is served on the UDP port matching each TCP listener, and advertised to clients with
+    /// an Alt-Svc header. Requires --tls-cert and --tls-key.
+    #[cfg(feature = "http3")]
+    #[arg(long = "http3", requires = "tls_cert", env = "MINISERVE_HTTP3")]
+    pub http3: bool,
+
     /// Enable README.md rendering in directories
+    ///
+    /// The readme of a directory is shown below its listing. 'README.md' is rendered as Markdown,
+    /// and is preferred over 'README.txt' and 'README', which are shown as plain text.
     #[arg(long, env = "MINISERVE_README")]
     pub readme: bool,
 
+    /// Render Markdown files as HTML pages
+    ///
+    /// '.md' files are shown with the current theme, along with a link to their raw content, which
+    /// is also served when adding '?raw=true' to their URL.
+    #[arg(
+        long = "render-markdown",
+        conflicts_with_all = ["browse_archive", "git", "receive"],
+        env = "MINISERVE_RENDER_MARKDOWN"
+    )]
+    pub render_markdown: bool,
+
+    /// Preview text and source files with syntax highlighting and line numbers
+    ///
+    /// Previews are shown when adding '?preview=true' to the URL of a file, which the listing
+    /// links to. CSV and TSV files up to 256 KiB are shown as tables which can be sorted. Files over
+    /// 1 MiB, or that aren't valid UTF-8, are served as they are instead.
+    #[arg(
+        long = "preview",
+        conflicts_with_all = ["browse_archive", "git", "receive"],
+        env = "MINISERVE_PREVIEW"
+    )]
+    pub preview: bool,
+
+    /// Show thumbnails of images in listings
+    ///
+    /// Thumbnails embedded in photos by cameras are used, or small images themselves. Larger
+    /// images without one are listed without a thumbnail.
+    #[arg(
+        long = "thumbnails",
+        conflicts_with_all = ["browse_archive", "git", "receive"],
+        env = "MINISERVE_THUMBNAILS"
+    )]
+    pub thumbnails: bool,
+
+    /// Serve images scaled down to a width when requested
+    ///
+    /// Adding '?w=<width>' to the URL of a JPEG or PNG image serves it scaled down to that width,
+    /// and '?thumb=1' to 320 pixels. Scaled down images are kept in memory for later requests.
+    /// Progressive JPEG images, and images that are narrower already, are served as they are.
+    #[arg(
+        long = "resize-images",
+        conflicts_with_all = ["browse_archive", "git", "receive"],
+        env = "MINISERVE_RESIZE_IMAGES"
+    )]
+    pub resize_images: bool,
+
+    /// Keep scaled down images and thumbnails in this directory
+    ///
+    /// Images scaled down for --resize-images and --thumbnails are written to it, so that they
+    /// aren't scaled down again after restarts. The directory is created if it doesn't exist.
+    #[arg(
+        long = "cache-dir",
+        value_name = "DIR",
+        value_hint = ValueHint::DirPath,
+        env = "MINISERVE_CACHE_DIR"
+    )]
+    pub cache_dir: Option<PathBuf>,
+
+    /// Largest size of the --cache-dir directory, e.g. '500 MiB'
+    ///
+    /// The files used least recently are removed once it's exceeded.
+    #[arg(
+        long = "cache-size",
+        value_name = "SIZE",
+        default_value = "1 GiB",
+        requires = "cache_dir",
+        env = "MINISERVE_CACHE_SIZE"
+    )]
+    pub cache_size: ByteSize,
+
+    /// Remove metadata, like the location photos were taken at, from the images served
+    ///
+    /// EXIF, XMP and IPTC data of JPEG images and text chunks of PNG images are removed as they are
+    /// served, which includes the orientation of photos. Their details pages aren't available.
+    #[arg(long = "strip-exif", env = "MINISERVE_STRIP_EXIF")]
+    pub strip_exif: bool,
+
     /// Disable indexing
     ///
     /// This will prevent directory listings from being generated
@@ -389,13 +1174,124 @@ pub struct CliArgs {
     pub file_external_url: Option<String>,
 }
 
-/// Checks whether an interface is valid, i.e. it can be parsed into an IP address
-fn parse_interface(src: &str) -> Result<IpAddr, std::net::AddrParseError> {
-    src.parse::<IpAddr>()
+/// Where to listen, as given with --interfaces
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Interface {
+    /// A literal IP address
+    Addr(IpAddr),
+
+    /// The name of a network interface, whose addresses are looked up at startup
+    Name(String),
+}
+
+/// What robots.txt to serve, as given with --robots
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Robots {
+    /// Let crawlers index everything
+    Allow,
+
+    /// Ask crawlers to index nothing
+    Deny,
+
+    /// Serve the robots.txt of this file
+    File(PathBuf),
+}
+
+/// Parses 'allow', 'deny' or 'file:<path>'
+pub fn parse_robots(src: &str) -> Result<Robots, String> {
+    match src {
+        "allow" => Ok(Robots::Allow),
+        "deny" => Ok(Robots::Deny),
+        _ => match src.strip_prefix("file:") {
+            Some(path) if !path.is_empty() => Ok(Robots::File(PathBuf::from(path))),
+            _ => Err(format!(
+                "Invalid robots.txt {src:?}, expected 'allow', 'deny' or 'file:<path>'"
+            )),
+        },
+    }
+}
+
+/// Checks whether an interface is valid, i.e. it is an IP address or could be an interface name
+fn parse_interface(src: &str) -> Result<Interface, String> {
+    if let Ok(addr) = src.parse::<IpAddr>() {
+        return Ok(Interface::Addr(addr));
+    }
+    // Interface names are pretty much free-form, e.g. "Ethernet 2" on Windows
+    if src.trim().is_empty() {
+        Err(format!(
+            "Invalid interface {src:?}, expected an IP address or a network interface name"
+        ))
+    } else {
+        Ok(Interface::Name(src.to_owned()))
+    }
+}
+
+/// Parses permissions given in octal, like '660'
+#[cfg(unix)]
+fn parse_socket_mode(src: &str) -> Result<u32, String> {
+    u32::from_str_radix(src, 8)
+        .ok()
+        .filter(|mode| *mode <= 0o777)
+        .ok_or_else(|| format!("Invalid permissions {src:?}, expected an octal mode like 660"))
+}
+
+/// Parses a time zone, either 'local', 'UTC' or an offset from UTC like '+05:30'
+fn parse_timezone(src: &str) -> Result<Timezone, String> {
+    if src.eq_ignore_ascii_case("local") {
+        return Ok(Timezone::Local);
+    }
+    if src.eq_ignore_ascii_case("utc") || src == "Z" {
+        return Ok(Timezone::Fixed(FixedOffset::east_opt(0).unwrap()));
+    }
+    src.parse::<FixedOffset>()
+        .map(Timezone::Fixed)
+        .map_err(|_| {
+            format!("Invalid time zone {src:?}, expected 'local', 'UTC' or an offset like '+05:30'")
+        })
+}
+
+/// Checks that a strftime-style date format only has specifiers that chrono knows
+fn parse_date_format(src: &str) -> Result<String, String> {
+    StrftimeItems::new(src)
+        .parse()
+        .map(|_| src.to_owned())
+        .map_err(|_| format!("Invalid date format {src:?}"))
+}
+
+/// Parses a duration like '90', '15m' or '1h30m', where plain numbers are seconds
+fn parse_duration(src: &str) -> Result<Duration, String> {
+    let invalid = || format!("Invalid duration {src:?}, expected something like '2h' or '1h30m'");
+    if let Ok(seconds) = src.parse::<u64>() {
+        return Ok(Duration::from_secs(seconds));
+    }
+    let mut seconds = 0u64;
+    let mut rest = src;
+    while !rest.is_empty() {
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .ok_or_else(invalid)?;
+        let value = rest[..digits].parse::<u64>().map_err(|_| invalid())?;
+        let unit = match rest[digits..].chars().next() {
+            Some('s') => 1,
+            Some('m') => 60,
+            Some('h') => 60 * 60,
+            Some('d') => 24 * 60 * 60,
+            _ => return Err(invalid()),
+        };
+        seconds = value
+            .checked_mul(unit)
+            .and_then(|value| seconds.checked_add(value))
+            .ok_or_else(invalid)?;
+        rest = &rest[digits + 1..];
+    }
+    if src.is_empty() {
+        return Err(invalid());
+    }
+    Ok(Duration::from_secs(seconds))
 }
 
 /// Validate that a path passed in is a directory and it exists.
-fn validate_is_dir_and_exists(s: &str) -> Result<PathBuf, String> {
+pub fn validate_is_dir_and_exists(s: &str) -> Result<PathBuf, String> {
     let path = PathBuf::from(s);
     if path.exists() && path.is_dir() {
         Ok(path)
@@ -473,6 +1369,69 @@ pub fn parse_auth(src: &str) -> Result<auth::RequiredAuth, AuthParseError> {
     })
 }
 
+/// Parse a 'route=path' file alias
+pub fn parse_alias(src: &str) -> Result<(String, PathBuf), String> {
+    let (route, path) = src
+        .split_once('=')
+        .ok_or_else(|| format!("Invalid alias {src:?}, expected 'route=path'"))?;
+
+    if !route.starts_with('/') {
+        return Err(format!("Alias route {route:?} must start with '/'"));
+    }
+    let route = route.trim_end_matches('/');
+    if route.is_empty() {
+        return Err("Alias route can't be '/', which serves the listing".to_owned());
+    }
+    // Routes are registered as patterns, in which braces stand for dynamic segments
+    if route.contains(['{', '}']) {
+        return Err(format!("Alias route {route:?} can't contain '{{' or '}}'"));
+    }
+    if path.is_empty() {
+        return Err(format!("Alias {route:?} is missing a file path"));
+    }
+
+    Ok((route.to_owned(), PathBuf::from(path)))
+}
+
+/// Parse a comma-separated list of 'bg=#hex', 'fg=#hex' and 'accent=#hex' colors
+pub fn parse_color_scheme_custom(src: &str) -> Result<CustomColorScheme, String> {
+    let mut scheme = CustomColorScheme::default();
+    for color in src
+        .split(',')
+        .map(str::trim)
+        .filter(|color| !color.is_empty())
+    {
+        let (name, value) = color
+            .split_once('=')
+            .ok_or_else(|| format!("Invalid color {color:?}, expected 'name=#hex'"))?;
+        let (name, value) = (name.trim(), value.trim());
+        let valid = value.strip_prefix('#').is_some_and(|hex| {
+            matches!(hex.len(), 3 | 4 | 6 | 8) && hex.chars().all(|c| c.is_ascii_hexdigit())
+        });
+        if !valid {
+            return Err(format!(
+                "Invalid color {value:?} for {name:?}, expected a hex value like '#00aaff'"
+            ));
+        }
+        let slot = match name {
+            "bg" => &mut scheme.background,
+            "fg" => &mut scheme.foreground,
+            "accent" => &mut scheme.accent,
+            _ => {
+                return Err(format!(
+                    "Unknown color {name:?}, expected 'bg', 'fg' or 'accent'"
+                ));
+            }
+        };
+        *slot = Some(value.to_ascii_lowercase());
+    }
+
+    if scheme == CustomColorScheme::default() {
+        return Err("Expected at least one of 'bg', 'fg' or 'accent'".to_owned());
+    }
+    Ok(scheme)
+}
+
 /// Custom header parser (allow multiple headers input)
 pub fn parse_header(src: &str) -> Result<HeaderMap, httparse::Error> {
     let mut headers = [httparse::EMPTY_HEADER; 1];
@@ -480,18 +1439,34 @@ pub fn parse_header(src: &str) -> Result<HeaderMap, httparse::Error> {
     httparse::parse_headers(header.as_bytes(), &mut headers)?;
 
     let mut header_map = HeaderMap::new();
-    if let Some(h) = headers.first() {
-        if h.name != httparse::EMPTY_HEADER.name {
-            header_map.insert(
-                HeaderName::from_bytes(h.name.as_bytes()).unwrap(),
-                HeaderValue::from_bytes(h.value).unwrap(),
-            );
-        }
+    if let Some(h) = headers.first()
+        && h.name != httparse::EMPTY_HEADER.name
+    {
+        header_map.insert(
+            HeaderName::from_bytes(h.name.as_bytes()).unwrap(),
+            HeaderValue::from_bytes(h.value).unwrap(),
+        );
     }
 
     Ok(header_map)
 }
 
+/// Checks that a CORS origin is either '*' or a 'scheme://host[:port]' origin
+pub fn parse_cors_origin(src: &str) -> Result<String, String> {
+    let origin = src.trim_end_matches('/');
+    let valid = origin == "*"
+        || origin.split_once("://").is_some_and(|(scheme, host)| {
+            !scheme.is_empty() && !host.is_empty() && !host.contains('/')
+        });
+    if valid {
+        Ok(origin.to_owned())
+    } else {
+        Err(format!(
+            "Invalid origin {src:?}, expected '*' or something like 'https://example.com'"
+        ))
+    }
+}
+
 #[rustfmt::skip]
 #[cfg(test)]
 mod tests {
@@ -553,4 +1528,205 @@ mod tests {
         let err = parse_auth(auth_string).unwrap_err();
         assert_eq!(format!("{err}"), err_msg.to_owned());
     }
+
+    #[rstest(
+        alias_string, route, path,
+        case("/latest.iso=/data/builds/nightly.iso", "/latest.iso", "/data/builds/nightly.iso"),
+        case("/latest/=nightly.iso", "/latest", "nightly.iso"),
+        case("/a=b=c", "/a", "b=c"),
+    )]
+    fn parse_alias_valid(alias_string: &str, route: &str, path: &str) {
+        assert_eq!(
+            parse_alias(alias_string).unwrap(),
+            (route.to_owned(), PathBuf::from(path)),
+        );
+    }
+
+    #[rstest(
+        alias_string,
+        case("/latest.iso"),
+        case("latest.iso=nightly.iso"),
+        case("/=nightly.iso"),
+        case("/latest.iso="),
+        case("/{file}=nightly.iso"),
+    )]
+    fn parse_alias_invalid(alias_string: &str) {
+        assert!(parse_alias(alias_string).is_err());
+    }
+
+    #[rstest(
+        robots_string,
+        expected,
+        case("allow", Robots::Allow),
+        case("deny", Robots::Deny),
+        case("file:robots.txt", Robots::File(PathBuf::from("robots.txt"))),
+        case("file:/srv/robots.txt", Robots::File(PathBuf::from("/srv/robots.txt"))),
+    )]
+    fn parse_robots_valid(robots_string: &str, expected: Robots) {
+        assert_eq!(parse_robots(robots_string).unwrap(), expected);
+    }
+
+    #[rstest(robots_string, case(""), case("Allow"), case("file:"), case("robots.txt"))]
+    fn parse_robots_invalid(robots_string: &str) {
+        assert!(parse_robots(robots_string).is_err());
+    }
+
+    #[rstest(
+        alias_string, err_msg,
+        case("latest.iso=nightly.iso", "Alias route \"latest.iso\" must start with '/'"),
+        case("/=nightly.iso", "Alias route can't be '/', which serves the listing"),
+        case("/{tail}*=nightly.iso", "Alias route \"/{tail}*\" can't contain '{' or '}'"),
+    )]
+    fn parse_alias_invalid_message(alias_string: &str, err_msg: &str) {
+        assert_eq!(parse_alias(alias_string).unwrap_err(), err_msg);
+    }
+
+    #[rstest(
+        interface_string, interface,
+        case("127.0.0.1", Interface::Addr(IpAddr::from([127, 0, 0, 1]))),
+        case("::", Interface::Addr(IpAddr::from([0u16; 8]))),
+        case("eth0", Interface::Name("eth0".to_owned())),
+        case("br-lan.10", Interface::Name("br-lan.10".to_owned())),
+        case("Ethernet 2", Interface::Name("Ethernet 2".to_owned())),
+    )]
+    fn parse_interface_valid(interface_string: &str, interface: Interface) {
+        assert_eq!(parse_interface(interface_string).unwrap(), interface);
+    }
+
+    #[rstest(
+        interface_string,
+        case(""),
+        case(" "),
+    )]
+    fn parse_interface_invalid(interface_string: &str) {
+        assert!(parse_interface(interface_string).is_err());
+    }
+
+    #[rstest(
+        scheme_string, background, foreground, accent,
+        case("bg=#101010,fg=#eeeeee,accent=#00aaff", Some("#101010"), Some("#eeeeee"), Some("#00aaff")),
+        case("accent=#0AF", None, None, Some("#0af")),
+        case(" fg = #ffffff80 , bg=#000 ", Some("#000"), Some("#ffffff80"), None),
+    )]
+    fn parse_color_scheme_custom_valid(
+        scheme_string: &str,
+        background: Option<&str>,
+        foreground: Option<&str>,
+        accent: Option<&str>,
+    ) {
+        let scheme = parse_color_scheme_custom(scheme_string).unwrap();
+        assert_eq!(scheme.background.as_deref(), background);
+        assert_eq!(scheme.foreground.as_deref(), foreground);
+        assert_eq!(scheme.accent.as_deref(), accent);
+    }
+
+    #[rstest(
+        scheme_string,
+        case(""),
+        case("bg"),
+        case("bg=101010"),
+        case("bg=#10101"),
+        case("fg=#gggggg"),
+        case("link=#00aaff"),
+        case("bg=red"),
+    )]
+    fn parse_color_scheme_custom_invalid(scheme_string: &str) {
+        assert!(parse_color_scheme_custom(scheme_string).is_err());
+    }
+
+    #[rstest(
+        origin_string, origin,
+        case("*", "*"),
+        case("https://app.example.com", "https://app.example.com"),
+        case("http://localhost:3000/", "http://localhost:3000"),
+    )]
+    fn parse_cors_origin_valid(origin_string: &str, origin: &str) {
+        assert_eq!(parse_cors_origin(origin_string).unwrap(), origin);
+    }
+
+    #[rstest(
+        origin_string,
+        case("app.example.com"),
+        case("https://app.example.com/path"),
+        case("://app.example.com"),
+    )]
+    fn parse_cors_origin_invalid(origin_string: &str) {
+        assert!(parse_cors_origin(origin_string).is_err());
+    }
+
+    #[rstest(
+        timezone_string, expected,
+        case("local", Timezone::Local),
+        case("UTC", Timezone::Fixed(FixedOffset::east_opt(0).unwrap())),
+        case("+05:30", Timezone::Fixed(FixedOffset::east_opt(5 * 3600 + 30 * 60).unwrap())),
+        case("-0800", Timezone::Fixed(FixedOffset::west_opt(8 * 3600).unwrap())),
+    )]
+    fn parse_timezone_valid(timezone_string: &str, expected: Timezone) {
+        assert_eq!(parse_timezone(timezone_string).unwrap(), expected);
+    }
+
+    #[rstest(timezone_string, case(""), case("Mars/Olympus"), case("+25:00"))]
+    fn parse_timezone_invalid(timezone_string: &str) {
+        assert!(parse_timezone(timezone_string).is_err());
+    }
+
+    #[test]
+    fn parse_date_format_checks_specifiers() {
+        assert!(parse_date_format("%d.%m.%Y %H:%M").is_ok());
+        assert!(parse_date_format("%Q").is_err());
+    }
+
+    #[rstest(
+        duration_string, seconds,
+        case("90", 90),
+        case("30s", 30),
+        case("15m", 15 * 60),
+        case("2h", 2 * 60 * 60),
+        case("1d", 24 * 60 * 60),
+        case("1h30m", 90 * 60),
+    )]
+    fn parse_duration_valid(duration_string: &str, seconds: u64) {
+        assert_eq!(parse_duration(duration_string).unwrap(), Duration::from_secs(seconds));
+    }
+
+    #[rstest(
+        duration_string,
+        case(""),
+        case("h"),
+        case("2w"),
+        case("1h30"),
+        case("-5m"),
+    )]
+    fn parse_duration_invalid(duration_string: &str) {
+        assert!(parse_duration(duration_string).is_err());
+    }
+
+    #[test]
+    fn public_is_parsed() {
+        let args = CliArgs::try_parse_from(["miniserve", "--public"]).unwrap();
+        assert!(args.public);
+
+        let args = CliArgs::try_parse_from(["miniserve"]).unwrap();
+        assert!(!args.public);
+    }
+
+    /// Every option can be set through the environment, except for the ones printing something
+    /// and exiting
+    #[test]
+    fn every_option_has_env_var() {
+        use clap::CommandFactory;
+
+        let cmd = CliArgs::command();
+        for arg in cmd.get_arguments() {
+            let id = arg.get_id().as_str();
+            if ["help", "version", "print_completions", "print_manpage", "service"].contains(&id) {
+                continue;
+            }
+            let env = arg.get_env().and_then(|env| env.to_str());
+            assert!(
+                env.is_some_and(|env| env.starts_with("MINISERVE_")),
+                "{id} has no MINISERVE_ environment variable"
+            );
+        }
+    }
 }
@@ -77,16 +77,17 @@ pub async fn handle_auth(
     req: ServiceRequest,
     cred: BasicAuth,
 ) -> actix_web::Result<ServiceRequest, (actix_web::Error, ServiceRequest)> {
-    let required_auth = &req
+    let required_auth = req
         .app_data::<web::Data<crate::MiniserveConfig>>()
         .unwrap()
-        .auth;
+        .auth
+        .get();
 
     req.extensions_mut().insert(CurrentUser {
         name: cred.user_id().to_string(),
     });
 
-    if match_auth(&cred.into(), required_auth) {
+    if match_auth(&cred.into(), &required_auth) {
         Ok(req)
     } else {
         Err((RuntimeError::InvalidHttpCredentials.into(), req))
@@ -0,0 +1,329 @@
+//! Checksums of served files, computed on demand with --checksums
+//!
+//! They're served for `?hash=sha256` queries of files, and as virtual sidecars like
+//! `file.iso.sha256` next to them, unless such files exist. Both answer with a line in the
+//! format of `sha256sum`, so that the sidecars can be checked with `sha256sum -c`.
+//!
+//! Directories also have manifests of the checksums of all the files below them, like
+//! `SHA256SUMS`, which are streamed as the files are hashed.
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use actix_web::{
+    HttpResponse,
+    body::{EitherBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    http::{
+        Method,
+        header::{self, ContentDisposition, DispositionParam, DispositionType},
+    },
+    middleware::Next,
+    web,
+};
+use futures::{StreamExt, stream};
+use serde::Deserialize;
+use sha2::{Digest, Sha256, Sha512};
+
+use crate::{MiniserveConfig, file_utils};
+
+/// Number of checksums kept in the cache at most
+const MAX_CACHED_CHECKSUMS: usize = 4096;
+
+/// Number of files hashed at once for manifests
+const MANIFEST_CONCURRENCY: usize = 4;
+
+/// Hash algorithms checksums can be computed with
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum HashAlgorithm {
+    Sha256,
+    Sha512,
+}
+
+impl HashAlgorithm {
+    pub const ALL: [Self; 2] = [Self::Sha256, Self::Sha512];
+
+    /// Name of the algorithm, as used in queries and as the extension of sidecars
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Sha256 => "sha256",
+            Self::Sha512 => "sha512",
+        }
+    }
+
+    /// Name of the manifests of directories, like `SHA256SUMS`
+    pub fn manifest_name(self) -> &'static str {
+        match self {
+            Self::Sha256 => "SHA256SUMS",
+            Self::Sha512 => "SHA512SUMS",
+        }
+    }
+
+    /// Hashes everything `reader` reads, giving the hex digest
+    fn digest(self, reader: &mut impl io::Read) -> io::Result<String> {
+        fn digest_with<D: Digest + io::Write>(
+            mut hasher: D,
+            reader: &mut impl io::Read,
+        ) -> io::Result<String> {
+            io::copy(reader, &mut hasher)?;
+            Ok(hex::encode(hasher.finalize()))
+        }
+        match self {
+            Self::Sha256 => digest_with(Sha256::new(), reader),
+            Self::Sha512 => digest_with(Sha512::new(), reader),
+        }
+    }
+
+    /// Hashes what `reader` reads in pieces of `piece_length` bytes, giving their hex digests
+    fn piece_digests(
+        self,
+        reader: &mut impl io::Read,
+        piece_length: u64,
+    ) -> io::Result<Vec<String>> {
+        let mut digests = Vec::new();
+        let mut buffered = io::BufReader::new(reader);
+        while !io::BufRead::fill_buf(&mut buffered)?.is_empty() {
+            digests.push(self.digest(&mut io::Read::take(&mut buffered, piece_length))?);
+        }
+        Ok(digests)
+    }
+}
+
+#[derive(Deserialize)]
+struct HashQuery {
+    hash: Option<HashAlgorithm>,
+}
+
+/// A file hashed with an algorithm, as a whole or in pieces of a length, as it was when last
+/// modified
+type Key = (PathBuf, HashAlgorithm, Option<u64>, Option<SystemTime>, u64);
+
+/// Checksums computed recently, so that large files aren't read again for every request
+#[derive(Default)]
+pub struct ChecksumCache {
+    checksums: Mutex<HashMap<Key, Vec<String>>>,
+}
+
+impl ChecksumCache {
+    /// Gets the hex checksum of a file, from the cache unless the file changed since
+    pub async fn checksum(&self, path: &Path, algorithm: HashAlgorithm) -> io::Result<String> {
+        let mut checksums = self.checksums(path, algorithm, None).await?;
+        Ok(checksums.swap_remove(0))
+    }
+
+    /// Gets the hex checksums of the pieces of `piece_length` bytes of a file, from the cache
+    /// unless the file changed since
+    pub async fn piece_checksums(
+        &self,
+        path: &Path,
+        algorithm: HashAlgorithm,
+        piece_length: u64,
+    ) -> io::Result<Vec<String>> {
+        self.checksums(path, algorithm, Some(piece_length)).await
+    }
+
+    async fn checksums(
+        &self,
+        path: &Path,
+        algorithm: HashAlgorithm,
+        piece_length: Option<u64>,
+    ) -> io::Result<Vec<String>> {
+        let metadata = std::fs::metadata(path)?;
+        let key = (
+            path.to_path_buf(),
+            algorithm,
+            piece_length,
+            metadata.modified().ok(),
+            metadata.len(),
+        );
+        let cached = self
+            .checksums
+            .lock()
+            .expect("Checksum cache poisoned")
+            .get(&key)
+            .cloned();
+        if let Some(checksums) = cached {
+            return Ok(checksums);
+        }
+
+        let checksums = web::block({
+            let path = path.to_path_buf();
+            move || {
+                let mut file = std::fs::File::open(path)?;
+                match piece_length {
+                    Some(piece_length) => algorithm.piece_digests(&mut file, piece_length),
+                    None => algorithm.digest(&mut file).map(|checksum| vec![checksum]),
+                }
+            }
+        })
+        .await
+        .map_err(io::Error::other)??;
+        let mut cached = self.checksums.lock().expect("Checksum cache poisoned");
+        // Checksums of the file as it was before are of contents it no longer has
+        cached.retain(|(path, _, _, modified, len), _| {
+            *path != key.0 || (*modified, *len) == (key.3, key.4)
+        });
+        if cached.len() >= MAX_CACHED_CHECKSUMS {
+            cached.clear();
+        }
+        cached.insert(key, checksums.clone());
+        Ok(checksums)
+    }
+
+    /// Forgets all checksums, so that files are hashed again
+    pub fn clear(&self) {
+        self.checksums
+            .lock()
+            .expect("Checksum cache poisoned")
+            .clear();
+    }
+}
+
+/// Line of a checksum, in the format of `sha256sum` and the like
+///
+/// Like `sha256sum` does, names with backslashes or line breaks are escaped, and their line is
+/// marked with a leading backslash.
+pub fn checksum_line(checksum: &str, name: &str) -> String {
+    if name.contains(['\\', '\n', '\r']) {
+        let name = name
+            .replace('\\', "\\\\")
+            .replace('\n', "\\n")
+            .replace('\r', "\\r");
+        format!("\\{checksum}  {name}\n")
+    } else {
+        format!("{checksum}  {name}\n")
+    }
+}
+
+/// Answers `?hash=` queries of files and requests of their sidecars with their checksum, and
+/// requests of manifests in directories with the manifest
+pub async fn checksum_middleware(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, actix_web::Error> {
+    let conf = req
+        .app_data::<web::Data<MiniserveConfig>>()
+        .expect("Could not get miniserve config")
+        .clone();
+    let cache = req
+        .app_data::<web::Data<ChecksumCache>>()
+        .expect("Could not get checksum cache")
+        .clone();
+
+    if !conf.checksums || req.method() != Method::GET {
+        return Ok(next.call(req).await?.map_into_left_body());
+    }
+    if let Some((dir, algorithm)) = requested_manifest(&conf, req.match_info().unprocessed()) {
+        let res = manifest(conf.into_inner(), cache.into_inner(), dir, algorithm).await?;
+        return Ok(req.into_response(res).map_into_right_body());
+    }
+    let requested = requested_checksum(&conf, req.match_info().unprocessed(), req.query_string());
+    let Some((file, algorithm)) = requested else {
+        return Ok(next.call(req).await?.map_into_left_body());
+    };
+
+    let name = file
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let res = match cache.checksum(&file, algorithm).await {
+        Ok(checksum) => HttpResponse::Ok()
+            .content_type(mime::TEXT_PLAIN_UTF_8)
+            .insert_header((header::CACHE_CONTROL, "no-cache"))
+            .body(checksum_line(&checksum, &name)),
+        Err(e) => {
+            log::error!("Failed to compute the checksum of {file:?}: {e}");
+            HttpResponse::InternalServerError().finish()
+        }
+    };
+    Ok(req.into_response(res).map_into_right_body())
+}
+
+/// Resolves the file whose checksum a request asks for, and the algorithm to compute it with
+fn requested_checksum(
+    conf: &MiniserveConfig,
+    path: &str,
+    query: &str,
+) -> Option<(PathBuf, HashAlgorithm)> {
+    if let Ok(HashQuery {
+        hash: Some(algorithm),
+    }) = web::Query::<HashQuery>::from_query(query).map(web::Query::into_inner)
+    {
+        return file_utils::requested_file(conf, path).map(|file| (file, algorithm));
+    }
+
+    // Sidecars that exist are served as they are
+    if file_utils::requested_file(conf, path).is_some() {
+        return None;
+    }
+    HashAlgorithm::ALL.into_iter().find_map(|algorithm| {
+        let file = path.strip_suffix(&format!(".{}", algorithm.name()))?;
+        file_utils::requested_file(conf, file).map(|file| (file, algorithm))
+    })
+}
+
+/// Resolves the directory whose manifest a request asks for, if it's served and has no file of
+/// that name, and the algorithm of the manifest
+fn requested_manifest(conf: &MiniserveConfig, path: &str) -> Option<(PathBuf, HashAlgorithm)> {
+    if conf.disable_indexing {
+        return None;
+    }
+    let (dir, algorithm) = HashAlgorithm::ALL.into_iter().find_map(|algorithm| {
+        let dir = path.strip_suffix(algorithm.manifest_name())?;
+        (dir.is_empty() || dir.ends_with('/')).then_some((dir, algorithm))
+    })?;
+    file_utils::requested_dir(conf, dir)
+        .filter(|dir| !dir.join(algorithm.manifest_name()).exists())
+        .map(|dir| (dir, algorithm))
+}
+
+/// Streams the manifest of the checksums of the files below `dir`, hashing a few at once
+///
+/// Files that can't be read once the manifest started are left out of it.
+async fn manifest(
+    conf: Arc<MiniserveConfig>,
+    cache: Arc<ChecksumCache>,
+    dir: PathBuf,
+    algorithm: HashAlgorithm,
+) -> Result<HttpResponse, actix_web::Error> {
+    let mut files: Vec<_> = web::block({
+        let dir = dir.clone();
+        move || file_utils::served_entries(&conf, &dir)
+    })
+    .await?
+    .into_iter()
+    .filter(|entry| entry.metadata.is_file())
+    .map(|entry| entry.path)
+    .collect();
+    files.sort();
+
+    let lines = stream::iter(files)
+        .map(move |path| {
+            let cache = cache.clone();
+            let file = dir.join(&path);
+            async move {
+                match cache.checksum(&file, algorithm).await {
+                    Ok(checksum) => Some(checksum_line(&checksum, &path)),
+                    Err(e) => {
+                        log::warn!("Leaving {file:?} out of a checksum manifest: {e}");
+                        None
+                    }
+                }
+            }
+        })
+        .buffered(MANIFEST_CONCURRENCY)
+        .filter_map(|line| async move { line.map(|line| Ok::<_, io::Error>(line.into())) });
+
+    Ok(HttpResponse::Ok()
+        .content_type(mime::TEXT_PLAIN_UTF_8)
+        .insert_header(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename(
+                algorithm.manifest_name().to_owned(),
+            )],
+        })
+        .streaming(lines))
+}
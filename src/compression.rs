@@ -0,0 +1,86 @@
+//! Exclusions from on-the-fly response compression
+//!
+//! Compressing data that already is compressed costs CPU time without saving any bandwidth, so
+//! such responses are marked with the identity encoding, which makes actix's `Compress` middleware
+//! pass them through untouched.
+use actix_web::{
+    body::MessageBody,
+    dev::{ServiceRequest, ServiceResponse},
+    http::{
+        StatusCode,
+        header::{self, ContentEncoding, HeaderValue},
+    },
+    middleware::Next,
+};
+
+/// Content types that aren't worth compressing, on top of images, audio and video
+const INCOMPRESSIBLE_TYPES: &[&str] = &[
+    "application/gzip",
+    "application/x-gzip",
+    "application/zip",
+    "application/tar",
+    "application/x-tar",
+    "application/x-bzip2",
+    "application/x-xz",
+    "application/x-7z-compressed",
+    "application/vnd.rar",
+    "application/x-rar-compressed",
+    "application/zstd",
+    "application/pdf",
+    "application/wasm",
+    "font/woff",
+    "font/woff2",
+];
+
+/// Middleware opting responses out of compression when it wouldn't help
+///
+/// Besides already compressed content, this covers archive downloads, which are streamed as they
+/// are created, and partial responses, whose ranges refer to the uncompressed content.
+pub async fn compression_filter(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let mut res = next.call(req).await?;
+
+    let incompressible = res.status() == StatusCode::PARTIAL_CONTENT
+        || res
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<mime::Mime>().ok())
+            .is_some_and(|mime| is_incompressible(&mime));
+    if incompressible && !res.headers().contains_key(header::CONTENT_ENCODING) {
+        res.headers_mut().insert(
+            header::CONTENT_ENCODING,
+            HeaderValue::from_static(ContentEncoding::Identity.as_str()),
+        );
+    }
+
+    Ok(res)
+}
+
+/// Whether content of the given type is usually compressed already
+fn is_incompressible(mime: &mime::Mime) -> bool {
+    match mime.type_() {
+        mime::IMAGE => mime.subtype() != mime::SVG,
+        mime::AUDIO | mime::VIDEO => true,
+        _ => INCOMPRESSIBLE_TYPES.contains(&mime.essence_str()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("image/png", true)]
+    #[case("image/svg+xml", false)]
+    #[case("video/x-matroska", true)]
+    #[case("application/x-tar", true)]
+    #[case("text/html; charset=utf-8", false)]
+    #[case("application/json", false)]
+    fn incompressible_types(#[case] mime: &str, #[case] expected: bool) {
+        assert_eq!(is_incompressible(&mime.parse().unwrap()), expected);
+    }
+}
@@ -1,24 +1,47 @@
 use std::{
+    ffi::OsString,
     fs::File,
-    io::{BufRead, BufReader},
+    io::{self, BufRead, BufReader, IsTerminal},
     net::{IpAddr, Ipv4Addr, Ipv6Addr},
-    path::PathBuf,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+    time::Duration,
 };
 
-use actix_web::http::header::HeaderMap;
+use actix_web::http::header::{self, HeaderMap, HeaderValue};
 use anyhow::{Context, Result, anyhow};
+use clap::{CommandFactory, FromArgMatches};
+use globset::GlobMatcher;
+use tempfile::TempPath;
 
 #[cfg(feature = "tls")]
 use rustls_pemfile as pemfile;
 
 use crate::{
-    args::{CliArgs, MediaType, parse_auth},
+    archive_fs::open_archive,
+    args::{CliArgs, Interface, MediaType, Robots, parse_auth, validate_is_dir_and_exists},
     auth::RequiredAuth,
-    file_utils::sanitize_path,
-    listing::{SortingMethod, SortingOrder},
-    renderer::ThemeSlug,
+    consts,
+    content_type::{CharsetRule, MimeMap, read_mime_map_file},
+    external_ip::ExternalIpSource,
+    file_utils::{IGNORE_FILE, IgnoredPaths, sanitize_path},
+    git_fs::GitTree,
+    i18n::Lang,
+    listing::{DateFormat, SortingMethod, SortingOrder, TimeFormat},
+    pipe::buffer_to_temp_file,
+    renderer::{CustomColorScheme, ThemeSlug},
 };
 
+#[cfg(feature = "s3")]
+use crate::s3_fs::S3Bucket;
+
+#[cfg(unix)]
+use crate::privileges::Credentials;
+
+/// Misspelled name of the MINISERVE_TEMP_UPLOAD_DIRECTORY environment variable, which is still
+/// read when the new one isn't set
+pub const DEPRECATED_TEMP_UPLOAD_DIRECTORY_ENV: &str = "MINISERVER_TEMP_UPLOAD_DIRECTORY";
+
 /// Possible characters for random routes
 const ROUTE_ALPHABET: [char; 16] = [
     '1', '2', '3', '4', '5', '6', '7', '8', '9', '0', 'a', 'b', 'c', 'd', 'e', 'f',
@@ -33,17 +56,104 @@ pub struct MiniserveConfig {
     /// Path to be served by miniserve
     pub path: std::path::PathBuf,
 
+    /// Temporary file holding the buffered standard input, if that is what's being served
+    ///
+    /// This is kept around so the file is only removed once miniserve stops.
+    pub stdin_buffer: Option<Arc<TempPath>>,
+
+    /// File name offered for download when serving a single file
+    pub filename: Option<String>,
+
+    /// If enabled, the served zip archive is browsed like a directory
+    pub browse_archive: bool,
+
+    /// Object storage bucket served instead of a local path, if one was given
+    #[cfg(feature = "s3")]
+    pub s3: Option<S3Bucket>,
+
+    #[cfg(not(feature = "s3"))]
+    pub s3: Option<()>,
+
+    /// Commit of a git repository served instead of a local path, if one was given
+    pub git: Option<GitTree>,
+
     /// Temporary directory that should be used when files are uploaded to the server
     pub temp_upload_directory: Option<std::path::PathBuf>,
 
+    /// HTML file served instead of the built-in error page for 404 responses
+    pub error_404: Option<PathBuf>,
+
+    /// Directory of templates, named after status codes, used instead of the built-in error pages
+    pub error_pages: Option<PathBuf>,
+
+    /// Directory of templates used instead of the built-in listing and error pages
+    pub template_dir: Option<PathBuf>,
+
+    /// Single files exposed at custom routes, as (route, file path) pairs
+    pub aliases: Vec<(String, PathBuf)>,
+
     /// Port on which miniserve will be listening
     pub port: u16,
 
+    /// Whether `interfaces` are the wildcard addresses used when none are given
+    pub default_interfaces: bool,
+
+    /// File descriptor to write the port to once it is bound
+    #[cfg(unix)]
+    pub print_bound_port_fd: Option<u32>,
+
     /// IP address(es) on which miniserve will be available
     pub interfaces: Vec<IpAddr>,
 
+    /// Unix domain socket to listen on instead of `interfaces`, with the permissions to give it
+    #[cfg(unix)]
+    pub listen_unix: Option<(PathBuf, Option<u32>)>,
+
+    /// Number of worker threads, or the number of physical CPU cores if not set
+    pub workers: Option<u16>,
+
+    /// Seconds an idle connection is kept open, 0 disabling keep-alive
+    pub keep_alive: u64,
+
+    /// Seconds a client has to send the headers of a request, 0 disabling the timeout
+    pub client_request_timeout: u64,
+
+    /// Seconds running transfers are given to finish when shutting down
+    pub shutdown_timeout: u64,
+
+    /// How long to serve for before stopping by itself
+    pub duration: Option<Duration>,
+
+    /// How many complete downloads to stop after
+    pub max_downloads: Option<u64>,
+
+    /// Where to write the single file received with --receive
+    pub receive: Option<PathBuf>,
+
+    /// Whether to fork into the background once bound
+    #[cfg(unix)]
+    pub daemon: bool,
+
+    /// File to write the process ID to
+    #[cfg(unix)]
+    pub pid_file: Option<PathBuf>,
+
+    /// File receiving output and logs when running in the background
+    #[cfg(unix)]
+    pub log_file: Option<PathBuf>,
+
+    /// User and group to switch to once bound
+    #[cfg(unix)]
+    pub credentials: Option<Credentials>,
+
+    /// Whether to restrict file system access to the files being served
+    #[cfg(target_os = "linux")]
+    pub sandbox: bool,
+
     /// Enable HTTP basic authentication
-    pub auth: Vec<RequiredAuth>,
+    ///
+    /// Credentials from --auth-file are read again when the configuration is reloaded.
+    pub auth: Reloadable<Vec<RequiredAuth>>,
 
     /// If false, miniserve will serve the current working directory
     pub path_explicitly_chosen: bool,
@@ -60,6 +170,15 @@ pub struct MiniserveConfig {
     /// Default sorting order
     pub default_sorting_order: SortingOrder,
 
+    /// How listings show when entries were last modified, unless chosen otherwise
+    pub time_format: TimeFormat,
+
+    /// How listings format the times entries were last modified at
+    pub date_format: DateFormat,
+
+    /// Language of pages, instead of the one browsers ask for
+    pub lang: Option<Lang>,
+
     /// Route prefix; Either empty or prefixed with slash
     pub route_prefix: String,
 
@@ -81,6 +200,18 @@ pub struct MiniserveConfig {
     /// Default dark mode color scheme
     pub default_color_scheme_dark: ThemeSlug,
 
+    /// Colors overriding those of the default color schemes, from --color-scheme-custom
+    pub custom_color_scheme: Option<CustomColorScheme>,
+
+    /// CSS added to the stylesheet after the styles of the themes, read from --custom-css
+    pub custom_css: Option<String>,
+
+    /// JavaScript added at the end of pages, read from --custom-js
+    pub custom_js: Option<String>,
+
+    /// Type and contents of the image served as the favicon, read from --favicon
+    pub favicon: Option<(mime::Mime, Vec<u8>)>,
+
     /// The name of a directory index file to serve, like "index.html"
     ///
     /// Normally, when miniserve serves a directory, it creates a listing for that directory.
@@ -98,12 +229,28 @@ pub struct MiniserveConfig {
     ///
     /// This will cause the server to serve the equivalent `.html` file indicated by the path.
     ///
-    /// `/about` will try to find `about.html` and serve it.
+    /// `/about` will try to find `about.html` and serve it, and `/about/` will serve
+    /// `about/index.html` unless another `index` is given.
     pub pretty_urls: bool,
 
     /// Enable QR code display
     pub show_qrcode: bool,
 
+    /// Whether to open the served URL in the browser once listening
+    pub open_browser: bool,
+
+    /// Whether to copy the served URL to the clipboard on startup
+    pub copy_url: bool,
+
+    /// Whether to announce the server through mDNS
+    pub announce: bool,
+
+    /// Whether to forward the port on the router through UPnP or NAT-PMP
+    pub public: bool,
+
+    /// Where to find out the public IP address of the machine, which printed URLs then use
+    pub external_ip: Option<ExternalIpSource>,
+
     /// Enable recursive directory size calculation
     pub directory_size: bool,
 
@@ -113,6 +260,12 @@ pub struct MiniserveConfig {
     /// Enable file upload
     pub file_upload: bool,
 
+    /// Set while uploads are paused from the admin page
+    pub uploads_paused: Reloadable<bool>,
+
+    /// User allowed to see the admin page, if it's served
+    pub admin: Option<String>,
+
     /// Max amount of concurrency when uploading multiple files
     pub web_upload_concurrency: usize,
 
@@ -134,33 +287,126 @@ pub struct MiniserveConfig {
     /// If false, creation of zip archives is disabled
     pub zip_enabled: bool,
 
+    /// Serve precompressed `.br` and `.gz` variants of files when the client accepts them
+    pub precompressed: bool,
+
     /// Enable  compress response
     pub compress_response: bool,
 
     /// If enabled, directories are listed first
     pub dirs_first: bool,
 
+    /// Maximum number of entries shown in a directory listing, unless all are requested
+    pub max_listing_entries: Option<usize>,
+
+    /// Whether open listings are refreshed when their directory changes
+    pub live_refresh: bool,
+
+    /// Whether directories have an Atom feed of their recent files at 'feed.xml'
+    pub feed: bool,
+
+    /// Whether a sitemap of the served entries is served at 'sitemap.xml'
+    pub sitemap: bool,
+
+    /// Whether checksums of files are served for '?hash=' queries and as sidecars
+    pub checksums: bool,
+
+    /// Whether Metalink descriptions of files are served as '.meta4' sidecars
+    pub metalink: bool,
+
+    /// Whether torrents of files and directories are served for '?torrent=true' queries
+    pub torrent: bool,
+
+    /// Contents of the robots.txt served at the root, as chosen with --robots
+    pub robots: Option<String>,
+
     /// Shown instead of host in page title and heading
     pub title: Option<String>,
 
+    /// Text shown above listings
+    pub header_text: Option<String>,
+
+    /// Text shown in the footer of listings
+    pub footer_text: Option<String>,
+
+    /// Whether --header-text and --footer-text are HTML, instead of text to escape
+    pub html_text: bool,
+
     /// If specified, header will be added
     pub header: Vec<HeaderMap>,
 
+    /// Content types overriding the guessed ones, by file extension
+    ///
+    /// Mappings from --mime-map-file are read again when the configuration is reloaded.
+    pub mime_map: Reloadable<MimeMap>,
+
+    /// Entries hidden from listings, archives and requests by the .miniserveignore file and the
+    /// patterns given with --ignore
+    ///
+    /// The file is read again when the configuration is reloaded.
+    pub ignored: Reloadable<IgnoredPaths>,
+
+    /// Charsets declared for text files, possibly depending on their path
+    pub charsets: Vec<CharsetRule>,
+
+    /// Files matching these globs are served as attachments
+    pub download_globs: Vec<GlobMatcher>,
+
+    /// Files matching these globs are served inline, unless they also match `download_globs`
+    pub inline_globs: Vec<GlobMatcher>,
+
+    /// Origins allowed to make cross-origin requests, possibly "*" for any origin
+    pub cors_origins: Vec<String>,
+
     /// If specified, symlink destination will be shown
     pub show_symlink_info: bool,
 
+    /// Whether listings show the mode, owner and group of entries
+    #[cfg(unix)]
+    pub show_permissions: bool,
+
     /// If enabled, version footer is hidden
     pub hide_version_footer: bool,
 
     /// If enabled, theme selector is hidden
     pub hide_theme_selector: bool,
 
+    /// Whether listings are bare indexes like those of nginx
+    pub minimal_ui: bool,
+
     /// If enabled, display a wget command to recursively download the current directory
     pub show_wget_footer: bool,
 
     /// If enabled, render the readme from the current directory
     pub readme: bool,
 
+    /// Whether Markdown files are rendered as HTML pages
+    pub render_markdown: bool,
+
+    /// Whether text files can be previewed with syntax highlighting
+    pub preview: bool,
+
+    /// Whether listings show thumbnails of images
+    pub thumbnails: bool,
+
+    /// Whether images are served scaled down when requested
+    pub resize_images: bool,
+
+    /// Directory scaled down images are kept in, and its largest size in bytes
+    pub cache_dir: Option<(PathBuf, u64)>,
+
+    /// Whether media files open in a player page, which needs them to be in local directories
+    pub media_player: bool,
+
+    /// Whether PDF files open in a page showing them, which needs them to be in local directories
+    pub pdf_viewer: bool,
+
+    /// Whether the metadata of images is removed as they are served
+    pub strip_exif: bool,
+
+    /// Whether the EXIF data of photos can be shown, which needs them to be in local directories
+    pub exif_details: bool,
+
     /// If enabled, indexing is disabled.
     pub disable_indexing: bool,
 
@@ -177,15 +423,131 @@ pub struct MiniserveConfig {
     #[cfg(not(feature = "tls"))]
     pub tls_rustls_config: Option<()>,
 
+    /// If enabled, HTTP/3 connections are accepted as well
+    #[cfg(feature = "http3")]
+    pub http3: bool,
+
     /// Optional external URL to prepend to file links in listings
     pub file_external_url: Option<String>,
+
+    /// Where the reloadable settings come from
+    reload_sources: ReloadSources,
+}
+
+/// A setting that can be replaced while serving, shared by all workers
+#[derive(Debug)]
+pub struct Reloadable<T>(Arc<RwLock<Arc<T>>>);
+
+impl<T> Reloadable<T> {
+    fn new(value: T) -> Self {
+        Self(Arc::new(RwLock::new(Arc::new(value))))
+    }
+
+    /// Returns the current value
+    pub fn get(&self) -> Arc<T> {
+        self.0.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    pub fn set(&self, value: T) {
+        *self.0.write().unwrap_or_else(|e| e.into_inner()) = Arc::new(value);
+    }
+}
+
+impl<T> Clone for Reloadable<T> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+/// Settings given on the command line along with the files the reloadable settings are read from
+#[derive(Debug, Clone)]
+struct ReloadSources {
+    auth: Vec<RequiredAuth>,
+    auth_file: Option<PathBuf>,
+    mime_map: Vec<(String, mime::Mime)>,
+    mime_map_file: Option<PathBuf>,
+    ignore_root: Option<PathBuf>,
+    ignore_file: Option<PathBuf>,
+    ignore_patterns: Vec<String>,
+}
+
+impl ReloadSources {
+    fn read_auth(&self) -> Result<Vec<RequiredAuth>> {
+        let mut auth = self.auth.clone();
+
+        if let Some(path) = &self.auth_file {
+            let file = File::open(path)?;
+            let lines = BufReader::new(file).lines();
+
+            for line in lines {
+                auth.push(parse_auth(line?.as_str())?);
+            }
+        }
+
+        Ok(auth)
+    }
+
+    fn read_mime_map(&self) -> Result<MimeMap> {
+        let mut mime_map = match &self.mime_map_file {
+            Some(path) => read_mime_map_file(path)
+                .map_err(|e| anyhow!(e))?
+                .into_iter()
+                .collect(),
+            None => MimeMap::new(),
+        };
+        mime_map.extend(self.mime_map.iter().cloned());
+        Ok(mime_map)
+    }
+
+    fn read_ignored(&self) -> Result<IgnoredPaths> {
+        let Some(root) = &self.ignore_root else {
+            return Ok(IgnoredPaths::new(Path::new(""), None, &[])?);
+        };
+        let ignored = IgnoredPaths::new(root, self.ignore_file.as_deref(), &self.ignore_patterns)
+            .with_context(|| format!("Failed to read {IGNORE_FILE}"))?;
+        Ok(ignored)
+    }
+}
+
+/// Reads the image of --favicon, telling its type by its extension
+fn read_favicon(path: &Path) -> Result<(mime::Mime, Vec<u8>)> {
+    let mime = path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(actix_files::file_extension_to_mime)
+        .filter(|mime| mime.type_() == mime::IMAGE)
+        .ok_or_else(|| {
+            anyhow!("The --favicon file {path:?} doesn't have the extension of an image")
+        })?;
+    let contents = std::fs::read(path)
+        .with_context(|| format!("Failed to read the --favicon file {path:?}"))?;
+    Ok((mime, contents))
+}
+
+/// Gives the robots.txt chosen with --robots, reading it from its file if need be
+fn read_robots(robots: &Robots) -> Result<String> {
+    match robots {
+        Robots::Allow => Ok("User-agent: *\nAllow: /\n".to_owned()),
+        Robots::Deny => Ok("User-agent: *\nDisallow: /\n".to_owned()),
+        Robots::File(path) => std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read the --robots file {path:?}")),
+    }
 }
 
 impl MiniserveConfig {
     /// Parses the command line arguments
     pub fn try_from_args(args: CliArgs) -> Result<Self> {
+        #[cfg(unix)]
+        let listen_unix = args.listen_unix.map(|path| (path, args.listen_unix_mode));
+
+        #[cfg(not(unix))]
+        let listen_unix: Option<()> = None;
+
+        let default_interfaces = args.interfaces.is_empty() && listen_unix.is_none();
         let interfaces = if !args.interfaces.is_empty() {
-            args.interfaces
+            resolve_interfaces(args.interfaces)?
+        } else if listen_unix.is_some() {
+            vec![]
         } else {
             vec![
                 IpAddr::V6(Ipv6Addr::new(0, 0, 0, 0, 0, 0, 0, 0)),
@@ -199,15 +561,32 @@ impl MiniserveConfig {
             _ => "".to_owned(),
         };
 
-        let mut auth = args.auth;
-
-        if let Some(path) = args.auth_file {
-            let file = File::open(path)?;
-            let lines = BufReader::new(file).lines();
-
-            for line in lines {
-                auth.push(parse_auth(line?.as_str())?);
-            }
+        // Entries of a directory served from disk can be hidden by an ignore file in it and by
+        // --ignore. Patterns are matched against canonical paths, as the listings see them.
+        let ignore_root = args
+            .git
+            .is_none()
+            .then(|| args.path.as_deref().unwrap_or(Path::new(".")))
+            .and_then(|path| path.canonicalize().ok())
+            .filter(|path| path.is_dir());
+        let ignore_file = ignore_root
+            .as_ref()
+            .map(|dir| dir.join(IGNORE_FILE))
+            .filter(|file| file.is_file());
+        let reload_sources = ReloadSources {
+            auth: args.auth,
+            auth_file: args.auth_file,
+            mime_map: args.mime_map,
+            mime_map_file: args.mime_map_file,
+            ignore_root,
+            ignore_file,
+            ignore_patterns: args.ignore_patterns,
+        };
+        let auth = reload_sources.read_auth()?;
+        if args.admin.is_some() && auth.is_empty() && reload_sources.auth_file.is_none() {
+            return Err(anyhow!(
+                "The --admin option was provided, but no user can log in without --auth or --auth-file"
+            ));
         }
 
         // Format some well-known routes at paths that are very unlikely to conflict with real
@@ -231,16 +610,153 @@ impl MiniserveConfig {
             )
         };
 
-        let default_color_scheme = args.color_scheme;
-        let default_color_scheme_dark = args.color_scheme_dark;
+        for (route, path) in &args.aliases {
+            if !path.is_file() {
+                return Err(anyhow!(
+                    "The file {path:?} provided for alias {route:?} could not be found"
+                ));
+            }
+        }
+
+        if let Some(page) = &args.error_404
+            && !page.is_file()
+        {
+            return Err(anyhow!(
+                "The file {page:?} provided for --error-404 could not be found"
+            ));
+        }
 
-        let path_explicitly_chosen = args.path.is_some() || args.index.is_some();
+        if let Some(dir) = &args.error_pages
+            && !dir.is_dir()
+        {
+            return Err(anyhow!(
+                "The directory {dir:?} provided for --error-pages could not be found"
+            ));
+        }
 
-        let port = match args.port {
-            0 => port_check::free_local_port().context("No free ports available")?,
-            _ => args.port,
+        if let Some(dir) = &args.template_dir
+            && !dir.is_dir()
+        {
+            return Err(anyhow!(
+                "The directory {dir:?} provided for --template-dir could not be found"
+            ));
+        }
+
+        #[cfg(unix)]
+        let credentials = Credentials::resolve(args.user.as_deref(), args.group.as_deref())?;
+
+        let stdin_buffer = if args.path.as_deref() == Some(Path::new("-")) {
+            if io::stdin().is_terminal() {
+                return Err(anyhow!(
+                    "Refusing to serve standard input as it is an interactive terminal"
+                ));
+            }
+            let buffered = buffer_to_temp_file(io::stdin().lock())
+                .context("Failed to buffer standard input")?;
+            Some(Arc::new(buffered.into_temp_path()))
+        } else {
+            None
         };
 
+        if args.browse_archive
+            && let Some(path) = &args.path
+        {
+            open_archive(path).with_context(|| format!("Can't browse {path:?} as an archive"))?;
+        }
+
+        #[cfg(feature = "s3")]
+        let s3 = args
+            .path
+            .as_deref()
+            .and_then(S3Bucket::from_path)
+            .transpose()
+            .map_err(|e| anyhow!("Invalid object storage location: {e}"))?;
+
+        #[cfg(not(feature = "s3"))]
+        let s3 = match args.path.as_deref().and_then(|path| path.to_str()) {
+            Some(path) if path.starts_with("s3://") => {
+                return Err(anyhow!(
+                    "Can't serve {path:?}: miniserve was built without the s3 feature"
+                ));
+            }
+            _ => None,
+        };
+        if s3.is_some() {
+            if args.allowed_upload_dir.is_some() || args.enable_webdav || args.browse_archive {
+                return Err(anyhow!(
+                    "Uploads, WebDAV and archive browsing aren't supported for object storage"
+                ));
+            }
+            if args.filename.is_some() {
+                return Err(anyhow!("--filename can't be used with object storage"));
+            }
+            if args.render_markdown {
+                return Err(anyhow!(
+                    "--render-markdown can't be used with object storage"
+                ));
+            }
+        }
+
+        let git = args
+            .git
+            .as_deref()
+            .map(|repo| {
+                let reference = args.git_ref.as_deref().unwrap_or("HEAD");
+                GitTree::open(repo, reference).with_context(|| {
+                    format!("Can't serve {reference:?} from git repository {repo:?}")
+                })
+            })
+            .transpose()?;
+
+        #[cfg(target_os = "linux")]
+        if args.sandbox && (s3.is_some() || git.is_some()) {
+            return Err(anyhow!(
+                "--sandbox isn't supported when serving object storage or git repositories"
+            ));
+        }
+
+        if let Some(dest) = &args.receive
+            && !dest.is_dir()
+        {
+            let dir = match dest.parent() {
+                Some(dir) if !dir.as_os_str().is_empty() => dir,
+                _ => Path::new("."),
+            };
+            if !dir.is_dir() {
+                return Err(anyhow!(
+                    "Can't receive into {dest:?}, as {dir:?} isn't a directory"
+                ));
+            }
+        }
+
+        let default_color_scheme = args.color_scheme;
+        let default_color_scheme_dark = args.color_scheme_dark;
+        let custom_color_scheme = args.color_scheme_custom;
+        let custom_css = args
+            .custom_css
+            .as_ref()
+            .map(|path| {
+                std::fs::read_to_string(path)
+                    .with_context(|| format!("Failed to read the --custom-css file {path:?}"))
+            })
+            .transpose()?;
+        let custom_js = args
+            .custom_js
+            .as_ref()
+            .map(|path| {
+                std::fs::read_to_string(path)
+                    .with_context(|| format!("Failed to read the --custom-js file {path:?}"))
+            })
+            .transpose()?;
+        let favicon = args.favicon.as_deref().map(read_favicon).transpose()?;
+        let robots = args.robots.as_ref().map(read_robots).transpose()?;
+
+        // Nothing is served when receiving a file, so the current directory is harmless then
+        let path_explicitly_chosen = args.path.is_some()
+            || args.index.is_some()
+            || args.git.is_some()
+            || args.receive.is_some();
+
         #[cfg(feature = "tls")]
         let tls_rustls_server_config =
             if let (Some(tls_cert), Some(tls_key)) = (args.tls_cert, args.tls_key) {
@@ -268,6 +784,35 @@ impl MiniserveConfig {
         #[cfg(not(feature = "tls"))]
         let tls_rustls_server_config = None;
 
+        #[cfg(all(unix, feature = "tls"))]
+        if listen_unix.is_some() && tls_rustls_server_config.is_some() {
+            return Err(anyhow!("TLS isn't supported on Unix domain sockets"));
+        }
+
+        let mut header = args.header;
+        if args.hardened_headers {
+            let mut hardened = vec![
+                (header::X_CONTENT_TYPE_OPTIONS, "nosniff"),
+                // The referrer is still needed by error pages to link back to the listing
+                (header::REFERRER_POLICY, "same-origin"),
+                (header::CONTENT_SECURITY_POLICY, consts::HARDENED_CSP),
+            ];
+            if tls_rustls_server_config.is_some() {
+                hardened.push((header::STRICT_TRANSPORT_SECURITY, "max-age=31536000"));
+            }
+
+            let mut hardened_map = HeaderMap::new();
+            for (name, value) in hardened {
+                if !header.iter().any(|h| h.contains_key(&name)) {
+                    hardened_map.insert(name, HeaderValue::from_static(value));
+                }
+            }
+            header.push(hardened_map);
+        }
+
+        let mime_map = reload_sources.read_mime_map()?;
+        let ignored = reload_sources.read_ignored()?;
+
         let uploadable_media_type = args.media_type_raw.or_else(|| {
             args.media_type.map(|types| {
                 types
@@ -297,6 +842,21 @@ impl MiniserveConfig {
             .transpose()?
             .unwrap_or_default();
 
+        let temp_upload_directory = match args.temp_upload_directory {
+            Some(dir) => Some(dir),
+            None if args.allowed_upload_dir.is_some() => {
+                std::env::var_os(DEPRECATED_TEMP_UPLOAD_DIRECTORY_ENV)
+                    .map(|dir| validate_is_dir_and_exists(&dir.to_string_lossy()))
+                    .transpose()
+                    .map_err(|e| anyhow!("Invalid {DEPRECATED_TEMP_UPLOAD_DIRECTORY_ENV}: {e}"))?
+            }
+            None => None,
+        };
+
+        let local_dirs = !args.browse_archive && s3.is_none() && git.is_none();
+        // Pages showing files are answered for files in the served directory
+        let media_player = local_dirs && args.receive.is_none();
+
         let show_exact_bytes = match args.size_display {
             crate::args::SizeDisplay::Human => false,
             crate::args::SizeDisplay::Exact => true,
@@ -304,16 +864,56 @@ impl MiniserveConfig {
 
         Ok(Self {
             verbose: args.verbose,
-            path: args.path.unwrap_or_else(|| PathBuf::from(".")),
-            temp_upload_directory: args.temp_upload_directory,
-            port,
+            path: match &stdin_buffer {
+                Some(buffered) => buffered.to_path_buf(),
+                None => args.path.or(args.git).unwrap_or_else(|| PathBuf::from(".")),
+            },
+            filename: args
+                .filename
+                .or_else(|| stdin_buffer.is_some().then(|| "stdin".to_owned())),
+            stdin_buffer,
+            browse_archive: args.browse_archive,
+            temp_upload_directory,
+            error_404: args.error_404,
+            error_pages: args.error_pages,
+            template_dir: args.template_dir,
+            aliases: args.aliases,
+            port: args.port,
+            #[cfg(unix)]
+            print_bound_port_fd: args.print_bound_port_fd,
             interfaces,
-            auth,
+            default_interfaces,
+            workers: args.workers,
+            keep_alive: args.keep_alive,
+            client_request_timeout: args.client_request_timeout,
+            shutdown_timeout: args.shutdown_timeout,
+            duration: args.duration,
+            max_downloads: args.max_downloads,
+            receive: args.receive,
+            #[cfg(unix)]
+            daemon: args.daemon,
+            #[cfg(unix)]
+            pid_file: args.pid_file,
+            #[cfg(unix)]
+            log_file: args.log_file,
+            #[cfg(unix)]
+            credentials,
+            #[cfg(target_os = "linux")]
+            sandbox: args.sandbox,
+            #[cfg(unix)]
+            listen_unix,
+            auth: Reloadable::new(auth),
             path_explicitly_chosen,
             no_symlinks: args.no_symlinks,
             show_hidden: args.hidden,
             default_sorting_method: args.default_sorting_method,
             default_sorting_order: args.default_sorting_order,
+            time_format: args.time_format,
+            date_format: DateFormat {
+                timezone: args.timezone,
+                pattern: args.date_format,
+            },
+            lang: args.lang,
             route_prefix,
             healthcheck_route,
             api_route,
@@ -321,34 +921,220 @@ impl MiniserveConfig {
             css_route,
             default_color_scheme,
             default_color_scheme_dark,
+            custom_color_scheme,
+            custom_css,
+            custom_js,
+            favicon,
             index: args.index,
             spa: args.spa,
             pretty_urls: args.pretty_urls,
             overwrite_files: args.overwrite_files,
             show_qrcode: args.qrcode,
+            open_browser: args.open,
+            copy_url: args.copy_url,
+            announce: args.announce,
+            public: args.public,
+            external_ip: args.external_ip,
             directory_size: args.directory_size,
             mkdir_enabled: args.mkdir_enabled,
             file_upload: args.allowed_upload_dir.is_some(),
+            uploads_paused: Reloadable::new(false),
+            admin: args.admin,
             web_upload_concurrency: args.web_upload_concurrency,
             allowed_upload_dir,
             uploadable_media_type,
-            tar_enabled: args.enable_tar,
-            tar_gz_enabled: args.enable_tar_gz,
-            zip_enabled: args.enable_zip,
+            // Archives can only be generated from directories that exist on disk
+            tar_enabled: args.enable_tar && local_dirs,
+            tar_gz_enabled: args.enable_tar_gz && local_dirs,
+            zip_enabled: args.enable_zip && local_dirs,
+            s3,
+            git,
             dirs_first: args.dirs_first,
+            max_listing_entries: args.max_listing_entries,
+            live_refresh: args.live_refresh,
+            feed: args.feed,
+            sitemap: args.sitemap,
+            checksums: args.checksums,
+            metalink: args.metalink,
+            torrent: args.torrent,
+            robots,
             title: args.title,
-            header: args.header,
+            header_text: args.header_text,
+            footer_text: args.footer_text,
+            html_text: args.html_text,
+            header,
+            cors_origins: args.cors_origins,
+            mime_map: Reloadable::new(mime_map),
+            ignored: Reloadable::new(ignored),
+            charsets: args.charsets,
+            download_globs: args.download_globs,
+            inline_globs: args.inline_globs,
             show_symlink_info: args.show_symlink_info,
+            #[cfg(unix)]
+            show_permissions: args.show_permissions,
             hide_version_footer: args.hide_version_footer,
             hide_theme_selector: args.hide_theme_selector,
+            minimal_ui: args.minimal_ui,
             show_wget_footer: args.show_wget_footer,
             readme: args.readme,
+            render_markdown: args.render_markdown,
+            preview: args.preview,
+            thumbnails: args.thumbnails,
+            resize_images: args.resize_images,
+            cache_dir: args.cache_dir.map(|dir| (dir, args.cache_size.as_u64())),
+            media_player,
+            pdf_viewer: media_player,
+            strip_exif: args.strip_exif,
+            exif_details: media_player && !args.strip_exif,
             disable_indexing: args.disable_indexing,
             webdav_enabled: args.enable_webdav,
             tls_rustls_config: tls_rustls_server_config,
+            precompressed: args.precompressed && local_dirs,
             compress_response: args.compress_response,
             show_exact_bytes,
+            #[cfg(feature = "http3")]
+            http3: args.http3,
             file_external_url: args.file_external_url,
+            reload_sources,
         })
     }
+
+    /// Reads the files that reloadable settings come from again, and replaces those settings
+    ///
+    /// Nothing is replaced if any of the files can't be read.
+    pub fn reload(&self) -> Result<()> {
+        let auth = self.reload_sources.read_auth()?;
+        let mime_map = self.reload_sources.read_mime_map()?;
+        let ignored = self.reload_sources.read_ignored()?;
+        self.auth.set(auth);
+        self.mime_map.set(mime_map);
+        self.ignored.set(ignored);
+        Ok(())
+    }
+
+    /// Files that reloadable settings come from
+    #[cfg(target_os = "linux")]
+    pub fn reloadable_files(&self) -> impl Iterator<Item = &Path> {
+        let sources = &self.reload_sources;
+        sources
+            .auth_file
+            .iter()
+            .chain(&sources.mime_map_file)
+            .chain(&sources.ignore_file)
+            .map(PathBuf::as_path)
+    }
+
+    /// Whether authentication is required, now or after reloading the configuration
+    pub fn auth_enabled(&self) -> bool {
+        !self.auth.get().is_empty() || self.reload_sources.auth_file.is_some()
+    }
+
+    /// Whether files can be uploaded right now, which they can't while paused
+    pub fn uploads_enabled(&self) -> bool {
+        self.file_upload && !*self.uploads_paused.get()
+    }
+
+    /// Starts building the configuration to serve `path` with, for embedding miniserve
+    pub fn builder(path: impl Into<PathBuf>) -> MiniserveConfigBuilder {
+        MiniserveConfigBuilder {
+            path: path.into(),
+            args: vec![],
+        }
+    }
+}
+
+/// Builds configurations from the options of the command line, for embedding miniserve
+///
+/// Options without a method of their own are given with [`arg`](Self::arg), as they would be on
+/// the command line. Unlike on the command line, `MINISERVE_*` environment variables aren't read.
+#[derive(Debug, Clone)]
+pub struct MiniserveConfigBuilder {
+    path: PathBuf,
+    args: Vec<OsString>,
+}
+
+impl MiniserveConfigBuilder {
+    /// Adds an option, or the value of the previous one, as given on the command line
+    pub fn arg(mut self, arg: impl Into<OsString>) -> Self {
+        self.args.push(arg.into());
+        self
+    }
+
+    /// Adds options, as given on the command line
+    pub fn args(self, args: impl IntoIterator<Item = impl Into<OsString>>) -> Self {
+        args.into_iter().fold(self, Self::arg)
+    }
+
+    /// Port to listen on, or 0 for one picked by the system
+    pub fn port(self, port: u16) -> Self {
+        self.arg("--port").arg(port.to_string())
+    }
+
+    /// Adds an address to listen on, instead of all of them
+    pub fn interface(self, addr: IpAddr) -> Self {
+        self.arg("--interfaces").arg(addr.to_string())
+    }
+
+    /// Serves under this route rather than at the root
+    pub fn route_prefix(self, prefix: &str) -> Self {
+        self.arg("--route-prefix").arg(prefix)
+    }
+
+    /// Title of the listing pages
+    pub fn title(self, title: &str) -> Self {
+        self.arg("--title").arg(title)
+    }
+
+    /// Checks the options and builds the configuration
+    pub fn build(self) -> Result<MiniserveConfig> {
+        let args = parse_args(
+            self.args
+                .into_iter()
+                .chain(["--".into(), self.path.into_os_string()]),
+        )?;
+        MiniserveConfig::try_from_args(args)
+    }
+}
+
+/// Parses options as given on the command line, without reading `MINISERVE_*` environment variables
+pub(crate) fn parse_args(args: impl IntoIterator<Item = OsString>) -> Result<CliArgs> {
+    let command = CliArgs::command().mut_args(|arg| arg.env(None::<&str>));
+    let matches = command
+        .try_get_matches_from(std::iter::once("miniserve".into()).chain(args))
+        .context("Invalid options")?;
+    CliArgs::from_arg_matches(&matches).context("Invalid options")
+}
+
+/// Turns the interfaces given on the command line into the IP addresses to listen on
+///
+/// Network interfaces are replaced with their current addresses, except for IPv6 link-local ones,
+/// which can't be bound to without knowing the scope they belong to.
+fn resolve_interfaces(interfaces: Vec<Interface>) -> Result<Vec<IpAddr>> {
+    let system_interfaces = if interfaces.iter().any(|i| matches!(i, Interface::Name(_))) {
+        if_addrs::get_if_addrs().context("Failed to list network interfaces")?
+    } else {
+        vec![]
+    };
+
+    let mut addrs = vec![];
+    for interface in interfaces {
+        match interface {
+            Interface::Addr(addr) => addrs.push(addr),
+            Interface::Name(name) => {
+                let found: Vec<_> = system_interfaces
+                    .iter()
+                    .filter(|iface| iface.name == name)
+                    .map(|iface| iface.ip())
+                    .filter(|ip| !matches!(ip, IpAddr::V6(ip) if ip.is_unicast_link_local()))
+                    .collect();
+                if found.is_empty() {
+                    return Err(anyhow!("No usable addresses on network interface {name:?}"));
+                }
+                addrs.extend(found);
+            }
+        }
+    }
+    addrs.sort();
+    addrs.dedup();
+    Ok(addrs)
 }
@@ -5,3 +5,11 @@ pub const QR_EC_LEVEL: ECL = ECL::L;
 
 /// The margin size for the SVG QR code on the webpage.
 pub const SVG_QR_MARGIN: usize = 1;
+
+/// Content-Security-Policy sent with --hardened-headers.
+///
+/// The built-in pages rely on inline scripts, styles and `javascript:` links, and embed the QR
+/// code as an image, so those have to be allowed. Everything else is restricted to this origin.
+pub const HARDENED_CSP: &str = "default-src 'self'; script-src 'self' 'unsafe-inline'; \
+    style-src 'self' 'unsafe-inline'; img-src 'self' data:; object-src 'none'; \
+    base-uri 'self'; form-action 'self'; frame-ancestors 'self'";
@@ -0,0 +1,262 @@
+//! Adjustments of the content type and disposition of served files, as configured on the command
+//! line
+use std::collections::HashMap;
+use std::path::Path;
+use std::str::FromStr;
+
+use actix_web::{
+    HttpMessage,
+    body::MessageBody,
+    dev::{ServiceRequest, ServiceResponse},
+    http::header::{
+        self, Charset, ContentDisposition, DispositionParam, DispositionType, ExtendedValue,
+        HeaderValue, TryIntoHeaderValue,
+    },
+    middleware::Next,
+    web,
+};
+use globset::{Glob, GlobMatcher};
+use percent_encoding::percent_decode_str;
+
+use crate::MiniserveConfig;
+
+/// Content types to use instead of the guessed ones, by lowercase file extension without the dot
+pub type MimeMap = HashMap<String, mime::Mime>;
+
+/// Parses a mapping of a file extension to a content type, like '.wasm=application/wasm'
+pub fn parse_mime_mapping(src: &str) -> Result<(String, mime::Mime), String> {
+    let (extension, mime) = src
+        .split_once('=')
+        .ok_or_else(|| format!("Invalid MIME mapping {src:?}, expected '.ext=type/subtype'"))?;
+    let extension = extension.trim().trim_start_matches('.').to_lowercase();
+    if extension.is_empty() {
+        return Err(format!("Missing file extension in MIME mapping {src:?}"));
+    }
+    let mime = mime
+        .trim()
+        .parse()
+        .map_err(|e| format!("Invalid content type in MIME mapping {src:?}: {e}"))?;
+    Ok((extension, mime))
+}
+
+/// Reads MIME mappings from a file, one per line
+///
+/// Empty lines and lines starting with '#' are ignored.
+pub fn read_mime_map_file(path: &Path) -> Result<Vec<(String, mime::Mime)>, String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("Couldn't read MIME mappings from {path:?}: {e}"))?;
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(parse_mime_mapping)
+        .collect()
+}
+
+/// Parses a glob matching paths relative to the served directory
+pub fn parse_glob(src: &str) -> Result<GlobMatcher, String> {
+    Glob::new(src.trim_start_matches('/'))
+        .map(|glob| glob.compile_matcher())
+        .map_err(|e| format!("Invalid glob {src:?}: {e}"))
+}
+
+/// Charset declared for text files, either for all of them or only for those matching a glob
+#[derive(Debug, Clone)]
+pub struct CharsetRule {
+    glob: Option<GlobMatcher>,
+    charset: String,
+}
+
+/// Parses a charset, optionally preceded by a glob, like 'utf-8' or '*.txt=windows-1252'
+pub fn parse_charset_rule(src: &str) -> Result<CharsetRule, String> {
+    let (glob, charset) = match src.rsplit_once('=') {
+        Some((glob, charset)) => (Some(parse_glob(glob)?), charset),
+        None => (None, src),
+    };
+    let valid = !charset.is_empty()
+        && charset
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_.:".contains(c));
+    if !valid {
+        return Err(format!("Invalid charset in charset rule {src:?}"));
+    }
+    Ok(CharsetRule {
+        glob,
+        charset: charset.to_owned(),
+    })
+}
+
+/// Finds the charset to declare for the file at `path`, relative to the served directory
+///
+/// Rules with a glob are tried in order before falling back to the last rule without one.
+fn charset_for<'a>(rules: &'a [CharsetRule], path: &str) -> Option<&'a str> {
+    rules
+        .iter()
+        .find(|rule| rule.glob.as_ref().is_some_and(|glob| glob.is_match(path)))
+        .or_else(|| rules.iter().rev().find(|rule| rule.glob.is_none()))
+        .map(|rule| rule.charset.as_str())
+}
+
+/// Marks requests answered with the content of a file, as opposed to pages generated for it
+#[derive(Clone, Copy)]
+pub struct FileBody;
+
+/// Middleware marking the responses of the services it wraps as file contents
+pub async fn mark_file_body(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let res = next.call(req).await?;
+    res.request().extensions_mut().insert(FileBody);
+    Ok(res)
+}
+
+/// Middleware adjusting the headers of successfully served files according to --mime-map,
+/// --charset, --download and --inline, and serving PDF files inline
+///
+/// Only the contents of files are adjusted, the pages rendered for them at their own path, like
+/// with --render-markdown or --preview, keep their headers.
+pub async fn content_type_middleware(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let mut res = next.call(req).await?;
+    let conf = res
+        .request()
+        .app_data::<web::Data<MiniserveConfig>>()
+        .expect("Could not get miniserve config")
+        .clone();
+
+    // Listings and redirections keep their own content type
+    let path = res.request().path().to_owned();
+    let file_body = res.request().extensions().contains::<FileBody>();
+    if !res.status().is_success() || path.ends_with('/') || !file_body {
+        return Ok(res);
+    }
+
+    let decoded = percent_decode_str(&path).decode_utf8_lossy();
+    let extension = Path::new(&*decoded)
+        .extension()
+        .map(|ext| ext.to_string_lossy().to_lowercase());
+    if let Some(mime) = extension.and_then(|ext| conf.mime_map.get().get(&ext).cloned())
+        && let Ok(value) = HeaderValue::from_str(mime.as_ref())
+    {
+        res.headers_mut().insert(header::CONTENT_TYPE, value);
+    }
+
+    let relative_path = decoded
+        .strip_prefix(&conf.route_prefix)
+        .unwrap_or(&decoded)
+        .trim_start_matches('/');
+    let text_mime = res
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| mime::Mime::from_str(value).ok())
+        .filter(|mime| mime.type_() == mime::TEXT);
+    if let Some(mime) = text_mime
+        && let Some(charset) = charset_for(&conf.charsets, relative_path)
+        && let Ok(value) =
+            HeaderValue::from_str(&format!("{}; charset={charset}", mime.essence_str()))
+    {
+        res.headers_mut().insert(header::CONTENT_TYPE, value);
+    }
+
+    let disposition = if conf
+        .download_globs
+        .iter()
+        .any(|g| g.is_match(relative_path))
+    {
+        Some(DispositionType::Attachment)
+    } else if conf.inline_globs.iter().any(|g| g.is_match(relative_path)) {
+        Some(DispositionType::Inline)
+    } else if conf.pdf_viewer
+        && res
+            .headers()
+            .get(header::CONTENT_TYPE)
+            .is_some_and(|value| value.as_bytes().starts_with(b"application/pdf"))
+    {
+        // Shown by the viewer of the browser rather than downloaded
+        Some(DispositionType::Inline)
+    } else {
+        None
+    };
+    if let Some(disposition) = disposition {
+        // Keep the file name that was already announced, if any
+        let parameters = res
+            .headers()
+            .get(header::CONTENT_DISPOSITION)
+            .and_then(|value| ContentDisposition::from_raw(value).ok())
+            .map(|existing| existing.parameters)
+            .unwrap_or_else(|| filename_parameters(relative_path));
+        let content_disposition = ContentDisposition {
+            disposition,
+            parameters,
+        };
+        if let Ok(value) = content_disposition.try_into_value() {
+            res.headers_mut().insert(header::CONTENT_DISPOSITION, value);
+        }
+    }
+
+    Ok(res)
+}
+
+/// Content-Disposition parameters announcing the name of the file at `path`
+fn filename_parameters(path: &str) -> Vec<DispositionParam> {
+    let filename = path.rsplit('/').next().unwrap_or(path);
+    let mut parameters = vec![DispositionParam::Filename(
+        filename.replace(['\r', '\n'], "_"),
+    )];
+    if !filename.is_ascii() {
+        parameters.push(DispositionParam::FilenameExt(ExtendedValue {
+            charset: Charset::Ext("UTF-8".to_owned()),
+            language_tag: None,
+            value: filename.as_bytes().to_vec(),
+        }));
+    }
+    parameters
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(".wasm=application/wasm", "wasm", "application/wasm")]
+    #[case("MJS = text/javascript", "mjs", "text/javascript")]
+    fn parse_mime_mapping_valid(#[case] src: &str, #[case] extension: &str, #[case] mime: &str) {
+        assert_eq!(
+            parse_mime_mapping(src).unwrap(),
+            (extension.to_owned(), mime.parse().unwrap())
+        );
+    }
+
+    #[rstest]
+    #[case("docs/old.txt", Some("windows-1252"))]
+    #[case("notes.txt", Some("windows-1252"))]
+    #[case("index.html", Some("utf-8"))]
+    fn charset_rules_with_globs_first(#[case] path: &str, #[case] charset: Option<&str>) {
+        let rules = [
+            parse_charset_rule("utf-8").unwrap(),
+            parse_charset_rule("*.txt=windows-1252").unwrap(),
+        ];
+        assert_eq!(charset_for(&rules, path), charset);
+    }
+
+    #[rstest]
+    #[case("utf 8")]
+    #[case("*.txt=")]
+    #[case("[=utf-8")]
+    fn parse_charset_rule_invalid(#[case] src: &str) {
+        assert!(parse_charset_rule(src).is_err());
+    }
+
+    #[rstest]
+    #[case(".wasm")]
+    #[case("=application/wasm")]
+    #[case(".wasm=not a type")]
+    fn parse_mime_mapping_invalid(#[case] src: &str) {
+        assert!(parse_mime_mapping(src).is_err());
+    }
+}
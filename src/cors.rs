@@ -0,0 +1,103 @@
+//! Cross-origin resource sharing, so web apps hosted elsewhere can fetch files and listings
+//!
+//! Requests from origins that aren't allowed are served as usual, just without the headers that
+//! would let browsers hand the response over to the other origin.
+use actix_web::{
+    HttpResponse,
+    body::{EitherBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    http::{
+        Method,
+        header::{self, HeaderValue},
+    },
+    middleware::Next,
+    web,
+};
+
+use crate::MiniserveConfig;
+
+/// Methods browsers may use cross-origin, as announced in preflight responses
+const ALLOWED_METHODS: &str = "GET, HEAD, POST, OPTIONS";
+
+/// How long browsers may cache the result of a preflight request, in seconds
+const PREFLIGHT_MAX_AGE: &str = "86400";
+
+/// Middleware adding CORS headers for the origins given with --cors and answering preflights
+pub async fn cors_middleware(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, actix_web::Error> {
+    let conf = req
+        .app_data::<web::Data<MiniserveConfig>>()
+        .expect("Could not get miniserve config")
+        .clone();
+    let allow_origin = req
+        .headers()
+        .get(header::ORIGIN)
+        .and_then(|origin| allowed_origin(&conf.cors_origins, origin));
+    // Responses differ between origins unless all of them are allowed, so caches must tell them
+    // apart even when this one came without an allowed origin
+    let varies = !conf.cors_origins.iter().any(|o| o == "*");
+    // Browsers only send credentials cross-origin when told so, which can't be done for any origin
+    let allow_credentials = varies && conf.auth_enabled();
+
+    let Some(allow_origin) = allow_origin else {
+        let mut res = next.call(req).await?;
+        if varies {
+            res.headers_mut()
+                .append(header::VARY, HeaderValue::from_static("Origin"));
+        }
+        return Ok(res.map_into_left_body());
+    };
+
+    let is_preflight = req.method() == Method::OPTIONS
+        && req
+            .headers()
+            .contains_key(header::ACCESS_CONTROL_REQUEST_METHOD);
+    if is_preflight {
+        let mut resp = HttpResponse::NoContent();
+        resp.insert_header((header::ACCESS_CONTROL_ALLOW_ORIGIN, allow_origin))
+            .insert_header((header::ACCESS_CONTROL_ALLOW_METHODS, ALLOWED_METHODS))
+            .insert_header((header::ACCESS_CONTROL_MAX_AGE, PREFLIGHT_MAX_AGE))
+            .insert_header((header::VARY, "Origin"));
+        if let Some(headers) = req
+            .headers()
+            .get(header::ACCESS_CONTROL_REQUEST_HEADERS)
+            .cloned()
+        {
+            resp.insert_header((header::ACCESS_CONTROL_ALLOW_HEADERS, headers));
+        }
+        if allow_credentials {
+            resp.insert_header((header::ACCESS_CONTROL_ALLOW_CREDENTIALS, "true"));
+        }
+        return Ok(req.into_response(resp.finish()).map_into_right_body());
+    }
+
+    let mut res = next.call(req).await?;
+    let headers = res.headers_mut();
+    headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, allow_origin);
+    if allow_credentials {
+        headers.insert(
+            header::ACCESS_CONTROL_ALLOW_CREDENTIALS,
+            HeaderValue::from_static("true"),
+        );
+    }
+    if varies {
+        headers.append(header::VARY, HeaderValue::from_static("Origin"));
+    }
+    Ok(res.map_into_left_body())
+}
+
+/// Returns the value of the `Access-Control-Allow-Origin` header for a request from `origin`, if
+/// that origin is allowed
+fn allowed_origin(allowed: &[String], origin: &HeaderValue) -> Option<HeaderValue> {
+    if allowed.iter().any(|o| o == "*") {
+        Some(HeaderValue::from_static("*"))
+    } else {
+        let origin_str = origin.to_str().ok()?;
+        allowed
+            .iter()
+            .any(|o| o.eq_ignore_ascii_case(origin_str))
+            .then(|| origin.clone())
+    }
+}
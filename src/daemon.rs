@@ -0,0 +1,63 @@
+//! Running in the background, detached from the terminal
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::os::fd::{AsRawFd, RawFd};
+use std::path::Path;
+
+/// Forks into the background, the parent process exiting as soon as the child is created
+///
+/// The child gets a session of its own, so that it isn't affected by the terminal it was started
+/// from going away. Its standard input is read from /dev/null, while standard output and error
+/// are appended to `log_file`, or discarded if there is none. The parent writes the ID of the
+/// child to `pid_file`, so that it's there by the time the parent exits.
+///
+/// Forking only keeps the calling thread, so this must be called before any other is started.
+pub fn daemonize(log_file: Option<&Path>, pid_file: Option<&Path>) -> io::Result<()> {
+    let stdin = File::open("/dev/null")?;
+    let output = match log_file {
+        Some(path) => OpenOptions::new().append(true).create(true).open(path)?,
+        None => OpenOptions::new().write(true).open("/dev/null")?,
+    };
+    let mut pid_file = pid_file.map(File::create).transpose()?;
+    io::stdout().flush()?;
+
+    // SAFETY: the process is single threaded at this point
+    match unsafe { libc::fork() } {
+        -1 => return Err(io::Error::last_os_error()),
+        0 => {}
+        child => {
+            let mut status = 0;
+            if let Some(Err(e)) = pid_file.as_mut().map(|file| writeln!(file, "{child}")) {
+                log::error!("Failed to write the process ID: {e}");
+                // SAFETY: the child was just created by us
+                unsafe { libc::kill(child, libc::SIGTERM) };
+                status = 1;
+            }
+            // Everything else belongs to the child now, so leave without running any cleanup
+            // SAFETY: exiting doesn't touch any state shared with the child
+            unsafe { libc::_exit(status) };
+        }
+    }
+
+    // SAFETY: the child of a fork never is a process group leader
+    if unsafe { libc::setsid() } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    redirect(&stdin, libc::STDIN_FILENO)?;
+    redirect(&output, libc::STDOUT_FILENO)?;
+    redirect(&output, libc::STDERR_FILENO)
+}
+
+/// Writes the ID of the current process to `path`
+pub fn write_pid_file(path: &Path) -> io::Result<()> {
+    writeln!(File::create(path)?, "{}", std::process::id())
+}
+
+/// Makes `fd` refer to the same file as `file`
+fn redirect(file: &File, fd: RawFd) -> io::Result<()> {
+    // SAFETY: both descriptors are open for the whole call
+    if unsafe { libc::dup2(file.as_raw_fd(), fd) } == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
@@ -0,0 +1,70 @@
+//! Handing URLs over to the desktop of the user running miniserve
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+
+/// Opens `url` in the browser named by $BROWSER, or in the default browser otherwise
+pub fn open_browser(url: &str) -> io::Result<()> {
+    let mut command = match std::env::var_os("BROWSER").filter(|browser| !browser.is_empty()) {
+        Some(browser) => Command::new(browser),
+        None if cfg!(windows) => {
+            let mut command = Command::new("cmd");
+            // The first quoted argument of start is the title of the window
+            command.args(["/C", "start", ""]);
+            command
+        }
+        None if cfg!(target_os = "macos") => Command::new("open"),
+        None => Command::new("xdg-open"),
+    };
+    let mut child = command
+        .arg(url)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    // Some browsers keep running in the foreground, which mustn't hold us up
+    std::thread::spawn(move || child.wait());
+    Ok(())
+}
+
+/// Places `text` on the clipboard, using whichever clipboard tool the system has
+pub fn copy_to_clipboard(text: &str) -> io::Result<()> {
+    let tools: &[&[&str]] = if cfg!(windows) {
+        &[&["clip"]]
+    } else if cfg!(target_os = "macos") {
+        &[&["pbcopy"]]
+    } else {
+        &[
+            &["wl-copy"],
+            &["xclip", "-selection", "clipboard"],
+            &["xsel", "--clipboard", "--input"],
+        ]
+    };
+    for tool in tools {
+        match Command::new(tool[0])
+            .args(&tool[1..])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+        {
+            Ok(mut child) => {
+                child
+                    .stdin
+                    .take()
+                    .expect("stdin is piped")
+                    .write_all(text.as_bytes())?;
+                // X11 tools keep running until something else is copied
+                std::thread::spawn(move || child.wait());
+                return Ok(());
+            }
+            // Only some of them are installed, e.g. wl-copy on Wayland only
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    let names = tools.iter().map(|tool| tool[0]).collect::<Vec<_>>();
+    Err(io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("None of {} is installed", names.join(", ")),
+    ))
+}
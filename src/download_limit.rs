@@ -0,0 +1,104 @@
+//! Stopping the server once files have been downloaded a given number of times
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::task::{Context, Poll};
+
+use actix_web::{
+    body::{BodySize, BoxBody, EitherBody, MessageBody},
+    dev::ServiceResponse,
+    http::{Method, header},
+    middleware::Next,
+    web::{self, Bytes},
+};
+use tokio::sync::Notify;
+
+use crate::MiniserveConfig;
+
+/// Number of downloads left before the server stops
+pub struct DownloadLimit {
+    remaining: AtomicU64,
+    reached: Notify,
+}
+
+impl DownloadLimit {
+    pub fn new(max_downloads: u64) -> Self {
+        Self {
+            remaining: AtomicU64::new(max_downloads),
+            reached: Notify::new(),
+        }
+    }
+
+    /// Waits until the last download allowed has completed
+    pub async fn reached(&self) {
+        self.reached.notified().await
+    }
+
+    fn complete_download(&self) {
+        let previous = self
+            .remaining
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1));
+        if previous == Ok(1) {
+            self.reached.notify_one();
+        }
+    }
+}
+
+/// Middleware counting complete downloads of files against --max-downloads
+///
+/// Only files, which are sent with a Content-Disposition header unlike listings, count once their
+/// whole content has been sent. Interrupted and partial transfers don't.
+pub async fn count_downloads(
+    req: actix_web::dev::ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody, impl MessageBody>>, actix_web::Error> {
+    let limited = req
+        .app_data::<web::Data<MiniserveConfig>>()
+        .expect("Could not get miniserve config")
+        .max_downloads
+        .is_some();
+    let res = next.call(req).await?;
+    if !limited {
+        return Ok(res.map_into_left_body());
+    }
+    let limit = res
+        .request()
+        .app_data::<web::Data<DownloadLimit>>()
+        .cloned();
+
+    let is_download = res.request().method() == Method::GET
+        && res.status() == actix_web::http::StatusCode::OK
+        && res.headers().contains_key(header::CONTENT_DISPOSITION);
+    Ok(res
+        .map_body(|_, body| CountedBody {
+            body: body.boxed(),
+            limit: limit.filter(|_| is_download),
+        })
+        .map_into_right_body())
+}
+
+/// Body reporting to `limit` once it has been sent in full
+struct CountedBody {
+    body: BoxBody,
+    limit: Option<web::Data<DownloadLimit>>,
+}
+
+impl MessageBody for CountedBody {
+    type Error = <BoxBody as MessageBody>::Error;
+
+    fn size(&self) -> BodySize {
+        self.body.size()
+    }
+
+    fn poll_next(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Bytes, Self::Error>>> {
+        let poll = Pin::new(&mut self.body).poll_next(cx);
+        if let Poll::Ready(None) = poll
+            && let Some(limit) = self.limit.take()
+        {
+            limit.complete_download();
+        }
+        poll
+    }
+}
@@ -4,13 +4,17 @@ use actix_web::{
     HttpRequest, HttpResponse, ResponseError,
     body::{BoxBody, MessageBody},
     dev::{ResponseHead, ServiceRequest, ServiceResponse},
-    http::{StatusCode, header},
+    http::{
+        StatusCode,
+        header::{self, Accept, Header, Quality},
+    },
     middleware::Next,
     web,
 };
+use serde::Serialize;
 use thiserror::Error;
 
-use crate::{MiniserveConfig, renderer::render_error};
+use crate::{MiniserveConfig, i18n, renderer::render_error};
 
 #[derive(Debug, Error)]
 pub enum StartupError {
@@ -29,6 +33,15 @@ Please set an explicit serve path like: `miniserve /my/path`")]
 
     #[error("The --enable-webdav option was provided, but the serve path '{0}' is a file")]
     WebdavWithFileServePath(String),
+
+    /// In case --sandbox was given but file system access couldn't be restricted
+    #[cfg(target_os = "linux")]
+    #[error("Failed to restrict file system access\ncaused by: {0}")]
+    SandboxError(String),
+
+    /// In case miniserve was invoked with --spa but the index file doesn't exist
+    #[error("The --spa option was provided, but the index file '{0}' could not be found")]
+    SpaIndexNotFound(String),
 }
 
 #[derive(Debug, Error)]
@@ -49,10 +62,22 @@ pub enum RuntimeError {
     #[error("File hash that was provided did not match checksum of uploaded file")]
     UploadHashMismatchError,
 
+    /// Might occur with --receive, once a file has been received
+    #[error("A file has already been received")]
+    AlreadyReceivedError,
+
     /// Upload not allowed
     #[error("Upload not allowed to this directory")]
     UploadForbiddenError,
 
+    /// Might occur during file upload, while uploads are paused from the admin page
+    #[error("Uploads are paused")]
+    UploadsPausedError,
+
+    /// Might occur when someone else than the user given with --admin asks for the admin page
+    #[error("Only the administrator may see this page")]
+    AdminForbiddenError,
+
     /// Any error related to an invalid path (failed to retrieve entry name, unexpected entry type, etc)
     #[error("Invalid path\ncaused by: {0}")]
     InvalidPathError(String),
@@ -86,6 +111,35 @@ pub enum RuntimeError {
     RouteNotFoundError(String),
 }
 
+/// Header telling clients which error a response is about, with the code of `RuntimeError`
+const ERROR_CODE_HEADER: &str = "X-Miniserve-Error";
+
+impl RuntimeError {
+    /// Stable code telling the kind of error, for logs and clients to tell errors apart without
+    /// parsing their message
+    pub fn code(&self) -> &'static str {
+        use RuntimeError as E;
+        match self {
+            E::IoError(_, _) => "io_error",
+            E::MultipartError(_) => "invalid_multipart",
+            E::DuplicateFileError => "file_exists",
+            E::UploadHashMismatchError => "upload_hash_mismatch",
+            E::AlreadyReceivedError => "already_received",
+            E::UploadForbiddenError => "upload_forbidden",
+            E::UploadsPausedError => "uploads_paused",
+            E::AdminForbiddenError => "admin_forbidden",
+            E::InvalidPathError(_) => "invalid_path",
+            E::InsufficientPermissionsError(_) => "insufficient_permissions",
+            E::ParseError(_, _) => "parse_error",
+            E::ArchiveCreationError(_, _) => "archive_creation_failed",
+            E::ArchiveCreationDetailError(_) => "archive_creation_failed",
+            E::InvalidHttpCredentials => "invalid_credentials",
+            E::InvalidHttpRequestError(_) => "invalid_request",
+            E::RouteNotFoundError(_) => "not_found",
+        }
+    }
+}
+
 impl ResponseError for RuntimeError {
     fn status_code(&self) -> StatusCode {
         use RuntimeError as E;
@@ -95,7 +149,10 @@ impl ResponseError for RuntimeError {
             E::UploadHashMismatchError => S::BAD_REQUEST,
             E::MultipartError(_) => S::BAD_REQUEST,
             E::DuplicateFileError => S::CONFLICT,
+            E::AlreadyReceivedError => S::CONFLICT,
             E::UploadForbiddenError => S::FORBIDDEN,
+            E::UploadsPausedError => S::SERVICE_UNAVAILABLE,
+            E::AdminForbiddenError => S::FORBIDDEN,
             E::InvalidPathError(_) => S::BAD_REQUEST,
             E::InsufficientPermissionsError(_) => S::FORBIDDEN,
             E::ParseError(_, _) => S::BAD_REQUEST,
@@ -108,9 +165,10 @@ impl ResponseError for RuntimeError {
     }
 
     fn error_response(&self) -> HttpResponse {
-        log_error_chain(self.to_string());
+        log_error_chain(format!("[{}] {self}", self.code()));
 
         let mut resp = HttpResponse::build(self.status_code());
+        resp.insert_header((ERROR_CODE_HEADER, self.code()));
         if let Self::InvalidHttpCredentials = self {
             resp.append_header((
                 header::WWW_AUTHENTICATE,
@@ -124,14 +182,16 @@ impl ResponseError for RuntimeError {
 }
 
 /// Middleware to convert plain-text error responses to user-friendly web pages
+///
+/// Clients of the API, and those asking for JSON, get JSON errors instead, which scripts can make
+/// sense of.
 pub async fn error_page_middleware(
     req: ServiceRequest,
     next: Next<impl MessageBody + 'static>,
 ) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
     let res = next.call(req).await?.map_into_boxed_body();
 
-    if (res.status().is_client_error() || res.status().is_server_error())
-        && res.request().path() != "/upload"
+    let is_plain_error = (res.status().is_client_error() || res.status().is_server_error())
         && res
             .headers()
             .get(header::CONTENT_TYPE)
@@ -140,8 +200,12 @@ pub async fn error_page_middleware(
             .and_then(|s| mime::Mime::from_str(s).ok())
             .as_ref()
             .map(mime::Mime::essence_str)
-            == Some(mime::TEXT_PLAIN.as_ref())
-    {
+            == Some(mime::TEXT_PLAIN.as_ref());
+
+    if is_plain_error && wants_json(res.request()) {
+        let req = res.request().clone();
+        Ok(res.map_body(|head, body| map_json_error(&req, head, body)))
+    } else if is_plain_error && res.request().path() != "/upload" {
         let req = res.request().clone();
         Ok(res.map_body(|head, body| map_error_page(&req, head, body)))
     } else {
@@ -149,6 +213,63 @@ pub async fn error_page_middleware(
     }
 }
 
+/// Whether errors should be told as JSON, to clients of the API or of JSON listings, and to those
+/// accepting JSON
+fn wants_json(req: &HttpRequest) -> bool {
+    let conf = req.app_data::<web::Data<MiniserveConfig>>().unwrap();
+    req.path() == conf.api_route
+        || req
+            .query_string()
+            .split('&')
+            .any(|pair| pair == "json=true")
+        || Accept::parse(req).is_ok_and(|accept| {
+            accept
+                .iter()
+                .any(|item| item.item == mime::APPLICATION_JSON && item.quality > Quality::ZERO)
+        })
+}
+
+/// Error as told to clients asking for JSON
+#[derive(Serialize)]
+struct JsonError<'a> {
+    /// HTTP status code
+    code: u16,
+    /// Code of the kind of error, if it's one of `RuntimeError`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<&'a str>,
+    message: &'a str,
+    /// Path of the request which failed
+    path: &'a str,
+}
+
+fn map_json_error(req: &HttpRequest, head: &mut ResponseHead, body: BoxBody) -> BoxBody {
+    let error_msg = match body.try_into_bytes() {
+        Ok(bytes) => bytes,
+        Err(body) => return body,
+    };
+
+    let message = String::from_utf8_lossy(&error_msg);
+    let error = JsonError {
+        code: head.status.as_u16(),
+        error: head
+            .headers
+            .get(ERROR_CODE_HEADER)
+            .and_then(|code| code.to_str().ok()),
+        message: message.trim(),
+        path: req.path(),
+    };
+    match serde_json::to_vec(&error) {
+        Ok(json) => {
+            head.headers.insert(
+                header::CONTENT_TYPE,
+                header::HeaderValue::from_static("application/json"),
+            );
+            BoxBody::new(json)
+        }
+        Err(_) => BoxBody::new(error_msg),
+    }
+}
+
 fn map_error_page(req: &HttpRequest, head: &mut ResponseHead, body: BoxBody) -> BoxBody {
     let error_msg = match body.try_into_bytes() {
         Ok(bytes) => bytes,
@@ -172,7 +293,22 @@ fn map_error_page(req: &HttpRequest, head: &mut ResponseHead, body: BoxBody) ->
         mime::TEXT_HTML_UTF_8.essence_str().try_into().unwrap(),
     );
 
-    BoxBody::new(render_error(error_msg, head.status, conf, return_address).into_string())
+    if head.status == StatusCode::NOT_FOUND
+        && let Some(page) = &conf.error_404
+    {
+        match std::fs::read(page) {
+            Ok(content) => return BoxBody::new(content),
+            Err(err) => log::error!("Failed to read the --error-404 page {page:?}: {err}"),
+        }
+    }
+
+    BoxBody::new(render_error(
+        error_msg,
+        head.status,
+        conf,
+        return_address,
+        i18n::texts(req),
+    ))
 }
 
 pub fn log_error_chain(description: String) {
@@ -0,0 +1,586 @@
+//! EXIF metadata of photos, shown on details pages or stripped with --strip-exif
+//!
+//! Only the parts of TIFF needed to read a few well known fields are implemented, which is enough
+//! for photos from cameras and phones.
+use std::io::Read;
+use std::path::Path;
+
+use actix_web::{
+    HttpResponse,
+    body::{self, EitherBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    http::{Method, StatusCode, header},
+    middleware::Next,
+    web,
+};
+use percent_encoding::{percent_decode_str, utf8_percent_encode};
+use serde::Deserialize;
+
+use crate::{MiniserveConfig, file_utils, i18n, listing::percent_encode_sets::COMPONENT, renderer};
+
+/// The EXIF data of a JPEG file is held within its first segments
+pub const MAX_EXIF_READ: u64 = 128 * 1024;
+
+/// Whether a file is a JPEG image, which is where EXIF data is found, going by its name
+pub fn is_jpeg(file_name: &str) -> bool {
+    Path::new(file_name)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("jpg") || ext.eq_ignore_ascii_case("jpeg"))
+}
+
+/// Whether metadata can be stripped from a file, going by its name
+fn is_strippable(file_name: &str) -> bool {
+    is_jpeg(file_name)
+        || Path::new(file_name)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("png"))
+}
+
+/// The IFDs fields are shown from
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Ifd {
+    Image,
+    Exif,
+    Gps,
+}
+
+/// Fields shown on details pages, along with the IFD they're in
+#[rustfmt::skip]
+const FIELDS: &[(Ifd, u16, &str)] = &[
+    (Ifd::Image, 0x010e, "Description"),
+    (Ifd::Image, 0x010f, "Camera make"),
+    (Ifd::Image, 0x0110, "Camera model"),
+    (Ifd::Exif, 0xa433, "Lens make"),
+    (Ifd::Exif, 0xa434, "Lens model"),
+    (Ifd::Exif, 0x9003, "Taken"),
+    (Ifd::Image, 0x0132, "Modified"),
+    (Ifd::Exif, 0xa002, "Width"),
+    (Ifd::Exif, 0xa003, "Height"),
+    (Ifd::Image, 0x0112, "Orientation"),
+    (Ifd::Exif, 0x829a, "Exposure time"),
+    (Ifd::Exif, 0x829d, "F-number"),
+    (Ifd::Exif, 0x8827, "ISO"),
+    (Ifd::Exif, 0x920a, "Focal length"),
+    (Ifd::Exif, 0x9209, "Flash"),
+    (Ifd::Image, 0x0131, "Software"),
+    (Ifd::Image, 0x013b, "Artist"),
+    (Ifd::Image, 0x8298, "Copyright"),
+    (Ifd::Gps, 0x0001, "Latitude reference"),
+    (Ifd::Gps, 0x0002, "Latitude"),
+    (Ifd::Gps, 0x0003, "Longitude reference"),
+    (Ifd::Gps, 0x0004, "Longitude"),
+    (Ifd::Gps, 0x0006, "Altitude"),
+];
+
+/// TIFF data, which EXIF data is laid out as
+struct Tiff<'a> {
+    data: &'a [u8],
+    big_endian: bool,
+}
+
+/// An entry of an IFD
+struct Entry {
+    tag: u16,
+    kind: u16,
+    count: usize,
+    /// Where its value, or the offset of its value if that doesn't fit, is
+    value_offset: usize,
+}
+
+impl<'a> Tiff<'a> {
+    fn new(data: &'a [u8]) -> Option<Self> {
+        let big_endian = match data.get(..2)? {
+            b"MM" => true,
+            b"II" => false,
+            _ => return None,
+        };
+        Some(Self { data, big_endian })
+    }
+
+    fn u16_at(&self, offset: usize) -> Option<u16> {
+        let bytes = self.data.get(offset..offset + 2)?.try_into().ok()?;
+        Some(if self.big_endian {
+            u16::from_be_bytes(bytes)
+        } else {
+            u16::from_le_bytes(bytes)
+        })
+    }
+
+    fn u32_at(&self, offset: usize) -> Option<usize> {
+        let bytes = self.data.get(offset..offset + 4)?.try_into().ok()?;
+        Some(if self.big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        } as usize)
+    }
+
+    /// The offset of the first IFD, describing the image
+    fn first_ifd(&self) -> Option<usize> {
+        self.u32_at(4)
+    }
+
+    /// The offset of the IFD following the one at `ifd`, if there's one
+    fn next_ifd(&self, ifd: usize) -> Option<usize> {
+        let next = self.u32_at(ifd + 2 + 12 * self.u16_at(ifd)? as usize)?;
+        (next != 0).then_some(next)
+    }
+
+    fn entries(&self, ifd: usize) -> impl Iterator<Item = Entry> + '_ {
+        let count = self.u16_at(ifd).unwrap_or(0) as usize;
+        (0..count).map_while(move |i| {
+            let entry = ifd + 2 + 12 * i;
+            Some(Entry {
+                tag: self.u16_at(entry)?,
+                kind: self.u16_at(entry + 2)?,
+                count: self.u32_at(entry + 4)?,
+                value_offset: entry + 8,
+            })
+        })
+    }
+
+    /// The value of an entry pointing to another IFD or data
+    fn offset(&self, ifd: usize, tag: u16) -> Option<usize> {
+        let entry = self.entries(ifd).find(|entry| entry.tag == tag)?;
+        self.u32_at(entry.value_offset)
+    }
+
+    /// The raw bytes of the value of an entry
+    fn value(&self, entry: &Entry) -> Option<&'a [u8]> {
+        let size = match entry.kind {
+            1 | 2 | 6 | 7 => 1,
+            3 | 8 => 2,
+            4 | 9 => 4,
+            5 | 10 => 8,
+            _ => return None,
+        } * entry.count;
+        let start = if size <= 4 {
+            entry.value_offset
+        } else {
+            self.u32_at(entry.value_offset)?
+        };
+        self.data.get(start..start.checked_add(size)?)
+    }
+
+    /// The value of an entry, formatted to be shown
+    fn display(&self, entry: &Entry) -> Option<String> {
+        let value = self.value(entry)?;
+        let tiff = Tiff {
+            data: value,
+            big_endian: self.big_endian,
+        };
+        let numbers: Vec<String> = match entry.kind {
+            2 => {
+                let text = String::from_utf8_lossy(value);
+                let text = text.trim_end_matches('\0').trim();
+                return (!text.is_empty()).then(|| text.to_string());
+            }
+            3 => (0..entry.count)
+                .filter_map(|i| tiff.u16_at(2 * i).map(|n| n.to_string()))
+                .collect(),
+            4 => (0..entry.count)
+                .filter_map(|i| tiff.u32_at(4 * i).map(|n| n.to_string()))
+                .collect(),
+            5 | 10 => (0..entry.count)
+                .filter_map(|i| {
+                    let (numerator, denominator) = (tiff.u32_at(8 * i)?, tiff.u32_at(8 * i + 4)?);
+                    if entry.kind == 10 {
+                        rational(
+                            numerator as u32 as i32 as i64,
+                            denominator as u32 as i32 as i64,
+                        )
+                    } else {
+                        rational(numerator as i64, denominator as i64)
+                    }
+                })
+                .collect(),
+            _ => return None,
+        };
+        (!numbers.is_empty()).then(|| numbers.join(", "))
+    }
+}
+
+/// Formats a fraction, keeping those of 1 like exposure times as they are
+fn rational(numerator: i64, denominator: i64) -> Option<String> {
+    if denominator == 0 {
+        return None;
+    }
+    Some(if numerator % denominator == 0 {
+        (numerator / denominator).to_string()
+    } else if numerator == 1 {
+        format!("1/{denominator}")
+    } else {
+        let decimal = format!("{:.4}", numerator as f64 / denominator as f64);
+        decimal.trim_end_matches('0').to_string()
+    })
+}
+
+/// Finds the TIFF data of the EXIF segment of the JPEG data starting with `jpeg`
+fn exif_data(jpeg: &[u8]) -> Option<&[u8]> {
+    if !jpeg.starts_with(&[0xff, 0xd8]) {
+        return None;
+    }
+    let mut segments = &jpeg[2..];
+    loop {
+        let [0xff, marker, len_high, len_low, ..] = *segments else {
+            return None;
+        };
+        // Image data follows the start of scan, there's no metadata after it
+        if marker == 0xda {
+            return None;
+        }
+        let len = u16::from_be_bytes([len_high, len_low]) as usize;
+        let data = segments.get(4..2 + len)?;
+        if marker == 0xe1
+            && let Some(tiff) = data.strip_prefix(b"Exif\0\0")
+        {
+            return Some(tiff);
+        }
+        segments = &segments[2 + len..];
+    }
+}
+
+/// Finds the thumbnail in the EXIF data of the JPEG data starting with `jpeg`
+///
+/// It's described by the second IFD, the one the IFD of the image links to.
+pub fn thumbnail(jpeg: &[u8]) -> Option<&[u8]> {
+    let tiff = Tiff::new(exif_data(jpeg)?)?;
+    let ifd1 = tiff.next_ifd(tiff.first_ifd()?)?;
+    let (mut offset, mut len) = (None, None);
+    for entry in tiff.entries(ifd1) {
+        match entry.tag {
+            0x0201 => offset = tiff.u32_at(entry.value_offset),
+            0x0202 => len = tiff.u32_at(entry.value_offset),
+            _ => {}
+        }
+    }
+    let thumbnail = tiff.data.get(offset?..offset?.checked_add(len?)?)?;
+    thumbnail.starts_with(&[0xff, 0xd8]).then_some(thumbnail)
+}
+
+/// Reads the orientation of the photo in the JPEG data starting with `jpeg`, if it's set
+pub fn orientation(jpeg: &[u8]) -> Option<u16> {
+    let tiff = Tiff::new(exif_data(jpeg)?)?;
+    let entry = tiff
+        .entries(tiff.first_ifd()?)
+        .find(|entry| entry.tag == 0x0112 && entry.kind == 3)?;
+    tiff.u16_at(entry.value_offset)
+}
+
+/// Reads the fields of the EXIF data of the JPEG data starting with `jpeg` shown on details pages
+pub fn fields(jpeg: &[u8]) -> Vec<(&'static str, String)> {
+    let Some(tiff) = exif_data(jpeg).and_then(Tiff::new) else {
+        return Vec::new();
+    };
+    let Some(image) = tiff.first_ifd() else {
+        return Vec::new();
+    };
+    let ifds = [
+        Some((Ifd::Image, image)),
+        tiff.offset(image, 0x8769).map(|ifd| (Ifd::Exif, ifd)),
+        tiff.offset(image, 0x8825).map(|ifd| (Ifd::Gps, ifd)),
+    ];
+    let mut values: Vec<(Ifd, u16, String)> = Vec::new();
+    for (kind, ifd) in ifds.into_iter().flatten() {
+        for entry in tiff.entries(ifd) {
+            if let Some(value) = tiff.display(&entry) {
+                values.push((kind, entry.tag, value));
+            }
+        }
+    }
+    FIELDS
+        .iter()
+        .filter_map(|&(ifd, tag, name)| {
+            let (.., value) = values.iter().find(|(i, t, _)| *i == ifd && *t == tag)?;
+            Some((name, value.clone()))
+        })
+        .collect()
+}
+
+/// Removes the metadata of a JPEG or PNG image: EXIF, XMP and IPTC data, and text chunks
+///
+/// Data that isn't a JPEG or PNG image is returned as it is, but `None` is returned for images
+/// that can't be read, which can't be told free of metadata.
+pub fn strip(data: &[u8]) -> Option<Vec<u8>> {
+    const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+    if data.starts_with(&[0xff, 0xd8]) {
+        let mut stripped = data[..2].to_vec();
+        let mut segments = &data[2..];
+        loop {
+            let [0xff, marker, ..] = *segments else {
+                return None;
+            };
+            // Markers can be padded with fill bytes
+            if marker == 0xff {
+                segments = &segments[1..];
+                continue;
+            }
+            // Markers of restarts stand alone
+            if marker == 0x01 || (0xd0..=0xd7).contains(&marker) {
+                stripped.extend_from_slice(&segments[..2]);
+                segments = &segments[2..];
+                continue;
+            }
+            if marker == 0xda || marker == 0xd9 {
+                stripped.extend_from_slice(segments);
+                return Some(stripped);
+            }
+            let len = u16::from_be_bytes([*segments.get(2)?, *segments.get(3)?]) as usize;
+            let segment = segments.get(..2 + len)?;
+            // APP1 holds EXIF and XMP data, APP13 IPTC data
+            if marker != 0xe1 && marker != 0xed {
+                stripped.extend_from_slice(segment);
+            }
+            segments = &segments[2 + len..];
+        }
+    } else if data.starts_with(PNG_SIGNATURE) {
+        let mut stripped = PNG_SIGNATURE.to_vec();
+        let mut chunks = &data[PNG_SIGNATURE.len()..];
+        while !chunks.is_empty() {
+            let len = u32::from_be_bytes(chunks.get(..4)?.try_into().ok()?) as usize;
+            let chunk = chunks.get(..len.checked_add(12)?)?;
+            if !matches!(&chunk[4..8], b"eXIf" | b"tEXt" | b"iTXt" | b"zTXt") {
+                stripped.extend_from_slice(chunk);
+            }
+            chunks = &chunks[chunk.len()..];
+        }
+        Some(stripped)
+    } else {
+        Some(data.to_vec())
+    }
+}
+
+/// Middleware removing the metadata of the JPEG and PNG images served, with --strip-exif
+///
+/// Images are sent whole rather than in ranges, and without content encoding, as they have to be
+/// read to be stripped. Images whose metadata can't be told apart aren't sent at all.
+pub async fn strip_exif_middleware(
+    mut req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, actix_web::Error> {
+    let strip_exif = req
+        .app_data::<web::Data<MiniserveConfig>>()
+        .expect("Could not get miniserve config")
+        .strip_exif;
+    let path = percent_decode_str(req.path())
+        .decode_utf8_lossy()
+        .into_owned();
+    if !strip_exif || req.method() != Method::GET || !is_strippable(&path) {
+        return Ok(next.call(req).await?.map_into_left_body());
+    }
+
+    let headers = req.headers_mut();
+    headers.remove(header::RANGE);
+    headers.remove(header::IF_RANGE);
+    headers.remove(header::ACCEPT_ENCODING);
+    let res = next.call(req).await?;
+    if res.status() != StatusCode::OK {
+        return Ok(res.map_into_left_body());
+    }
+
+    let (req, res) = res.into_parts();
+    let (mut res, body) = res.into_parts();
+    let data = body::to_bytes(body)
+        .await
+        .map_err(|e| actix_web::error::ErrorInternalServerError(e.into().to_string()))?;
+    let Some(stripped) = strip(&data) else {
+        log::error!("Couldn't strip the metadata of {path}");
+        let res = HttpResponse::InternalServerError().finish();
+        return Ok(ServiceResponse::new(req, res).map_into_right_body());
+    };
+    let headers = res.headers_mut();
+    for name in [
+        header::CONTENT_LENGTH,
+        header::ETAG,
+        header::ACCEPT_RANGES,
+        header::CONTENT_ENCODING,
+    ] {
+        headers.remove(name);
+    }
+    let res = res.set_body(stripped).map_into_boxed_body();
+    Ok(ServiceResponse::new(req, res).map_into_right_body())
+}
+
+#[derive(Deserialize)]
+struct DetailsQuery {
+    exif: Option<bool>,
+}
+
+/// Middleware answering requests for JPEG images with '?exif=true' with a page of their details
+pub async fn details_middleware(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, actix_web::Error> {
+    let conf = req
+        .app_data::<web::Data<MiniserveConfig>>()
+        .expect("Could not get miniserve config")
+        .clone();
+
+    let details = web::Query::<DetailsQuery>::from_query(req.query_string())
+        .is_ok_and(|query| query.exif == Some(true));
+    let file = (conf.exif_details && req.method() == Method::GET && details)
+        .then(|| file_utils::requested_file(&conf, req.match_info().unprocessed()))
+        .flatten();
+    let file_name = file
+        .as_ref()
+        .and_then(|file| Some(file.file_name()?.to_string_lossy().into_owned()))
+        .filter(|name| is_jpeg(name));
+    let (Some(file), Some(file_name)) = (file, file_name) else {
+        return Ok(next.call(req).await?.map_into_left_body());
+    };
+
+    let fields = web::block(move || {
+        let mut head = Vec::new();
+        std::fs::File::open(&file)?
+            .take(MAX_EXIF_READ)
+            .read_to_end(&mut head)?;
+        std::io::Result::Ok(fields(&head))
+    })
+    .await??;
+    let link = utf8_percent_encode(&file_name, COMPONENT).to_string();
+    let res = HttpResponse::Ok().content_type(mime::TEXT_HTML_UTF_8).body(
+        renderer::exif(
+            &file_name,
+            &link,
+            &fields,
+            &conf,
+            i18n::texts(req.request()),
+        )
+        .into_string(),
+    );
+    Ok(req.into_response(res).map_into_right_body())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    /// Appends an IFD entry holding a value that fits in it to little-endian TIFF data
+    fn entry(tiff: &mut Vec<u8>, tag: u16, kind: u16, count: u32, value: u32) {
+        tiff.extend(tag.to_le_bytes());
+        tiff.extend(kind.to_le_bytes());
+        tiff.extend(count.to_le_bytes());
+        tiff.extend(value.to_le_bytes());
+    }
+
+    /// Wraps TIFF data in the EXIF segment of a JPEG file
+    fn jpeg_with_exif(tiff: &[u8]) -> Vec<u8> {
+        let mut jpeg = vec![0xff, 0xd8];
+        // An unrelated segment first
+        jpeg.extend([0xff, 0xe0, 0x00, 0x04, 0x00, 0x00]);
+        jpeg.extend([0xff, 0xe1]);
+        jpeg.extend(((2 + 6 + tiff.len()) as u16).to_be_bytes());
+        jpeg.extend(b"Exif\0\0");
+        jpeg.extend(tiff);
+        jpeg.extend([0xff, 0xda, 0x00, 0x02, 0x12, 0x34]);
+        jpeg
+    }
+
+    /// Builds a JPEG file whose EXIF data holds `thumbnail`, in little-endian TIFF layout
+    fn jpeg_with_thumbnail(thumbnail: &[u8]) -> Vec<u8> {
+        let mut tiff = b"II\x2a\x00".to_vec();
+        tiff.extend(8u32.to_le_bytes());
+        // IFD0 without entries, linking to IFD1
+        tiff.extend(0u16.to_le_bytes());
+        tiff.extend(14u32.to_le_bytes());
+        // IFD1 with the offset and length of the thumbnail
+        tiff.extend(2u16.to_le_bytes());
+        let thumbnail_offset = 14 + 2 + 2 * 12 + 4;
+        entry(&mut tiff, 0x0201, 4, 1, thumbnail_offset);
+        entry(&mut tiff, 0x0202, 4, 1, thumbnail.len() as u32);
+        tiff.extend(0u32.to_le_bytes());
+        tiff.extend(thumbnail);
+        jpeg_with_exif(&tiff)
+    }
+
+    #[test]
+    fn finds_exif_thumbnail() {
+        let thumbnail_data = [0xff, 0xd8, 0x01, 0x02, 0xff, 0xd9];
+        let jpeg = jpeg_with_thumbnail(&thumbnail_data);
+        assert_eq!(thumbnail(&jpeg), Some(&thumbnail_data[..]));
+    }
+
+    #[test]
+    fn ignores_images_without_thumbnail() {
+        assert_eq!(thumbnail(b"\x89PNG\r\n"), None);
+        assert_eq!(thumbnail(&[0xff, 0xd8, 0xff, 0xda, 0x00, 0x02]), None);
+        // What's pointed at isn't a JPEG image
+        assert_eq!(thumbnail(&jpeg_with_thumbnail(b"nope")), None);
+    }
+
+    #[test]
+    fn reads_fields() {
+        let mut tiff = b"II\x2a\x00".to_vec();
+        tiff.extend(8u32.to_le_bytes());
+        // IFD0 with the model, the orientation and a link to the EXIF IFD
+        tiff.extend(3u16.to_le_bytes());
+        let exif_ifd = 8 + 2 + 3 * 12 + 4;
+        let strings = exif_ifd + 2 + 12 + 4;
+        entry(&mut tiff, 0x0110, 2, 7, strings);
+        entry(&mut tiff, 0x0112, 3, 1, 6);
+        entry(&mut tiff, 0x8769, 4, 1, exif_ifd);
+        tiff.extend(0u32.to_le_bytes());
+        // EXIF IFD with the exposure time
+        tiff.extend(1u16.to_le_bytes());
+        entry(&mut tiff, 0x829a, 5, 1, strings + 7);
+        tiff.extend(0u32.to_le_bytes());
+        tiff.extend(b"Camera\0");
+        tiff.extend(1u32.to_le_bytes());
+        tiff.extend(250u32.to_le_bytes());
+
+        let jpeg = jpeg_with_exif(&tiff);
+        assert_eq!(orientation(&jpeg), Some(6));
+        assert_eq!(
+            fields(&jpeg),
+            [
+                ("Camera model", "Camera".to_string()),
+                ("Orientation", "6".to_string()),
+                ("Exposure time", "1/250".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn formats_rationals() {
+        assert_eq!(rational(28, 10).as_deref(), Some("2.8"));
+        assert_eq!(rational(48, 1).as_deref(), Some("48"));
+        assert_eq!(rational(1, 0), None);
+    }
+
+    #[test]
+    fn strips_jpeg_metadata() {
+        let jpeg = jpeg_with_thumbnail(&[0xff, 0xd8, 0xff, 0xd9]);
+        assert_eq!(
+            strip(&jpeg),
+            Some(vec![
+                0xff, 0xd8, 0xff, 0xe0, 0x00, 0x04, 0x00, 0x00, 0xff, 0xda, 0x00, 0x02, 0x12, 0x34
+            ])
+        );
+        // Truncated images can't be told free of metadata
+        assert_eq!(strip(&jpeg[..20]), None);
+        assert_eq!(strip(b"not an image"), Some(b"not an image".to_vec()));
+    }
+
+    #[test]
+    fn strips_png_metadata() {
+        let chunk = |kind: &[u8], data: &[u8]| {
+            let mut chunk = (data.len() as u32).to_be_bytes().to_vec();
+            chunk.extend(kind);
+            chunk.extend(data);
+            chunk.extend([0; 4]);
+            chunk
+        };
+        let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+        png.extend(chunk(b"IHDR", &[1; 13]));
+        png.extend(chunk(b"tEXt", b"Author\0me"));
+        png.extend(chunk(b"IEND", &[]));
+
+        let mut expected = b"\x89PNG\r\n\x1a\n".to_vec();
+        expected.extend(chunk(b"IHDR", &[1; 13]));
+        expected.extend(chunk(b"IEND", &[]));
+        assert_eq!(strip(&png), Some(expected));
+    }
+}
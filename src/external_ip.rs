@@ -0,0 +1,276 @@
+//! Finding out the public IP address of the machine, through STUN or an HTTP endpoint
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::Duration;
+
+use actix_web::rt::time::timeout;
+use anyhow::{Context, Result, anyhow, bail};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpStream, UdpSocket, lookup_host};
+
+/// Source used when --external-ip is given without one
+pub const DEFAULT_SOURCE: &str = "stun:stun.l.google.com:19302";
+
+/// How long to wait for the source to answer
+const TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Identifies STUN messages following RFC 5389
+const MAGIC_COOKIE: u32 = 0x2112_A442;
+
+const BINDING_REQUEST: u16 = 0x0001;
+const BINDING_SUCCESS: u16 = 0x0101;
+const MAPPED_ADDRESS: u16 = 0x0001;
+const XOR_MAPPED_ADDRESS: u16 = 0x0020;
+
+/// Where to ask for the public IP address, as given with --external-ip
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExternalIpSource {
+    /// A STUN server, as 'host:port'
+    Stun(String),
+
+    /// A plain HTTP endpoint answering with the address alone
+    Http {
+        /// Server to connect to, as 'host:port'
+        authority: String,
+        host: String,
+        path: String,
+    },
+}
+
+/// Parses 'stun:host[:port]' or 'http://host[:port][/path]'
+pub fn parse_external_ip_source(src: &str) -> Result<ExternalIpSource, String> {
+    let invalid = || {
+        format!(
+            "Invalid source {src:?}, expected something like 'stun:stun.example.com:3478' or \
+             'http://ip.example.com/'"
+        )
+    };
+    if let Some(server) = src.strip_prefix("stun:") {
+        let server = server.trim_start_matches("//");
+        if server.is_empty() {
+            return Err(invalid());
+        }
+        return Ok(ExternalIpSource::Stun(with_default_port(server, 3478)));
+    }
+    if let Some(rest) = src.strip_prefix("http://") {
+        let (host, path) = match rest.find('/') {
+            Some(i) => (&rest[..i], &rest[i..]),
+            None => (rest, "/"),
+        };
+        if host.is_empty() {
+            return Err(invalid());
+        }
+        return Ok(ExternalIpSource::Http {
+            authority: with_default_port(host, 80),
+            host: host.to_owned(),
+            path: path.to_owned(),
+        });
+    }
+    if src.starts_with("https://") {
+        return Err(format!(
+            "Invalid source {src:?}, only plain http:// endpoints are supported"
+        ));
+    }
+    Err(invalid())
+}
+
+/// Appends `port` to `host` unless it already has one
+fn with_default_port(host: &str, port: u16) -> String {
+    let has_port = match host.rsplit_once(':') {
+        // A bracketed IPv6 address ends with ']' when it has no port
+        Some((before, after)) => {
+            !after.contains(']') && (!before.contains(':') || before.ends_with(']'))
+        }
+        None => false,
+    };
+    if has_port {
+        host.to_owned()
+    } else {
+        format!("{host}:{port}")
+    }
+}
+
+/// Asks `source` for the public IP address of this machine
+pub async fn detect(source: &ExternalIpSource) -> Result<IpAddr> {
+    let request = async {
+        match source {
+            ExternalIpSource::Stun(server) => ask_stun(server).await,
+            ExternalIpSource::Http {
+                authority,
+                host,
+                path,
+            } => ask_http(authority, host, path).await,
+        }
+    };
+    timeout(TIMEOUT, request)
+        .await
+        .map_err(|_| anyhow!("No answer within {} seconds", TIMEOUT.as_secs()))?
+}
+
+/// Sends a STUN binding request, whose answer holds the address it came from
+async fn ask_stun(server: &str) -> Result<IpAddr> {
+    let server_addr = lookup_host(server)
+        .await?
+        .next()
+        .with_context(|| format!("Failed to resolve {server}"))?;
+    let local_addr = match server_addr {
+        SocketAddr::V4(_) => SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0)),
+        SocketAddr::V6(_) => SocketAddr::from((Ipv6Addr::UNSPECIFIED, 0)),
+    };
+    let socket = UdpSocket::bind(local_addr).await?;
+    socket.connect(server_addr).await?;
+
+    let transaction_id: [u8; 12] = nanoid::nanoid!(12)
+        .as_bytes()
+        .try_into()
+        .expect("IDs are made of 12 ASCII characters");
+    let mut request = Vec::with_capacity(20);
+    request.extend(BINDING_REQUEST.to_be_bytes());
+    request.extend(0u16.to_be_bytes());
+    request.extend(MAGIC_COOKIE.to_be_bytes());
+    request.extend(transaction_id);
+    socket.send(&request).await?;
+
+    let mut response = [0; 512];
+    let len = socket.recv(&mut response).await?;
+    parse_binding_response(&response[..len], &transaction_id)
+}
+
+/// Extracts the address from the answer to a STUN binding request
+fn parse_binding_response(response: &[u8], transaction_id: &[u8; 12]) -> Result<IpAddr> {
+    if response.len() < 20
+        || u16::from_be_bytes([response[0], response[1]]) != BINDING_SUCCESS
+        || response[4..8] != MAGIC_COOKIE.to_be_bytes()
+        || response[8..20] != *transaction_id
+    {
+        bail!("Unexpected answer from the STUN server");
+    }
+
+    let mut mapped = None;
+    let mut attributes = &response[20..];
+    while attributes.len() >= 4 {
+        let kind = u16::from_be_bytes([attributes[0], attributes[1]]);
+        let len = u16::from_be_bytes([attributes[2], attributes[3]]) as usize;
+        let Some(value) = attributes.get(4..4 + len) else {
+            break;
+        };
+        match kind {
+            XOR_MAPPED_ADDRESS => {
+                let mut mask = MAGIC_COOKIE.to_be_bytes().to_vec();
+                mask.extend(transaction_id);
+                return parse_address(value, &mask);
+            }
+            // Only ancient servers send this one alone
+            MAPPED_ADDRESS => mapped = Some(parse_address(value, &[0; 16])),
+            _ => {}
+        }
+        // Attributes are padded to 4 bytes
+        attributes = attributes
+            .get(4 + len.next_multiple_of(4)..)
+            .unwrap_or_default();
+    }
+    mapped.unwrap_or_else(|| bail!("The STUN server didn't tell our address"))
+}
+
+/// Parses the value of an address attribute, whose address is XORed with `mask`
+fn parse_address(value: &[u8], mask: &[u8]) -> Result<IpAddr> {
+    let unmask = |bytes: &[u8]| {
+        bytes
+            .iter()
+            .zip(mask)
+            .map(|(b, m)| b ^ m)
+            .collect::<Vec<_>>()
+    };
+    match (value.get(1), value.get(4..)) {
+        (Some(0x01), Some(addr)) if addr.len() == 4 => {
+            let octets: [u8; 4] = unmask(addr).try_into().expect("length was checked");
+            Ok(IpAddr::from(octets))
+        }
+        (Some(0x02), Some(addr)) if addr.len() == 16 => {
+            let octets: [u8; 16] = unmask(addr).try_into().expect("length was checked");
+            Ok(IpAddr::from(octets))
+        }
+        _ => bail!("Malformed address in the answer of the STUN server"),
+    }
+}
+
+/// Fetches `path` from `host`, which answers with the address as plain text
+async fn ask_http(authority: &str, host: &str, path: &str) -> Result<IpAddr> {
+    let mut stream = TcpStream::connect(authority).await?;
+    stream
+        .write_all(
+            format!(
+                "GET {path} HTTP/1.0\r\nHost: {host}\r\nUser-Agent: miniserve/{}\r\n\r\n",
+                env!("CARGO_PKG_VERSION")
+            )
+            .as_bytes(),
+        )
+        .await?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await?;
+
+    let (head, body) = response
+        .split_once("\r\n\r\n")
+        .context("Malformed answer from the endpoint")?;
+    let status = head.split(' ').nth(1).unwrap_or_default();
+    if status != "200" {
+        bail!("The endpoint answered with status {status}");
+    }
+    body.trim()
+        .parse()
+        .with_context(|| format!("The endpoint answered with {:?}", body.trim()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("stun:stun.example.com", ExternalIpSource::Stun("stun.example.com:3478".to_owned()))]
+    #[case("stun:example.com:19302", ExternalIpSource::Stun("example.com:19302".to_owned()))]
+    #[case("stun:[::1]", ExternalIpSource::Stun("[::1]:3478".to_owned()))]
+    #[case("stun:[::1]:5000", ExternalIpSource::Stun("[::1]:5000".to_owned()))]
+    #[case("http://ip.example.com", ExternalIpSource::Http {
+        authority: "ip.example.com:80".to_owned(),
+        host: "ip.example.com".to_owned(),
+        path: "/".to_owned(),
+    })]
+    #[case("http://ip.example.com:8080/ip", ExternalIpSource::Http {
+        authority: "ip.example.com:8080".to_owned(),
+        host: "ip.example.com:8080".to_owned(),
+        path: "/ip".to_owned(),
+    })]
+    fn parse_valid_source(#[case] src: &str, #[case] expected: ExternalIpSource) {
+        assert_eq!(parse_external_ip_source(src), Ok(expected));
+    }
+
+    #[rstest]
+    #[case("")]
+    #[case("stun:")]
+    #[case("http://")]
+    #[case("https://ip.example.com")]
+    #[case("ip.example.com")]
+    fn parse_invalid_source(#[case] src: &str) {
+        assert!(parse_external_ip_source(src).is_err());
+    }
+
+    #[test]
+    fn parse_xor_mapped_address() {
+        let transaction_id = *b"abcdefghijkl";
+        let mut response = vec![0x01, 0x01, 0x00, 0x0c];
+        response.extend(MAGIC_COOKIE.to_be_bytes());
+        response.extend(transaction_id);
+        // 203.0.113.7, port 4242
+        let cookie = MAGIC_COOKIE.to_be_bytes();
+        response.extend([0x00, 0x20, 0x00, 0x08, 0x00, 0x01]);
+        response.extend((4242 ^ 0x2112u16).to_be_bytes());
+        response.extend([203, 0, 113, 7].iter().zip(cookie).map(|(b, m)| b ^ m));
+
+        assert_eq!(
+            parse_binding_response(&response, &transaction_id).unwrap(),
+            IpAddr::from([203, 0, 113, 7])
+        );
+        assert!(parse_binding_response(&response, b"another id!!").is_err());
+    }
+}
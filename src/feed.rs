@@ -0,0 +1,137 @@
+//! Atom feeds of the files recently added to or modified in directories, served with --feed
+//!
+//! Every directory has one at `feed.xml`, unless it holds a file of that name, which is served
+//! instead.
+use std::cmp::Reverse;
+use std::path::Path;
+use std::time::SystemTime;
+
+use actix_web::{
+    HttpResponse,
+    body::{EitherBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    http::Method,
+    middleware::Next,
+    web,
+};
+use chrono::{DateTime, SecondsFormat, Utc};
+use maud::{Markup, PreEscaped, html};
+use percent_encoding::percent_decode_str;
+
+use crate::{MiniserveConfig, file_utils};
+
+/// Name of the feed of a directory
+const FEED_FILE: &str = "feed.xml";
+
+/// Number of files feeds list at most, the most recently modified ones
+const MAX_FEED_ENTRIES: usize = 50;
+
+/// File of a feed, with its path from the directory of the feed
+struct FeedEntry {
+    path: String,
+    size: u64,
+    modified: SystemTime,
+}
+
+/// Answers requests of `feed.xml` in directories with their feed
+pub async fn feed_middleware(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, actix_web::Error> {
+    let conf = req
+        .app_data::<web::Data<MiniserveConfig>>()
+        .expect("Could not get miniserve config")
+        .clone();
+
+    let dir = (conf.feed && req.method() == Method::GET && !conf.disable_indexing)
+        .then(|| req.match_info().unprocessed().strip_suffix(FEED_FILE))
+        .flatten()
+        .filter(|dir| dir.is_empty() || dir.ends_with('/'))
+        .and_then(|dir| file_utils::requested_dir(&conf, dir))
+        .filter(|dir| !dir.join(FEED_FILE).exists());
+    let Some(dir) = dir else {
+        return Ok(next.call(req).await?.map_into_left_body());
+    };
+
+    let entries = web::block({
+        let conf = conf.clone();
+        move || recent_files(&conf, &dir)
+    })
+    .await?;
+
+    let dir_url = {
+        let info = req.connection_info();
+        let path = req.path().strip_suffix(FEED_FILE).unwrap_or_default();
+        format!("{}://{}{}", info.scheme(), info.host(), path)
+    };
+    let title = {
+        let path = percent_decode_str(req.match_info().unprocessed())
+            .decode_utf8_lossy()
+            .into_owned();
+        let path = path.strip_suffix(FEED_FILE).unwrap_or_default();
+        let host = req.connection_info().host().to_owned();
+        format!(
+            "{}/{}",
+            conf.title.as_deref().unwrap_or(&host),
+            path.trim_start_matches('/')
+        )
+    };
+    let res = HttpResponse::Ok()
+        .content_type("application/atom+xml; charset=utf-8")
+        .body(render(&title, &dir_url, &entries).into_string());
+    Ok(req.into_response(res).map_into_right_body())
+}
+
+/// Most recently modified files below `dir`, newest first
+fn recent_files(conf: &MiniserveConfig, dir: &Path) -> Vec<FeedEntry> {
+    let mut entries: Vec<_> = file_utils::served_entries(conf, dir)
+        .into_iter()
+        .filter(|entry| entry.metadata.is_file())
+        .filter_map(|entry| {
+            Some(FeedEntry {
+                modified: entry.metadata.modified().ok()?,
+                size: entry.metadata.len(),
+                path: entry.path,
+            })
+        })
+        .collect();
+    entries.sort_by_key(|entry| Reverse(entry.modified));
+    entries.truncate(MAX_FEED_ENTRIES);
+    entries
+}
+
+/// Atom date of a time
+fn atom_date(time: SystemTime) -> String {
+    DateTime::<Utc>::from(time).to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// Renders the feed of the directory at `dir_url`
+///
+/// Empty elements are closed explicitly, as Atom is XML.
+fn render(title: &str, dir_url: &str, entries: &[FeedEntry]) -> Markup {
+    let updated = entries
+        .first()
+        .map_or(SystemTime::UNIX_EPOCH, |entry| entry.modified);
+    html! {
+        (PreEscaped(r#"<?xml version="1.0" encoding="utf-8"?>"#))
+        feed xmlns="http://www.w3.org/2005/Atom" {
+            title { (title) }
+            id { (dir_url) }
+            link href=(dir_url) {}
+            link rel="self" href=(format!("{dir_url}{FEED_FILE}")) {}
+            updated { (atom_date(updated)) }
+            author { name { "miniserve" } }
+            @for entry in entries {
+                @let url = format!("{dir_url}{}", file_utils::encode_path(&entry.path));
+                entry {
+                    title { (entry.path) }
+                    // Files are new entries whenever they change
+                    id { (url) "#" (atom_date(entry.modified)) }
+                    link href=(url) {}
+                    updated { (atom_date(entry.modified)) }
+                    summary { (bytesize::ByteSize::b(entry.size)) }
+                }
+            }
+        }
+    }
+}
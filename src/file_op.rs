@@ -1,5 +1,6 @@
 //! Handlers for file upload and removal
 
+use std::collections::HashMap;
 #[cfg(target_family = "unix")]
 use std::collections::HashSet;
 
@@ -12,6 +13,8 @@ use std::path::{Component, Path, PathBuf};
 
 #[cfg(target_family = "unix")]
 use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
 use actix_web::{HttpRequest, HttpResponse, http::header, web};
 use async_walkdir::WalkDir;
@@ -31,7 +34,7 @@ use crate::{
     file_utils::sanitize_path,
 };
 
-enum FileHash {
+pub enum FileHash {
     SHA256(String),
     SHA512(String),
 }
@@ -67,24 +70,24 @@ pub async fn recursive_dir_size(dir: &Path) -> Result<u64, RuntimeError> {
     loop {
         match entries.next().await {
             Some(Ok(entry)) => {
-                if let Ok(metadata) = entry.metadata().await {
-                    if metadata.is_file() {
-                        // On Unix, we want to filter inodes that we've already seen so we get a
-                        // more accurate count of real size used on disk.
-                        #[cfg(target_family = "unix")]
-                        {
-                            let (device_id, inode) = (metadata.dev(), metadata.ino());
-
-                            // Check if this file has been seen before based on its device ID and
-                            // inode number
-                            if seen_inodes.read().await.contains(&(device_id, inode)) {
-                                continue;
-                            } else {
-                                seen_inodes.write().await.insert((device_id, inode));
-                            }
+                if let Ok(metadata) = entry.metadata().await
+                    && metadata.is_file()
+                {
+                    // On Unix, we want to filter inodes that we've already seen so we get a
+                    // more accurate count of real size used on disk.
+                    #[cfg(target_family = "unix")]
+                    {
+                        let (device_id, inode) = (metadata.dev(), metadata.ino());
+
+                        // Check if this file has been seen before based on its device ID and
+                        // inode number
+                        if seen_inodes.read().await.contains(&(device_id, inode)) {
+                            continue;
+                        } else {
+                            seen_inodes.write().await.insert((device_id, inode));
                         }
-                        total_size += metadata.len();
                     }
+                    total_size += metadata.len();
                 }
             }
             Some(Err(e)) => {
@@ -103,11 +106,52 @@ pub async fn recursive_dir_size(dir: &Path) -> Result<u64, RuntimeError> {
     Ok(total_size)
 }
 
+/// How long the size of a directory is reused for
+const DIR_SIZE_MAX_AGE: Duration = Duration::from_secs(60);
+
+/// Sizes of directories calculated recently, so that listings shown again don't walk them again
+#[derive(Default)]
+pub struct DirSizeCache {
+    sizes: Mutex<HashMap<PathBuf, (Instant, u64)>>,
+}
+
+impl DirSizeCache {
+    /// Gets the recursively calculated size of a directory, calculated up to a minute ago
+    ///
+    /// Expects `dir` to be sanitized, like [`recursive_dir_size`].
+    pub async fn size(&self, dir: &Path) -> Result<u64, RuntimeError> {
+        let cached = self
+            .sizes
+            .lock()
+            .expect("Directory size cache poisoned")
+            .get(dir)
+            .filter(|(calculated, _)| calculated.elapsed() < DIR_SIZE_MAX_AGE)
+            .map(|&(_, size)| size);
+        if let Some(size) = cached {
+            return Ok(size);
+        }
+
+        let size = recursive_dir_size(dir).await?;
+        let mut sizes = self.sizes.lock().expect("Directory size cache poisoned");
+        sizes.retain(|_, (calculated, _)| calculated.elapsed() < DIR_SIZE_MAX_AGE);
+        sizes.insert(dir.to_path_buf(), (Instant::now(), size));
+        Ok(size)
+    }
+
+    /// Forgets all sizes, so that they're calculated again
+    pub fn clear(&self) {
+        self.sizes
+            .lock()
+            .expect("Directory size cache poisoned")
+            .clear();
+    }
+}
+
 /// Saves file data from a multipart form field (`field`) to `file_path`. Optionally overwriting
 /// existing file and comparing the uploaded file checksum to the user provided `file_hash`.
 ///
 /// Returns total bytes written to file.
-async fn save_file(
+pub async fn save_file(
     field: &mut actix_multipart::Field,
     file_path: PathBuf,
     overwrite_files: bool,
@@ -213,16 +257,16 @@ async fn save_file(
     // - https://github.com/actix/actix-web/discussions/3011
     // Therefore, we are relying on the fact that the web UI uploads a
     // hash of the file to determine if it was completed uploaded or not.
-    if let Some(hasher) = hasher {
-        if let Some(expected_hash) = file_checksum.as_ref().map(|f| f.get_hash()) {
-            let actual_hash = hex::encode(hasher.finalize());
-            if actual_hash != expected_hash {
-                warn!(
-                    "The expected file hash {expected_hash} did not match the calculated hash of {actual_hash}. This can be caused if a file upload was aborted."
-                );
-                let _ = tokio::fs::remove_file(&temp_path).await;
-                return Err(RuntimeError::UploadHashMismatchError);
-            }
+    if let Some(hasher) = hasher
+        && let Some(expected_hash) = file_checksum.as_ref().map(|f| f.get_hash())
+    {
+        let actual_hash = hex::encode(hasher.finalize());
+        if actual_hash != expected_hash {
+            warn!(
+                "The expected file hash {expected_hash} did not match the calculated hash of {actual_hash}. This can be caused if a file upload was aborted."
+            );
+            let _ = tokio::fs::remove_file(&temp_path).await;
+            return Err(RuntimeError::UploadHashMismatchError);
         }
     }
 
@@ -400,7 +444,7 @@ async fn handle_multipart(
 /// Query parameters used by upload and rm APIs
 #[derive(Deserialize, Default)]
 pub struct FileOpQueryParameters {
-    path: PathBuf,
+    pub path: PathBuf,
 }
 
 /// Handle incoming request to upload a file or create a directory.
@@ -414,6 +458,9 @@ pub async fn upload_file(
     payload: web::Payload,
 ) -> Result<HttpResponse, RuntimeError> {
     let conf = req.app_data::<web::Data<MiniserveConfig>>().unwrap();
+    if !conf.uploads_enabled() {
+        return Err(RuntimeError::UploadsPausedError);
+    }
     let upload_path = sanitize_path(&query.path, conf.show_hidden).ok_or_else(|| {
         RuntimeError::InvalidPathError("Invalid value for 'path' parameter".to_string())
     })?;
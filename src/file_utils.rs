@@ -1,8 +1,63 @@
 use std::{
+    fs::Metadata,
     io,
     path::{Component, Path, PathBuf},
 };
 
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use percent_encoding::{percent_decode_str, utf8_percent_encode};
+
+use crate::MiniserveConfig;
+use crate::listing::percent_encode_sets::COMPONENT;
+
+/// Name of the file listing entries of the served directory to hide, like a .gitignore does
+pub const IGNORE_FILE: &str = ".miniserveignore";
+
+/// Entries of the served directory hidden by its ignore file and by --ignore
+#[derive(Debug)]
+pub struct IgnoredPaths(Gitignore);
+
+impl IgnoredPaths {
+    /// Gathers the gitignore-style patterns of an ignore file and of --ignore, which apply to
+    /// the served directory
+    ///
+    /// The patterns given on the command line come last, so they take precedence. The ignore
+    /// file is skipped when it doesn't exist anymore.
+    pub fn new(
+        root: &Path,
+        file: Option<&Path>,
+        patterns: &[String],
+    ) -> Result<Self, ignore::Error> {
+        let mut builder = GitignoreBuilder::new(root);
+        if let Some(file) = file.filter(|file| file.is_file())
+            && let Some(err) = builder.add(file)
+        {
+            return Err(err);
+        }
+        for pattern in patterns {
+            builder.add_line(None, pattern)?;
+        }
+        builder.build().map(Self)
+    }
+
+    /// Whether an entry or any directory it's in is ignored
+    ///
+    /// Paths are either relative to the served directory or absolute ones within it, after
+    /// canonicalizing the served directory.
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        if path.is_absolute() && !path.starts_with(self.0.path()) {
+            return false;
+        }
+        self.0.matched_path_or_any_parents(path, is_dir).is_ignore()
+    }
+}
+
+/// Checks that --ignore is given a valid gitignore-style pattern
+pub fn parse_ignore_pattern(pattern: &str) -> Result<String, ignore::Error> {
+    GitignoreBuilder::new("").add_line(None, pattern)?;
+    Ok(pattern.to_owned())
+}
+
 /// Guarantee that the path is relative and cannot traverse back to parent directories
 /// and optionally prevent traversing hidden directories.
 ///
@@ -51,6 +106,86 @@ pub fn contains_symlink(path: impl AsRef<Path>) -> io::Result<bool> {
     Ok(contains_symlink)
 }
 
+/// Entry found below a directory by [`served_entries`]
+pub struct ServedEntry {
+    /// Path from the directory, with a trailing '/' for directories
+    pub path: String,
+    pub metadata: Metadata,
+}
+
+/// Files and directories below `dir` which listings of it and its subdirectories show
+///
+/// Hidden, ignored and symlinked entries are left out like listings leave them out, and
+/// symlinked directories aren't gone into, which could be endless.
+pub fn served_entries(conf: &MiniserveConfig, dir: &Path) -> Vec<ServedEntry> {
+    let ignored = conf.ignored.get();
+    let relative_dir = dir.strip_prefix(&conf.path).unwrap_or(dir);
+    let mut entries = Vec::new();
+    let mut pending = vec![(dir.to_path_buf(), String::new())];
+    while let Some((path, prefix)) = pending.pop() {
+        let Ok(read_dir) = path.read_dir() else {
+            continue;
+        };
+        for entry in read_dir.filter_map(Result::ok) {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let is_symlink = entry
+                .file_type()
+                .is_ok_and(|file_type| file_type.is_symlink());
+            let Ok(metadata) = std::fs::metadata(entry.path()) else {
+                continue;
+            };
+            let relative = relative_dir.join(format!("{prefix}{name}"));
+            if (name.starts_with('.') && !conf.show_hidden)
+                || (is_symlink && conf.no_symlinks)
+                || ignored.is_ignored(&relative, metadata.is_dir())
+            {
+                continue;
+            }
+            if metadata.is_dir() {
+                let path = format!("{prefix}{name}/");
+                if !is_symlink {
+                    pending.push((entry.path(), path.clone()));
+                }
+                entries.push(ServedEntry { path, metadata });
+            } else {
+                let path = format!("{prefix}{name}");
+                entries.push(ServedEntry { path, metadata });
+            }
+        }
+    }
+    entries
+}
+
+/// Percent-encodes the components of a path, to be part of a URL
+pub fn encode_path(path: &str) -> String {
+    path.split('/')
+        .map(|component| utf8_percent_encode(component, COMPONENT).to_string())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Resolves the regular file a request path relative to the route prefix points to, if any
+pub fn requested_file(conf: &MiniserveConfig, path: &str) -> Option<PathBuf> {
+    let decoded = percent_decode_str(path).decode_utf8().ok()?;
+    let relative = sanitize_path(Path::new(&*decoded), conf.show_hidden)?;
+    let file = conf.path.join(&relative);
+    let allowed = file.is_file()
+        && !(conf.no_symlinks && file.is_symlink())
+        && !conf.ignored.get().is_ignored(&relative, false);
+    allowed.then_some(file)
+}
+
+/// Resolves the directory a request path relative to the route prefix points to, if it's served
+pub fn requested_dir(conf: &MiniserveConfig, path: &str) -> Option<PathBuf> {
+    let decoded = percent_decode_str(path).decode_utf8().ok()?;
+    let relative = sanitize_path(Path::new(&*decoded), conf.show_hidden)?;
+    let dir = conf.path.join(&relative);
+    let allowed = dir.is_dir()
+        && !(conf.no_symlinks && dir.is_symlink())
+        && (relative.as_os_str().is_empty() || !conf.ignored.get().is_ignored(&relative, true));
+    allowed.then_some(dir)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -74,6 +209,27 @@ mod tests {
         );
     }
 
+    #[test]
+    fn ignores_paths_within_the_served_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join(IGNORE_FILE);
+        std::fs::write(&file, "*.log\nbuild/\n!keep.log\n").unwrap();
+        let patterns = ["*.tmp".to_owned(), "!keep.tmp".to_owned()];
+        let ignored = IgnoredPaths::new(dir.path(), Some(&file), &patterns).unwrap();
+
+        assert!(ignored.is_ignored(Path::new("debug.log"), false));
+        assert!(ignored.is_ignored(&dir.path().join("sub/debug.log"), false));
+        assert!(ignored.is_ignored(Path::new("build/out.txt"), false));
+        assert!(!ignored.is_ignored(Path::new("build"), false));
+        assert!(!ignored.is_ignored(Path::new("keep.log"), false));
+        assert!(!ignored.is_ignored(Path::new("/elsewhere/debug.log"), false));
+        assert!(ignored.is_ignored(Path::new("sub/data.tmp"), false));
+        assert!(!ignored.is_ignored(Path::new("keep.tmp"), false));
+
+        let ignored = IgnoredPaths::new(dir.path(), None, &[]).unwrap();
+        assert!(!ignored.is_ignored(Path::new("debug.log"), false));
+    }
+
     #[rstest]
     #[case(".foo")]
     #[case("/.foo")]
@@ -0,0 +1,238 @@
+//! Browse the tree of a git commit as if it were a regular directory.
+//!
+//! Trees and blobs are read straight from the repository's object database through the `git`
+//! command, so no checkout is needed and the working tree is never looked at.
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::time::{Duration, SystemTime};
+
+use actix_web::{HttpRequest, HttpResponse, http::header, web};
+use bytesize::ByteSize;
+use percent_encoding::utf8_percent_encode;
+
+use crate::errors::RuntimeError;
+use crate::listing::{self, Entry, EntryType, percent_encode_sets::COMPONENT};
+
+/// A commit of a local repository, resolved once at startup
+#[derive(Debug, Clone)]
+pub struct GitTree {
+    repo: PathBuf,
+    /// Ref as given on the command line, only used for display
+    reference: String,
+    /// Full hash of the commit the ref pointed to
+    commit: String,
+    /// Commit date, used as modification date of every entry
+    commit_date: Option<SystemTime>,
+}
+
+/// An entry of a tree, as printed by `git ls-tree --long`
+struct TreeEntry {
+    object_type: String,
+    object: String,
+    size: Option<u64>,
+    path: String,
+}
+
+impl GitTree {
+    /// Resolves `reference` to a commit of the repository at `repo`
+    pub fn open(repo: &Path, reference: &str) -> Result<Self, RuntimeError> {
+        let rev = format!("{reference}^{{commit}}");
+        let commit = git_output(repo, &["rev-parse", "--verify", "--end-of-options", &rev])?;
+        let commit = commit.trim().to_owned();
+        let commit_date = git_output(repo, &["show", "-s", "--format=%ct", &commit])?
+            .trim()
+            .parse()
+            .ok()
+            .map(|secs| SystemTime::UNIX_EPOCH + Duration::from_secs(secs));
+
+        Ok(Self {
+            repo: repo.to_path_buf(),
+            reference: reference.to_owned(),
+            commit,
+            commit_date,
+        })
+    }
+
+    /// Human-readable description of what is being served
+    pub fn location(&self) -> String {
+        format!("{} at {}", self.repo.display(), self.reference)
+    }
+
+    /// Lists the entries of the tree at `path`, which is either empty or ends with a `/`, or the
+    /// entry at `path` itself otherwise
+    fn ls_tree(&self, path: &str) -> Result<Vec<TreeEntry>, RuntimeError> {
+        let mut args = vec!["ls-tree", "-z", "--long", self.commit.as_str()];
+        if !path.is_empty() {
+            args.extend(["--", path]);
+        }
+        let output = git_output(&self.repo, &args)?;
+
+        Ok(output
+            .split_terminator('\0')
+            .filter_map(|line| {
+                let (info, path) = line.split_once('\t')?;
+                let mut info = info.split_whitespace();
+                let _mode = info.next()?;
+                Some(TreeEntry {
+                    object_type: info.next()?.to_owned(),
+                    object: info.next()?.to_owned(),
+                    size: info.next()?.parse().ok(),
+                    path: path.to_owned(),
+                })
+            })
+            .collect())
+    }
+
+    /// Same as `ls_tree`, but runs git on the blocking thread pool so the worker isn't stalled
+    async fn ls_tree_blocking(&self, path: &str) -> Result<Vec<TreeEntry>, RuntimeError> {
+        let tree = self.clone();
+        let path = path.to_owned();
+        web::block(move || tree.ls_tree(&path)).await.map_err(|e| {
+            RuntimeError::IoError("Failed to run git".to_owned(), io::Error::other(e))
+        })?
+    }
+}
+
+/// Runs a git command in `repo` and returns its standard output
+fn git_output(repo: &Path, args: &[&str]) -> Result<String, RuntimeError> {
+    let Output {
+        status,
+        stdout,
+        stderr,
+    } = git_command(repo, args)
+        .output()
+        .map_err(|e| RuntimeError::IoError("Failed to run git".to_owned(), e))?;
+    if !status.success() {
+        return Err(RuntimeError::IoError(
+            format!("git {} failed", args.join(" ")),
+            io::Error::other(String::from_utf8_lossy(&stderr).trim().to_owned()),
+        ));
+    }
+    Ok(String::from_utf8_lossy(&stdout).into_owned())
+}
+
+fn git_command(repo: &Path, args: &[&str]) -> Command {
+    let mut command = Command::new("git");
+    // Paths come from requests, so they must never be interpreted as pathspec magic
+    command
+        .arg("--literal-pathspecs")
+        .arg("-C")
+        .arg(repo)
+        .args(args)
+        .stdin(Stdio::null());
+    command
+}
+
+/// Serve either a listing of a tree of the commit, or the content of one of its blobs
+pub async fn git_handler(req: HttpRequest) -> Result<HttpResponse, RuntimeError> {
+    let conf = req.app_data::<web::Data<crate::MiniserveConfig>>().unwrap();
+    let tree = conf.git.as_ref().expect("git handler without a tree");
+    let tail = req.match_info().query("tail").to_string();
+
+    let hidden = tail.split('/').any(|component| component.starts_with('.'));
+    if hidden && !conf.show_hidden {
+        return Err(RuntimeError::RouteNotFoundError(tail));
+    }
+
+    if tail.is_empty() || tail.ends_with('/') {
+        let children = tree.ls_tree_blocking(&tail).await?;
+        // Git doesn't store empty trees, so a directory without entries doesn't exist
+        if !tail.is_empty() && children.is_empty() {
+            return Err(RuntimeError::RouteNotFoundError(tail));
+        }
+        let entries = directory_entries(tree, children, &tail, req.path(), conf.show_hidden);
+        return Ok(listing::render_listing(&req, entries, None).into_parts().1);
+    }
+
+    let entry = tree
+        .ls_tree_blocking(&tail)
+        .await?
+        .into_iter()
+        .find(|entry| entry.path == tail)
+        .ok_or_else(|| RuntimeError::RouteNotFoundError(tail.clone()))?;
+
+    match entry.object_type.as_str() {
+        "tree" => {
+            return Ok(HttpResponse::Found()
+                .append_header((header::LOCATION, format!("{}/", req.path())))
+                .finish());
+        }
+        "blob" => {}
+        // Submodules point to commits of other repositories, which we can't serve
+        _ => return Err(RuntimeError::RouteNotFoundError(tail)),
+    }
+
+    let mime = Path::new(&tail)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map_or(
+            mime::APPLICATION_OCTET_STREAM,
+            actix_files::file_extension_to_mime,
+        );
+
+    // Blobs are read by git in a separate thread and streamed through a pipe, the same way
+    // archives of directories are generated.
+    let mut child = git_command(&tree.repo, &["cat-file", "blob", &entry.object])
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .map_err(|e| RuntimeError::IoError("Failed to run git".to_owned(), e))?;
+    let (tx, rx) = futures::channel::mpsc::channel::<io::Result<actix_web::web::Bytes>>(10);
+    let mut pipe = crate::pipe::Pipe::new(tx);
+    std::thread::spawn(move || {
+        let result = child
+            .stdout
+            .take()
+            .map_or(Ok(0), |mut stdout| io::copy(&mut stdout, &mut pipe));
+        if let Err(err) = result {
+            log::error!("Error while streaming {tail} from git: {err:?}");
+        }
+        let _ = child.kill();
+        let _ = child.wait();
+    });
+
+    let mut builder = HttpResponse::Ok();
+    builder.content_type(mime);
+    if let Some(size) = entry.size {
+        builder.no_chunking(size);
+    }
+    Ok(builder.body(actix_web::body::BodyStream::new(rx)))
+}
+
+/// Turns the entries of the tree at `dir` into listing entries
+fn directory_entries(
+    tree: &GitTree,
+    children: Vec<TreeEntry>,
+    dir: &str,
+    base: &str,
+    show_hidden: bool,
+) -> Vec<Entry> {
+    children
+        .into_iter()
+        .filter_map(|child| {
+            let name = child.path.strip_prefix(dir)?.to_owned();
+            if name.starts_with('.') && !show_hidden {
+                return None;
+            }
+            let entry_type = match child.object_type.as_str() {
+                "tree" => EntryType::Directory,
+                "blob" => EntryType::File,
+                _ => return None,
+            };
+            let link = Path::new(base)
+                .join(utf8_percent_encode(&name, COMPONENT).to_string())
+                .to_string_lossy()
+                .to_string();
+            let size = child.size.map(ByteSize::b);
+            Some(Entry::new(
+                name,
+                entry_type,
+                link,
+                size,
+                tree.commit_date,
+                None,
+            ))
+        })
+        .collect()
+}
@@ -0,0 +1,368 @@
+//! Previews of text and source files with syntax highlighting, shown with --preview
+//!
+//! The highlighting is a simple tokenizer telling comments, strings, numbers and keywords apart,
+//! which is enough to make source files readable without the weight of full grammars.
+use std::path::Path;
+
+use actix_web::{
+    HttpResponse,
+    body::{EitherBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    http::Method,
+    middleware::Next,
+    web,
+};
+use serde::Deserialize;
+
+use crate::{MiniserveConfig, file_utils, i18n, renderer, table};
+
+/// Files larger than this are served as they are instead of being previewed
+pub const MAX_PREVIEW_SIZE: u64 = 1024 * 1024;
+
+/// Extensions of text files previewed without highlighting
+const PLAIN_TEXT: &[&str] = &[
+    "txt", "log", "md", "markdown", "csv", "tsv", "rst", "diff", "patch",
+];
+
+/// How to tell the tokens of a language apart
+struct Language {
+    extensions: &'static [&'static str],
+    line_comments: &'static [&'static str],
+    block_comment: Option<(&'static str, &'static str)>,
+    quotes: &'static [char],
+    keywords: &'static [&'static str],
+}
+
+#[rustfmt::skip]
+const LANGUAGES: &[Language] = &[
+    Language {
+        extensions: &["rs"],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"'],
+        keywords: &[
+            "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum",
+            "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod",
+            "move", "mut", "pub", "ref", "return", "self", "Self", "static", "struct", "super",
+            "trait", "true", "type", "unsafe", "use", "where", "while",
+        ],
+    },
+    Language {
+        extensions: &["c", "h", "cc", "cpp", "cxx", "hpp", "java", "cs", "kt", "swift"],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"', '\''],
+        keywords: &[
+            "auto", "bool", "break", "case", "catch", "char", "class", "const", "continue",
+            "default", "delete", "do", "double", "else", "enum", "extends", "extern", "false",
+            "final", "float", "for", "fun", "func", "if", "implements", "import", "int", "let",
+            "long", "namespace", "new", "null", "nullptr", "override", "package", "private",
+            "protected", "public", "return", "short", "sizeof", "static", "struct", "switch",
+            "template", "this", "throw", "true", "try", "typedef", "union", "unsigned", "using",
+            "val", "var", "virtual", "void", "volatile", "while",
+        ],
+    },
+    Language {
+        extensions: &["js", "mjs", "cjs", "jsx", "ts", "tsx"],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"', '\'', '`'],
+        keywords: &[
+            "async", "await", "break", "case", "catch", "class", "const", "continue", "default",
+            "delete", "do", "else", "export", "extends", "false", "finally", "for", "from",
+            "function", "if", "import", "in", "instanceof", "interface", "let", "new", "null", "of",
+            "return", "static", "super", "switch", "this", "throw", "true", "try", "type", "typeof",
+            "undefined", "var", "void", "while", "yield",
+        ],
+    },
+    Language {
+        extensions: &["go"],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"', '\'', '`'],
+        keywords: &[
+            "break", "case", "chan", "const", "continue", "default", "defer", "else", "false",
+            "for", "func", "go", "goto", "if", "import", "interface", "map", "nil", "package",
+            "range", "return", "select", "struct", "switch", "true", "type", "var",
+        ],
+    },
+    Language {
+        extensions: &["py", "pyw"],
+        line_comments: &["#"],
+        block_comment: None,
+        quotes: &['"', '\''],
+        keywords: &[
+            "and", "as", "assert", "async", "await", "break", "class", "continue", "def", "del",
+            "elif", "else", "except", "False", "finally", "for", "from", "global", "if", "import",
+            "in", "is", "lambda", "None", "nonlocal", "not", "or", "pass", "raise", "return",
+            "True", "try", "while", "with", "yield",
+        ],
+    },
+    Language {
+        extensions: &["sh", "bash", "zsh", "fish"],
+        line_comments: &["#"],
+        block_comment: None,
+        quotes: &['"', '\''],
+        keywords: &[
+            "case", "do", "done", "elif", "else", "esac", "export", "fi", "for", "function", "if",
+            "in", "local", "return", "then", "until", "while",
+        ],
+    },
+    Language {
+        extensions: &["toml", "yaml", "yml", "ini", "conf", "cfg"],
+        line_comments: &["#", ";"],
+        block_comment: None,
+        quotes: &['"', '\''],
+        keywords: &["true", "false", "yes", "no", "null"],
+    },
+    Language {
+        extensions: &["json"],
+        line_comments: &[],
+        block_comment: None,
+        quotes: &['"'],
+        keywords: &["true", "false", "null"],
+    },
+    Language {
+        extensions: &["sql"],
+        line_comments: &["--"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &['\''],
+        keywords: &[
+            "AND", "AS", "BY", "CREATE", "DELETE", "FROM", "GROUP", "INSERT", "INTO", "JOIN", "NOT",
+            "NULL", "ON", "OR", "ORDER", "SELECT", "SET", "TABLE", "UPDATE", "VALUES", "WHERE",
+        ],
+    },
+    Language {
+        extensions: &["html", "htm", "xml", "svg"],
+        line_comments: &[],
+        block_comment: Some(("<!--", "-->")),
+        quotes: &['"'],
+        keywords: &[],
+    },
+    Language {
+        extensions: &["css", "scss"],
+        line_comments: &[],
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"', '\''],
+        keywords: &["important"],
+    },
+];
+
+/// Finds the language of a file from its extension
+fn language(file_name: &str) -> Option<&'static Language> {
+    let extension = Path::new(file_name).extension()?.to_str()?.to_lowercase();
+    LANGUAGES
+        .iter()
+        .find(|language| language.extensions.contains(&extension.as_str()))
+}
+
+/// Whether a file is source code of a language known to the highlighter, going by its name
+pub fn is_code(file_name: &str) -> bool {
+    language(file_name).is_some()
+}
+
+/// Whether a file can be previewed, going by its name
+pub fn is_previewable(file_name: &str) -> bool {
+    language(file_name).is_some()
+        || Path::new(file_name)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| PLAIN_TEXT.contains(&ext.to_lowercase().as_str()))
+}
+
+#[derive(Deserialize)]
+struct PreviewQuery {
+    preview: Option<bool>,
+}
+
+/// Middleware answering requests for files with '?preview=true' with a highlighted preview
+///
+/// Small CSV and TSV files are shown as tables. Files that are too large, or aren't valid UTF-8,
+/// are served as they are instead.
+pub async fn preview_middleware(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, actix_web::Error> {
+    let conf = req
+        .app_data::<web::Data<MiniserveConfig>>()
+        .expect("Could not get miniserve config")
+        .clone();
+
+    let preview = web::Query::<PreviewQuery>::from_query(req.query_string())
+        .is_ok_and(|query| query.preview == Some(true));
+    let file = (conf.preview && req.method() == Method::GET && preview)
+        .then(|| file_utils::requested_file(&conf, req.match_info().unprocessed()))
+        .flatten()
+        .filter(|file| file.metadata().is_ok_and(|m| m.len() <= MAX_PREVIEW_SIZE));
+    let contents = match file {
+        Some(file) => tokio::fs::read_to_string(&file)
+            .await
+            .ok()
+            .map(|contents| (file, contents)),
+        None => None,
+    };
+    let Some((file, contents)) = contents else {
+        return Ok(next.call(req).await?.map_into_left_body());
+    };
+
+    let file_name = file
+        .file_name()
+        .map(|name| name.to_string_lossy())
+        .unwrap_or_default();
+    if let Some(delimiter) = table::delimiter(&file_name)
+        && contents.len() as u64 <= table::MAX_TABLE_SIZE
+    {
+        let rows = table::parse(&contents, delimiter);
+        let res = HttpResponse::Ok().content_type(mime::TEXT_HTML_UTF_8).body(
+            renderer::table(&file_name, &rows, &conf, i18n::texts(req.request())).into_string(),
+        );
+        return Ok(req.into_response(res).map_into_right_body());
+    }
+    let mut lines = highlight(&contents, language(&file_name));
+    // The newline ending the last line doesn't start another one
+    if lines.len() > 1 && lines.last().is_some_and(String::is_empty) {
+        lines.pop();
+    }
+    let res = HttpResponse::Ok().content_type(mime::TEXT_HTML_UTF_8).body(
+        renderer::preview(&file_name, &lines, &conf, i18n::texts(req.request())).into_string(),
+    );
+    Ok(req.into_response(res).map_into_right_body())
+}
+
+/// Highlights `source`, returning the HTML of each of its lines
+///
+/// Highlighted tokens are wrapped in spans with an 'hl-' class, which never span several lines.
+fn highlight(source: &str, language: Option<&Language>) -> Vec<String> {
+    let mut lines = vec![String::new()];
+    let mut emit = |class: Option<&str>, text: &str| {
+        for (i, part) in text.split('\n').enumerate() {
+            if i > 0 {
+                lines.push(String::new());
+            }
+            if part.is_empty() {
+                continue;
+            }
+            let line = lines.last_mut().expect("there's always a line");
+            match class {
+                Some(class) => line.push_str(&format!(
+                    "<span class=\"hl-{class}\">{}</span>",
+                    escape(part)
+                )),
+                None => line.push_str(&escape(part)),
+            }
+        }
+    };
+
+    let Some(language) = language else {
+        emit(None, source);
+        return lines;
+    };
+
+    let mut rest = source;
+    while let Some(c) = rest.chars().next() {
+        let (class, len) = if language
+            .line_comments
+            .iter()
+            .any(|prefix| rest.starts_with(prefix))
+        {
+            (Some("comment"), rest.find('\n').unwrap_or(rest.len()))
+        } else if let Some((open, close)) = language.block_comment
+            && rest.starts_with(open)
+        {
+            let len = rest[open.len()..]
+                .find(close)
+                .map_or(rest.len(), |end| open.len() + end + close.len());
+            (Some("comment"), len)
+        } else if language.quotes.contains(&c) {
+            (Some("string"), string_len(rest, c))
+        } else if c.is_ascii_digit() {
+            let len = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '.' || c == '_'))
+                .unwrap_or(rest.len());
+            (Some("number"), len)
+        } else if c.is_alphabetic() || c == '_' {
+            let len = rest
+                .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            let keyword = language.keywords.contains(&&rest[..len]);
+            (keyword.then_some("keyword"), len)
+        } else {
+            (None, c.len_utf8())
+        };
+        emit(class, &rest[..len]);
+        rest = &rest[len..];
+    }
+    lines
+}
+
+/// Length of the string literal `rest` starts with, delimited by `quote`
+///
+/// Only backticks delimit strings spanning several lines, others end with the line when unclosed.
+fn string_len(rest: &str, quote: char) -> usize {
+    let mut escaped = false;
+    for (i, c) in rest.char_indices().skip(1) {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '\n' if quote != '`' => return i,
+            c if c == quote => return i + c.len_utf8(),
+            _ => {}
+        }
+    }
+    rest.len()
+}
+
+/// Escapes text to be put in HTML
+fn escape(text: &str) -> String {
+    maud::html! { (text) }.into_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn highlight_rust() {
+        let lines = highlight(
+            "/* a\nb */ fn main() { // <main>\n    let s = \"x\\\"y\"; 42\n}",
+            language("main.rs"),
+        );
+        assert_eq!(
+            lines,
+            [
+                r#"<span class="hl-comment">/* a</span>"#,
+                concat!(
+                    r#"<span class="hl-comment">b */</span> <span class="hl-keyword">fn</span> "#,
+                    r#"main() { <span class="hl-comment">// &lt;main&gt;</span>"#,
+                ),
+                concat!(
+                    r#"    <span class="hl-keyword">let</span> s = "#,
+                    r#"<span class="hl-string">&quot;x\&quot;y&quot;</span>; "#,
+                    r#"<span class="hl-number">42</span>"#,
+                ),
+                "}",
+            ]
+        );
+    }
+
+    #[test]
+    fn unclosed_string_ends_with_line() {
+        let lines = highlight("x = 'a\ny = 1", language("x.py"));
+        assert_eq!(
+            lines,
+            [
+                r#"x = <span class="hl-string">'a</span>"#,
+                r#"y = <span class="hl-number">1</span>"#,
+            ]
+        );
+    }
+
+    #[test]
+    fn plain_text_is_escaped() {
+        assert_eq!(highlight("<b>\n", None), ["&lt;b&gt;", ""]);
+        assert!(is_previewable("notes.TXT"));
+        assert!(is_previewable("main.rs"));
+        assert!(!is_previewable("photo.jpg"));
+    }
+}
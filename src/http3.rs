@@ -0,0 +1,210 @@
+//! Experimental HTTP/3 listener
+//!
+//! QUIC connections are accepted on the UDP port matching every TCP listener. actix-web doesn't
+//! speak HTTP/3, so requests arriving on them are translated and handed to an instance of the
+//! same application the TCP listeners serve, so HTTP/3 clients get exactly the same responses as
+//! everyone else.
+use std::error::Error;
+use std::io;
+use std::net::{SocketAddr, UdpSocket};
+use std::pin::pin;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use actix_http::{Payload, Request as ActixRequest};
+use actix_service::IntoServiceFactory;
+use actix_web::body::{BodySize, MessageBody};
+use actix_web::dev::{AppConfig, Service, ServiceFactory, ServiceResponse};
+use actix_web::error::PayloadError;
+use actix_web::web::{Buf, Bytes};
+use actix_web::{HttpResponse, http as actix};
+use futures::SinkExt;
+use h3::server::RequestStream;
+use http::{HeaderName, HeaderValue, Method, Request, Response, StatusCode, header};
+use log::{error, warn};
+use quinn::crypto::rustls::QuicServerConfig;
+
+/// Headers that only concern a single connection, which HTTP/3 doesn't allow
+const HOP_BY_HOP_HEADERS: [header::HeaderName; 5] = [
+    header::CONNECTION,
+    header::TRANSFER_ENCODING,
+    header::TE,
+    header::UPGRADE,
+    header::HeaderName::from_static("keep-alive"),
+];
+
+/// Starts accepting HTTP/3 connections on `socket` in the background, serving them with `app`
+///
+/// This must be called from within the actix runtime, as connections are handled on it.
+pub fn spawn_listener<T, S, B>(
+    socket: UdpSocket,
+    tls_config: &rustls::ServerConfig,
+    app: T,
+) -> io::Result<()>
+where
+    T: IntoServiceFactory<S, ActixRequest>,
+    S: ServiceFactory<
+            ActixRequest,
+            Config = AppConfig,
+            Response = ServiceResponse<B>,
+            Error = actix_web::Error,
+        > + 'static,
+    B: MessageBody + 'static,
+{
+    let mut tls_config = tls_config.clone();
+    tls_config.alpn_protocols = vec![b"h3".to_vec()];
+    let crypto = QuicServerConfig::try_from(tls_config).map_err(io::Error::other)?;
+    let server_config = quinn::ServerConfig::with_crypto(Arc::new(crypto));
+    let runtime = quinn::default_runtime()
+        .ok_or_else(|| io::Error::other("HTTP/3 requires an async runtime"))?;
+    let endpoint = quinn::Endpoint::new(
+        quinn::EndpointConfig::default(),
+        Some(server_config),
+        socket,
+        runtime,
+    )?;
+    let addr = endpoint.local_addr()?;
+    let factory = app.into_factory();
+
+    actix_web::rt::spawn(async move {
+        // Requests carry an absolute URI, so the scheme and host don't come from this config
+        let Ok(service) = factory.new_service(AppConfig::default()).await else {
+            error!("Failed to start the HTTP/3 listener on {addr}");
+            return;
+        };
+        let service = Rc::new(service);
+        while let Some(incoming) = endpoint.accept().await {
+            let service = Rc::clone(&service);
+            actix_web::rt::spawn(async move {
+                if let Err(e) = handle_connection(incoming, service).await {
+                    warn!("HTTP/3 connection failed: {e}");
+                }
+            });
+        }
+    });
+
+    Ok(())
+}
+
+/// Creates a UDP socket bound to `addr`, which only accepts IPv6 traffic for IPv6 addresses like
+/// the TCP listeners do
+pub fn create_udp_socket(addr: SocketAddr) -> io::Result<UdpSocket> {
+    use socket2::{Domain, Protocol, Socket, Type};
+    let socket = Socket::new(Domain::for_address(addr), Type::DGRAM, Some(Protocol::UDP))?;
+    if addr.is_ipv6() {
+        socket.set_only_v6(true)?;
+    }
+    socket.bind(&addr.into())?;
+    Ok(UdpSocket::from(socket))
+}
+
+/// Serves the requests of a single QUIC connection
+async fn handle_connection<S, B>(
+    incoming: quinn::Incoming,
+    service: Rc<S>,
+) -> Result<(), Box<dyn Error>>
+where
+    S: Service<ActixRequest, Response = ServiceResponse<B>, Error = actix_web::Error> + 'static,
+    B: MessageBody + 'static,
+{
+    let conn = incoming.await?;
+    let peer = conn.remote_address();
+    let mut h3_conn = h3::server::Connection::new(h3_quinn::Connection::new(conn)).await?;
+
+    while let Some(resolver) = h3_conn.accept().await? {
+        let service = Rc::clone(&service);
+        actix_web::rt::spawn(async move {
+            let result = match resolver.resolve_request().await {
+                Ok((req, stream)) => serve_request(req, stream, &*service, peer).await,
+                Err(e) => Err(e.into()),
+            };
+            if let Err(e) = result {
+                error!("Failed to answer HTTP/3 request: {e}");
+            }
+        });
+    }
+
+    Ok(())
+}
+
+/// Has `service` answer a request, then sends back its response
+async fn serve_request<S, B>(
+    req: Request<()>,
+    stream: RequestStream<h3_quinn::BidiStream<Bytes>, Bytes>,
+    service: &S,
+    peer: SocketAddr,
+) -> Result<(), Box<dyn Error>>
+where
+    S: Service<ActixRequest, Response = ServiceResponse<B>, Error = actix_web::Error>,
+    B: MessageBody + 'static,
+{
+    let (mut send, mut recv) = stream.split();
+
+    let (mut tx, rx) = futures::channel::mpsc::channel::<Result<Bytes, PayloadError>>(10);
+    if !matches!(*req.method(), Method::GET | Method::HEAD) {
+        actix_web::rt::spawn(async move {
+            loop {
+                let chunk = match recv.recv_data().await {
+                    Ok(Some(mut chunk)) => Ok(chunk.copy_to_bytes(chunk.remaining())),
+                    Ok(None) => break,
+                    Err(e) => Err(PayloadError::Io(io::Error::other(e))),
+                };
+                let failed = chunk.is_err();
+                if tx.send(chunk).await.is_err() || failed {
+                    break;
+                }
+            }
+        });
+    }
+    let mut actix_req =
+        ActixRequest::with_payload(Payload::from(Box::pin(rx) as actix_http::BoxedPayloadStream));
+    let head = actix_req.head_mut();
+    head.method = actix::Method::from_bytes(req.method().as_str().as_bytes())?;
+    head.uri = req.uri().to_string().parse()?;
+    head.version = actix::Version::HTTP_3;
+    head.peer_addr = Some(peer);
+    for (name, value) in req.headers() {
+        head.headers.append(
+            actix::header::HeaderName::from_bytes(name.as_str().as_bytes())?,
+            actix::header::HeaderValue::from_bytes(value.as_bytes())?,
+        );
+    }
+    // HTTP/3 replaces the Host header with the :authority pseudo-header
+    if let Some(authority) = req.uri().authority()
+        && !head.headers.contains_key(actix::header::HOST)
+    {
+        head.headers.insert(
+            actix::header::HOST,
+            actix::header::HeaderValue::from_str(authority.as_str())?,
+        );
+    }
+
+    let res: HttpResponse = match service.call(actix_req).await {
+        Ok(res) => res.into_parts().1.map_into_boxed_body(),
+        Err(e) => e.error_response(),
+    };
+
+    let mut builder = Response::builder().status(StatusCode::from_u16(res.status().as_u16())?);
+    for (name, value) in res.headers() {
+        let name = HeaderName::from_bytes(name.as_str().as_bytes())?;
+        if !HOP_BY_HOP_HEADERS.contains(&name) {
+            builder = builder.header(name, HeaderValue::from_bytes(value.as_bytes())?);
+        }
+    }
+    let body = res.into_body();
+    // The HTTP/1 and HTTP/2 encoders of actix-web add this one themselves
+    if let BodySize::Sized(size) = body.size() {
+        builder = builder.header(header::CONTENT_LENGTH, size);
+    }
+    send.send_response(builder.body(())?).await?;
+
+    if *req.method() != Method::HEAD {
+        let mut body = pin!(body);
+        while let Some(chunk) = futures::future::poll_fn(|cx| body.as_mut().poll_next(cx)).await {
+            send.send_data(chunk?).await?;
+        }
+    }
+    send.finish().await?;
+
+    Ok(())
+}
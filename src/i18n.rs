@@ -0,0 +1,932 @@
+//! Translations of the texts shown in pages, in the language chosen with --lang or else the one
+//! the browser asks for
+use std::time::SystemTime;
+
+use actix_web::{HttpRequest, http::header, web};
+use chrono_humanize::Humanize;
+use clap::ValueEnum;
+use strum::{Display, EnumIter, IntoEnumIterator};
+
+use crate::MiniserveConfig;
+
+/// Languages pages can be shown in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Display, EnumIter, ValueEnum)]
+#[strum(serialize_all = "lowercase")]
+pub enum Lang {
+    /// English
+    #[default]
+    En,
+
+    /// German
+    De,
+
+    /// French
+    Fr,
+
+    /// Spanish
+    Es,
+
+    /// Dutch
+    Nl,
+}
+
+impl Lang {
+    pub fn texts(self) -> &'static Texts {
+        match self {
+            Self::En => &EN,
+            Self::De => &DE,
+            Self::Fr => &FR,
+            Self::Es => &ES,
+            Self::Nl => &NL,
+        }
+    }
+
+    /// Picks the available language an Accept-Language header prefers, if there is one
+    ///
+    /// Only the primary language of each tag is looked at, so 'de-AT' gets German.
+    pub fn negotiate(accept_language: &str) -> Option<Self> {
+        let mut ranges: Vec<(&str, f32)> = accept_language
+            .split(',')
+            .filter_map(|range| {
+                let mut parts = range.split(';');
+                let tag = parts.next()?.trim();
+                let quality = parts
+                    .find_map(|param| param.trim().strip_prefix("q="))
+                    .map_or(Some(1.0), |q| q.trim().parse().ok())?;
+                Some((tag, quality))
+            })
+            .filter(|(_, quality)| *quality > 0.0)
+            .collect();
+        // The sort is stable, so ranges of the same quality keep their order
+        ranges.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+        ranges.into_iter().find_map(|(tag, _)| {
+            let primary = tag.split('-').next().unwrap_or_default();
+            Self::iter().find(|lang| primary.eq_ignore_ascii_case(&lang.to_string()))
+        })
+    }
+}
+
+/// Texts of the language a request is answered in
+pub fn texts(req: &HttpRequest) -> &'static Texts {
+    let chosen = req
+        .app_data::<web::Data<MiniserveConfig>>()
+        .and_then(|conf| conf.lang);
+    chosen
+        .or_else(|| {
+            req.headers()
+                .get(header::ACCEPT_LANGUAGE)
+                .and_then(|value| value.to_str().ok())
+                .and_then(Lang::negotiate)
+        })
+        .unwrap_or_default()
+        .texts()
+}
+
+/// Units of the rough time elapsed since something happened
+enum TimeUnit {
+    Minutes,
+    Hours,
+    Days,
+    Weeks,
+    Months,
+    Years,
+}
+
+/// Rounds the time elapsed since `time` to its largest unit, or nothing for the last few seconds
+fn elapsed(time: SystemTime) -> Option<(u64, TimeUnit)> {
+    let seconds = SystemTime::now()
+        .duration_since(time)
+        .map_or(0, |elapsed| elapsed.as_secs());
+    let rounded = |unit: u64| ((seconds + unit / 2) / unit).max(1);
+    let (minute, hour, day) = (60, 60 * 60, 24 * 60 * 60);
+    match seconds {
+        s if s < 45 => None,
+        s if s < 45 * minute => Some((rounded(minute), TimeUnit::Minutes)),
+        s if s < day => Some((rounded(hour), TimeUnit::Hours)),
+        s if s < 7 * day => Some((rounded(day), TimeUnit::Days)),
+        s if s < 30 * day => Some((rounded(7 * day), TimeUnit::Weeks)),
+        s if s < 365 * day => Some((rounded(30 * day), TimeUnit::Months)),
+        _ => Some((rounded(365 * day), TimeUnit::Years)),
+    }
+}
+
+/// A count followed by the singular or plural form of what's counted
+fn count(count: u64, one: &str, many: &str) -> String {
+    match count {
+        1 => format!("1 {one}"),
+        _ => format!("{count} {many}"),
+    }
+}
+
+/// Makes the first letter of a column name lowercase, for it to be used within a sentence
+fn lowercase_first(name: &str) -> String {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .map(|first| first.to_lowercase().chain(chars).collect())
+        .unwrap_or_default()
+}
+
+/// Texts shown in pages
+///
+/// Functions fill in the parts that vary, like counts and names.
+pub struct Texts {
+    /// Language tag of the `lang` attribute of pages
+    pub code: &'static str,
+
+    // Listings
+    pub name: &'static str,
+    pub size: &'static str,
+    pub last_modification: &'static str,
+    pub permissions: &'static str,
+    pub owner: &'static str,
+    pub group: &'static str,
+    pub parent_directory: &'static str,
+    pub sort_ascending: fn(&str) -> String,
+    pub sort_descending: fn(&str) -> String,
+    pub preview: &'static str,
+    pub preview_help: &'static str,
+    pub details: &'static str,
+    pub details_help: &'static str,
+    pub copy_link: &'static str,
+    pub copy_link_help: &'static str,
+    pub link_copied: &'static str,
+    pub broken_link: &'static str,
+    pub relative_time: fn(SystemTime) -> String,
+    pub totals: fn(u64, u64, &str) -> String,
+    pub entries_shown: fn(usize, usize) -> String,
+    pub show_all: &'static str,
+    pub to_list_remaining: fn(usize) -> String,
+    pub refine_prompt: &'static str,
+    pub refine: &'static str,
+    pub filter: &'static str,
+    pub filter_help: &'static str,
+    pub skip_to_listing: &'static str,
+    pub back_to_top: &'static str,
+
+    // Toolbar
+    pub download_archive: fn(&str) -> String,
+    pub list_view: &'static str,
+    pub gallery_view: &'static str,
+    pub tree_view: &'static str,
+    pub expand: &'static str,
+    pub collapse: &'static str,
+    pub mix_directories: &'static str,
+    pub directories_first: &'static str,
+    pub hide_hidden_files: &'static str,
+    pub show_hidden_files: &'static str,
+    pub flat_listing: &'static str,
+    pub flat_listing_help: &'static str,
+    pub nested_listing: &'static str,
+    pub nested_listing_help: &'static str,
+    pub feed: &'static str,
+    pub feed_help: &'static str,
+    pub checksums: &'static str,
+    pub checksums_help: &'static str,
+    pub torrent: &'static str,
+    pub torrent_help: &'static str,
+    pub relative_and_absolute_times: &'static str,
+    pub relative_times: &'static str,
+    pub absolute_times: &'static str,
+    pub playlist: &'static str,
+    pub playlist_help: &'static str,
+    pub qr_code: &'static str,
+    pub change_theme: &'static str,
+    pub default_theme: &'static str,
+    pub light: &'static str,
+    pub dark: &'static str,
+    pub switch_theme: fn(&str) -> String,
+    pub download_folder: &'static str,
+    pub click_to_copy: &'static str,
+
+    // Uploads and directory creation
+    pub drop_to_upload: &'static str,
+    pub select_file_to_upload: &'static str,
+    pub upload_file: &'static str,
+    pub create_new_directory: &'static str,
+    pub directory_name_prompt: &'static str,
+    pub directory_name: &'static str,
+    pub create_directory: &'static str,
+    pub starting_upload: &'static str,
+    pub cancel: &'static str,
+    /// Texts of the upload widget, where the script puts the numbers in place of '{count}'
+    pub uploads_remaining: &'static str,
+    pub uploads_complete: &'static str,
+    pub uploading_count: &'static str,
+    pub pending_count: &'static str,
+    pub complete_count: &'static str,
+    pub cancelled_count: &'static str,
+    pub failed_count: &'static str,
+
+    // Pages showing files
+    pub raw: &'static str,
+    pub directory: &'static str,
+    pub open: &'static str,
+    pub download: &'static str,
+    pub download_file: fn(&str) -> String,
+    pub previous: &'static str,
+    pub next: &'static str,
+    pub close: &'static str,
+    pub sort: &'static str,
+    pub no_exif_data: &'static str,
+
+    // --receive
+    pub send_a_file: &'static str,
+    pub send_file: &'static str,
+    pub file_received: &'static str,
+
+    // --admin
+    pub administration: &'static str,
+    pub transfers_in_progress: &'static str,
+    pub no_transfers: &'static str,
+    pub path: &'static str,
+    pub user: &'static str,
+    pub client: &'static str,
+    pub progress: &'static str,
+    pub uploads_enabled: &'static str,
+    pub uploads_paused: &'static str,
+    pub pause_uploads: &'static str,
+    pub resume_uploads: &'static str,
+    pub clear_caches: &'static str,
+    pub recent_events: &'static str,
+    pub no_events: &'static str,
+    pub time: &'static str,
+    pub event: &'static str,
+
+    // Error pages
+    pub back_to_listing: &'static str,
+}
+
+static EN: Texts = Texts {
+    code: "en",
+    name: "Name",
+    size: "Size",
+    last_modification: "Last modification",
+    permissions: "Permissions",
+    owner: "Owner",
+    group: "Group",
+    parent_directory: "Parent directory",
+    sort_ascending: |column| format!("Sort by {} in ascending order", lowercase_first(column)),
+    sort_descending: |column| format!("Sort by {} in descending order", lowercase_first(column)),
+    preview: "preview",
+    preview_help: "Preview",
+    details: "details",
+    details_help: "EXIF data",
+    copy_link: "copy link",
+    copy_link_help: "Copy the address of this entry",
+    link_copied: "copied",
+    broken_link: "Broken link",
+    relative_time: |time| time.humanize(),
+    totals: |files, directories, size| {
+        format!(
+            "{}, {}, {size} total",
+            count(files, "file", "files"),
+            count(directories, "directory", "directories")
+        )
+    },
+    entries_shown: |shown, total| format!("{shown} of {total} entries shown. "),
+    show_all: "Show all",
+    to_list_remaining: |remaining| format!(" to list the remaining {remaining}."),
+    refine_prompt: "Or list only the entries whose name contains",
+    refine: "Refine",
+    filter: "Filter",
+    filter_help: "Show only entries whose name contains this text (/)",
+    skip_to_listing: "Skip to the listing",
+    back_to_top: "Back to top",
+    download_archive: |extension| format!("Download .{extension}"),
+    list_view: "List view",
+    gallery_view: "Gallery view",
+    tree_view: "Tree view",
+    expand: "Expand",
+    collapse: "Collapse",
+    mix_directories: "Mix directories with files",
+    directories_first: "Directories first",
+    hide_hidden_files: "Hide hidden files",
+    show_hidden_files: "Show hidden files",
+    flat_listing: "All files",
+    flat_listing_help: "List the files of all subdirectories, with their paths",
+    nested_listing: "By directory",
+    nested_listing_help: "List the entries of this directory only",
+    feed: "Feed",
+    feed_help: "Subscribe to the files added or modified here",
+    checksums: "Checksums",
+    checksums_help: "Download the SHA-256 checksums of the files here",
+    torrent: "Torrent",
+    torrent_help: "Download a torrent of this, seeded by this server",
+    relative_and_absolute_times: "Relative and absolute times",
+    relative_times: "Relative times",
+    absolute_times: "Absolute times",
+    playlist: "Playlist",
+    playlist_help: "Playlist of the audio and video files, for media players",
+    qr_code: "QR code",
+    change_theme: "Change theme...",
+    default_theme: "Default",
+    light: "light",
+    dark: "dark",
+    switch_theme: |theme| format!("Switch to {theme} theme"),
+    download_folder: "Download folder:",
+    click_to_copy: "Click to copy!",
+    drop_to_upload: "Drop your file here to upload it",
+    select_file_to_upload: "Select a file to upload or drag it anywhere into the window",
+    upload_file: "Upload file",
+    create_new_directory: "Create a new directory",
+    directory_name_prompt: "Specify a directory name to create",
+    directory_name: "Directory name",
+    create_directory: "Create directory",
+    starting_upload: "Starting upload...",
+    cancel: "CANCEL",
+    uploads_remaining: "{count} uploads remaining...",
+    uploads_complete: "Complete! Reloading Page!",
+    uploading_count: "Uploading {count}",
+    pending_count: "Pending {count}",
+    complete_count: "Complete {count}",
+    cancelled_count: "Cancelled {count}",
+    failed_count: "Failed {count}",
+    raw: "Raw",
+    directory: "Directory",
+    open: "Open",
+    download: "Download",
+    download_file: |name| format!("Download {name}"),
+    previous: "Previous",
+    next: "Next",
+    close: "Close",
+    sort: "Sort",
+    no_exif_data: "This image has no EXIF data.",
+    send_a_file: "Send a file",
+    send_file: "Send file",
+    file_received: "The file has been received, this page can be closed.",
+    administration: "Administration",
+    transfers_in_progress: "Transfers in progress",
+    no_transfers: "No transfers in progress.",
+    path: "Path",
+    user: "User",
+    client: "Client",
+    progress: "Progress",
+    uploads_enabled: "Uploads are enabled.",
+    uploads_paused: "Uploads are paused.",
+    pause_uploads: "Pause uploads",
+    resume_uploads: "Resume uploads",
+    clear_caches: "Clear caches",
+    recent_events: "Recent events",
+    no_events: "Nothing happened yet.",
+    time: "Time",
+    event: "Event",
+    back_to_listing: "Go back to file listing",
+};
+
+static DE: Texts = Texts {
+    code: "de",
+    name: "Name",
+    size: "Größe",
+    last_modification: "Letzte Änderung",
+    permissions: "Berechtigungen",
+    owner: "Besitzer",
+    group: "Gruppe",
+    parent_directory: "Übergeordnetes Verzeichnis",
+    sort_ascending: |column| format!("Aufsteigend nach {column} sortieren"),
+    sort_descending: |column| format!("Absteigend nach {column} sortieren"),
+    preview: "Vorschau",
+    preview_help: "Vorschau",
+    details: "Details",
+    details_help: "EXIF-Daten",
+    copy_link: "Link kopieren",
+    copy_link_help: "Adresse dieses Eintrags kopieren",
+    link_copied: "kopiert",
+    broken_link: "Defekter Link",
+    relative_time: |time| match elapsed(time) {
+        None => "gerade eben".to_string(),
+        Some((n, TimeUnit::Minutes)) => format!("vor {}", count(n, "Minute", "Minuten")),
+        Some((n, TimeUnit::Hours)) => format!("vor {}", count(n, "Stunde", "Stunden")),
+        Some((n, TimeUnit::Days)) => format!("vor {}", count(n, "Tag", "Tagen")),
+        Some((n, TimeUnit::Weeks)) => format!("vor {}", count(n, "Woche", "Wochen")),
+        Some((n, TimeUnit::Months)) => format!("vor {}", count(n, "Monat", "Monaten")),
+        Some((n, TimeUnit::Years)) => format!("vor {}", count(n, "Jahr", "Jahren")),
+    },
+    totals: |files, directories, size| {
+        format!(
+            "{}, {}, insgesamt {size}",
+            count(files, "Datei", "Dateien"),
+            count(directories, "Verzeichnis", "Verzeichnisse")
+        )
+    },
+    entries_shown: |shown, total| format!("{shown} von {total} Einträgen angezeigt. "),
+    show_all: "Alle anzeigen",
+    to_list_remaining: |remaining| format!(", um die übrigen {remaining} aufzulisten."),
+    refine_prompt: "Oder nur Einträge auflisten, deren Name dies enthält",
+    refine: "Eingrenzen",
+    filter: "Filtern",
+    filter_help: "Nur Einträge zeigen, deren Name diesen Text enthält (/)",
+    skip_to_listing: "Zur Dateiliste springen",
+    back_to_top: "Nach oben",
+    download_archive: |extension| format!(".{extension} herunterladen"),
+    list_view: "Listenansicht",
+    gallery_view: "Galerieansicht",
+    tree_view: "Baumansicht",
+    expand: "Aufklappen",
+    collapse: "Zuklappen",
+    mix_directories: "Verzeichnisse zwischen Dateien",
+    directories_first: "Verzeichnisse zuerst",
+    hide_hidden_files: "Versteckte Dateien ausblenden",
+    show_hidden_files: "Versteckte Dateien anzeigen",
+    flat_listing: "Alle Dateien",
+    flat_listing_help: "Die Dateien aller Unterverzeichnisse mit ihren Pfaden auflisten",
+    nested_listing: "Nach Verzeichnis",
+    nested_listing_help: "Nur die Einträge dieses Verzeichnisses auflisten",
+    feed: "Feed",
+    feed_help: "Die hier hinzugefügten oder geänderten Dateien abonnieren",
+    checksums: "Prüfsummen",
+    checksums_help: "Die SHA-256-Prüfsummen der Dateien hier herunterladen",
+    torrent: "Torrent",
+    torrent_help: "Einen Torrent hiervon herunterladen, den dieser Server seedet",
+    relative_and_absolute_times: "Relative und absolute Zeiten",
+    relative_times: "Relative Zeiten",
+    absolute_times: "Absolute Zeiten",
+    playlist: "Wiedergabeliste",
+    playlist_help: "Wiedergabeliste der Audio- und Videodateien, für Mediaplayer",
+    qr_code: "QR-Code",
+    change_theme: "Design ändern...",
+    default_theme: "Standard",
+    light: "hell",
+    dark: "dunkel",
+    switch_theme: |theme| format!("Zum Design {theme} wechseln"),
+    download_folder: "Ordner herunterladen:",
+    click_to_copy: "Zum Kopieren klicken!",
+    drop_to_upload: "Datei hier ablegen, um sie hochzuladen",
+    select_file_to_upload: "Datei zum Hochladen auswählen oder irgendwo ins Fenster ziehen",
+    upload_file: "Datei hochladen",
+    create_new_directory: "Neues Verzeichnis erstellen",
+    directory_name_prompt: "Name des zu erstellenden Verzeichnisses angeben",
+    directory_name: "Verzeichnisname",
+    create_directory: "Verzeichnis erstellen",
+    starting_upload: "Upload wird gestartet...",
+    cancel: "ABBRECHEN",
+    uploads_remaining: "{count} Uploads verbleibend...",
+    uploads_complete: "Fertig! Seite wird neu geladen!",
+    uploading_count: "Wird hochgeladen: {count}",
+    pending_count: "Wartend: {count}",
+    complete_count: "Fertig: {count}",
+    cancelled_count: "Abgebrochen: {count}",
+    failed_count: "Fehlgeschlagen: {count}",
+    raw: "Rohdaten",
+    directory: "Verzeichnis",
+    open: "Öffnen",
+    download: "Herunterladen",
+    download_file: |name| format!("{name} herunterladen"),
+    previous: "Zurück",
+    next: "Weiter",
+    close: "Schließen",
+    sort: "Sortieren",
+    no_exif_data: "Dieses Bild hat keine EXIF-Daten.",
+    send_a_file: "Datei senden",
+    send_file: "Datei senden",
+    file_received: "Die Datei wurde empfangen, diese Seite kann geschlossen werden.",
+    administration: "Verwaltung",
+    transfers_in_progress: "Laufende Übertragungen",
+    no_transfers: "Keine laufenden Übertragungen.",
+    path: "Pfad",
+    user: "Benutzer",
+    client: "Client",
+    progress: "Fortschritt",
+    uploads_enabled: "Uploads sind aktiviert.",
+    uploads_paused: "Uploads sind pausiert.",
+    pause_uploads: "Uploads pausieren",
+    resume_uploads: "Uploads fortsetzen",
+    clear_caches: "Caches leeren",
+    recent_events: "Letzte Ereignisse",
+    no_events: "Bisher ist nichts passiert.",
+    time: "Zeit",
+    event: "Ereignis",
+    back_to_listing: "Zurück zur Dateiliste",
+};
+
+static FR: Texts = Texts {
+    code: "fr",
+    name: "Nom",
+    size: "Taille",
+    last_modification: "Dernière modification",
+    permissions: "Permissions",
+    owner: "Propriétaire",
+    group: "Groupe",
+    parent_directory: "Dossier parent",
+    sort_ascending: |column| format!("Trier par {} en ordre croissant", lowercase_first(column)),
+    sort_descending: |column| format!("Trier par {} en ordre décroissant", lowercase_first(column)),
+    preview: "aperçu",
+    preview_help: "Aperçu",
+    details: "détails",
+    details_help: "Données EXIF",
+    copy_link: "copier le lien",
+    copy_link_help: "Copier l'adresse de cette entrée",
+    link_copied: "copié",
+    broken_link: "Lien cassé",
+    relative_time: |time| match elapsed(time) {
+        None => "à l'instant".to_string(),
+        Some((n, TimeUnit::Minutes)) => format!("il y a {}", count(n, "minute", "minutes")),
+        Some((n, TimeUnit::Hours)) => format!("il y a {}", count(n, "heure", "heures")),
+        Some((n, TimeUnit::Days)) => format!("il y a {}", count(n, "jour", "jours")),
+        Some((n, TimeUnit::Weeks)) => format!("il y a {}", count(n, "semaine", "semaines")),
+        Some((n, TimeUnit::Months)) => format!("il y a {n} mois"),
+        Some((n, TimeUnit::Years)) => format!("il y a {}", count(n, "an", "ans")),
+    },
+    totals: |files, directories, size| {
+        // French counts zero in the singular too
+        let count = |n: u64, one, many| format!("{n} {}", if n > 1 { many } else { one });
+        format!(
+            "{}, {}, {size} au total",
+            count(files, "fichier", "fichiers"),
+            count(directories, "dossier", "dossiers")
+        )
+    },
+    entries_shown: |shown, total| format!("{shown} éléments affichés sur {total}. "),
+    show_all: "Tout afficher",
+    to_list_remaining: |remaining| format!(" pour lister les {remaining} restants."),
+    refine_prompt: "Ou ne lister que les entrées dont le nom contient",
+    refine: "Affiner",
+    filter: "Filtrer",
+    filter_help: "N'afficher que les entrées dont le nom contient ce texte (/)",
+    skip_to_listing: "Aller à la liste",
+    back_to_top: "Retour en haut",
+    download_archive: |extension| format!("Télécharger en .{extension}"),
+    list_view: "Vue en liste",
+    gallery_view: "Vue en galerie",
+    tree_view: "Vue en arbre",
+    expand: "Déplier",
+    collapse: "Replier",
+    mix_directories: "Mélanger dossiers et fichiers",
+    directories_first: "Dossiers en premier",
+    hide_hidden_files: "Masquer les fichiers cachés",
+    show_hidden_files: "Afficher les fichiers cachés",
+    flat_listing: "Tous les fichiers",
+    flat_listing_help: "Lister les fichiers de tous les sous-dossiers, avec leurs chemins",
+    nested_listing: "Par dossier",
+    nested_listing_help: "Ne lister que les entrées de ce dossier",
+    feed: "Flux",
+    feed_help: "S'abonner aux fichiers ajoutés ou modifiés ici",
+    checksums: "Sommes de contrôle",
+    checksums_help: "Télécharger les sommes de contrôle SHA-256 des fichiers ici",
+    torrent: "Torrent",
+    torrent_help: "Télécharger un torrent de ceci, partagé par ce serveur",
+    relative_and_absolute_times: "Heures relatives et absolues",
+    relative_times: "Heures relatives",
+    absolute_times: "Heures absolues",
+    playlist: "Liste de lecture",
+    playlist_help: "Liste de lecture des fichiers audio et vidéo, pour les lecteurs multimédias",
+    qr_code: "Code QR",
+    change_theme: "Changer de thème...",
+    default_theme: "Par défaut",
+    light: "clair",
+    dark: "sombre",
+    switch_theme: |theme| format!("Passer au thème {theme}"),
+    download_folder: "Télécharger le dossier :",
+    click_to_copy: "Cliquez pour copier !",
+    drop_to_upload: "Déposez votre fichier ici pour l'envoyer",
+    select_file_to_upload: "Sélectionnez un fichier à envoyer ou faites-le glisser dans la fenêtre",
+    upload_file: "Envoyer le fichier",
+    create_new_directory: "Créer un nouveau dossier",
+    directory_name_prompt: "Indiquez le nom du dossier à créer",
+    directory_name: "Nom du dossier",
+    create_directory: "Créer le dossier",
+    starting_upload: "Démarrage de l'envoi...",
+    cancel: "ANNULER",
+    uploads_remaining: "{count} envois restants...",
+    uploads_complete: "Terminé ! Rechargement de la page !",
+    uploading_count: "En cours : {count}",
+    pending_count: "En attente : {count}",
+    complete_count: "Terminés : {count}",
+    cancelled_count: "Annulés : {count}",
+    failed_count: "Échoués : {count}",
+    raw: "Brut",
+    directory: "Dossier",
+    open: "Ouvrir",
+    download: "Télécharger",
+    download_file: |name| format!("Télécharger {name}"),
+    previous: "Précédent",
+    next: "Suivant",
+    close: "Fermer",
+    sort: "Trier",
+    no_exif_data: "Cette image n'a pas de données EXIF.",
+    send_a_file: "Envoyer un fichier",
+    send_file: "Envoyer le fichier",
+    file_received: "Le fichier a été reçu, cette page peut être fermée.",
+    administration: "Administration",
+    transfers_in_progress: "Transferts en cours",
+    no_transfers: "Aucun transfert en cours.",
+    path: "Chemin",
+    user: "Utilisateur",
+    client: "Client",
+    progress: "Progression",
+    uploads_enabled: "Les envois sont activés.",
+    uploads_paused: "Les envois sont en pause.",
+    pause_uploads: "Mettre les envois en pause",
+    resume_uploads: "Reprendre les envois",
+    clear_caches: "Vider les caches",
+    recent_events: "Événements récents",
+    no_events: "Rien ne s'est encore passé.",
+    time: "Heure",
+    event: "Événement",
+    back_to_listing: "Retour à la liste des fichiers",
+};
+
+static ES: Texts = Texts {
+    code: "es",
+    name: "Nombre",
+    size: "Tamaño",
+    last_modification: "Última modificación",
+    permissions: "Permisos",
+    owner: "Propietario",
+    group: "Grupo",
+    parent_directory: "Directorio superior",
+    sort_ascending: |column| {
+        format!(
+            "Ordenar por {} en orden ascendente",
+            lowercase_first(column)
+        )
+    },
+    sort_descending: |column| {
+        format!(
+            "Ordenar por {} en orden descendente",
+            lowercase_first(column)
+        )
+    },
+    preview: "vista previa",
+    preview_help: "Vista previa",
+    details: "detalles",
+    details_help: "Datos EXIF",
+    copy_link: "copiar enlace",
+    copy_link_help: "Copiar la dirección de esta entrada",
+    link_copied: "copiado",
+    broken_link: "Enlace roto",
+    relative_time: |time| match elapsed(time) {
+        None => "justo ahora".to_string(),
+        Some((n, TimeUnit::Minutes)) => format!("hace {}", count(n, "minuto", "minutos")),
+        Some((n, TimeUnit::Hours)) => format!("hace {}", count(n, "hora", "horas")),
+        Some((n, TimeUnit::Days)) => format!("hace {}", count(n, "día", "días")),
+        Some((n, TimeUnit::Weeks)) => format!("hace {}", count(n, "semana", "semanas")),
+        Some((n, TimeUnit::Months)) => format!("hace {}", count(n, "mes", "meses")),
+        Some((n, TimeUnit::Years)) => format!("hace {}", count(n, "año", "años")),
+    },
+    totals: |files, directories, size| {
+        format!(
+            "{}, {}, {size} en total",
+            count(files, "archivo", "archivos"),
+            count(directories, "directorio", "directorios")
+        )
+    },
+    entries_shown: |shown, total| format!("Se muestran {shown} de {total} elementos. "),
+    show_all: "Mostrar todos",
+    to_list_remaining: |remaining| format!(" para listar los {remaining} restantes."),
+    refine_prompt: "O listar solo los elementos cuyo nombre contiene",
+    refine: "Refinar",
+    filter: "Filtrar",
+    filter_help: "Mostrar solo las entradas cuyo nombre contiene este texto (/)",
+    skip_to_listing: "Saltar a la lista",
+    back_to_top: "Volver arriba",
+    download_archive: |extension| format!("Descargar .{extension}"),
+    list_view: "Vista de lista",
+    gallery_view: "Vista de galería",
+    tree_view: "Vista de árbol",
+    expand: "Expandir",
+    collapse: "Contraer",
+    mix_directories: "Mezclar directorios con archivos",
+    directories_first: "Directorios primero",
+    hide_hidden_files: "Ocultar archivos ocultos",
+    show_hidden_files: "Mostrar archivos ocultos",
+    flat_listing: "Todos los archivos",
+    flat_listing_help: "Listar los archivos de todos los subdirectorios, con sus rutas",
+    nested_listing: "Por directorio",
+    nested_listing_help: "Listar solo las entradas de este directorio",
+    feed: "Feed",
+    feed_help: "Suscribirse a los archivos añadidos o modificados aquí",
+    checksums: "Sumas de verificación",
+    checksums_help: "Descargar las sumas de verificación SHA-256 de los archivos aquí",
+    torrent: "Torrent",
+    torrent_help: "Descargar un torrent de esto, compartido por este servidor",
+    relative_and_absolute_times: "Horas relativas y absolutas",
+    relative_times: "Horas relativas",
+    absolute_times: "Horas absolutas",
+    playlist: "Lista de reproducción",
+    playlist_help: "Lista de reproducción de los archivos de audio y vídeo, para reproductores",
+    qr_code: "Código QR",
+    change_theme: "Cambiar tema...",
+    default_theme: "Predeterminado",
+    light: "claro",
+    dark: "oscuro",
+    switch_theme: |theme| format!("Cambiar al tema {theme}"),
+    download_folder: "Descargar la carpeta:",
+    click_to_copy: "¡Haz clic para copiar!",
+    drop_to_upload: "Suelta tu archivo aquí para subirlo",
+    select_file_to_upload: "Selecciona un archivo para subirlo o arrástralo a la ventana",
+    upload_file: "Subir archivo",
+    create_new_directory: "Crear un directorio nuevo",
+    directory_name_prompt: "Indica el nombre del directorio a crear",
+    directory_name: "Nombre del directorio",
+    create_directory: "Crear directorio",
+    starting_upload: "Iniciando la subida...",
+    cancel: "CANCELAR",
+    uploads_remaining: "{count} subidas pendientes...",
+    uploads_complete: "¡Completado! ¡Recargando la página!",
+    uploading_count: "Subiendo: {count}",
+    pending_count: "En espera: {count}",
+    complete_count: "Completadas: {count}",
+    cancelled_count: "Canceladas: {count}",
+    failed_count: "Fallidas: {count}",
+    raw: "Sin formato",
+    directory: "Directorio",
+    open: "Abrir",
+    download: "Descargar",
+    download_file: |name| format!("Descargar {name}"),
+    previous: "Anterior",
+    next: "Siguiente",
+    close: "Cerrar",
+    sort: "Ordenar",
+    no_exif_data: "Esta imagen no tiene datos EXIF.",
+    send_a_file: "Enviar un archivo",
+    send_file: "Enviar archivo",
+    file_received: "Se ha recibido el archivo, ya puedes cerrar esta página.",
+    administration: "Administración",
+    transfers_in_progress: "Transferencias en curso",
+    no_transfers: "No hay transferencias en curso.",
+    path: "Ruta",
+    user: "Usuario",
+    client: "Cliente",
+    progress: "Progreso",
+    uploads_enabled: "Las subidas están activadas.",
+    uploads_paused: "Las subidas están en pausa.",
+    pause_uploads: "Pausar las subidas",
+    resume_uploads: "Reanudar las subidas",
+    clear_caches: "Vaciar las cachés",
+    recent_events: "Eventos recientes",
+    no_events: "Todavía no ha pasado nada.",
+    time: "Hora",
+    event: "Evento",
+    back_to_listing: "Volver a la lista de archivos",
+};
+
+static NL: Texts = Texts {
+    code: "nl",
+    name: "Naam",
+    size: "Grootte",
+    last_modification: "Laatst gewijzigd",
+    permissions: "Rechten",
+    owner: "Eigenaar",
+    group: "Groep",
+    parent_directory: "Bovenliggende map",
+    sort_ascending: |column| {
+        format!(
+            "Sorteren op {} in oplopende volgorde",
+            lowercase_first(column)
+        )
+    },
+    sort_descending: |column| {
+        format!(
+            "Sorteren op {} in aflopende volgorde",
+            lowercase_first(column)
+        )
+    },
+    preview: "voorbeeld",
+    preview_help: "Voorbeeld",
+    details: "details",
+    details_help: "EXIF-gegevens",
+    copy_link: "link kopiëren",
+    copy_link_help: "Het adres van dit item kopiëren",
+    link_copied: "gekopieerd",
+    broken_link: "Verbroken link",
+    relative_time: |time| match elapsed(time) {
+        None => "zojuist".to_string(),
+        Some((n, TimeUnit::Minutes)) => format!("{} geleden", count(n, "minuut", "minuten")),
+        Some((n, TimeUnit::Hours)) => format!("{n} uur geleden"),
+        Some((n, TimeUnit::Days)) => format!("{} geleden", count(n, "dag", "dagen")),
+        Some((n, TimeUnit::Weeks)) => format!("{} geleden", count(n, "week", "weken")),
+        Some((n, TimeUnit::Months)) => format!("{} geleden", count(n, "maand", "maanden")),
+        Some((n, TimeUnit::Years)) => format!("{n} jaar geleden"),
+    },
+    totals: |files, directories, size| {
+        format!(
+            "{}, {}, {size} in totaal",
+            count(files, "bestand", "bestanden"),
+            count(directories, "map", "mappen")
+        )
+    },
+    entries_shown: |shown, total| format!("{shown} van {total} items getoond. "),
+    show_all: "Alles tonen",
+    to_list_remaining: |remaining| format!(" om de overige {remaining} te tonen."),
+    refine_prompt: "Of alleen items tonen waarvan de naam dit bevat",
+    refine: "Verfijnen",
+    filter: "Filteren",
+    filter_help: "Alleen items tonen waarvan de naam deze tekst bevat (/)",
+    skip_to_listing: "Naar de lijst springen",
+    back_to_top: "Terug naar boven",
+    download_archive: |extension| format!(".{extension} downloaden"),
+    list_view: "Lijstweergave",
+    gallery_view: "Galerieweergave",
+    tree_view: "Boomweergave",
+    expand: "Uitklappen",
+    collapse: "Inklappen",
+    mix_directories: "Mappen tussen bestanden",
+    directories_first: "Mappen eerst",
+    hide_hidden_files: "Verborgen bestanden verbergen",
+    show_hidden_files: "Verborgen bestanden tonen",
+    flat_listing: "Alle bestanden",
+    flat_listing_help: "De bestanden van alle submappen met hun paden tonen",
+    nested_listing: "Per map",
+    nested_listing_help: "Alleen de items van deze map tonen",
+    feed: "Feed",
+    feed_help: "Abonneren op de hier toegevoegde of gewijzigde bestanden",
+    checksums: "Controlegetallen",
+    checksums_help: "De SHA-256-controlegetallen van de bestanden hier downloaden",
+    torrent: "Torrent",
+    torrent_help: "Een torrent hiervan downloaden, geseed door deze server",
+    relative_and_absolute_times: "Relatieve en absolute tijden",
+    relative_times: "Relatieve tijden",
+    absolute_times: "Absolute tijden",
+    playlist: "Afspeellijst",
+    playlist_help: "Afspeellijst van de audio- en videobestanden, voor mediaspelers",
+    qr_code: "QR-code",
+    change_theme: "Thema wijzigen...",
+    default_theme: "Standaard",
+    light: "licht",
+    dark: "donker",
+    switch_theme: |theme| format!("Overschakelen naar het thema {theme}"),
+    download_folder: "Map downloaden:",
+    click_to_copy: "Klik om te kopiëren!",
+    drop_to_upload: "Sleep je bestand hierheen om het te uploaden",
+    select_file_to_upload: "Kies een bestand om te uploaden of sleep het ergens in het venster",
+    upload_file: "Bestand uploaden",
+    create_new_directory: "Nieuwe map aanmaken",
+    directory_name_prompt: "Geef de naam op van de aan te maken map",
+    directory_name: "Mapnaam",
+    create_directory: "Map aanmaken",
+    starting_upload: "Upload wordt gestart...",
+    cancel: "ANNULEREN",
+    uploads_remaining: "{count} uploads resterend...",
+    uploads_complete: "Klaar! Pagina wordt herladen!",
+    uploading_count: "Bezig: {count}",
+    pending_count: "Wachtend: {count}",
+    complete_count: "Voltooid: {count}",
+    cancelled_count: "Geannuleerd: {count}",
+    failed_count: "Mislukt: {count}",
+    raw: "Onbewerkt",
+    directory: "Map",
+    open: "Openen",
+    download: "Downloaden",
+    download_file: |name| format!("{name} downloaden"),
+    previous: "Vorige",
+    next: "Volgende",
+    close: "Sluiten",
+    sort: "Sorteren",
+    no_exif_data: "Deze afbeelding heeft geen EXIF-gegevens.",
+    send_a_file: "Een bestand versturen",
+    send_file: "Bestand versturen",
+    file_received: "Het bestand is ontvangen, deze pagina kan worden gesloten.",
+    administration: "Beheer",
+    transfers_in_progress: "Lopende overdrachten",
+    no_transfers: "Geen lopende overdrachten.",
+    path: "Pad",
+    user: "Gebruiker",
+    client: "Client",
+    progress: "Voortgang",
+    uploads_enabled: "Uploads zijn ingeschakeld.",
+    uploads_paused: "Uploads zijn gepauzeerd.",
+    pause_uploads: "Uploads pauzeren",
+    resume_uploads: "Uploads hervatten",
+    clear_caches: "Caches legen",
+    recent_events: "Recente gebeurtenissen",
+    no_events: "Er is nog niets gebeurd.",
+    time: "Tijd",
+    event: "Gebeurtenis",
+    back_to_listing: "Terug naar de bestandslijst",
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("de-DE,de;q=0.9,en;q=0.8", Some(Lang::De))]
+    #[case("en-US,en;q=0.5", Some(Lang::En))]
+    #[case("ja, fr;q=0.4, nl;q=0.7", Some(Lang::Nl))]
+    #[case("pt-BR, es;q=0", None)]
+    #[case("*", None)]
+    #[case("", None)]
+    fn negotiates_languages(#[case] accept_language: &str, #[case] expected: Option<Lang>) {
+        assert_eq!(Lang::negotiate(accept_language), expected);
+    }
+
+    #[test]
+    fn fills_in_counts() {
+        assert_eq!((EN.totals)(1, 2, "3 B"), "1 file, 2 directories, 3 B total");
+        assert_eq!(
+            (FR.totals)(0, 1, "3 B"),
+            "0 fichier, 1 dossier, 3 B au total"
+        );
+        assert_eq!(
+            (DE.totals)(2, 1, "3 B"),
+            "2 Dateien, 1 Verzeichnis, insgesamt 3 B"
+        );
+        assert_eq!(
+            (EN.sort_ascending)(EN.last_modification),
+            "Sort by last modification in ascending order"
+        );
+    }
+}
@@ -0,0 +1,69 @@
+//! Range requests made on the condition that the file didn't change, with an If-Range header
+//!
+//! actix-files answers range requests of files, whether a single one or those of a directory is
+//! served, but ignores If-Range. Download managers resuming a download send it to make sure the
+//! file is still the one they started with, and need all of it otherwise, rather than a range of
+//! a different file.
+use actix_files::NamedFile;
+use actix_web::{
+    body::MessageBody,
+    dev::{ServiceRequest, ServiceResponse},
+    http::header::{self, EntityTag, Header, HeaderMap, HttpDate, IfRange},
+    middleware::Next,
+    web,
+};
+
+use crate::{MiniserveConfig, file_utils};
+
+/// Drops the range asked for if the file changed since the validator of If-Range was given
+pub async fn if_range_middleware(
+    mut req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let if_range = req
+        .headers()
+        .contains_key(header::RANGE)
+        .then(|| IfRange::parse(&req).ok())
+        .flatten();
+    if let Some(if_range) = if_range {
+        let conf = req
+            .app_data::<web::Data<MiniserveConfig>>()
+            .expect("Could not get miniserve config")
+            .clone();
+        let file = if conf.path.is_file() {
+            Some(conf.path.clone())
+        } else {
+            file_utils::requested_file(&conf, req.match_info().unprocessed())
+        };
+        // The validators of the file are those actix-files would answer with, from its metadata
+        let unchanged = match file {
+            Some(file) => match NamedFile::open_async(&file).await {
+                Ok(file) => is_unchanged(file.into_response(req.request()).headers(), &if_range),
+                Err(_) => true,
+            },
+            // Nothing of it is known here, so it's left to whatever answers the request
+            None => true,
+        };
+        if !unchanged {
+            req.headers_mut().remove(header::RANGE);
+        }
+    }
+
+    next.call(req).await
+}
+
+/// Whether the file answered with `headers` still has the validator given with If-Range
+///
+/// Entity tags are compared strongly, as ranges of different contents mustn't be combined, and
+/// dates have to match the last modification exactly.
+fn is_unchanged(headers: &HeaderMap, if_range: &IfRange) -> bool {
+    let header = |name| headers.get(name).and_then(|value| value.to_str().ok());
+    match if_range {
+        IfRange::EntityTag(tag) => header(header::ETAG)
+            .and_then(|etag| etag.parse::<EntityTag>().ok())
+            .is_some_and(|etag| etag.strong_eq(tag)),
+        IfRange::Date(date) => header(header::LAST_MODIFIED)
+            .and_then(|modified| modified.parse::<HttpDate>().ok())
+            .is_some_and(|modified| modified == *date),
+    }
+}
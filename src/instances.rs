@@ -0,0 +1,133 @@
+//! Several servers started by one process, described in a file given with --instances
+//!
+//! Each line of the file has the options and path of a server, as they'd be given on the command
+//! line, so that one service can host several shares. Words are split like a shell would, without
+//! expanding anything, and `MINISERVE_*` environment variables aren't read for them.
+use std::path::Path;
+
+use anyhow::{Context, Result, bail};
+
+use crate::{MiniserveConfig, args::CliArgs, config};
+
+/// Reads the configurations of the servers described in a file
+pub fn read_instances(path: &Path) -> Result<Vec<MiniserveConfig>> {
+    let content =
+        std::fs::read_to_string(path).with_context(|| format!("Failed to read {path:?}"))?;
+
+    let mut configs = vec![];
+    for (index, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let config = parse_instance(line)
+            .with_context(|| format!("Invalid server on line {} of {path:?}", index + 1))?;
+        configs.push(config);
+    }
+
+    if configs.is_empty() {
+        bail!("{path:?} doesn't describe any server");
+    }
+    Ok(configs)
+}
+
+/// Parses the configuration of a server from a line of the file
+fn parse_instance(line: &str) -> Result<MiniserveConfig> {
+    let args = config::parse_args(split_words(line)?.into_iter().map(Into::into))?;
+    if args.path.is_none() {
+        bail!("The path to serve isn't given");
+    }
+    if let Some(option) = process_option(&args) {
+        bail!("{option} affects the whole process, so it can't be given for a single server");
+    }
+    MiniserveConfig::try_from_args(args)
+}
+
+/// The first option given which affects the whole process rather than a single server
+fn process_option(args: &CliArgs) -> Option<&'static str> {
+    #[cfg(unix)]
+    if args.daemon {
+        return Some("--daemon");
+    }
+    #[cfg(unix)]
+    if args.pid_file.is_some() {
+        return Some("--pid-file");
+    }
+    #[cfg(unix)]
+    if args.user.is_some() || args.group.is_some() {
+        return Some("--user or --group");
+    }
+    #[cfg(target_os = "linux")]
+    if args.sandbox {
+        return Some("--sandbox");
+    }
+    if args.print_completions.is_some() {
+        return Some("--print-completions");
+    }
+    if args.print_manpage {
+        return Some("--print-manpage");
+    }
+    None
+}
+
+/// Splits a line into words at whitespace, keeping it inside quotes or escaped with a backslash
+fn split_words(line: &str) -> Result<Vec<String>> {
+    let mut words = vec![];
+    let mut word: Option<String> = None;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => words.extend(word.take()),
+            '\'' | '"' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some(end) if end == c => break,
+                        Some('\\') if c == '"' => match chars.next() {
+                            Some(escaped @ ('"' | '\\')) => word.push(escaped),
+                            Some(other) => {
+                                word.push('\\');
+                                word.push(other);
+                            }
+                            None => bail!("Unterminated quote"),
+                        },
+                        Some(other) => word.push(other),
+                        None => bail!("Unterminated quote"),
+                    }
+                }
+            }
+            '\\' => {
+                let escaped = chars.next().context("Trailing backslash")?;
+                word.get_or_insert_with(String::new).push(escaped);
+            }
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("-p 8080 /srv/files", &["-p", "8080", "/srv/files"])]
+    #[case("  --title 'My files'  /srv ", &["--title", "My files", "/srv"])]
+    #[case(r#"--title "Say \"hi\"" /srv"#, &["--title", r#"Say "hi""#, "/srv"])]
+    #[case(r"/srv/my\ files", &["/srv/my files"])]
+    #[case("--title '' /srv", &["--title", "", "/srv"])]
+    #[case("-a 'joe:pass word'x", &["-a", "joe:pass wordx"])]
+    fn splits_words(#[case] line: &str, #[case] expected: &[&str]) {
+        assert_eq!(split_words(line).unwrap(), expected);
+    }
+
+    #[rstest]
+    #[case("--title 'My files /srv")]
+    #[case(r#"--title "My files /srv"#)]
+    #[case(r"/srv\")]
+    fn rejects_unterminated_words(#[case] line: &str) {
+        assert!(split_words(line).is_err());
+    }
+}
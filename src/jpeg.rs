@@ -0,0 +1,754 @@
+//! Decoding and encoding of baseline JPEG images, for resized images
+//!
+//! Only what photos from cameras and phones use is supported: baseline Huffman coding with one or
+//! three components, in any sampling. Progressive images aren't decoded.
+use crate::resize::Image;
+
+/// Order in which the coefficients of a block are coded
+#[rustfmt::skip]
+const ZIGZAG: [usize; 64] = [
+     0,  1,  8, 16,  9,  2,  3, 10,
+    17, 24, 32, 25, 18, 11,  4,  5,
+    12, 19, 26, 33, 40, 48, 41, 34,
+    27, 20, 13,  6,  7, 14, 21, 28,
+    35, 42, 49, 56, 57, 50, 43, 36,
+    29, 22, 15, 23, 30, 37, 44, 51,
+    58, 59, 52, 45, 38, 31, 39, 46,
+    53, 60, 61, 54, 47, 55, 62, 63,
+];
+
+/// Cosines of the discrete cosine transform, with its normalization: `COS[x][u]`
+fn cosines() -> [[f32; 8]; 8] {
+    let mut table = [[0.0; 8]; 8];
+    for (x, row) in table.iter_mut().enumerate() {
+        for (u, value) in row.iter_mut().enumerate() {
+            let scale = if u == 0 { 1.0 / 2f32.sqrt() } else { 1.0 };
+            *value = scale / 2.0 * (((2 * x + 1) * u) as f32 * std::f32::consts::PI / 16.0).cos();
+        }
+    }
+    table
+}
+
+/// A Huffman table, as described in section F.2.2.3 of the JPEG standard
+#[derive(Clone, Default)]
+struct Huffman {
+    max_code: [i32; 17],
+    val_ptr: [i32; 17],
+    min_code: [i32; 17],
+    values: Vec<u8>,
+}
+
+impl Huffman {
+    fn new(counts: &[u8; 16], values: &[u8]) -> Self {
+        let mut table = Self {
+            values: values.to_vec(),
+            ..Self::default()
+        };
+        let (mut code, mut k) = (0, 0);
+        for len in 1..=16 {
+            let count = counts[len - 1] as i32;
+            if count == 0 {
+                table.max_code[len] = -1;
+            } else {
+                table.val_ptr[len] = k;
+                table.min_code[len] = code;
+                code += count;
+                k += count;
+                table.max_code[len] = code - 1;
+            }
+            code <<= 1;
+        }
+        table
+    }
+}
+
+/// Reads the bits of entropy-coded data, which stops at the first marker
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    bits: u32,
+    len: u32,
+}
+
+impl BitReader<'_> {
+    fn bit(&mut self) -> Option<u32> {
+        if self.len == 0 {
+            let byte = *self.data.get(self.pos)?;
+            if byte == 0xff {
+                // Stuffed zero bytes follow data bytes of 0xff, anything else is a marker
+                if self.data.get(self.pos + 1) != Some(&0) {
+                    return None;
+                }
+                self.pos += 1;
+            }
+            self.pos += 1;
+            self.bits = byte as u32;
+            self.len = 8;
+        }
+        self.len -= 1;
+        Some((self.bits >> self.len) & 1)
+    }
+
+    fn bits(&mut self, count: u8) -> Option<u32> {
+        (0..count).try_fold(0, |value, _| Some((value << 1) | self.bit()?))
+    }
+
+    fn decode(&mut self, table: &Huffman) -> Option<u8> {
+        let mut code = self.bit()? as i32;
+        for len in 1..=16 {
+            if table.max_code[len] >= code {
+                let index = table.val_ptr[len] + code - table.min_code[len];
+                return table.values.get(index as usize).copied();
+            }
+            code = (code << 1) | self.bit()? as i32;
+        }
+        None
+    }
+
+    /// Reads a coefficient coded with `size` bits, of which there are at most 16
+    fn coefficient(&mut self, size: u8) -> Option<i32> {
+        if size == 0 {
+            return Some(0);
+        }
+        if size > 16 {
+            return None;
+        }
+        let value = self.bits(size)? as i32;
+        Some(if value < 1 << (size - 1) {
+            value - (1 << size) + 1
+        } else {
+            value
+        })
+    }
+
+    /// Skips to the data following the restart marker expected next
+    fn restart(&mut self) -> Option<()> {
+        self.len = 0;
+        match self.data.get(self.pos..self.pos + 2)? {
+            [0xff, 0xd0..=0xd7] => {
+                self.pos += 2;
+                Some(())
+            }
+            _ => None,
+        }
+    }
+}
+
+/// A component of a frame
+struct Component {
+    id: u8,
+    h: usize,
+    v: usize,
+    quant: usize,
+    dc: usize,
+    ac: usize,
+}
+
+/// Decodes a baseline JPEG image into RGB, or grayscale, pixels
+///
+/// Images of more than `max_pixels` pixels are refused as soon as their size is known, before
+/// anything is allocated for them.
+pub fn decode(data: &[u8], max_pixels: usize) -> Option<Image> {
+    if !data.starts_with(&[0xff, 0xd8]) {
+        return None;
+    }
+    let mut quant = [[0u16; 64]; 4];
+    let mut dc_tables = vec![Huffman::default(); 4];
+    let mut ac_tables = vec![Huffman::default(); 4];
+    let mut components = Vec::new();
+    let (mut width, mut height) = (0, 0);
+    let mut restart_interval = 0;
+    let mut pos = 2;
+    loop {
+        let [0xff, marker, len_high, len_low] = *data.get(pos..pos + 4)? else {
+            return None;
+        };
+        let len = u16::from_be_bytes([len_high, len_low]) as usize;
+        let segment = data.get(pos + 4..pos + 2 + len)?;
+        pos += 2 + len;
+        match marker {
+            0xdb => {
+                let mut tables = segment;
+                while let [info, rest @ ..] = tables {
+                    let id = (*info & 3) as usize;
+                    let sixteen_bits = info >> 4 == 1;
+                    let size = if sixteen_bits { 128 } else { 64 };
+                    let values = rest.get(..size)?;
+                    for (i, value) in quant[id].iter_mut().enumerate() {
+                        *value = if sixteen_bits {
+                            u16::from_be_bytes([values[2 * i], values[2 * i + 1]])
+                        } else {
+                            values[i] as u16
+                        };
+                    }
+                    tables = &rest[size..];
+                }
+            }
+            0xc0 | 0xc1 => {
+                let [8, h_high, h_low, w_high, w_low, count, ref specs @ ..] = *segment else {
+                    return None;
+                };
+                height = u16::from_be_bytes([h_high, h_low]) as usize;
+                width = u16::from_be_bytes([w_high, w_low]) as usize;
+                if width.checked_mul(height)? > max_pixels {
+                    return None;
+                }
+                for spec in specs.chunks_exact(3).take(count as usize) {
+                    components.push(Component {
+                        id: spec[0],
+                        h: (spec[1] >> 4) as usize,
+                        v: (spec[1] & 15) as usize,
+                        quant: (spec[2] & 3) as usize,
+                        dc: 0,
+                        ac: 0,
+                    });
+                }
+            }
+            // Progressive and arithmetic coding
+            0xc2 | 0xc3 | 0xc5..=0xc7 | 0xc9..=0xcb | 0xcd..=0xcf => return None,
+            0xc4 => {
+                let mut tables = segment;
+                while let [info, rest @ ..] = tables {
+                    let counts: &[u8; 16] = rest.get(..16)?.try_into().ok()?;
+                    let total = counts.iter().map(|&count| count as usize).sum::<usize>();
+                    let table = Huffman::new(counts, rest.get(16..16 + total)?);
+                    let id = (info & 3) as usize;
+                    if info >> 4 == 0 {
+                        dc_tables[id] = table;
+                    } else {
+                        ac_tables[id] = table;
+                    }
+                    tables = &rest[16 + total..];
+                }
+            }
+            0xdd => restart_interval = u16::from_be_bytes(segment.get(..2)?.try_into().ok()?),
+            0xda => {
+                let count = *segment.first()? as usize;
+                // Components coded in separate scans aren't supported
+                if count != components.len() {
+                    return None;
+                }
+                for spec in segment.get(1..1 + 2 * count)?.chunks_exact(2) {
+                    let component = components.iter_mut().find(|c| c.id == spec[0])?;
+                    component.dc = ((spec[1] >> 4) & 3) as usize;
+                    component.ac = (spec[1] & 3) as usize;
+                }
+                let reader = BitReader {
+                    data: data.get(pos..)?,
+                    pos: 0,
+                    bits: 0,
+                    len: 0,
+                };
+                let tables = Tables {
+                    quant: &quant,
+                    dc: &dc_tables,
+                    ac: &ac_tables,
+                };
+                return decode_scan(
+                    reader,
+                    &components,
+                    &tables,
+                    width,
+                    height,
+                    restart_interval as usize,
+                );
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The tables entropy-coded data is decoded with
+struct Tables<'a> {
+    quant: &'a [[u16; 64]; 4],
+    dc: &'a [Huffman],
+    ac: &'a [Huffman],
+}
+
+fn decode_scan(
+    mut reader: BitReader,
+    components: &[Component],
+    tables: &Tables,
+    width: usize,
+    height: usize,
+    restart_interval: usize,
+) -> Option<Image> {
+    if width == 0 || height == 0 || !matches!(components.len(), 1 | 3) {
+        return None;
+    }
+    let h_max = components.iter().map(|c| c.h).max()?;
+    let v_max = components.iter().map(|c| c.v).max()?;
+    if components
+        .iter()
+        .any(|c| !(1..=4).contains(&c.h) || !(1..=4).contains(&c.v))
+    {
+        return None;
+    }
+    // A single component is coded block by block, whatever its sampling
+    let (h_max, v_max) = if components.len() == 1 {
+        (1, 1)
+    } else {
+        (h_max, v_max)
+    };
+    let mcus_x = width.div_ceil(8 * h_max);
+    let mcus_y = height.div_ceil(8 * v_max);
+    let cos = cosines();
+
+    let mut planes: Vec<(usize, Vec<u8>)> = components
+        .iter()
+        .map(|c| {
+            let (h, v) = if components.len() == 1 {
+                (1, 1)
+            } else {
+                (c.h, c.v)
+            };
+            let stride = mcus_x * h * 8;
+            (stride, vec![0; stride * mcus_y * v * 8])
+        })
+        .collect();
+    let mut predictions = vec![0i32; components.len()];
+    let mut block = [0f32; 64];
+    for mcu in 0..mcus_x * mcus_y {
+        if restart_interval > 0 && mcu > 0 && mcu % restart_interval == 0 {
+            reader.restart()?;
+            predictions.iter_mut().for_each(|p| *p = 0);
+        }
+        let (mcu_x, mcu_y) = (mcu % mcus_x, mcu / mcus_x);
+        for (i, component) in components.iter().enumerate() {
+            let (h, v) = if components.len() == 1 {
+                (1, 1)
+            } else {
+                (component.h, component.v)
+            };
+            let quant = &tables.quant[component.quant];
+            for block_y in 0..v {
+                for block_x in 0..h {
+                    // Coefficients, in natural order
+                    let mut coefficients = [0i32; 64];
+                    let size = reader.decode(tables.dc.get(component.dc)?)?;
+                    // Corrupt data can't be told apart, and mustn't overflow
+                    predictions[i] = predictions[i].wrapping_add(reader.coefficient(size)?);
+                    coefficients[0] = predictions[i].wrapping_mul(quant[0] as i32);
+                    let mut k = 1;
+                    while k < 64 {
+                        let symbol = reader.decode(tables.ac.get(component.ac)?)?;
+                        let (run, size) = ((symbol >> 4) as usize, symbol & 15);
+                        if size == 0 {
+                            if run != 15 {
+                                break;
+                            }
+                            k += 16;
+                            continue;
+                        }
+                        k += run;
+                        if k > 63 {
+                            return None;
+                        }
+                        coefficients[ZIGZAG[k]] =
+                            reader.coefficient(size)?.wrapping_mul(quant[k] as i32);
+                        k += 1;
+                    }
+
+                    inverse_dct(&coefficients, &cos, &mut block);
+                    let (stride, plane) = &mut planes[i];
+                    let x0 = (mcu_x * h + block_x) * 8;
+                    let y0 = (mcu_y * v + block_y) * 8;
+                    for y in 0..8 {
+                        for x in 0..8 {
+                            plane[(y0 + y) * *stride + x0 + x] =
+                                (block[y * 8 + x] + 128.0).round().clamp(0.0, 255.0) as u8;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    let channels = components.len();
+    let mut pixels = vec![0; width * height * channels];
+    for y in 0..height {
+        for x in 0..width {
+            let sample = |i: usize| {
+                let (stride, plane) = &planes[i];
+                let component = &components[i];
+                let (sx, sy) = if channels == 1 {
+                    (x, y)
+                } else {
+                    (x * component.h / h_max, y * component.v / v_max)
+                };
+                plane[sy * stride + sx] as f32
+            };
+            let pixel = &mut pixels[(y * width + x) * channels..][..channels];
+            if channels == 1 {
+                pixel[0] = sample(0) as u8;
+            } else {
+                let (luma, cb, cr) = (sample(0), sample(1) - 128.0, sample(2) - 128.0);
+                pixel[0] = (luma + 1.402 * cr).round().clamp(0.0, 255.0) as u8;
+                pixel[1] = (luma - 0.344136 * cb - 0.714136 * cr)
+                    .round()
+                    .clamp(0.0, 255.0) as u8;
+                pixel[2] = (luma + 1.772 * cb).round().clamp(0.0, 255.0) as u8;
+            }
+        }
+    }
+    Some(Image {
+        width,
+        height,
+        channels,
+        pixels,
+    })
+}
+
+fn inverse_dct(coefficients: &[i32; 64], cos: &[[f32; 8]; 8], out: &mut [f32; 64]) {
+    let mut rows = [0f32; 64];
+    for v in 0..8 {
+        for x in 0..8 {
+            rows[v * 8 + x] = (0..8)
+                .map(|u| cos[x][u] * coefficients[v * 8 + u] as f32)
+                .sum();
+        }
+    }
+    for y in 0..8 {
+        for x in 0..8 {
+            out[y * 8 + x] = (0..8).map(|v| cos[y][v] * rows[v * 8 + x]).sum();
+        }
+    }
+}
+
+fn forward_dct(samples: &[f32; 64], cos: &[[f32; 8]; 8], out: &mut [f32; 64]) {
+    let mut rows = [0f32; 64];
+    for y in 0..8 {
+        for u in 0..8 {
+            rows[y * 8 + u] = (0..8).map(|x| cos[x][u] * samples[y * 8 + x]).sum();
+        }
+    }
+    for v in 0..8 {
+        for u in 0..8 {
+            out[v * 8 + u] = (0..8).map(|y| cos[y][v] * rows[y * 8 + u]).sum();
+        }
+    }
+}
+
+/// Quantization tables suggested by the JPEG standard, for luminance then chrominance
+#[rustfmt::skip]
+const QUANT: [[u8; 64]; 2] = [
+    [
+        16, 11, 10, 16,  24,  40,  51,  61,
+        12, 12, 14, 19,  26,  58,  60,  55,
+        14, 13, 16, 24,  40,  57,  69,  56,
+        14, 17, 22, 29,  51,  87,  80,  62,
+        18, 22, 37, 56,  68, 109, 103,  77,
+        24, 35, 55, 64,  81, 104, 113,  92,
+        49, 64, 78, 87, 103, 121, 120, 101,
+        72, 92, 95, 98, 112, 100, 103,  99,
+    ],
+    [
+        17, 18, 24, 47, 99, 99, 99, 99,
+        18, 21, 26, 66, 99, 99, 99, 99,
+        24, 26, 56, 99, 99, 99, 99, 99,
+        47, 66, 99, 99, 99, 99, 99, 99,
+        99, 99, 99, 99, 99, 99, 99, 99,
+        99, 99, 99, 99, 99, 99, 99, 99,
+        99, 99, 99, 99, 99, 99, 99, 99,
+        99, 99, 99, 99, 99, 99, 99, 99,
+    ],
+];
+
+/// Huffman tables suggested by the JPEG standard: the DC and AC tables of luminance, then those
+/// of chrominance, as counts of codes by length and values
+#[rustfmt::skip]
+const HUFFMAN: [([u8; 16], &[u8]); 4] = [
+    (
+        [0, 1, 5, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0, 0, 0],
+        &[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11],
+    ),
+    (
+        [0, 2, 1, 3, 3, 2, 4, 3, 5, 5, 4, 4, 0, 0, 1, 0x7d],
+        &[
+            0x01, 0x02, 0x03, 0x00, 0x04, 0x11, 0x05, 0x12, 0x21, 0x31, 0x41, 0x06, 0x13, 0x51,
+            0x61, 0x07, 0x22, 0x71, 0x14, 0x32, 0x81, 0x91, 0xa1, 0x08, 0x23, 0x42, 0xb1, 0xc1,
+            0x15, 0x52, 0xd1, 0xf0, 0x24, 0x33, 0x62, 0x72, 0x82, 0x09, 0x0a, 0x16, 0x17, 0x18,
+            0x19, 0x1a, 0x25, 0x26, 0x27, 0x28, 0x29, 0x2a, 0x34, 0x35, 0x36, 0x37, 0x38, 0x39,
+            0x3a, 0x43, 0x44, 0x45, 0x46, 0x47, 0x48, 0x49, 0x4a, 0x53, 0x54, 0x55, 0x56, 0x57,
+            0x58, 0x59, 0x5a, 0x63, 0x64, 0x65, 0x66, 0x67, 0x68, 0x69, 0x6a, 0x73, 0x74, 0x75,
+            0x76, 0x77, 0x78, 0x79, 0x7a, 0x83, 0x84, 0x85, 0x86, 0x87, 0x88, 0x89, 0x8a, 0x92,
+            0x93, 0x94, 0x95, 0x96, 0x97, 0x98, 0x99, 0x9a, 0xa2, 0xa3, 0xa4, 0xa5, 0xa6, 0xa7,
+            0xa8, 0xa9, 0xaa, 0xb2, 0xb3, 0xb4, 0xb5, 0xb6, 0xb7, 0xb8, 0xb9, 0xba, 0xc2, 0xc3,
+            0xc4, 0xc5, 0xc6, 0xc7, 0xc8, 0xc9, 0xca, 0xd2, 0xd3, 0xd4, 0xd5, 0xd6, 0xd7, 0xd8,
+            0xd9, 0xda, 0xe1, 0xe2, 0xe3, 0xe4, 0xe5, 0xe6, 0xe7, 0xe8, 0xe9, 0xea, 0xf1, 0xf2,
+            0xf3, 0xf4, 0xf5, 0xf6, 0xf7, 0xf8, 0xf9, 0xfa,
+        ],
+    ),
+    (
+        [0, 3, 1, 1, 1, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0],
+        &[0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11],
+    ),
+    (
+        [0, 2, 1, 2, 4, 4, 3, 4, 7, 5, 4, 4, 0, 1, 2, 0x77],
+        &[
+            0x00, 0x01, 0x02, 0x03, 0x11, 0x04, 0x05, 0x21, 0x31, 0x06, 0x12, 0x41, 0x51, 0x07,
+            0x61, 0x71, 0x13, 0x22, 0x32, 0x81, 0x08, 0x14, 0x42, 0x91, 0xa1, 0xb1, 0xc1, 0x09,
+            0x23, 0x33, 0x52, 0xf0, 0x15, 0x62, 0x72, 0xd1, 0x0a, 0x16, 0x24, 0x34, 0xe1, 0x25,
+            0xf1, 0x17, 0x18, 0x19, 0x1a, 0x26, 0x27, 0x28, 0x29, 0x2a, 0x35, 0x36, 0x37, 0x38,
+            0x39, 0x3a, 0x43, 0x44, 0x45, 0x46, 0x47, 0x48, 0x49, 0x4a, 0x53, 0x54, 0x55, 0x56,
+            0x57, 0x58, 0x59, 0x5a, 0x63, 0x64, 0x65, 0x66, 0x67, 0x68, 0x69, 0x6a, 0x73, 0x74,
+            0x75, 0x76, 0x77, 0x78, 0x79, 0x7a, 0x82, 0x83, 0x84, 0x85, 0x86, 0x87, 0x88, 0x89,
+            0x8a, 0x92, 0x93, 0x94, 0x95, 0x96, 0x97, 0x98, 0x99, 0x9a, 0xa2, 0xa3, 0xa4, 0xa5,
+            0xa6, 0xa7, 0xa8, 0xa9, 0xaa, 0xb2, 0xb3, 0xb4, 0xb5, 0xb6, 0xb7, 0xb8, 0xb9, 0xba,
+            0xc2, 0xc3, 0xc4, 0xc5, 0xc6, 0xc7, 0xc8, 0xc9, 0xca, 0xd2, 0xd3, 0xd4, 0xd5, 0xd6,
+            0xd7, 0xd8, 0xd9, 0xda, 0xe2, 0xe3, 0xe4, 0xe5, 0xe6, 0xe7, 0xe8, 0xe9, 0xea, 0xf2,
+            0xf3, 0xf4, 0xf5, 0xf6, 0xf7, 0xf8, 0xf9, 0xfa,
+        ],
+    ),
+];
+
+/// Codes of a Huffman table by value, with their length
+fn codes(counts: &[u8; 16], values: &[u8]) -> [(u16, u8); 256] {
+    let mut codes = [(0, 0); 256];
+    let (mut code, mut k) = (0u16, 0);
+    for len in 1..=16 {
+        for _ in 0..counts[len - 1] {
+            codes[values[k] as usize] = (code, len as u8);
+            code += 1;
+            k += 1;
+        }
+        code <<= 1;
+    }
+    codes
+}
+
+/// Writes entropy-coded data, stuffing a zero byte after each byte of 0xff
+struct BitWriter {
+    out: Vec<u8>,
+    bits: u32,
+    len: u32,
+}
+
+impl BitWriter {
+    fn write(&mut self, value: u16, len: u8) {
+        self.bits = (self.bits << len) | (value as u32 & ((1 << len) - 1));
+        self.len += len as u32;
+        while self.len >= 8 {
+            let byte = (self.bits >> (self.len - 8)) as u8;
+            self.out.push(byte);
+            if byte == 0xff {
+                self.out.push(0);
+            }
+            self.len -= 8;
+        }
+    }
+
+    /// Writes a coefficient with the code of its size, from `codes`
+    fn coefficient(&mut self, codes: &[(u16, u8); 256], symbol: u8, value: i32) {
+        let (code, len) = codes[symbol as usize];
+        self.write(code, len);
+        let size = symbol & 15;
+        if size > 0 {
+            let bits = if value < 0 { value - 1 } else { value };
+            self.write(bits as u16, size);
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        // The last byte is padded with ones
+        if self.len > 0 {
+            self.write(0x7f, (8 - self.len) as u8);
+        }
+        self.out
+    }
+}
+
+/// Number of bits needed for the magnitude of a coefficient
+fn magnitude_size(value: i32) -> u8 {
+    (32 - value.unsigned_abs().leading_zeros()) as u8
+}
+
+/// Encodes RGB, or grayscale, pixels as a baseline JPEG image of the given quality, from 1 to 100
+pub fn encode(image: &Image, quality: u8) -> Vec<u8> {
+    let channels = if image.channels < 3 { 1 } else { 3 };
+    let scale = match quality.clamp(1, 100) as u32 {
+        q if q < 50 => 5000 / q,
+        q => 200 - 2 * q,
+    };
+    let quant: Vec<[u8; 64]> = QUANT
+        .iter()
+        .map(|table| table.map(|q| ((q as u32 * scale + 50) / 100).clamp(1, 255) as u8))
+        .collect();
+
+    let mut out = vec![0xff, 0xd8];
+    let mut segment = |marker: u8, data: &[u8]| {
+        out.extend([0xff, marker]);
+        out.extend(((data.len() + 2) as u16).to_be_bytes());
+        out.extend(data);
+    };
+    segment(0xe0, b"JFIF\0\x01\x01\0\0\x01\0\x01\0\0");
+    for (id, table) in quant.iter().enumerate().take(channels.min(2)) {
+        let mut data = vec![id as u8];
+        data.extend(ZIGZAG.map(|i| table[i]));
+        segment(0xdb, &data);
+    }
+    let mut frame = vec![8];
+    frame.extend((image.height as u16).to_be_bytes());
+    frame.extend((image.width as u16).to_be_bytes());
+    frame.push(channels as u8);
+    for id in 0..channels as u8 {
+        frame.extend([id + 1, 0x11, id.min(1)]);
+    }
+    segment(0xc0, &frame);
+    for (i, (counts, values)) in HUFFMAN.iter().enumerate().take(2 * channels.min(2)) {
+        let mut data = vec![(((i % 2) << 4) | (i / 2)) as u8];
+        data.extend(counts);
+        data.extend(*values);
+        segment(0xc4, &data);
+    }
+    let mut scan = vec![channels as u8];
+    for id in 0..channels as u8 {
+        scan.extend([id + 1, if id == 0 { 0x00 } else { 0x11 }]);
+    }
+    scan.extend([0, 63, 0]);
+    segment(0xda, &scan);
+
+    let tables: Vec<_> = HUFFMAN
+        .iter()
+        .map(|(counts, values)| codes(counts, values))
+        .collect();
+    let cos = cosines();
+    let mut writer = BitWriter {
+        out: Vec::new(),
+        bits: 0,
+        len: 0,
+    };
+    let mut predictions = [0i32; 3];
+    let (mut samples, mut coefficients) = ([0f32; 64], [0f32; 64]);
+    for block_y in 0..image.height.div_ceil(8) {
+        for block_x in 0..image.width.div_ceil(8) {
+            for (c, prediction) in predictions.iter_mut().enumerate().take(channels) {
+                for y in 0..8 {
+                    for x in 0..8 {
+                        // Blocks beyond the edges repeat the last pixels
+                        let px = (block_x * 8 + x).min(image.width - 1);
+                        let py = (block_y * 8 + y).min(image.height - 1);
+                        let pixel = &image.pixels[(py * image.width + px) * image.channels..];
+                        let (r, g, b) = if image.channels < 3 {
+                            (pixel[0] as f32, pixel[0] as f32, pixel[0] as f32)
+                        } else {
+                            (pixel[0] as f32, pixel[1] as f32, pixel[2] as f32)
+                        };
+                        samples[y * 8 + x] = match c {
+                            0 => 0.299 * r + 0.587 * g + 0.114 * b - 128.0,
+                            1 => -0.168736 * r - 0.331264 * g + 0.5 * b,
+                            _ => 0.5 * r - 0.418688 * g - 0.081312 * b,
+                        };
+                    }
+                }
+                forward_dct(&samples, &cos, &mut coefficients);
+                let table = &quant[c.min(1)];
+                let quantized: [i32; 64] =
+                    ZIGZAG.map(|i| (coefficients[i] / table[i] as f32).round() as i32);
+
+                let (dc_codes, ac_codes) = (&tables[2 * c.min(1)], &tables[2 * c.min(1) + 1]);
+                let diff = quantized[0] - *prediction;
+                *prediction = quantized[0];
+                writer.coefficient(dc_codes, magnitude_size(diff), diff);
+                let mut run = 0;
+                for &value in &quantized[1..] {
+                    if value == 0 {
+                        run += 1;
+                        continue;
+                    }
+                    while run > 15 {
+                        writer.coefficient(ac_codes, 0xf0, 0);
+                        run -= 16;
+                    }
+                    writer.coefficient(ac_codes, (run << 4) | magnitude_size(value), value);
+                    run = 0;
+                }
+                if run > 0 {
+                    writer.coefficient(ac_codes, 0x00, 0);
+                }
+            }
+        }
+    }
+    out.extend(writer.finish());
+    out.extend([0xff, 0xd9]);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    fn gradient(width: usize, height: usize) -> Image {
+        let mut pixels = Vec::new();
+        for y in 0..height {
+            for x in 0..width {
+                pixels.extend([(x * 10) as u8, (y * 10) as u8, 128]);
+            }
+        }
+        Image {
+            width,
+            height,
+            channels: 3,
+            pixels,
+        }
+    }
+
+    #[test]
+    fn decodes_what_it_encodes() {
+        let image = gradient(20, 11);
+        let decoded = decode(&encode(&image, 95), usize::MAX).unwrap();
+        assert_eq!(
+            (decoded.width, decoded.height, decoded.channels),
+            (20, 11, 3)
+        );
+        let max_error = image
+            .pixels
+            .iter()
+            .zip(&decoded.pixels)
+            .map(|(a, b)| a.abs_diff(*b))
+            .max();
+        assert!(max_error.unwrap() < 12, "{max_error:?}");
+    }
+
+    #[test]
+    fn decodes_grayscale() {
+        let image = Image {
+            width: 9,
+            height: 9,
+            channels: 1,
+            pixels: vec![200; 81],
+        };
+        let decoded = decode(&encode(&image, 90), usize::MAX).unwrap();
+        assert_eq!(decoded.channels, 1);
+        assert!(decoded.pixels.iter().all(|&p| p.abs_diff(200) <= 2));
+    }
+
+    #[test]
+    fn refuses_progressive_images() {
+        let data = [0xff, 0xd8, 0xff, 0xc2, 0x00, 0x02];
+        assert!(decode(&data, usize::MAX).is_none());
+    }
+
+    #[test]
+    fn refuses_images_too_large_before_decoding() {
+        // A frame of 65535x65535 pixels, with no data at all
+        let data = [
+            0xff, 0xd8, 0xff, 0xc0, 0x00, 0x0b, 0x08, 0xff, 0xff, 0xff, 0xff, 0x01, 0x01, 0x11,
+            0x00,
+        ];
+        assert!(decode(&data, 1 << 20).is_none());
+    }
+
+    #[test]
+    fn refuses_oversized_coefficients() {
+        let mut reader = BitReader {
+            data: &[0xaa; 8],
+            pos: 0,
+            bits: 0,
+            len: 0,
+        };
+        assert!(reader.coefficient(200).is_none());
+        assert!(reader.coefficient(16).is_some());
+    }
+}
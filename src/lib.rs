@@ -0,0 +1,1256 @@
+//! miniserve, a CLI tool to serve files and directories over HTTP
+//!
+//! Besides the `miniserve` binary, this crate can be used to serve files from other programs, with
+//! the same web interface:
+//!
+//! ```no_run
+//! # async fn example() -> anyhow::Result<()> {
+//! let config = miniserve::MiniserveConfig::builder("/srv/files")
+//!     .port(8080)
+//!     .route_prefix("files")
+//!     .arg("--upload-files")
+//!     .build()?;
+//! miniserve::serve(config).await?;
+//! # Ok(())
+//! # }
+//! ```
+use std::io::{self, IsTerminal, Write};
+use std::net::{IpAddr, SocketAddr, TcpListener};
+use std::path::PathBuf;
+use std::thread;
+use std::time::Duration;
+
+use actix_files::NamedFile;
+use actix_web::body::MessageBody;
+use actix_web::middleware::{Next, from_fn};
+use actix_web::{
+    App, HttpRequest, HttpResponse, Responder,
+    dev::{ServiceRequest, ServiceResponse, fn_service},
+    guard,
+    http::{KeepAlive, Method, header::ContentType},
+    middleware, web,
+};
+use actix_web_httpauth::middleware::HttpAuthentication;
+use anyhow::Result;
+use bytesize::ByteSize;
+use clap::crate_version;
+use colored::*;
+use dav_server::{
+    DavConfig, DavHandler, DavMethodSet,
+    actix::{DavRequest, DavResponse},
+};
+use fast_qr::QRBuilder;
+use log::{error, info, warn};
+use percent_encoding::percent_decode_str;
+use serde::Deserialize;
+
+mod admin;
+mod archive;
+mod archive_fs;
+pub mod args;
+mod auth;
+mod checksum;
+mod compression;
+mod config;
+mod consts;
+mod content_type;
+mod cors;
+#[cfg(unix)]
+mod daemon;
+mod desktop;
+mod download_limit;
+mod errors;
+mod exif;
+mod external_ip;
+mod feed;
+mod file_op;
+mod file_utils;
+mod git_fs;
+mod highlight;
+#[cfg(feature = "http3")]
+mod http3;
+mod i18n;
+mod if_range;
+mod instances;
+mod jpeg;
+mod listing;
+mod live_refresh;
+mod markdown;
+mod mdns;
+mod metalink;
+mod methods;
+mod pdf;
+#[cfg(unix)]
+mod permissions;
+mod pipe;
+mod player;
+mod png;
+mod port_mapping;
+mod precompressed;
+#[cfg(unix)]
+mod privileges;
+mod receive;
+mod renderer;
+mod resize;
+#[cfg(feature = "s3")]
+mod s3_fs;
+#[cfg(target_os = "linux")]
+mod sandbox;
+#[cfg(windows)]
+#[doc(hidden)]
+pub mod service;
+mod sitemap;
+mod table;
+mod thumbnail;
+mod torrent;
+mod webdav_fs;
+
+pub use crate::config::{MiniserveConfig, MiniserveConfigBuilder};
+pub use crate::errors::StartupError;
+pub use crate::instances::read_instances;
+#[doc(hidden)]
+pub use crate::{config::DEPRECATED_TEMP_UPLOAD_DIRECTORY_ENV, errors::log_error_chain};
+
+use crate::errors::RuntimeError;
+use crate::file_op::DirSizeCache;
+use crate::methods::get_or_head;
+use crate::renderer::CustomColorScheme;
+use crate::webdav_fs::RestrictedFs;
+
+static STYLESHEET: &str = grass::include!("data/style.scss");
+
+/// Binds and announces the server, then serves until it's stopped, like the `miniserve` binary
+///
+/// Forking into the background only keeps the calling thread, so it's done before the runtime and
+/// its threads are started, once everything likely to fail has been reported on the terminal.
+pub fn run(mut miniserve_config: MiniserveConfig) -> Result<(), StartupError> {
+    let canon_path = check_config(&miniserve_config)?;
+    let path_string = canon_path.to_string_lossy();
+
+    println!(
+        "{name} v{version}",
+        name = "miniserve".bold(),
+        version = crate_version!()
+    );
+    if !miniserve_config.path_explicitly_chosen {
+        // If the path to serve has NOT been explicitly chosen and if this is NOT an interactive
+        // terminal, we should refuse to start for security reasons. This would be the case when
+        // running miniserve as a service but forgetting to set the path. This could be pretty
+        // dangerous if given with an undesired context path (for instance /root or /).
+        if !io::stdout().is_terminal() {
+            return Err(StartupError::NoExplicitPathAndNoTerminal);
+        }
+
+        warn!(
+            "miniserve has been invoked without an explicit path so it will serve the current directory after a short delay."
+        );
+        warn!(
+            "Invoke with -h|--help to see options or invoke as `miniserve .` to hide this advice."
+        );
+        print!("Starting server in ");
+        io::stdout()
+            .flush()
+            .map_err(|e| StartupError::IoError("Failed to write data".to_string(), e))?;
+        for c in "3… 2… 1… \n".chars() {
+            print!("{c}");
+            io::stdout()
+                .flush()
+                .map_err(|e| StartupError::IoError("Failed to write data".to_string(), e))?;
+            thread::sleep(Duration::from_millis(500));
+        }
+    }
+
+    let listeners = bind_and_announce(&mut miniserve_config, &path_string)?;
+
+    #[cfg(unix)]
+    if miniserve_config.daemon {
+        daemon::daemonize(
+            miniserve_config.log_file.as_deref(),
+            miniserve_config.pid_file.as_deref(),
+        )
+        .map_err(|e| StartupError::IoError("Failed to run in the background".to_string(), e))?;
+    } else if let Some(path) = &miniserve_config.pid_file {
+        daemon::write_pid_file(path).map_err(|e| {
+            StartupError::IoError(format!("Failed to write the process ID to {path:?}"), e)
+        })?;
+    }
+
+    actix_web::rt::System::new().block_on(serve_on(miniserve_config, listeners, true))
+}
+
+/// Binds and announces several servers, then serves with all of them until they're stopped
+///
+/// Every configuration is checked before any server is bound, so that a mistake in one of them
+/// doesn't leave the others running.
+pub fn run_instances(miniserve_configs: Vec<MiniserveConfig>) -> Result<(), StartupError> {
+    let paths = miniserve_configs
+        .iter()
+        .map(check_config)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    println!(
+        "{name} v{version}",
+        name = "miniserve".bold(),
+        version = crate_version!()
+    );
+    let instances = miniserve_configs
+        .into_iter()
+        .zip(paths)
+        .map(|(mut miniserve_config, path)| {
+            let listeners = bind_and_announce(&mut miniserve_config, &path.to_string_lossy())?;
+            Ok((miniserve_config, listeners))
+        })
+        .collect::<Result<Vec<_>, StartupError>>()?;
+
+    actix_web::rt::System::new().block_on(async {
+        futures::future::try_join_all(
+            instances
+                .into_iter()
+                .map(|(miniserve_config, listeners)| serve_on(miniserve_config, listeners, true)),
+        )
+        .await
+        .map(|_| ())
+    })
+}
+
+/// Binds the server, then tells where it can be reached and what it serves
+fn bind_and_announce(
+    miniserve_config: &mut MiniserveConfig,
+    path_string: &str,
+) -> Result<Listeners, StartupError> {
+    // Bind before announcing anything, so that only addresses we actually listen on are shown
+    let listeners = bind(miniserve_config)?;
+
+    #[cfg_attr(not(unix), allow(unused_mut))]
+    let mut display_sockets = listeners
+        .tcp
+        .iter()
+        .map(|(sock, _)| sock.to_string().green().bold().to_string())
+        .collect::<Vec<_>>();
+
+    #[cfg(unix)]
+    if let Some((path, _)) = &miniserve_config.listen_unix {
+        display_sockets.push(
+            format!("unix:{}", path.display())
+                .green()
+                .bold()
+                .to_string(),
+        );
+    }
+
+    println!("Bound to {}", display_sockets.join(", "));
+
+    match (
+        &miniserve_config.receive,
+        &miniserve_config.stdin_buffer,
+        &miniserve_config.filename,
+    ) {
+        (Some(dest), _, _) => println!(
+            "Receiving a file into {}",
+            dest.display().to_string().yellow().bold()
+        ),
+        (_, Some(_), Some(filename)) => {
+            println!("Serving standard input as {}", filename.yellow().bold())
+        }
+        _ => println!("Serving path {}", path_string.yellow().bold()),
+    }
+
+    if miniserve_config.announce {
+        println!(
+            "Announcing as {} on the local network",
+            mdns::service_name(miniserve_config).yellow().bold()
+        );
+    }
+
+    if !listeners.urls.is_empty() {
+        println!(
+            "Available at (non-exhaustive list):\n    {}\n",
+            listeners
+                .urls
+                .iter()
+                .map(|url| url.green().bold().to_string())
+                .collect::<Vec<_>>()
+                .join("\n    "),
+        );
+    }
+
+    // print QR code to terminal
+    if miniserve_config.show_qrcode && io::stdout().is_terminal() {
+        print_qr_codes(listeners.urls.iter().filter(|url| !is_loopback_url(url)));
+    }
+
+    Ok(listeners)
+}
+
+/// Binds the server and serves until it's stopped, without printing anything
+///
+/// This runs on the runtime of the caller, which has to be an Actix one, such as the one started
+/// by `#[actix_web::main]`, as tasks are spawned on it.
+pub async fn serve(mut miniserve_config: MiniserveConfig) -> Result<(), StartupError> {
+    check_config(&miniserve_config)?;
+    let listeners = bind(&mut miniserve_config)?;
+    serve_on(miniserve_config, listeners, false).await
+}
+
+/// Checks that the configuration can be served with, giving the path to be served
+fn check_config(miniserve_config: &MiniserveConfig) -> Result<PathBuf, StartupError> {
+    if miniserve_config.no_symlinks && miniserve_config.path.is_symlink() {
+        return Err(StartupError::NoSymlinksOptionWithSymlinkServePath(
+            miniserve_config.path.to_string_lossy().to_string(),
+        ));
+    }
+
+    if miniserve_config.webdav_enabled && miniserve_config.path.is_file() {
+        return Err(StartupError::WebdavWithFileServePath(
+            miniserve_config.path.to_string_lossy().to_string(),
+        ));
+    }
+
+    let canon_path = match (&miniserve_config.s3, &miniserve_config.git) {
+        #[cfg(feature = "s3")]
+        (Some(bucket), _) => bucket.location().into(),
+        (_, Some(tree)) => tree.location().into(),
+        _ => miniserve_config.path.canonicalize().map_err(|e| {
+            StartupError::IoError("Failed to resolve path to be served".to_string(), e)
+        })?,
+    };
+
+    // warn if --index is specified but not found
+    if let Some(ref index) = miniserve_config.index
+        && miniserve_config.s3.is_none()
+        && miniserve_config.git.is_none()
+        && !canon_path.join(index).exists()
+    {
+        // In SPA mode, the index file is what gets served for most paths
+        if miniserve_config.spa {
+            return Err(StartupError::SpaIndexNotFound(
+                index.to_string_lossy().to_string(),
+            ));
+        }
+        warn!(
+            "The file '{}' provided for option --index could not be found.",
+            index.to_string_lossy(),
+        );
+    }
+
+    Ok(canon_path)
+}
+
+/// Binds the sockets the configuration asks for
+///
+/// With port 0, the port picked by the system is known from then on, so the configuration gets
+/// what depends on it.
+fn bind(
+    #[cfg_attr(not(feature = "http3"), allow(unused_mut))] miniserve_config: &mut MiniserveConfig,
+) -> Result<Listeners, StartupError> {
+    let mut listeners = vec![];
+    let mut bind_error = None;
+    // With port 0, the system picks a free port for the first listener and the others reuse it
+    let mut port = miniserve_config.port;
+    for &interface in &miniserve_config.interfaces {
+        let addr = SocketAddr::new(interface, port);
+        match create_tcp_listener(addr).and_then(|listener| Ok((listener.local_addr()?, listener)))
+        {
+            Ok((addr, listener)) => {
+                port = addr.port();
+                listeners.push((addr, listener));
+            }
+            // Hosts lacking IPv6 or IPv4 can still be served over the other one by default
+            Err(e)
+                if miniserve_config.default_interfaces
+                    && !matches!(
+                        e.kind(),
+                        io::ErrorKind::AddrInUse | io::ErrorKind::PermissionDenied
+                    ) =>
+            {
+                warn!("Not listening on {addr}: {e}");
+                bind_error.get_or_insert((addr, e));
+            }
+            Err(e) => {
+                return Err(StartupError::IoError(
+                    format!("Failed to bind server to {addr}"),
+                    e,
+                ));
+            }
+        }
+    }
+    if listeners.is_empty()
+        && let Some((addr, e)) = bind_error
+    {
+        return Err(StartupError::IoError(
+            format!("Failed to bind server to {addr}"),
+            e,
+        ));
+    }
+
+    let display_urls = {
+        let (mut ifaces, wildcard): (Vec<_>, Vec<_>) = listeners
+            .iter()
+            .map(|(addr, _)| addr.ip())
+            .partition(|addr| !addr.is_unspecified());
+
+        // Replace wildcard addresses with local interface addresses
+        if !wildcard.is_empty() {
+            let all_ipv4 = wildcard.iter().any(|addr| addr.is_ipv4());
+            let all_ipv6 = wildcard.iter().any(|addr| addr.is_ipv6());
+            ifaces = if_addrs::get_if_addrs()
+                .unwrap_or_else(|e| {
+                    error!("Failed to get local interface addresses: {}", e);
+                    Default::default()
+                })
+                .into_iter()
+                .map(|iface| iface.ip())
+                .filter(|ip| (all_ipv4 && ip.is_ipv4()) || (all_ipv6 && ip.is_ipv6()))
+                // Link-local addresses aren't reachable without a zone ID
+                .filter(|ip| !matches!(ip, IpAddr::V6(ip) if ip.is_unicast_link_local()))
+                .collect();
+            ifaces.sort();
+        }
+
+        ifaces
+            .into_iter()
+            .map(|addr| match addr {
+                IpAddr::V4(_) => format!("{addr}:{port}"),
+                IpAddr::V6(_) => format!("[{addr}]:{port}"),
+            })
+            .map(|addr| match miniserve_config.tls_rustls_config {
+                Some(_) => format!("https://{addr}"),
+                None => format!("http://{addr}"),
+            })
+            .map(|url| format!("{}{}", url, miniserve_config.route_prefix))
+            .collect::<Vec<_>>()
+    };
+
+    // HTTP/3 is served on the same port, which is only known once bound with -p 0
+    #[cfg(feature = "http3")]
+    if miniserve_config.http3 {
+        use actix_web::http::header::{ALT_SVC, HeaderMap, HeaderValue};
+        let alt_svc = HeaderValue::try_from(format!("h3=\":{port}\"; ma=86400"))
+            .expect("Alt-Svc header is always valid");
+        miniserve_config
+            .header
+            .push(HeaderMap::from_iter([(ALT_SVC, alt_svc)]));
+    }
+
+    #[cfg(unix)]
+    let unix_listener = match &miniserve_config.listen_unix {
+        Some((path, mode)) => {
+            let listener = create_unix_listener(path, *mode).map_err(|e| {
+                StartupError::IoError(format!("Failed to bind server to {path:?}"), e)
+            })?;
+            Some(listener)
+        }
+        None => None,
+    };
+
+    #[cfg(feature = "http3")]
+    let quic_sockets = if miniserve_config.tls_rustls_config.is_some() && miniserve_config.http3 {
+        listeners
+            .iter()
+            .map(|(addr, _)| {
+                http3::create_udp_socket(*addr).map_err(|e| {
+                    StartupError::IoError(format!("Failed to bind HTTP/3 listener to {addr}"), e)
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?
+    } else {
+        vec![]
+    };
+
+    #[cfg(unix)]
+    if let Some(fd) = miniserve_config.print_bound_port_fd {
+        // Going through /dev/fd keeps us from taking ownership of a descriptor we didn't open
+        std::fs::OpenOptions::new()
+            .write(true)
+            .open(format!("/dev/fd/{fd}"))
+            .and_then(|mut file| writeln!(file, "{port}"))
+            .map_err(|e| {
+                StartupError::IoError(format!("Failed to write the port to descriptor {fd}"), e)
+            })?;
+    }
+
+    Ok(Listeners {
+        tcp: listeners,
+        port,
+        urls: display_urls,
+        #[cfg(unix)]
+        unix: unix_listener,
+        #[cfg(feature = "http3")]
+        quic: quic_sockets,
+    })
+}
+
+/// Sockets bound before forking, so that failing to bind them is reported on the terminal
+struct Listeners {
+    tcp: Vec<(SocketAddr, TcpListener)>,
+    /// Port of the TCP listeners, which is picked by the system with -p 0
+    port: u16,
+    /// URLs the TCP listeners can be reached at, as printed
+    urls: Vec<String>,
+    #[cfg(unix)]
+    unix: Option<std::os::unix::net::UnixListener>,
+    #[cfg(feature = "http3")]
+    quic: Vec<std::net::UdpSocket>,
+}
+
+/// Serves on `listeners` until the server is stopped, telling how to reach it if `announce`
+async fn serve_on(
+    miniserve_config: MiniserveConfig,
+    listeners: Listeners,
+    announce: bool,
+) -> Result<(), StartupError> {
+    let socket_addresses = listeners
+        .tcp
+        .iter()
+        .map(|(addr, _)| *addr)
+        .collect::<Vec<_>>();
+
+    let port_mapping = if miniserve_config.public {
+        port_mapping::PortMapping::new(listeners.port)
+            .await
+            .inspect_err(|e| warn!("Failed to forward the port on the router: {e:#}"))
+            .ok()
+    } else {
+        None
+    };
+
+    let external_addr = match &miniserve_config.external_ip {
+        Some(source) => external_ip::detect(source)
+            .await
+            .inspect_err(|e| warn!("Failed to find out the public IP address: {e:#}"))
+            .ok()
+            .map(|ip| SocketAddr::new(ip, listeners.port)),
+        None => None,
+    };
+
+    let mut public_urls = port_mapping
+        .iter()
+        .map(|mapping| mapping.external_addr())
+        .chain(external_addr)
+        .map(|addr| match miniserve_config.tls_rustls_config {
+            Some(_) => format!("https://{addr}"),
+            None => format!("http://{addr}"),
+        })
+        .map(|url| format!("{}{}", url, miniserve_config.route_prefix))
+        .collect::<Vec<_>>();
+    public_urls.dedup();
+
+    if announce && !public_urls.is_empty() {
+        println!(
+            "Publicly available at:\n    {}\n",
+            public_urls
+                .iter()
+                .map(|url| url.green().bold().to_string())
+                .collect::<Vec<_>>()
+                .join("\n    "),
+        );
+        // Public URLs are meant to be shared, so their QR codes are always shown
+        if io::stdout().is_terminal() {
+            print_qr_codes(&public_urls);
+        }
+    }
+
+    // Links are meant to be shared, so the one likely reachable by the most people is copied
+    if miniserve_config.copy_url
+        && let Some(url) = public_urls
+            .iter()
+            .chain(listeners.urls.iter().filter(|url| !is_loopback_url(url)))
+            .chain(&listeners.urls)
+            .next()
+    {
+        match desktop::copy_to_clipboard(url) {
+            Ok(()) => println!("Copied {} to the clipboard", url.green().bold()),
+            Err(e) => warn!("Failed to copy {url} to the clipboard: {e}"),
+        }
+    }
+
+    #[cfg(unix)]
+    let daemon = miniserve_config.daemon;
+    #[cfg(not(unix))]
+    let daemon = false;
+
+    if announce && let Some(duration) = miniserve_config.duration {
+        let stop_time = chrono::Local::now() + duration;
+        println!(
+            "Stopping automatically at {}",
+            stop_time
+                .format("%Y-%m-%d %H:%M:%S")
+                .to_string()
+                .yellow()
+                .bold()
+        );
+    }
+
+    if announce && io::stdout().is_terminal() && !daemon {
+        println!("Quit by pressing CTRL-C");
+    }
+
+    let inside_config = miniserve_config.clone();
+
+    let stylesheet = web::Data::new(
+        [
+            STYLESHEET,
+            inside_config.default_color_scheme.css(),
+            inside_config.default_color_scheme_dark.css_dark().as_str(),
+            inside_config
+                .custom_color_scheme
+                .as_ref()
+                .map(CustomColorScheme::css)
+                .unwrap_or_default()
+                .as_str(),
+            inside_config.custom_css.as_deref().unwrap_or_default(),
+        ]
+        .join("\n"),
+    );
+
+    let download_limit = web::Data::new(download_limit::DownloadLimit::new(
+        miniserve_config.max_downloads.unwrap_or(0),
+    ));
+    let inside_download_limit = download_limit.clone();
+    let receiver = web::Data::new(receive::Receiver::default());
+    let inside_receiver = receiver.clone();
+    let resize_cache = match &miniserve_config.cache_dir {
+        Some((dir, max_size)) => {
+            std::fs::create_dir_all(dir).map_err(|e| {
+                StartupError::IoError(format!("Failed to create the cache directory {dir:?}"), e)
+            })?;
+            resize::ResizeCache::new(Some(dir.clone()), *max_size)
+        }
+        None => resize::ResizeCache::default(),
+    };
+    let resize_cache = web::Data::new(resize_cache);
+    let dir_size_cache = web::Data::new(DirSizeCache::default());
+    let checksum_cache = web::Data::new(checksum::ChecksumCache::default());
+    let torrent_cache = web::Data::new(torrent::TorrentCache::default());
+    let activity = web::Data::new(admin::Activity::default());
+
+    // The same application serves HTTP/3 requests, which actix-web doesn't handle itself
+    let app = move || {
+        App::new()
+            .wrap(from_fn(custom_headers))
+            .wrap(from_fn(content_type::content_type_middleware))
+            .app_data(web::Data::new(inside_config.clone()))
+            .app_data(stylesheet.clone())
+            .app_data(inside_download_limit.clone())
+            .app_data(inside_receiver.clone())
+            .app_data(resize_cache.clone())
+            .app_data(dir_size_cache.clone())
+            .app_data(checksum_cache.clone())
+            .app_data(torrent_cache.clone())
+            .app_data(activity.clone())
+            .wrap(from_fn(errors::error_page_middleware))
+            .wrap(from_fn(methods::methods_middleware))
+            .wrap(middleware::Logger::default())
+            .wrap(middleware::Condition::new(
+                miniserve_config.compress_response,
+                from_fn(compression::compression_filter),
+            ))
+            .wrap(middleware::Condition::new(
+                miniserve_config.compress_response,
+                middleware::Compress::default(),
+            ))
+            // Preflight requests don't carry credentials, so they must be answered before auth
+            .wrap(middleware::Condition::new(
+                !inside_config.cors_origins.is_empty(),
+                from_fn(cors::cors_middleware),
+            ))
+            .route(
+                &inside_config.healthcheck_route,
+                get_or_head().to(healthcheck),
+            )
+            .route(&inside_config.api_route, web::post().to(api))
+            .route(&inside_config.favicon_route, get_or_head().to(favicon))
+            .route(&inside_config.css_route, get_or_head().to(css))
+            .configure(|c| {
+                if inside_config.robots.is_some() {
+                    c.route("/robots.txt", get_or_head().to(robots));
+                }
+            })
+            .service(
+                web::scope(&inside_config.route_prefix)
+                    .wrap(from_fn(download_limit::count_downloads))
+                    .wrap(from_fn(admin::transfers_middleware))
+                    // These check their own option rather than being wrapped in a `Condition`, whose
+                    // response types double in size with every layer and wear out the compiler
+                    .wrap(from_fn(precompressed::precompressed_middleware))
+                    .wrap(from_fn(markdown::markdown_middleware))
+                    .wrap(from_fn(highlight::preview_middleware))
+                    .wrap(from_fn(feed::feed_middleware))
+                    .wrap(from_fn(live_refresh::events_middleware))
+                    .wrap(from_fn(checksum::checksum_middleware))
+                    .wrap(from_fn(metalink::metalink_middleware))
+                    .wrap(from_fn(torrent::torrent_middleware))
+                    .wrap(from_fn(sitemap::sitemap_middleware))
+                    .wrap(from_fn(thumbnail::thumbnail_middleware))
+                    .wrap(from_fn(resize::resize_middleware))
+                    .wrap(from_fn(player::player_middleware))
+                    .wrap(from_fn(pdf::pdf_middleware))
+                    .wrap(from_fn(exif::details_middleware))
+                    .wrap(from_fn(if_range::if_range_middleware))
+                    .wrap(from_fn(exif::strip_exif_middleware))
+                    .wrap(middleware::Condition::new(
+                        inside_config.auth_enabled(),
+                        actix_web::middleware::Compat::new(HttpAuthentication::basic(
+                            auth::handle_auth,
+                        )),
+                    ))
+                    .configure(|c| configure_app(c, &inside_config)),
+            )
+            .default_service(get_or_head().to(error_404))
+    };
+
+    let srv = actix_web::HttpServer::new(app.clone());
+
+    let srv = match miniserve_config.workers {
+        Some(workers) => srv.workers(workers.into()),
+        None => srv,
+    };
+    let srv = srv
+        .keep_alive(match miniserve_config.keep_alive {
+            0 => KeepAlive::Disabled,
+            secs => KeepAlive::Timeout(Duration::from_secs(secs)),
+        })
+        .client_request_timeout(Duration::from_secs(miniserve_config.client_request_timeout));
+
+    let srv = listeners
+        .tcp
+        .into_iter()
+        .try_fold(srv, |srv, (addr, listener)| {
+            // HTTP/2 is negotiated through ALPN with TLS, and plaintext connections are upgraded
+            // when clients start them with the HTTP/2 preface
+            #[cfg(feature = "tls")]
+            let srv = match &miniserve_config.tls_rustls_config {
+                Some(tls_config) => srv.listen_rustls_0_23(listener, tls_config.clone()),
+                None => srv.listen_auto_h2c(listener),
+            };
+
+            #[cfg(not(feature = "tls"))]
+            let srv = srv.listen_auto_h2c(listener);
+
+            srv.map_err(|e| StartupError::IoError(format!("Failed to bind server to {addr}"), e))
+        })?;
+
+    #[cfg(unix)]
+    let srv = match (&miniserve_config.listen_unix, listeners.unix) {
+        (Some((path, _)), Some(listener)) => srv
+            .listen_uds(listener)
+            .map_err(|e| StartupError::IoError(format!("Failed to bind server to {path:?}"), e))?,
+        _ => srv,
+    };
+
+    #[cfg(feature = "http3")]
+    if let Some(tls_config) = &miniserve_config.tls_rustls_config {
+        for socket in listeners.quic {
+            let addr = socket.local_addr();
+            http3::spawn_listener(socket, tls_config, app()).map_err(|e| {
+                StartupError::IoError(format!("Failed to start HTTP/3 listener on {addr:?}"), e)
+            })?;
+        }
+    }
+
+    #[cfg(unix)]
+    if let Some(credentials) = &miniserve_config.credentials {
+        credentials.apply().map_err(|e| {
+            StartupError::IoError(
+                "Failed to switch to the given user and group".to_string(),
+                e,
+            )
+        })?;
+    }
+
+    // The sandbox would forbid starting the browser
+    if miniserve_config.open_browser
+        && let Some(url) = listeners
+            .urls
+            .iter()
+            .find(|url| is_loopback_url(url))
+            .or(listeners.urls.first())
+    {
+        desktop::open_browser(url)
+            .unwrap_or_else(|e| warn!("Failed to open {url} in the browser: {e}"));
+    }
+
+    // Workers are started right after, so that they are restricted as well
+    #[cfg(target_os = "linux")]
+    if miniserve_config.sandbox {
+        sandbox::restrict(&miniserve_config)?;
+    }
+
+    let srv = srv
+        .shutdown_timeout(miniserve_config.shutdown_timeout)
+        .run();
+
+    #[cfg(windows)]
+    let _ = service::SERVER_HANDLE.set(srv.handle());
+
+    if let Some(duration) = miniserve_config.duration {
+        let handle = srv.handle();
+        actix_web::rt::spawn(async move {
+            actix_web::rt::time::sleep(duration).await;
+            info!("Stopping as the given duration has elapsed");
+            handle.stop(true).await;
+        });
+    }
+
+    if miniserve_config.max_downloads.is_some() {
+        let handle = srv.handle();
+        actix_web::rt::spawn(async move {
+            download_limit.reached().await;
+            info!("Stopping as files have been downloaded as many times as allowed");
+            handle.stop(true).await;
+        });
+    }
+
+    if miniserve_config.receive.is_some() {
+        let handle = srv.handle();
+        actix_web::rt::spawn(async move {
+            receiver.received().await;
+            info!("Stopping as the file has been received");
+            handle.stop(true).await;
+        });
+    }
+
+    // The announcement runs on a thread of its own, so it's only started after forking
+    let _announcement = if miniserve_config.announce {
+        let addrs = socket_addresses
+            .iter()
+            .map(SocketAddr::ip)
+            .collect::<Vec<_>>();
+        mdns::Announcement::new(&miniserve_config, listeners.port, &addrs)
+            .inspect_err(|e| warn!("Failed to announce the server through mDNS: {e}"))
+            .ok()
+    } else {
+        None
+    };
+
+    // SIGHUP otherwise keeps terminating miniserve, as it does for most programs run in a terminal
+    #[cfg(unix)]
+    if daemon || miniserve_config.reloadable_files().next().is_some() {
+        let config = miniserve_config.clone();
+        actix_web::rt::spawn(async move {
+            use actix_web::rt::signal::unix::{SignalKind, signal};
+
+            let Ok(mut hangups) = signal(SignalKind::hangup()) else {
+                warn!("Failed to listen for SIGHUP, the configuration can't be reloaded");
+                return;
+            };
+            while hangups.recv().await.is_some() {
+                match config.reload() {
+                    Ok(()) => info!("Reloaded the configuration"),
+                    Err(e) => error!("Failed to reload the configuration: {e:#}"),
+                }
+            }
+        });
+    }
+
+    let result = srv
+        .await
+        .map_err(|e| StartupError::IoError("".to_owned(), e));
+
+    if let Some(port_mapping) = port_mapping {
+        port_mapping.remove().await;
+    }
+
+    #[cfg(unix)]
+    if let Some((path, _)) = &miniserve_config.listen_unix {
+        remove_created_file(path);
+    }
+
+    #[cfg(unix)]
+    if let Some(path) = &miniserve_config.pid_file {
+        remove_created_file(path);
+    }
+
+    result
+}
+
+/// Whether `url` points to this machine only, which is no use to anyone else
+fn is_loopback_url(url: &str) -> bool {
+    url.contains("//127.0.0.1:") || url.contains("//[::1]:")
+}
+
+/// Prints a QR code of each of `urls` to the terminal
+fn print_qr_codes<'a>(urls: impl IntoIterator<Item = &'a String>) {
+    for url in urls {
+        match QRBuilder::new(url.clone()).ecl(consts::QR_EC_LEVEL).build() {
+            Ok(qr) => {
+                println!("QR code for {}:", url.green().bold());
+                qr.print();
+            }
+            Err(e) => {
+                error!("Failed to render QR to terminal: {:?}", e);
+            }
+        };
+    }
+}
+
+/// Allows us to set low-level socket options
+///
+/// This mainly used to set `set_only_v6` socket option
+/// to get a consistent behavior across platforms.
+/// see: https://github.com/svenstaro/miniserve/pull/500
+fn create_tcp_listener(addr: SocketAddr) -> io::Result<TcpListener> {
+    use socket2::{Domain, Protocol, Socket, Type};
+    let socket = Socket::new(Domain::for_address(addr), Type::STREAM, Some(Protocol::TCP))?;
+    if addr.is_ipv6() {
+        socket.set_only_v6(true)?;
+    }
+    socket.set_reuse_address(true)?;
+    socket.bind(&addr.into())?;
+    socket.listen(1024 /* Default backlog */)?;
+    Ok(TcpListener::from(socket))
+}
+
+/// Binds a Unix domain socket at `path`, replacing a stale one left by a previous run
+#[cfg(unix)]
+fn create_unix_listener(
+    path: &std::path::Path,
+    mode: Option<u32>,
+) -> io::Result<std::os::unix::net::UnixListener> {
+    use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+    use std::os::unix::net::{UnixListener, UnixStream};
+
+    // Only sockets nobody listens on anymore are replaced, never regular files
+    if let Ok(metadata) = std::fs::symlink_metadata(path)
+        && metadata.file_type().is_socket()
+        && UnixStream::connect(path).is_err()
+    {
+        std::fs::remove_file(path)?;
+    }
+    let listener = UnixListener::bind(path)?;
+    if let Some(mode) = mode {
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode))?;
+    }
+    Ok(listener)
+}
+
+/// Removes a file created on startup, which may no longer be allowed after switching users
+#[cfg(unix)]
+fn remove_created_file(path: &std::path::Path) {
+    if let Err(e) = std::fs::remove_file(path)
+        && e.kind() != io::ErrorKind::NotFound
+    {
+        warn!("Failed to remove {path:?}: {e}");
+    }
+}
+
+/// Adds the headers given with --header to every response
+///
+/// Headers already set by the response are left alone, while a header given several times is sent
+/// with all of its values.
+async fn custom_headers(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    let mut res = next.call(req).await?;
+    let conf = res
+        .request()
+        .app_data::<web::Data<MiniserveConfig>>()
+        .expect("Could not get miniserve config")
+        .clone();
+
+    let headers = res.headers_mut();
+    let already_set: Vec<_> = conf
+        .header
+        .iter()
+        .flat_map(|h| h.keys())
+        .filter(|name| headers.contains_key(*name))
+        .cloned()
+        .collect();
+    for (name, value) in conf.header.iter().flatten() {
+        if !already_set.contains(name) {
+            headers.append(name.clone(), value.clone());
+        }
+    }
+
+    Ok(res)
+}
+
+/// Configures the Actix application
+///
+/// This is where we configure the app to serve an index file, the file listing, or a single file.
+fn configure_app(app: &mut web::ServiceConfig, conf: &MiniserveConfig) {
+    let dir_service = || {
+        // use routing guard so propfind and options requests fall through to the webdav handler
+        let mut files = actix_files::Files::new("", &conf.path)
+            .guard(guard::Any(guard::Get()).or(guard::Head()));
+
+        // Use specific index file if one was provided.
+        //
+        // Pretty URLs are meant for exported static sites, which put an "index.html" in every
+        // directory they link to with a trailing slash.
+        if let Some(ref index_file) = conf.index {
+            files = files.index_file(index_file.to_string_lossy());
+        } else if conf.pretty_urls {
+            files = files.index_file("index.html");
+        }
+
+        // Handle --pretty-urls and --spa options, which both serve something else when a path
+        // doesn't match any file.
+        //
+        // Note: --spa requires --index in clap.
+        if conf.pretty_urls || conf.spa {
+            files = files.default_handler(fn_service(not_found_fallback));
+        }
+
+        if conf.show_hidden {
+            files = files.use_hidden_files();
+        }
+
+        let base_path = conf.path.clone();
+        let no_symlinks = conf.no_symlinks;
+        let ignored = conf.ignored.clone();
+        files
+            .show_files_listing()
+            .files_listing_renderer(listing::directory_listing)
+            .prefer_utf8(true)
+            .redirect_to_slash_directory()
+            .path_filter(move |path, _| {
+                // deny symlinks if conf.no_symlinks, and entries the ignore file hides
+                let full_path = base_path.join(path);
+                let denied = (no_symlinks && full_path.is_symlink())
+                    || ignored.get().is_ignored(path, full_path.is_dir());
+                !denied
+            })
+    };
+
+    // The admin page is behind the authentication of the scope, so it takes precedence over files
+    if conf.admin.is_some() {
+        app.service(
+            web::resource("/__admin")
+                .route(get_or_head().to(admin::admin_page))
+                .route(web::post().to(admin::admin_action)),
+        );
+    }
+
+    // Nothing but the upload page is served when receiving a file
+    if conf.receive.is_some() {
+        app.service(
+            web::resource(["", "/"])
+                .route(get_or_head().to(receive::receive_page))
+                .route(web::post().to(receive::receive_file)),
+        );
+        return;
+    }
+
+    // Aliases are registered first so they take precedence over the served directory
+    for (route, path) in &conf.aliases {
+        let path = path.clone();
+        app.service(
+            web::resource(route.as_str())
+                .guard(guard::Any(guard::Get()).or(guard::Head()))
+                .wrap(from_fn(content_type::mark_file_body))
+                .to(move |req: HttpRequest| {
+                    let path = path.clone();
+                    async move {
+                        NamedFile::open_async(path)
+                            .await
+                            .map(|f| f.into_response(&req))
+                    }
+                }),
+        );
+    }
+
+    if conf.s3.is_some() {
+        // Handle buckets of an object storage browsed as directories
+        #[cfg(feature = "s3")]
+        app.service(
+            web::resource(["", "/{tail:.*}"])
+                .guard(guard::Any(guard::Get()).or(guard::Head()))
+                .wrap(from_fn(content_type::mark_file_body))
+                .to(s3_fs::s3_handler),
+        );
+    } else if conf.git.is_some() {
+        // Handle trees of a git commit browsed as directories
+        app.service(
+            web::resource(["", "/{tail:.*}"])
+                .guard(guard::Any(guard::Get()).or(guard::Head()))
+                .wrap(from_fn(content_type::mark_file_body))
+                .to(git_fs::git_handler),
+        );
+    } else if conf.browse_archive {
+        // Handle zip archives browsed as directories
+        app.service(
+            web::resource(["", "/{tail:.*}"])
+                .guard(guard::Any(guard::Get()).or(guard::Head()))
+                .wrap(from_fn(content_type::mark_file_body))
+                .to(archive_fs::archive_handler),
+        );
+    } else if conf.path.is_file() {
+        // Handle single files
+        app.service(
+            web::resource(["", "/"])
+                .wrap(from_fn(content_type::mark_file_body))
+                .route(web::to(listing::file_handler)),
+        );
+    } else {
+        if conf.file_upload {
+            // Allow file upload
+            app.service(web::resource("/upload").route(web::post().to(file_op::upload_file)));
+        }
+        // Handle directories, in a scope only to tell the files served apart from the pages made
+        // for them
+        app.service(
+            web::scope("")
+                .guard(guard::Any(guard::Get()).or(guard::Head()))
+                .wrap(from_fn(content_type::mark_file_body))
+                .service(dir_service()),
+        );
+    }
+
+    if conf.webdav_enabled {
+        let fs = RestrictedFs::new(&conf.path, conf.show_hidden);
+
+        let dav_server = DavHandler::builder()
+            .filesystem(fs)
+            .methods(DavMethodSet::WEBDAV_RO)
+            .hide_symlinks(conf.no_symlinks)
+            .strip_prefix(conf.route_prefix.to_owned())
+            .build_handler();
+
+        app.app_data(web::Data::new(dav_server.clone()));
+
+        app.service(
+            // actix requires tail segment to be named, even if unused
+            web::resource("/{tail}*")
+                .guard(
+                    guard::Any(guard::Options())
+                        .or(guard::Method(Method::from_bytes(b"PROPFIND").unwrap())),
+                )
+                .to(dav_handler),
+        );
+    }
+}
+
+/// Serves whatever should be served in place of a path that doesn't match any file
+///
+/// With --pretty-urls, we rewrite the request to append ".html" to the path and serve that file.
+/// If the path ends with a `/`, we remove it before appending ".html". This is done to allow for
+/// pretty URLs, e.g. "/about" instead of "/about.html". Directories containing an "index.html" are
+/// already served by that file, so "/about/" also works for "about/index.html".
+///
+/// With --spa, the index file is served whenever a 404 would otherwise occur, so that the SPA
+/// router can handle the request instead.
+async fn not_found_fallback(req: ServiceRequest) -> Result<ServiceResponse, actix_web::Error> {
+    let (req, _) = req.into_parts();
+    let conf = req
+        .app_data::<web::Data<MiniserveConfig>>()
+        .expect("Could not get miniserve config");
+
+    let mut result = Err(RuntimeError::RouteNotFoundError(req.path().to_string()).into());
+
+    if conf.pretty_urls {
+        let decoded = percent_decode_str(req.match_info().unprocessed()).decode_utf8_lossy();
+        let mut path_base = decoded.trim_start_matches('/').to_string();
+        if path_base.ends_with('/') {
+            path_base.pop();
+        }
+        if !path_base.ends_with(".html") {
+            path_base = format!("{}.html", path_base);
+        }
+        if let Some(path_base) = file_utils::sanitize_path(path_base, conf.show_hidden) {
+            result = NamedFile::open_async(conf.path.join(path_base))
+                .await
+                .map_err(actix_web::Error::from);
+        }
+    }
+
+    if conf.spa
+        && result.is_err()
+        && let Some(index_file) = &conf.index
+    {
+        result = NamedFile::open_async(conf.path.join(index_file))
+            .await
+            .map_err(actix_web::Error::from);
+    }
+
+    let res = result?.into_response(&req);
+    Ok(ServiceResponse::new(req, res))
+}
+
+async fn dav_handler(req: DavRequest, davhandler: web::Data<DavHandler>) -> DavResponse {
+    if let Some(prefix) = req.prefix() {
+        let config = DavConfig::new().strip_prefix(prefix);
+        davhandler.handle_with(config, req.request).await.into()
+    } else {
+        davhandler.handle(req.request).await.into()
+    }
+}
+
+async fn error_404(req: HttpRequest) -> Result<HttpResponse, RuntimeError> {
+    Err(RuntimeError::RouteNotFoundError(req.path().to_string()))
+}
+
+async fn healthcheck() -> impl Responder {
+    HttpResponse::Ok().body("OK")
+}
+
+#[derive(Deserialize, Debug)]
+enum ApiCommand {
+    /// Request the size of a particular directory
+    DirSize(String),
+}
+
+/// This "API" is pretty shitty but frankly miniserve doesn't really need a very fancy API. Or at
+/// least I hope so.
+async fn api(
+    command: web::Json<ApiCommand>,
+    config: web::Data<MiniserveConfig>,
+    dir_size_cache: web::Data<DirSizeCache>,
+) -> Result<impl Responder, RuntimeError> {
+    match command.into_inner() {
+        ApiCommand::DirSize(path) => {
+            if config.directory_size {
+                // The dir argument might be percent-encoded so let's decode it just in case.
+                let decoded_path = percent_decode_str(&path)
+                    .decode_utf8()
+                    .map_err(|e| RuntimeError::ParseError(path.clone(), e.to_string()))?;
+
+                // Convert the relative dir to an absolute path on the system.
+                let sanitized_path = file_utils::sanitize_path(&*decoded_path, true)
+                    .expect("Expected a path to directory");
+
+                let full_path = config
+                    .path
+                    .canonicalize()
+                    .expect("Couldn't canonicalize path")
+                    .join(sanitized_path);
+                info!("Requested directory listing for {full_path:?}");
+
+                let dir_size = dir_size_cache.size(&full_path).await?;
+                if config.show_exact_bytes {
+                    Ok(format!("{dir_size} B"))
+                } else {
+                    let dir_size = ByteSize::b(dir_size);
+                    Ok(dir_size.to_string())
+                }
+            } else {
+                Ok("-".to_string())
+            }
+        }
+    }
+}
+
+async fn favicon(conf: web::Data<MiniserveConfig>) -> impl Responder {
+    if let Some((mime, image)) = &conf.favicon {
+        return HttpResponse::Ok()
+            .insert_header(ContentType(mime.clone()))
+            .body(image.clone());
+    }
+    let logo = include_str!("../data/logo.svg");
+    HttpResponse::Ok()
+        .insert_header(ContentType(mime::IMAGE_SVG))
+        .body(logo)
+}
+
+async fn robots(conf: web::Data<MiniserveConfig>) -> impl Responder {
+    HttpResponse::Ok()
+        .insert_header(ContentType::plaintext())
+        .body(conf.robots.clone().unwrap_or_default())
+}
+
+async fn css(stylesheet: web::Data<String>) -> impl Responder {
+    HttpResponse::Ok()
+        .insert_header(ContentType(mime::TEXT_CSS))
+        .body(stylesheet.to_string())
+}
@@ -1,23 +1,35 @@
 #![allow(clippy::format_push_string)]
+use std::cmp::Ordering;
 use std::io;
-use std::path::{Component, Path};
+use std::path::{Component, Path, PathBuf};
 use std::time::SystemTime;
 
 use actix_web::{
-    HttpMessage, HttpRequest, HttpResponse, dev::ServiceResponse, http::Uri, web, web::Query,
+    HttpMessage, HttpRequest, HttpResponse,
+    cookie::{self, Cookie, SameSite},
+    dev::ServiceResponse,
+    http::{
+        Method, Uri,
+        header::{self, Header},
+    },
+    web,
+    web::Query,
 };
+use actix_web_httpauth::headers::authorization::{Authorization, Basic};
 use bytesize::ByteSize;
+use chrono::{DateTime, FixedOffset, Local, Utc};
 use clap::ValueEnum;
-use comrak::{ComrakOptions, markdown_to_html};
 use percent_encoding::{percent_decode_str, utf8_percent_encode};
-use regex::Regex;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use strum::{Display, EnumString};
 
 use crate::archive::ArchiveMethod;
 use crate::auth::CurrentUser;
 use crate::errors::{self, RuntimeError};
-use crate::renderer;
+use crate::i18n;
+use crate::markdown;
+use crate::player;
+use crate::renderer::{self, ThemeSlug};
 
 use self::percent_encode_sets::COMPONENT;
 
@@ -47,7 +59,49 @@ pub struct ListingQueryParameters {
     pub sort: Option<SortingMethod>,
     pub order: Option<SortingOrder>,
     pub raw: Option<bool>,
+    pub show_all: Option<bool>,
+    pub dirs_first: Option<bool>,
+    pub hidden: Option<bool>,
+    pub time: Option<TimeFormat>,
+    pub view: Option<ListingView>,
+    pub json: Option<bool>,
+    /// Text the names of the entries listed contain
+    pub filter: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_flag")]
+    pub flat: Option<bool>,
     download: Option<ArchiveMethod>,
+    playlist: Option<bool>,
+}
+
+/// Deserializes a query parameter which is set with `1` as well as with `true`
+fn deserialize_flag<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<bool>, D::Error> {
+    match Option::<String>::deserialize(deserializer)?.as_deref() {
+        None => Ok(None),
+        Some("1" | "true") => Ok(Some(true)),
+        Some("0" | "false") => Ok(Some(false)),
+        Some(value) => Err(serde::de::Error::invalid_value(
+            serde::de::Unexpected::Str(value),
+            &"true, false, 1 or 0",
+        )),
+    }
+}
+
+/// Ways of showing the entries of a directory
+#[derive(Debug, Deserialize, Default, Clone, Display, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum ListingView {
+    /// Table of the entries
+    #[default]
+    List,
+
+    /// Grid of tiles, showing images and videos
+    Gallery,
+
+    /// Table whose directories expand in place, listing their entries below them
+    Tree,
 }
 
 /// Available sorting methods
@@ -56,13 +110,13 @@ pub struct ListingQueryParameters {
 #[strum(serialize_all = "snake_case")]
 pub enum SortingMethod {
     #[default]
-    /// Sort by name
+    /// Sort by name, naturally: file2 before file10, and v1.9 before v1.10
     Name,
 
     /// Sort by size
     Size,
 
-    /// Sort by last modification date (natural sort: follows alphanumerical order)
+    /// Sort by last modification date
     Date,
 }
 
@@ -81,8 +135,79 @@ pub enum SortingOrder {
     Desc,
 }
 
+/// Ways of showing when entries were last modified
+#[derive(
+    Debug, Deserialize, Default, Clone, EnumString, Display, Copy, PartialEq, Eq, ValueEnum,
+)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum TimeFormat {
+    /// Both the date and time, and how long ago that was
+    #[default]
+    Both,
+
+    /// How long ago, e.g. '3 hours ago'
+    Relative,
+
+    /// The date and time, as formatted with --date-format
+    Absolute,
+}
+
+impl TimeFormat {
+    /// The format the toggle in listings switches to
+    pub fn next(self) -> Self {
+        match self {
+            Self::Both => Self::Relative,
+            Self::Relative => Self::Absolute,
+            Self::Absolute => Self::Both,
+        }
+    }
+
+    pub fn shows_absolute(self) -> bool {
+        self != Self::Relative
+    }
+
+    pub fn shows_relative(self) -> bool {
+        self != Self::Absolute
+    }
+}
+
+/// Time zone that listings show times in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Timezone {
+    /// The time zone of the server
+    #[default]
+    Local,
+
+    /// A fixed offset from UTC, which UTC itself is as well
+    Fixed(FixedOffset),
+}
+
+/// How listings format the times entries were last modified at
+#[derive(Debug, Clone)]
+pub struct DateFormat {
+    pub timezone: Timezone,
+
+    /// strftime-style format, checked to be valid beforehand
+    pub pattern: String,
+}
+
+impl DateFormat {
+    pub fn format(&self, time: SystemTime) -> String {
+        let time = DateTime::<Utc>::from(time);
+        match self.timezone {
+            Timezone::Local => time.with_timezone(&Local).format(&self.pattern).to_string(),
+            Timezone::Fixed(offset) => time
+                .with_timezone(&offset)
+                .format(&self.pattern)
+                .to_string(),
+        }
+    }
+}
+
 /// Possible entry types
-#[derive(PartialEq, Clone, Display, Eq)]
+#[derive(PartialEq, Clone, Display, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
 #[strum(serialize_all = "snake_case")]
 pub enum EntryType {
     /// Entry is a directory
@@ -90,6 +215,9 @@ pub enum EntryType {
 
     /// Entry is a file
     File,
+
+    /// Entry is a symlink to nothing, listed with --show-symlink-info
+    BrokenSymlink,
 }
 
 /// Entry
@@ -111,10 +239,50 @@ pub struct Entry {
 
     /// Path of symlink pointed to
     pub symlink_info: Option<String>,
+
+    /// Mode, owner and group, shown with --show-permissions
+    pub permissions: Option<Permissions>,
+}
+
+/// Entry of a listing asked for as JSON, with `?json=true`
+#[derive(Serialize)]
+struct JsonEntry<'a> {
+    name: &'a str,
+    #[serde(rename = "type")]
+    entry_type: &'a EntryType,
+    link: &'a str,
+    /// Size in bytes, of files only
+    size: Option<u64>,
+    /// Last modification, in seconds since the Unix epoch
+    modified: Option<u64>,
+    symlink_target: Option<&'a str>,
+}
+
+impl<'a> From<&'a Entry> for JsonEntry<'a> {
+    fn from(entry: &'a Entry) -> Self {
+        Self {
+            name: &entry.name,
+            entry_type: &entry.entry_type,
+            link: &entry.link,
+            size: entry.size.map(|size| size.as_u64()),
+            modified: entry
+                .last_modification_date
+                .and_then(|time| time.duration_since(SystemTime::UNIX_EPOCH).ok())
+                .map(|duration| duration.as_secs()),
+            symlink_target: entry.symlink_info.as_deref(),
+        }
+    }
+}
+
+/// Mode, owner and group of an entry, as `ls -l` shows them
+pub struct Permissions {
+    pub mode: String,
+    pub owner: String,
+    pub group: String,
 }
 
 impl Entry {
-    fn new(
+    pub fn new(
         name: String,
         entry_type: EntryType,
         link: String,
@@ -129,6 +297,15 @@ impl Entry {
             size,
             last_modification_date,
             symlink_info,
+            permissions: None,
+        }
+    }
+
+    /// Sets the mode, owner and group shown with --show-permissions
+    pub fn with_permissions(self, permissions: Option<Permissions>) -> Self {
+        Self {
+            permissions,
+            ..self
         }
     }
 
@@ -141,6 +318,43 @@ impl Entry {
     pub fn is_file(&self) -> bool {
         self.entry_type == EntryType::File
     }
+
+    /// Returns whether the entry is a symlink to nothing
+    pub fn is_broken_symlink(&self) -> bool {
+        self.entry_type == EntryType::BrokenSymlink
+    }
+}
+
+/// What a listing holds in all, before it's truncated
+pub struct Totals {
+    /// Number of entries, including those that are neither files nor directories
+    pub entries: usize,
+
+    /// Number of files
+    pub files: usize,
+
+    /// Number of directories
+    pub directories: usize,
+
+    /// Sum of the sizes of the files, without the contents of directories
+    pub size: ByteSize,
+}
+
+impl Totals {
+    fn of(entries: &[Entry]) -> Self {
+        let files = entries.iter().filter(|entry| entry.is_file());
+        Self {
+            entries: entries.len(),
+            files: files.clone().count(),
+            directories: entries.iter().filter(|entry| entry.is_dir()).count(),
+            size: ByteSize::b(
+                files
+                    .filter_map(|entry| entry.size)
+                    .map(|s| s.as_u64())
+                    .sum(),
+            ),
+        }
+    }
 }
 
 /// One entry in the path to the listed directory
@@ -159,11 +373,40 @@ impl Breadcrumb {
 }
 
 pub async fn file_handler(req: HttpRequest) -> actix_web::Result<actix_files::NamedFile> {
-    let path = &req
-        .app_data::<web::Data<crate::MiniserveConfig>>()
-        .unwrap()
-        .path;
-    actix_files::NamedFile::open(path).map_err(Into::into)
+    let conf = req.app_data::<web::Data<crate::MiniserveConfig>>().unwrap();
+    let file = actix_files::NamedFile::open(&conf.path)?;
+
+    Ok(match &conf.filename {
+        Some(filename) => {
+            let file = file.set_content_disposition(header::ContentDisposition {
+                disposition: header::DispositionType::Attachment,
+                parameters: vec![header::DispositionParam::Filename(filename.clone())],
+            });
+            match mime_guess_from_name(filename) {
+                Some(mime) => file.set_content_type(mime),
+                None => file,
+            }
+        }
+        None => file,
+    })
+}
+
+/// Guess the mime type of a file from its name, ignoring the generic fallback
+fn mime_guess_from_name(name: &str) -> Option<mime::Mime> {
+    let mime = actix_files::file_extension_to_mime(Path::new(name).extension()?.to_str()?);
+    (mime != mime::APPLICATION_OCTET_STREAM).then_some(mime)
+}
+
+/// Ranks a file name among readme names, the lowest rank being shown first
+///
+/// Like code forges, Markdown readmes are preferred over plain text ones.
+fn readme_rank(file_name: &str) -> Option<usize> {
+    match file_name.to_lowercase().as_str() {
+        "readme.md" | "readme.markdown" => Some(0),
+        "readme.txt" => Some(1),
+        "readme" => Some(2),
+        _ => None,
+    }
 }
 
 /// List a directory and renders a HTML file accordingly
@@ -172,9 +415,6 @@ pub fn directory_listing(
     dir: &actix_files::Directory,
     req: &HttpRequest,
 ) -> io::Result<ServiceResponse> {
-    let extensions = req.extensions();
-    let current_user: Option<&CurrentUser> = extensions.get::<CurrentUser>();
-
     let conf = req.app_data::<web::Data<crate::MiniserveConfig>>().unwrap();
     if conf.disable_indexing {
         return Ok(ServiceResponse::new(
@@ -184,9 +424,289 @@ pub fn directory_listing(
                 .body("File not found."),
         ));
     }
-    let serve_path = req.path();
 
-    let base = Path::new(serve_path);
+    let base = Path::new(req.path());
+    let encoded_dir = encoded_dir(req, conf);
+    let query_params = extract_query_parameters(req);
+    let mut entries: Vec<Entry> = Vec::new();
+    // The readme shown below the listing, along with its rank
+    let mut readme: Option<(usize, String, PathBuf)> = None;
+    #[cfg(unix)]
+    let mut owners = crate::permissions::Owners::default();
+    let ignored = conf.ignored.get();
+
+    // Directories left to read, along with the path of their entries from the listed one, as is
+    // and percent-encoded. Flat listings go down into every directory.
+    let flat = query_params.flat == Some(true);
+    let mut pending = vec![(dir.path.clone(), String::new(), String::new())];
+    while let Some((dir_path, prefix, encoded_prefix)) = pending.pop() {
+        let read_dir = match dir_path.read_dir() {
+            Ok(read_dir) => read_dir,
+            // Subdirectories which can't be read are left out rather than failing the listing
+            Err(_) if !prefix.is_empty() => continue,
+            Err(err) => return Err(err),
+        };
+        for entry in read_dir {
+            if dir.is_visible(&entry) || conf.show_hidden {
+                let entry = entry?;
+                // show file url as relative to static path
+                let file_name = entry.file_name().to_string_lossy().to_string();
+                // Entries of flat listings are named by their path from the listed directory
+                let name = format!("{prefix}{file_name}");
+                let encoded_name = format!(
+                    "{encoded_prefix}{}",
+                    utf8_percent_encode(&file_name, COMPONENT)
+                );
+                let (is_symlink, metadata) = match entry.metadata() {
+                    Ok(metadata) if metadata.file_type().is_symlink() => {
+                        // for symlinks, get the metadata of the original file
+                        (true, std::fs::metadata(entry.path()))
+                    }
+                    res => (false, res),
+                };
+                let is_dir = metadata.as_ref().is_ok_and(|metadata| metadata.is_dir());
+                if ignored.is_ignored(&entry.path(), is_dir) {
+                    continue;
+                }
+                let symlink_dest = (is_symlink && conf.show_symlink_info)
+                    .then(|| entry.path())
+                    .and_then(|path| std::fs::read_link(path).ok())
+                    .map(|path| path.to_string_lossy().into_owned());
+                let file_url = base.join(&encoded_name).to_string_lossy().to_string();
+
+                // if file is a directory, add '/' to the end of the name
+                if let Ok(metadata) = metadata {
+                    if conf.no_symlinks && is_symlink {
+                        continue;
+                    }
+                    let last_modification_date = metadata.modified().ok();
+                    #[cfg(unix)]
+                    let permissions = conf.show_permissions.then(|| owners.permissions(&metadata));
+                    #[cfg(not(unix))]
+                    let permissions = None;
+
+                    if metadata.is_dir() && flat && !is_symlink {
+                        // Symlinked directories are listed rather than gone into, which could be
+                        // endless
+                        pending.push((
+                            entry.path(),
+                            format!("{name}/"),
+                            format!("{encoded_name}/"),
+                        ));
+                    } else if metadata.is_dir() {
+                        entries.push(
+                            Entry::new(
+                                name,
+                                EntryType::Directory,
+                                file_url,
+                                None,
+                                last_modification_date,
+                                symlink_dest,
+                            )
+                            .with_permissions(permissions),
+                        );
+                    } else if metadata.is_file() {
+                        let file_link = match &conf.file_external_url {
+                            Some(external_url) => {
+                                // Construct the full relative path including subdirectories
+                                // encoded_dir holds the current directory path relative to the prefix (e.g., /subdir1/subdir2)
+                                let current_relative_dir = encoded_dir.trim_matches('/'); // Remove leading/trailing slashes if any
+
+                                // Combine the relative directory path and the filename
+                                let full_relative_path = if current_relative_dir.is_empty() {
+                                    // If in the root directory, just use the filename
+                                    encoded_name
+                                } else {
+                                    // Otherwise, join directory and filename
+                                    format!("{current_relative_dir}/{encoded_name}")
+                                };
+
+                                // Join the external external URL with the full relative path
+                                format!(
+                                    "{}/{}",
+                                    external_url.trim_end_matches('/'), // Base URL without trailing slash
+                                    full_relative_path // Relative path (dir + file) - should not have leading slash here
+                                )
+                            }
+                            None => file_url,
+                        };
+                        entries.push(
+                            Entry::new(
+                                name,
+                                EntryType::File,
+                                file_link,
+                                Some(ByteSize::b(metadata.len())),
+                                last_modification_date,
+                                symlink_dest,
+                            )
+                            .with_permissions(permissions),
+                        );
+                        let rank = readme_rank(&file_name);
+                        if conf.readme
+                            && prefix.is_empty()
+                            && let Some(rank) = rank
+                            && readme.as_ref().is_none_or(|(best, ..)| rank < *best)
+                        {
+                            readme = Some((rank, file_name.clone(), entry.path()));
+                        }
+                    }
+                } else if is_symlink && !conf.no_symlinks && conf.show_symlink_info {
+                    // Broken symlinks are listed as such, but can't be opened
+                    let last_modification_date = std::fs::symlink_metadata(entry.path())
+                        .and_then(|metadata| metadata.modified())
+                        .ok();
+                    entries.push(Entry::new(
+                        name,
+                        EntryType::BrokenSymlink,
+                        file_url,
+                        None,
+                        last_modification_date,
+                        symlink_dest,
+                    ));
+                } else {
+                    continue;
+                }
+            }
+        }
+    }
+
+    if query_params.playlist == Some(true) {
+        let origin = {
+            let info = req.connection_info();
+            format!("{}://{}", info.scheme(), info.host())
+        };
+        // The credentials the page was requested with are needed by players to get the files
+        let credentials = conf
+            .auth_enabled()
+            .then(|| Authorization::<Basic>::parse(req).ok())
+            .flatten()
+            .map(Authorization::into_scheme);
+        let credentials = credentials
+            .as_ref()
+            .map(|basic| (basic.user_id(), basic.password().unwrap_or_default()));
+        let file_name = format!(
+            "{}.m3u8",
+            dir.path
+                .file_name()
+                .map_or("playlist".into(), |name| name.to_string_lossy())
+        );
+        return Ok(ServiceResponse::new(
+            req.clone(),
+            HttpResponse::Ok()
+                .content_type("audio/x-mpegurl; charset=utf-8")
+                .append_header((
+                    "Content-Disposition",
+                    format!("attachment; filename={file_name:?}"),
+                ))
+                .body(player::playlist(&entries, &origin, credentials)),
+        ));
+    }
+
+    if let Some(archive_method) = query_params.download {
+        if !archive_method.is_enabled(conf.tar_enabled, conf.tar_gz_enabled, conf.zip_enabled) {
+            return Ok(ServiceResponse::new(
+                req.clone(),
+                HttpResponse::Forbidden()
+                    .content_type(mime::TEXT_PLAIN_UTF_8)
+                    .body("Archive creation is disabled."),
+            ));
+        }
+        log::info!(
+            "Creating an archive ({extension}) of {path}...",
+            extension = archive_method.extension(),
+            path = &dir.path.display().to_string()
+        );
+
+        let file_name = format!(
+            "{}.{}",
+            dir.path.file_name().unwrap().to_str().unwrap(),
+            archive_method.extension()
+        );
+
+        let mut res = HttpResponse::Ok();
+        res.content_type(archive_method.content_type())
+            .append_header(("Content-Transfer-Encoding", "binary"))
+            .append_header((
+                "Content-Disposition",
+                format!("attachment; filename={file_name:?}"),
+            ));
+
+        // Archives aren't created only to tell their headers
+        if req.method() == Method::HEAD {
+            return Ok(ServiceResponse::new(
+                req.clone(),
+                res.streaming(futures::stream::empty::<io::Result<web::Bytes>>()),
+            ));
+        }
+
+        // We will create the archive in a separate thread, and stream the content using a pipe.
+        // The pipe is made of a futures channel, and an adapter to implement the `Write` trait.
+        // Include 10 messages of buffer for erratic connection speeds.
+        let (tx, rx) = futures::channel::mpsc::channel::<io::Result<actix_web::web::Bytes>>(10);
+        let pipe = crate::pipe::Pipe::new(tx);
+
+        // Start the actual archive creation in a separate thread.
+        let dir = dir.path.to_path_buf();
+        let skip_symlinks = conf.no_symlinks;
+        let ignored = conf.ignored.get();
+        std::thread::spawn(move || {
+            if let Err(err) = archive_method.create_archive(dir, skip_symlinks, &ignored, pipe) {
+                log::error!("Error during archive creation: {:?}", err);
+            }
+        });
+
+        return Ok(ServiceResponse::new(
+            req.clone(),
+            res.body(actix_web::body::BodyStream::new(rx)),
+        ));
+    }
+
+    let readme = match readme {
+        Some((_, file_name, path)) => {
+            let contents = std::fs::read_to_string(path)?;
+            let html = if readme_rank(&file_name) == Some(0) {
+                markdown::to_html(&contents)
+            } else {
+                maud::html! { pre { (contents) } }.into_string()
+            };
+            Some((file_name, html))
+        }
+        None => None,
+    };
+    Ok(render(req, entries, readme, true))
+}
+
+/// Sorts the given entries as requested and renders them as a listing of the requested directory
+///
+/// This is shared by everything that presents a directory, whether or not it exists on disk.
+pub fn render_listing(
+    req: &HttpRequest,
+    entries: Vec<Entry>,
+    readme: Option<(String, String)>,
+) -> ServiceResponse {
+    render(req, entries, readme, false)
+}
+
+/// Renders a listing, which offers to list the entries of subdirectories too if it `can_flatten`
+fn render(
+    req: &HttpRequest,
+    mut entries: Vec<Entry>,
+    readme: Option<(String, String)>,
+    can_flatten: bool,
+) -> ServiceResponse {
+    let conf = req.app_data::<web::Data<crate::MiniserveConfig>>().unwrap();
+    // Cookies are parsed into the extensions of the request, so they're read before those are
+    // borrowed
+    let mut query_params = extract_query_parameters(req);
+    let preferences_cookie = remember_preferences(req, &mut query_params, conf);
+    let theme = req
+        .cookie(THEME_COOKIE)
+        .and_then(|cookie| ThemeSlug::from_str(cookie.value(), true).ok());
+
+    let extensions = req.extensions();
+    let current_user: Option<&CurrentUser> = extensions.get::<CurrentUser>();
+
+    let base = Path::new(req.path());
     let random_route_abs = format!("/{}", conf.route_prefix);
     let abs_uri = {
         let res = Uri::builder()
@@ -196,17 +716,11 @@ pub fn directory_listing(
             .build();
         match res {
             Ok(uri) => uri,
-            Err(err) => return Ok(ServiceResponse::from_err(err, req.clone())),
+            Err(err) => return ServiceResponse::from_err(err, req.clone()),
         }
     };
     let is_root = base.parent().is_none() || Path::new(&req.path()) == Path::new(&random_route_abs);
-
-    let encoded_dir = match base.strip_prefix(random_route_abs) {
-        Ok(c_d) => Path::new("/").join(c_d),
-        Err(_) => base.to_path_buf(),
-    }
-    .display()
-    .to_string();
+    let encoded_dir = encoded_dir(req, conf);
 
     let breadcrumbs = {
         let title = conf
@@ -247,110 +761,10 @@ pub fn directory_listing(
         res
     };
 
-    let query_params = extract_query_parameters(req);
-    let mut entries: Vec<Entry> = Vec::new();
-    let mut readme: Option<(String, String)> = None;
-    let readme_rx: Regex = Regex::new("^readme([.](md|txt))?$").unwrap();
-
-    for entry in dir.path.read_dir()? {
-        if dir.is_visible(&entry) || conf.show_hidden {
-            let entry = entry?;
-            // show file url as relative to static path
-            let file_name = entry.file_name().to_string_lossy().to_string();
-            let (is_symlink, metadata) = match entry.metadata() {
-                Ok(metadata) if metadata.file_type().is_symlink() => {
-                    // for symlinks, get the metadata of the original file
-                    (true, std::fs::metadata(entry.path()))
-                }
-                res => (false, res),
-            };
-            let symlink_dest = (is_symlink && conf.show_symlink_info)
-                .then(|| entry.path())
-                .and_then(|path| std::fs::read_link(path).ok())
-                .map(|path| path.to_string_lossy().into_owned());
-            let file_url = base
-                .join(utf8_percent_encode(&file_name, COMPONENT).to_string())
-                .to_string_lossy()
-                .to_string();
-
-            // if file is a directory, add '/' to the end of the name
-            if let Ok(metadata) = metadata {
-                if conf.no_symlinks && is_symlink {
-                    continue;
-                }
-                let last_modification_date = metadata.modified().ok();
-
-                if metadata.is_dir() {
-                    entries.push(Entry::new(
-                        file_name,
-                        EntryType::Directory,
-                        file_url,
-                        None,
-                        last_modification_date,
-                        symlink_dest,
-                    ));
-                } else if metadata.is_file() {
-                    let file_link = match &conf.file_external_url {
-                        Some(external_url) => {
-                            // Construct the full relative path including subdirectories
-                            // encoded_dir holds the current directory path relative to the prefix (e.g., /subdir1/subdir2)
-                            let current_relative_dir = encoded_dir.trim_matches('/'); // Remove leading/trailing slashes if any
-
-                            // Combine the relative directory path and the filename
-                            let full_relative_path = if current_relative_dir.is_empty() {
-                                // If in the root directory, just use the filename
-                                utf8_percent_encode(&file_name, COMPONENT).to_string()
-                            } else {
-                                // Otherwise, join directory and filename
-                                format!(
-                                    "{}/{}",
-                                    current_relative_dir,
-                                    utf8_percent_encode(&file_name, COMPONENT)
-                                )
-                            };
-
-                            // Join the external external URL with the full relative path
-                            format!(
-                                "{}/{}",
-                                external_url.trim_end_matches('/'), // Base URL without trailing slash
-                                full_relative_path // Relative path (dir + file) - should not have leading slash here
-                            )
-                        }
-                        None => file_url,
-                    };
-                    entries.push(Entry::new(
-                        file_name.clone(),
-                        EntryType::File,
-                        file_link,
-                        Some(ByteSize::b(metadata.len())),
-                        last_modification_date,
-                        symlink_dest,
-                    ));
-                    if conf.readme && readme_rx.is_match(&file_name.to_lowercase()) {
-                        let ext = file_name.split('.').next_back().unwrap().to_lowercase();
-                        readme = Some((
-                            file_name.to_string(),
-                            if ext == "md" {
-                                markdown_to_html(
-                                    &std::fs::read_to_string(entry.path())?,
-                                    &ComrakOptions::default(),
-                                )
-                            } else {
-                                format!("<pre>{}</pre>", &std::fs::read_to_string(entry.path())?)
-                            },
-                        ));
-                    }
-                }
-            } else {
-                continue;
-            }
-        }
-    }
+    let simple_ui = is_legacy_browser(req);
 
     match query_params.sort.unwrap_or(conf.default_sorting_method) {
-        SortingMethod::Name => entries.sort_by(|e1, e2| {
-            alphanumeric_sort::compare_str(e1.name.to_lowercase(), e2.name.to_lowercase())
-        }),
+        SortingMethod::Name => entries.sort_by(|e1, e2| compare_names(&e1.name, &e2.name)),
         SortingMethod::Size => entries.sort_by(|e1, e2| {
             // If we can't get the size of the entry (directory for instance)
             // let's consider it's 0b
@@ -371,77 +785,197 @@ pub fn directory_listing(
         entries.reverse()
     }
 
+    // Hidden entries can be left out of listings showing them
+    if query_params.hidden == Some(false) {
+        entries.retain(|entry| !entry.name.starts_with('.'));
+    }
+
+    // Listings can be narrowed down to names containing a text, as truncated ones offer to
+    if let Some(filter) = query_params.filter.as_deref().filter(|f| !f.is_empty()) {
+        let filter = filter.to_lowercase();
+        entries.retain(|entry| entry.name.to_lowercase().contains(&filter));
+    }
+
     // List directories first
-    if conf.dirs_first {
+    if query_params.dirs_first.unwrap_or(conf.dirs_first) {
         entries.sort_by_key(|e| !e.is_dir());
     }
 
-    if let Some(archive_method) = query_params.download {
-        if !archive_method.is_enabled(conf.tar_enabled, conf.tar_gz_enabled, conf.zip_enabled) {
-            return Ok(ServiceResponse::new(
-                req.clone(),
-                HttpResponse::Forbidden()
-                    .content_type(mime::TEXT_PLAIN_UTF_8)
-                    .body("Archive creation is disabled."),
-            ));
+    // Like raw listings, those asked for as JSON are meant for tools, and so aren't truncated
+    if query_params.json == Some(true) {
+        let entries: Vec<_> = entries.iter().map(JsonEntry::from).collect();
+        let mut res = HttpResponse::Ok();
+        if let Some(cookie) = preferences_cookie {
+            res.cookie(cookie);
         }
-        log::info!(
-            "Creating an archive ({extension}) of {path}...",
-            extension = archive_method.extension(),
-            path = &dir.path.display().to_string()
-        );
+        return ServiceResponse::new(req.clone(), res.json(entries));
+    }
 
-        let file_name = format!(
-            "{}.{}",
-            dir.path.file_name().unwrap().to_str().unwrap(),
-            archive_method.extension()
-        );
+    let totals = Totals::of(&entries);
+    // Raw and minimal listings are meant for tools, which need to see all entries
+    let untruncated = query_params.raw.unwrap_or(false)
+        || conf.minimal_ui
+        || query_params.show_all.unwrap_or(false);
+    if let Some(max_entries) = conf.max_listing_entries
+        && !untruncated
+    {
+        entries.truncate(max_entries);
+    }
 
-        // We will create the archive in a separate thread, and stream the content using a pipe.
-        // The pipe is made of a futures channel, and an adapter to implement the `Write` trait.
-        // Include 10 messages of buffer for erratic connection speeds.
-        let (tx, rx) = futures::channel::mpsc::channel::<io::Result<actix_web::web::Bytes>>(10);
-        let pipe = crate::pipe::Pipe::new(tx);
+    let flat = can_flatten.then_some(query_params.flat == Some(true));
+    let mut res = HttpResponse::Ok();
+    res.content_type(mime::TEXT_HTML_UTF_8);
+    if let Some(cookie) = preferences_cookie {
+        res.cookie(cookie);
+    }
+    ServiceResponse::new(
+        req.clone(),
+        res.body(
+            renderer::page(
+                entries,
+                totals,
+                readme,
+                &abs_uri,
+                is_root,
+                query_params,
+                &breadcrumbs,
+                &encoded_dir,
+                conf,
+                current_user,
+                simple_ui,
+                theme,
+                flat,
+                i18n::texts(req),
+            )
+            .into_string(),
+        ),
+    )
+}
 
-        // Start the actual archive creation in a separate thread.
-        let dir = dir.path.to_path_buf();
-        let skip_symlinks = conf.no_symlinks;
-        std::thread::spawn(move || {
-            if let Err(err) = archive_method.create_archive(dir, skip_symlinks, pipe) {
-                log::error!("Error during archive creation: {:?}", err);
-            }
-        });
+/// Compares names the way listings are sorted by name
+///
+/// Case is ignored, and numbers are compared by their values, so that 'file2' comes before
+/// 'file10' and 'v1.9' before 'v1.10'. Names only differing in case are still kept in a stable
+/// order.
+pub fn compare_names(a: &str, b: &str) -> Ordering {
+    alphanumeric_sort::compare_str(a.to_lowercase(), b.to_lowercase()).then_with(|| a.cmp(b))
+}
 
-        Ok(ServiceResponse::new(
-            req.clone(),
-            HttpResponse::Ok()
-                .content_type(archive_method.content_type())
-                .append_header(("Content-Transfer-Encoding", "binary"))
-                .append_header((
-                    "Content-Disposition",
-                    format!("attachment; filename={file_name:?}"),
-                ))
-                .body(actix_web::body::BodyStream::new(rx)),
-        ))
-    } else {
-        Ok(ServiceResponse::new(
-            req.clone(),
-            HttpResponse::Ok().content_type(mime::TEXT_HTML_UTF_8).body(
-                renderer::page(
-                    entries,
-                    readme,
-                    &abs_uri,
-                    is_root,
-                    query_params,
-                    &breadcrumbs,
-                    &encoded_dir,
-                    conf,
-                    current_user,
-                )
-                .into_string(),
-            ),
-        ))
+/// Path of the requested directory, relative to the route prefix and still percent-encoded
+fn encoded_dir(req: &HttpRequest, conf: &crate::MiniserveConfig) -> String {
+    let base = Path::new(req.path());
+    let random_route_abs = format!("/{}", conf.route_prefix);
+    match base.strip_prefix(random_route_abs) {
+        Ok(c_d) => Path::new("/").join(c_d),
+        Err(_) => base.to_path_buf(),
+    }
+    .display()
+    .to_string()
+}
+
+/// Returns whether the client is a browser that can't handle the full UI
+///
+/// This matches old Internet Explorer versions as well as text-mode browsers, which either lack
+/// JavaScript entirely or don't support what the upload widget needs. These clients are served
+/// the simple UI instead.
+fn is_legacy_browser(req: &HttpRequest) -> bool {
+    // Text-mode browsers, matched on the product token so that e.g. crawlers mentioning them
+    // aren't caught
+    const LEGACY_PRODUCTS: &[&str] = &["Lynx", "w3m", "Links", "ELinks"];
+    // Internet Explorer only identifies itself in the comment of its User-Agent
+    const LEGACY_COMMENTS: &[&str] = &["; MSIE ", "Trident/"];
+
+    let Some(ua) = req
+        .headers()
+        .get(header::USER_AGENT)
+        .and_then(|ua| ua.to_str().ok())
+    else {
+        return false;
+    };
+
+    LEGACY_COMMENTS.iter().any(|legacy| ua.contains(legacy))
+        || user_agent_products(ua).any(|product| LEGACY_PRODUCTS.contains(&product))
+}
+
+/// Names of the products listed in a User-Agent, leaving out versions and comments
+fn user_agent_products(ua: &str) -> impl Iterator<Item = &str> {
+    let mut depth = 0usize;
+    ua.split_whitespace()
+        .filter(move |token| {
+            let in_comment = depth > 0 || token.starts_with('(');
+            depth += token.matches('(').count();
+            depth = depth.saturating_sub(token.matches(')').count());
+            !in_comment
+        })
+        .map(|token| token.split_once('/').map_or(token, |(name, _)| name))
+}
+
+/// Cookie keeping how listings were chosen to be shown last, so that it's kept while browsing
+const PREFERENCES_COOKIE: &str = "miniserve_listing";
+
+/// Cookie keeping the theme chosen last, which the theme selector sets
+const THEME_COOKIE: &str = "miniserve_theme";
+
+/// How listings are sorted and shown, as kept in [`PREFERENCES_COOKIE`]
+#[derive(Deserialize, Default)]
+struct ListingPreferences {
+    sort: Option<SortingMethod>,
+    order: Option<SortingOrder>,
+    dirs_first: Option<bool>,
+    hidden: Option<bool>,
+    time: Option<TimeFormat>,
+}
+
+/// Fills in how the user chose listings to be shown last where the query doesn't choose
+///
+/// When the query does, the cookie keeping what's chosen now is returned.
+fn remember_preferences(
+    req: &HttpRequest,
+    query_params: &mut ListingQueryParameters,
+    conf: &crate::MiniserveConfig,
+) -> Option<Cookie<'static>> {
+    let remembered = req
+        .cookie(PREFERENCES_COOKIE)
+        .and_then(|cookie| Query::<ListingPreferences>::from_query(cookie.value()).ok())
+        .map(Query::into_inner)
+        .unwrap_or_default();
+    let chosen = query_params.sort.is_some()
+        || query_params.order.is_some()
+        || query_params.dirs_first.is_some()
+        || query_params.hidden.is_some()
+        || query_params.time.is_some();
+    query_params.sort = query_params.sort.or(remembered.sort);
+    query_params.order = query_params.order.or(remembered.order);
+    query_params.dirs_first = query_params.dirs_first.or(remembered.dirs_first);
+    query_params.hidden = query_params.hidden.or(remembered.hidden);
+    query_params.time = query_params.time.or(remembered.time);
+    if !chosen {
+        return None;
+    }
+
+    let mut value = Vec::new();
+    if let Some(sort) = query_params.sort {
+        value.push(format!("sort={sort}"));
+    }
+    if let Some(order) = query_params.order {
+        value.push(format!("order={order}"));
+    }
+    if let Some(dirs_first) = query_params.dirs_first {
+        value.push(format!("dirs_first={dirs_first}"));
+    }
+    if let Some(hidden) = query_params.hidden {
+        value.push(format!("hidden={hidden}"));
+    }
+    if let Some(time) = query_params.time {
+        value.push(format!("time={time}"));
     }
+    Some(
+        Cookie::build(PREFERENCES_COOKIE, value.join("&"))
+            .path(format!("{}/", conf.route_prefix))
+            .max_age(cookie::time::Duration::days(365))
+            .same_site(SameSite::Lax)
+            .finish(),
+    )
 }
 
 pub fn extract_query_parameters(req: &HttpRequest) -> ListingQueryParameters {
@@ -449,8 +983,28 @@ pub fn extract_query_parameters(req: &HttpRequest) -> ListingQueryParameters {
         Ok(Query(query_params)) => query_params,
         Err(e) => {
             let err = RuntimeError::ParseError("query parameters".to_string(), e.to_string());
-            errors::log_error_chain(err.to_string());
+            errors::log_error_chain(format!("[{}] {err}", err.code()));
             ListingQueryParameters::default()
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn sorts_names_naturally() {
+        let mut names = vec![
+            "v1.10", "file10", "File2", "v1.9", "file1", "v2.0", "file2", "a", "v1.9.1",
+        ];
+        names.sort_by(|a, b| compare_names(a, b));
+        assert_eq!(
+            names,
+            [
+                "a", "file1", "File2", "file2", "file10", "v1.9", "v1.9.1", "v1.10", "v2.0"
+            ]
+        );
+    }
+}
@@ -0,0 +1,127 @@
+//! Telling open listings that their directory changed, with --live-refresh
+//!
+//! Directories are polled rather than watched through the OS, which works the same on every
+//! platform and filesystem, network mounts included.
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use actix_web::{
+    HttpResponse,
+    body::{EitherBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    http::{
+        Method,
+        header::{CacheControl, CacheDirective, ContentEncoding},
+    },
+    middleware::Next,
+    web::{self, Bytes},
+};
+use futures::stream;
+use serde::Deserialize;
+
+use crate::{MiniserveConfig, file_utils};
+
+/// How often directories are checked for changes
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Number of checks between comments keeping idle connections open through proxies
+const PINGS_EVERY: u32 = 15;
+
+#[derive(Deserialize)]
+struct EventsQuery {
+    events: Option<bool>,
+}
+
+/// Middleware answering requests for listings with '?events=true' with a stream of server-sent
+/// events, which has a `change` event whenever an entry of the directory is added, removed or
+/// modified
+///
+/// The directory is fingerprinted before answering, so that changes made as soon as the stream
+/// is open are told about.
+pub async fn events_middleware(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, actix_web::Error> {
+    let conf = req
+        .app_data::<web::Data<MiniserveConfig>>()
+        .expect("Could not get miniserve config")
+        .clone();
+
+    let events = web::Query::<EventsQuery>::from_query(req.query_string())
+        .is_ok_and(|query| query.events == Some(true));
+    let dir =
+        (conf.live_refresh && !conf.disable_indexing && req.method() == Method::GET && events)
+            .then(|| req.match_info().unprocessed())
+            .filter(|path| path.is_empty() || path.ends_with('/'))
+            .and_then(|path| file_utils::requested_dir(&conf, path))
+            // Directories served by their index file have no listing to refresh
+            .filter(|dir| {
+                conf.index
+                    .as_ref()
+                    .is_none_or(|index| !dir.join(index).is_file())
+            });
+    let Some(dir) = dir else {
+        return Ok(next.call(req).await?.map_into_left_body());
+    };
+
+    let baseline = blocking_fingerprint(dir.clone()).await;
+    let stream = stream::unfold((baseline, 0), move |(mut last, mut ticks)| {
+        let dir = dir.clone();
+        async move {
+            loop {
+                actix_web::rt::time::sleep(POLL_INTERVAL).await;
+                ticks += 1;
+                let current = blocking_fingerprint(dir.clone()).await;
+                let event = if current != last {
+                    last = current;
+                    "event: change\ndata: \n\n"
+                } else if ticks % PINGS_EVERY == 0 {
+                    ": ping\n\n"
+                } else {
+                    continue;
+                };
+                return Some((
+                    Ok::<_, actix_web::Error>(Bytes::from_static(event.as_bytes())),
+                    (last, ticks),
+                ));
+            }
+        }
+    });
+
+    let res = HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(CacheControl(vec![CacheDirective::NoCache]))
+        // Compressing would hold events back until enough of them were buffered
+        .insert_header(ContentEncoding::Identity)
+        .streaming(stream);
+    Ok(req.into_response(res).map_into_right_body())
+}
+
+/// Fingerprint of `dir`, read on the thread pool for blocking operations
+async fn blocking_fingerprint(dir: PathBuf) -> Option<u64> {
+    web::block(move || fingerprint(&dir)).await.ok().flatten()
+}
+
+/// Hash of the names, sizes and modification times of the entries of `dir`
+fn fingerprint(dir: &Path) -> Option<u64> {
+    let mut entries: Vec<_> = dir
+        .read_dir()
+        .ok()?
+        .filter_map(Result::ok)
+        .map(|entry| {
+            let metadata = entry.metadata().ok();
+            (
+                entry.file_name(),
+                metadata.as_ref().map(|metadata| metadata.len()),
+                metadata.and_then(|metadata| metadata.modified().ok()),
+            )
+        })
+        .collect();
+    entries.sort();
+
+    let mut hasher = DefaultHasher::new();
+    entries.hash(&mut hasher);
+    Some(hasher.finish())
+}
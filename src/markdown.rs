@@ -0,0 +1,74 @@
+//! Rendering of Markdown files as HTML pages with --render-markdown
+use std::path::Path;
+
+use actix_web::{
+    HttpResponse,
+    body::{EitherBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    http::Method,
+    middleware::Next,
+    web,
+};
+use comrak::{ComrakOptions, markdown_to_html};
+
+use crate::{MiniserveConfig, file_utils, i18n, listing::ListingQueryParameters, renderer};
+
+/// Middleware answering requests for Markdown files with an HTML page rendering them
+///
+/// Requests with '?raw=true' are passed on to be served the file as it is, which the page links
+/// to. So are files that aren't valid UTF-8.
+pub async fn markdown_middleware(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, actix_web::Error> {
+    let conf = req
+        .app_data::<web::Data<MiniserveConfig>>()
+        .expect("Could not get miniserve config")
+        .clone();
+
+    let raw = web::Query::<ListingQueryParameters>::from_query(req.query_string())
+        .is_ok_and(|query| query.raw == Some(true));
+    let file = (conf.render_markdown && req.method() == Method::GET && !raw)
+        .then(|| file_utils::requested_file(&conf, req.match_info().unprocessed()))
+        .flatten()
+        .filter(|file| is_markdown(file));
+    let contents = match file {
+        Some(file) => tokio::fs::read_to_string(&file)
+            .await
+            .ok()
+            .map(|contents| (file, contents)),
+        None => None,
+    };
+    let Some((file, contents)) = contents else {
+        return Ok(next.call(req).await?.map_into_left_body());
+    };
+
+    let file_name = file
+        .file_name()
+        .map(|name| name.to_string_lossy())
+        .unwrap_or_default();
+    let html = to_html(&contents);
+    let res = HttpResponse::Ok().content_type(mime::TEXT_HTML_UTF_8).body(
+        renderer::markdown(&file_name, &html, &conf, i18n::texts(req.request())).into_string(),
+    );
+    Ok(req.into_response(res).map_into_right_body())
+}
+
+/// Converts Markdown to HTML, with the extensions of GitHub Flavored Markdown
+///
+/// Raw HTML in the Markdown is left out rather than passed through.
+pub fn to_html(markdown: &str) -> String {
+    let mut options = ComrakOptions::default();
+    options.extension.strikethrough = true;
+    options.extension.table = true;
+    options.extension.autolink = true;
+    options.extension.tasklist = true;
+    markdown_to_html(markdown, &options)
+}
+
+/// Whether the file has the extension of Markdown files
+fn is_markdown(file: &Path) -> bool {
+    file.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("md") || ext.eq_ignore_ascii_case("markdown"))
+}
@@ -0,0 +1,77 @@
+//! Announcing the server on the local network through mDNS, also known as Bonjour or Zeroconf
+use std::net::IpAddr;
+use std::time::Duration;
+
+use mdns_sd::{ServiceDaemon, ServiceInfo};
+
+use crate::config::MiniserveConfig;
+
+/// Announcement of the server, withdrawn when dropped
+pub struct Announcement {
+    daemon: ServiceDaemon,
+    fullname: String,
+}
+
+impl Announcement {
+    /// Starts announcing the server, which listens on `port` of `addrs`
+    ///
+    /// Clients are pointed at the addresses of every network interface if any of `addrs` is a
+    /// wildcard address.
+    pub fn new(conf: &MiniserveConfig, port: u16, addrs: &[IpAddr]) -> mdns_sd::Result<Self> {
+        let service_type = if conf.tls_rustls_config.is_some() {
+            "_https._tcp.local."
+        } else {
+            "_http._tcp.local."
+        };
+        let specific_addrs: Vec<_> = addrs
+            .iter()
+            .copied()
+            .filter(|addr| !addr.is_unspecified())
+            .collect();
+        // Clients are meant to open this path, as defined by RFC 6763
+        let path = format!("{}/", conf.route_prefix);
+
+        let mut service = ServiceInfo::new(
+            service_type,
+            &service_name(conf),
+            &format!("{}.local.", host_name()),
+            &specific_addrs[..],
+            port,
+            &[("path", path.as_str())][..],
+        )?;
+        if specific_addrs.len() < addrs.len() {
+            service = service.enable_addr_auto();
+        }
+
+        let daemon = ServiceDaemon::new()?;
+        let fullname = service.get_fullname().to_owned();
+        daemon.register(service)?;
+        Ok(Self { daemon, fullname })
+    }
+}
+
+impl Drop for Announcement {
+    fn drop(&mut self) {
+        // Clients would otherwise keep listing the server until the announcement expires
+        if let Ok(status) = self.daemon.unregister(&self.fullname) {
+            let _ = status.recv_timeout(Duration::from_secs(1));
+        }
+        let _ = self.daemon.shutdown();
+    }
+}
+
+/// Name the server is announced under
+pub fn service_name(conf: &MiniserveConfig) -> String {
+    conf.title
+        .clone()
+        .unwrap_or_else(|| format!("miniserve on {}", host_name()))
+}
+
+/// Name of this machine, without any domain it may be part of
+fn host_name() -> String {
+    let name = gethostname::gethostname().to_string_lossy().into_owned();
+    match name.split_once('.') {
+        Some((host, _)) => host.to_owned(),
+        None => name,
+    }
+}
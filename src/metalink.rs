@@ -0,0 +1,163 @@
+//! Metalink descriptions of served files, served with --metalink
+//!
+//! They're virtual sidecars like `file.iso.meta4` next to the files, unless such files exist.
+//! Besides the URL of the file, they have its SHA-256 checksum and those of its pieces, so that
+//! download managers can fetch pieces in parallel and verify each of them.
+use std::path::PathBuf;
+
+use actix_web::{
+    HttpResponse,
+    body::{EitherBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    http::{Method, header},
+    middleware::Next,
+    web,
+};
+use maud::{Markup, PreEscaped, html};
+
+use crate::{
+    MiniserveConfig,
+    checksum::{ChecksumCache, HashAlgorithm},
+    file_utils,
+};
+
+/// Extension of Metalink sidecars
+const METALINK_EXTENSION: &str = ".meta4";
+
+/// Length of pieces of files, unless they'd have more than `MAX_PIECES` pieces
+const MIN_PIECE_LENGTH: u64 = 1024 * 1024;
+
+/// Number of pieces files are split into at most, with longer pieces for larger files
+const MAX_PIECES: u64 = 2048;
+
+/// Answers requests of `.meta4` sidecars of files with their Metalink description
+pub async fn metalink_middleware(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, actix_web::Error> {
+    let conf = req
+        .app_data::<web::Data<MiniserveConfig>>()
+        .expect("Could not get miniserve config")
+        .clone();
+    let cache = req
+        .app_data::<web::Data<ChecksumCache>>()
+        .expect("Could not get checksum cache")
+        .clone();
+
+    let file = (conf.metalink && req.method() == Method::GET)
+        .then(|| requested_metalink(&conf, req.match_info().unprocessed()))
+        .flatten();
+    let Some(file) = file else {
+        return Ok(next.call(req).await?.map_into_left_body());
+    };
+
+    let size = file.metadata()?.len();
+    let piece_length = piece_length(size);
+    let algorithm = HashAlgorithm::Sha256;
+    let checksums = match cache.checksum(&file, algorithm).await {
+        Ok(checksum) => cache
+            .piece_checksums(&file, algorithm, piece_length)
+            .await
+            .map(|pieces| (checksum, pieces)),
+        Err(e) => Err(e),
+    };
+    let (checksum, pieces) = match checksums {
+        Ok(checksums) => checksums,
+        Err(e) => {
+            log::error!("Failed to compute the checksums of {file:?}: {e}");
+            let res = HttpResponse::InternalServerError().finish();
+            return Ok(req.into_response(res).map_into_right_body());
+        }
+    };
+
+    let name = file
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let url = {
+        let info = req.connection_info();
+        let path = req
+            .path()
+            .strip_suffix(METALINK_EXTENSION)
+            .unwrap_or_default();
+        format!("{}://{}{}", info.scheme(), info.host(), path)
+    };
+    let description = Description {
+        name: &name,
+        url: &url,
+        size,
+        checksum: &checksum,
+        piece_length,
+        pieces: &pieces,
+    };
+    let res = HttpResponse::Ok()
+        .content_type("application/metalink4+xml")
+        .insert_header((header::CACHE_CONTROL, "no-cache"))
+        .body(render(&description).into_string());
+    Ok(req.into_response(res).map_into_right_body())
+}
+
+/// Resolves the file whose Metalink sidecar a request path asks for, unless the sidecar exists
+fn requested_metalink(conf: &MiniserveConfig, path: &str) -> Option<PathBuf> {
+    let file = path.strip_suffix(METALINK_EXTENSION)?;
+    if file_utils::requested_file(conf, path).is_some() {
+        return None;
+    }
+    file_utils::requested_file(conf, file)
+}
+
+/// Length of the pieces of a file of `size` bytes
+fn piece_length(size: u64) -> u64 {
+    size.div_ceil(MAX_PIECES)
+        .next_power_of_two()
+        .max(MIN_PIECE_LENGTH)
+}
+
+/// What Metalink descriptions tell of a file
+struct Description<'a> {
+    name: &'a str,
+    url: &'a str,
+    size: u64,
+    checksum: &'a str,
+    piece_length: u64,
+    pieces: &'a [String],
+}
+
+/// Renders the Metalink description of a file, as defined by RFC 5854
+///
+/// Empty elements are closed explicitly, as Metalink is XML.
+fn render(description: &Description) -> Markup {
+    html! {
+        (PreEscaped(r#"<?xml version="1.0" encoding="UTF-8"?>"#))
+        metalink xmlns="urn:ietf:params:xml:ns:metalink" {
+            generator { "miniserve/" (env!("CARGO_PKG_VERSION")) }
+            file name=(description.name) {
+                size { (description.size) }
+                hash type="sha-256" { (description.checksum) }
+                pieces length=(description.piece_length) type="sha-256" {
+                    @for piece in description.pieces {
+                        hash { (piece) }
+                    }
+                }
+                url { (description.url) }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(0, MIN_PIECE_LENGTH)]
+    #[case(10, MIN_PIECE_LENGTH)]
+    #[case(MIN_PIECE_LENGTH * MAX_PIECES, MIN_PIECE_LENGTH)]
+    #[case(MIN_PIECE_LENGTH * MAX_PIECES + 1, 2 * MIN_PIECE_LENGTH)]
+    #[case(100 * 1024 * 1024 * 1024, 64 * 1024 * 1024)]
+    fn piece_lengths(#[case] size: u64, #[case] expected: u64) {
+        assert_eq!(piece_length(size), expected);
+        assert!(size.div_ceil(piece_length(size)) <= MAX_PIECES);
+    }
+}
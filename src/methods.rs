@@ -0,0 +1,92 @@
+//! Methods the routes answer to, as told to clients sending OPTIONS requests or other methods
+//!
+//! WebDAV clients discover the server with OPTIONS requests too, which its handler answers for
+//! the served files when --enable-webdav is given.
+use actix_web::{
+    HttpResponse, Route,
+    body::{EitherBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    guard,
+    http::{Method, header},
+    middleware::Next,
+    web,
+};
+
+use crate::MiniserveConfig;
+
+/// Route answering GET requests, and HEAD ones with the same headers but no body
+pub fn get_or_head() -> Route {
+    web::route().guard(guard::Any(guard::Get()).or(guard::Head()))
+}
+
+/// Methods answered to at `path`
+///
+/// It's `None` where requests are left to other handlers: to the WebDAV one for served files with
+/// --enable-webdav, and to the one of missing routes outside of the route prefix.
+pub fn allowed_methods(conf: &MiniserveConfig, path: &str) -> Option<Vec<Method>> {
+    let read = vec![Method::GET, Method::HEAD];
+    let internal_routes = [
+        &conf.healthcheck_route,
+        &conf.favicon_route,
+        &conf.css_route,
+    ];
+    let mut methods = if path == conf.api_route {
+        vec![Method::POST]
+    } else if internal_routes.iter().any(|route| *route == path)
+        || (conf.robots.is_some() && path == "/robots.txt")
+    {
+        read
+    } else {
+        let tail = path.strip_prefix(&conf.route_prefix)?;
+        if conf.receive.is_some() || (conf.admin.is_some() && tail == "/__admin") {
+            vec![Method::GET, Method::HEAD, Method::POST]
+        } else if conf.file_upload && tail == "/upload" {
+            vec![Method::POST]
+        } else if conf.webdav_enabled {
+            return None;
+        } else {
+            read
+        }
+    };
+    methods.push(Method::OPTIONS);
+    Some(methods)
+}
+
+/// Value of an `Allow` header listing `methods`
+pub fn allow_header(methods: &[Method]) -> String {
+    methods
+        .iter()
+        .map(Method::as_str)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Answers OPTIONS requests with the methods allowed at their path, and requests of other methods
+/// with 405 Method Not Allowed
+///
+/// CORS preflight requests are answered before, by the CORS middleware.
+pub async fn methods_middleware(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, actix_web::Error> {
+    let conf = req
+        .app_data::<web::Data<MiniserveConfig>>()
+        .expect("Could not get miniserve config")
+        .clone();
+
+    let Some(methods) = allowed_methods(&conf, req.path()) else {
+        return Ok(next.call(req).await?.map_into_left_body());
+    };
+    let mut res = if req.method() == Method::OPTIONS {
+        HttpResponse::NoContent()
+    } else if !methods.contains(req.method()) {
+        HttpResponse::MethodNotAllowed()
+    } else {
+        return Ok(next.call(req).await?.map_into_left_body());
+    };
+
+    let res = res
+        .insert_header((header::ALLOW, allow_header(&methods)))
+        .finish();
+    Ok(req.into_response(res).map_into_right_body())
+}
@@ -0,0 +1,59 @@
+//! Pages showing PDF files with the viewer of the browser
+//!
+//! PDF files are served inline for the viewer to show them, and listings link them to a page
+//! embedding it with a button to download them.
+use std::path::Path;
+
+use actix_web::{
+    HttpResponse,
+    body::{EitherBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    http::Method,
+    middleware::Next,
+    web,
+};
+use percent_encoding::utf8_percent_encode;
+use serde::Deserialize;
+
+use crate::{MiniserveConfig, file_utils, i18n, listing::percent_encode_sets::COMPONENT, renderer};
+
+/// Whether a file is a PDF document, going by its name
+pub fn is_pdf(file_name: &str) -> bool {
+    Path::new(file_name)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("pdf"))
+}
+
+#[derive(Deserialize)]
+struct ViewerQuery {
+    viewer: Option<bool>,
+}
+
+/// Middleware answering requests for PDF files with '?viewer=true' with a page showing them
+pub async fn pdf_middleware(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, actix_web::Error> {
+    let conf = req
+        .app_data::<web::Data<MiniserveConfig>>()
+        .expect("Could not get miniserve config")
+        .clone();
+
+    let viewer = web::Query::<ViewerQuery>::from_query(req.query_string())
+        .is_ok_and(|query| query.viewer == Some(true));
+    let file_name = (conf.pdf_viewer && req.method() == Method::GET && viewer)
+        .then(|| file_utils::requested_file(&conf, req.match_info().unprocessed()))
+        .flatten()
+        .and_then(|file| Some(file.file_name()?.to_string_lossy().into_owned()))
+        .filter(|name| is_pdf(name));
+    let Some(file_name) = file_name else {
+        return Ok(next.call(req).await?.map_into_left_body());
+    };
+
+    let link = utf8_percent_encode(&file_name, COMPONENT).to_string();
+    let res = HttpResponse::Ok()
+        .content_type(mime::TEXT_HTML_UTF_8)
+        .body(renderer::pdf(&file_name, &link, &conf, i18n::texts(req.request())).into_string());
+    Ok(req.into_response(res).map_into_right_body())
+}
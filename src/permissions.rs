@@ -0,0 +1,148 @@
+//! Modes and owners of files, shown in listings with --show-permissions
+use std::collections::HashMap;
+use std::ffi::CStr;
+use std::fs::{FileType, Metadata};
+use std::os::unix::fs::{FileTypeExt, MetadataExt};
+
+use crate::listing::Permissions;
+
+/// Formats the type and mode of a file like `ls -l` does, e.g. 'drwxr-xr-x'
+fn mode_string(file_type: FileType, mode: u32) -> String {
+    let kind = if file_type.is_dir() {
+        'd'
+    } else if file_type.is_symlink() {
+        'l'
+    } else if file_type.is_fifo() {
+        'p'
+    } else if file_type.is_socket() {
+        's'
+    } else if file_type.is_char_device() {
+        'c'
+    } else if file_type.is_block_device() {
+        'b'
+    } else {
+        '-'
+    };
+    let mut string = String::from(kind);
+    // Read, write and execute bits of the owner, group and others, with the setuid, setgid and
+    // sticky bits taking the place of their execute bits
+    for (shift, special, set, unset) in [
+        (6, 0o4000, 's', 'S'),
+        (3, 0o2000, 's', 'S'),
+        (0, 0o1000, 't', 'T'),
+    ] {
+        let bits = (mode >> shift) & 0o7;
+        string.push(if bits & 0o4 != 0 { 'r' } else { '-' });
+        string.push(if bits & 0o2 != 0 { 'w' } else { '-' });
+        string.push(match (bits & 0o1 != 0, mode & special != 0) {
+            (true, true) => set,
+            (false, true) => unset,
+            (true, false) => 'x',
+            (false, false) => '-',
+        });
+    }
+    string
+}
+
+/// Looks up the names of owners and groups, remembering them for the rest of a listing
+#[derive(Default)]
+pub struct Owners {
+    users: HashMap<u32, String>,
+    groups: HashMap<u32, String>,
+}
+
+impl Owners {
+    /// Describes the mode, owner and group of an entry
+    pub fn permissions(&mut self, metadata: &Metadata) -> Permissions {
+        let owner = self
+            .users
+            .entry(metadata.uid())
+            .or_insert_with_key(|&uid| user_name(uid).unwrap_or_else(|| uid.to_string()))
+            .clone();
+        let group = self
+            .groups
+            .entry(metadata.gid())
+            .or_insert_with_key(|&gid| group_name(gid).unwrap_or_else(|| gid.to_string()))
+            .clone();
+        Permissions {
+            mode: mode_string(metadata.file_type(), metadata.mode()),
+            owner,
+            group,
+        }
+    }
+}
+
+/// Finds the name of a user, with the reentrant lookup as listings are made on several threads
+fn user_name(uid: u32) -> Option<String> {
+    let mut buffer = vec![0 as libc::c_char; 4096];
+    // SAFETY: all zeroes is a valid passwd, which getpwuid_r fills with pointers into `buffer`
+    let mut entry: libc::passwd = unsafe { std::mem::zeroed() };
+    let mut result = std::ptr::null_mut();
+    // SAFETY: the pointers are to live values, and `buffer` is as long as it's said to be
+    let status = unsafe {
+        libc::getpwuid_r(
+            uid,
+            &mut entry,
+            buffer.as_mut_ptr(),
+            buffer.len(),
+            &mut result,
+        )
+    };
+    if status != 0 || result.is_null() {
+        return None;
+    }
+    // SAFETY: the name is a NUL terminated string in `buffer`, which is still alive
+    let name = unsafe { CStr::from_ptr(entry.pw_name) };
+    Some(name.to_string_lossy().into_owned())
+}
+
+/// Finds the name of a group, with the reentrant lookup as listings are made on several threads
+fn group_name(gid: u32) -> Option<String> {
+    let mut buffer = vec![0 as libc::c_char; 4096];
+    // SAFETY: all zeroes is a valid group, which getgrgid_r fills with pointers into `buffer`
+    let mut entry: libc::group = unsafe { std::mem::zeroed() };
+    let mut result = std::ptr::null_mut();
+    // SAFETY: the pointers are to live values, and `buffer` is as long as it's said to be
+    let status = unsafe {
+        libc::getgrgid_r(
+            gid,
+            &mut entry,
+            buffer.as_mut_ptr(),
+            buffer.len(),
+            &mut result,
+        )
+    };
+    if status != 0 || result.is_null() {
+        return None;
+    }
+    // SAFETY: the name is a NUL terminated string in `buffer`, which is still alive
+    let name = unsafe { CStr::from_ptr(entry.gr_name) };
+    Some(name.to_string_lossy().into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn formats_modes() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("file");
+        std::fs::write(&file, "").unwrap();
+        let dir_type = std::fs::metadata(dir.path()).unwrap().file_type();
+        let file_type = std::fs::metadata(&file).unwrap().file_type();
+
+        assert_eq!(mode_string(dir_type, 0o755), "drwxr-xr-x");
+        assert_eq!(mode_string(file_type, 0o640), "-rw-r-----");
+        assert_eq!(mode_string(file_type, 0o4755), "-rwsr-xr-x");
+        assert_eq!(mode_string(dir_type, 0o1777), "drwxrwxrwt");
+        assert_eq!(mode_string(file_type, 0o2644), "-rw-r-Sr--");
+    }
+
+    #[test]
+    fn names_owners() {
+        assert_eq!(user_name(0).as_deref(), Some("root"));
+        assert!(user_name(u32::MAX - 1).is_none());
+    }
+}
@@ -1,10 +1,12 @@
-//! Define an adapter to implement `std::io::Write` on `Sender<Bytes>`.
-use std::io::{self, Error, ErrorKind, Write};
+//! Define an adapter to implement `std::io::Write` on `Sender<Bytes>`, and helpers to buffer
+//! streams so they can be served like regular files.
+use std::io::{self, Error, ErrorKind, Read, Write};
 
 use actix_web::web::{Bytes, BytesMut};
 use futures::channel::mpsc::Sender;
 use futures::executor::block_on;
 use futures::sink::SinkExt;
+use tempfile::NamedTempFile;
 
 /// Adapter to implement the `std::io::Write` trait on a `Sender<Bytes>` from a futures channel.
 ///
@@ -47,3 +49,15 @@ impl Write for Pipe {
         block_on(self.dest.flush()).map_err(|e| Error::new(ErrorKind::UnexpectedEof, e))
     }
 }
+
+/// Buffer everything read from `source` into a temporary file.
+///
+/// Streams have no known size and can't be seeked, so they are buffered to disk first in order
+/// to be served with a correct `Content-Length` and support for range requests. The file is
+/// removed once the returned handle is dropped.
+pub fn buffer_to_temp_file(mut source: impl Read) -> io::Result<NamedTempFile> {
+    let mut file = NamedTempFile::new()?;
+    io::copy(&mut source, &mut file)?;
+    file.flush()?;
+    Ok(file)
+}
@@ -0,0 +1,235 @@
+//! Decoding and encoding of PNG images, for resized images
+//!
+//! Images with 8 or 16 bits per sample are decoded, or with palettes of 8 bits. Interlaced images
+//! aren't.
+use std::io::{Read, Write};
+
+use libflate::zlib;
+
+use crate::resize::Image;
+
+const SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+/// Decodes a PNG image into grayscale, gray and alpha, RGB or RGBA pixels
+///
+/// Images of more than `max_pixels` pixels are refused as soon as their size is known, before
+/// anything is inflated or allocated for them.
+pub fn decode(data: &[u8], max_pixels: usize) -> Option<Image> {
+    let mut chunks = data.strip_prefix(SIGNATURE)?;
+    let mut header = None;
+    let mut palette: &[u8] = &[];
+    let mut transparency: &[u8] = &[];
+    let mut compressed = Vec::new();
+    while chunks.len() >= 12 {
+        let len = u32::from_be_bytes(chunks[..4].try_into().ok()?) as usize;
+        let kind = &chunks[4..8];
+        let chunk = chunks.get(8..8 + len)?;
+        match kind {
+            b"IHDR" => header = Some(chunk),
+            b"PLTE" => palette = chunk,
+            b"tRNS" => transparency = chunk,
+            b"IDAT" => compressed.extend_from_slice(chunk),
+            b"IEND" => break,
+            _ => {}
+        }
+        chunks = chunks.get(12 + len..)?;
+    }
+
+    let header = header?;
+    let width = u32::from_be_bytes(header.get(0..4)?.try_into().ok()?) as usize;
+    let height = u32::from_be_bytes(header.get(4..8)?.try_into().ok()?) as usize;
+    if width.checked_mul(height)? > max_pixels {
+        return None;
+    }
+    let [depth, color, _, _, interlace] = *header.get(8..13)? else {
+        return None;
+    };
+    let samples = match color {
+        0 => 1,
+        2 => 3,
+        3 => 1,
+        4 => 2,
+        6 => 4,
+        _ => return None,
+    };
+    let valid_depth = depth == 8 || (depth == 16 && color != 3);
+    if !valid_depth || interlace != 0 || width == 0 || height == 0 {
+        return None;
+    }
+    let bytes_per_pixel = samples * depth as usize / 8;
+    let stride = width * bytes_per_pixel;
+
+    let mut raw = Vec::new();
+    zlib::Decoder::new(compressed.as_slice())
+        .ok()?
+        .take((stride as u64 + 1) * height as u64)
+        .read_to_end(&mut raw)
+        .ok()?;
+    if raw.len() != (stride + 1) * height {
+        return None;
+    }
+    let mut rows = vec![0; stride * height];
+    for y in 0..height {
+        let filter = raw[y * (stride + 1)];
+        let line = &raw[y * (stride + 1) + 1..][..stride];
+        for x in 0..stride {
+            let a = if x >= bytes_per_pixel {
+                rows[y * stride + x - bytes_per_pixel]
+            } else {
+                0
+            };
+            let b = if y > 0 { rows[(y - 1) * stride + x] } else { 0 };
+            let c = if x >= bytes_per_pixel && y > 0 {
+                rows[(y - 1) * stride + x - bytes_per_pixel]
+            } else {
+                0
+            };
+            let predictor = match filter {
+                0 => 0,
+                1 => a,
+                2 => b,
+                3 => ((a as u16 + b as u16) / 2) as u8,
+                4 => paeth(a, b, c),
+                _ => return None,
+            };
+            rows[y * stride + x] = line[x].wrapping_add(predictor);
+        }
+    }
+
+    // Samples of 16 bits are cut down to their most significant byte
+    let samples_8: Vec<u8> = if depth == 16 {
+        rows.chunks_exact(2).map(|sample| sample[0]).collect()
+    } else {
+        rows
+    };
+    if color != 3 {
+        return Some(Image {
+            width,
+            height,
+            channels: samples,
+            pixels: samples_8,
+        });
+    }
+
+    let channels = if transparency.is_empty() { 3 } else { 4 };
+    let mut pixels = Vec::with_capacity(width * height * channels);
+    for &index in &samples_8 {
+        let index = index as usize;
+        pixels.extend_from_slice(palette.get(index * 3..index * 3 + 3)?);
+        if channels == 4 {
+            pixels.push(transparency.get(index).copied().unwrap_or(255));
+        }
+    }
+    Some(Image {
+        width,
+        height,
+        channels,
+        pixels,
+    })
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = a as i16 + b as i16 - c as i16;
+    let (pa, pb, pc) = (
+        (p - a as i16).abs(),
+        (p - b as i16).abs(),
+        (p - c as i16).abs(),
+    );
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+/// Encodes pixels as a PNG image
+pub fn encode(image: &Image) -> Vec<u8> {
+    let color: u8 = match image.channels {
+        1 => 0,
+        2 => 4,
+        3 => 2,
+        _ => 6,
+    };
+    let stride = image.width * image.channels;
+    // Each row is filtered by the difference to the pixel on its left
+    let mut raw = Vec::with_capacity((stride + 1) * image.height);
+    for row in image.pixels.chunks_exact(stride) {
+        raw.push(1);
+        for x in 0..stride {
+            let left = if x >= image.channels {
+                row[x - image.channels]
+            } else {
+                0
+            };
+            raw.push(row[x].wrapping_sub(left));
+        }
+    }
+    let mut encoder = zlib::Encoder::new(Vec::new()).expect("Writing to memory can't fail");
+    encoder
+        .write_all(&raw)
+        .expect("Writing to memory can't fail");
+    let compressed = encoder
+        .finish()
+        .into_result()
+        .expect("Writing to memory can't fail");
+
+    let mut out = SIGNATURE.to_vec();
+    let mut chunk = |kind: &[u8], data: &[u8]| {
+        out.extend((data.len() as u32).to_be_bytes());
+        let start = out.len();
+        out.extend(kind);
+        out.extend(data);
+        let crc = crc32fast::hash(&out[start..]);
+        out.extend(crc.to_be_bytes());
+    };
+    let mut header = Vec::new();
+    header.extend((image.width as u32).to_be_bytes());
+    header.extend((image.height as u32).to_be_bytes());
+    header.extend([8, color, 0, 0, 0]);
+    chunk(b"IHDR", &header);
+    chunk(b"IDAT", &compressed);
+    chunk(b"IEND", &[]);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn decodes_what_it_encodes() {
+        let image = Image {
+            width: 3,
+            height: 2,
+            channels: 4,
+            pixels: (0..24).map(|i| i * 10).collect(),
+        };
+        let decoded = decode(&encode(&image), usize::MAX).unwrap();
+        assert_eq!((decoded.width, decoded.height, decoded.channels), (3, 2, 4));
+        assert_eq!(decoded.pixels, image.pixels);
+    }
+
+    #[test]
+    fn refuses_other_data() {
+        assert!(decode(b"GIF89a", usize::MAX).is_none());
+        assert!(decode(SIGNATURE, usize::MAX).is_none());
+    }
+
+    #[test]
+    fn refuses_images_too_large_before_decoding() {
+        let image = Image {
+            width: 3,
+            height: 2,
+            channels: 4,
+            pixels: vec![0; 24],
+        };
+        let mut data = encode(&image);
+        // Claims 65535x65535 pixels, so that its data would be inflated to about 16 GB
+        data[16..24].copy_from_slice(&[0, 0, 0xff, 0xff, 0, 0, 0xff, 0xff]);
+        assert!(decode(&data, 1 << 20).is_none());
+        assert!(decode(&encode(&image), 6).is_some());
+    }
+}
@@ -0,0 +1,196 @@
+//! Forwarding the server's port on the router through UPnP or NAT-PMP
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, UdpSocket};
+use std::time::Duration;
+
+use actix_web::rt::{self, task::JoinHandle, time::timeout};
+use anyhow::{Context, Result, anyhow, bail};
+use igd_next::aio::{Gateway, tokio::Tokio};
+use igd_next::{PortMappingProtocol, SearchOptions};
+use log::{debug, warn};
+use natpmp::{Protocol, Response};
+
+/// How long routers are asked to keep the port forwarded, in seconds
+///
+/// Mappings are renewed halfway through, so they lapse shortly after miniserve is killed.
+const LEASE: u32 = 3600;
+
+/// How long to wait for routers to answer
+const TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Port forwarded on the router, which is renewed until removed
+pub struct PortMapping {
+    router: Router,
+    local_port: u16,
+    external_addr: SocketAddr,
+    renewal: JoinHandle<()>,
+}
+
+impl PortMapping {
+    /// Asks the router to forward a public port to `port` on this machine
+    ///
+    /// UPnP is tried first, then NAT-PMP. The same public port is requested, but routers may pick
+    /// another one.
+    pub async fn new(port: u16) -> Result<Self> {
+        let (router, public_ip) = match Router::discover_upnp().await {
+            Ok(found) => found,
+            Err(upnp_err) => match Router::discover_natpmp().await {
+                Ok(found) => found,
+                Err(natpmp_err) => bail!(
+                    "No router answered through UPnP ({upnp_err:#}) or NAT-PMP ({natpmp_err:#})"
+                ),
+            },
+        };
+        let external_port = router.map_any(port, LEASE).await?;
+
+        let renewal = rt::spawn({
+            let router = router.clone();
+            async move {
+                loop {
+                    rt::time::sleep(Duration::from_secs((LEASE / 2).into())).await;
+                    match router.map(port, external_port, LEASE).await {
+                        Ok(_) => debug!("Renewed the forwarding of port {external_port}"),
+                        Err(e) => {
+                            warn!("Failed to renew the forwarding of port {external_port}: {e:#}")
+                        }
+                    }
+                }
+            }
+        });
+
+        Ok(Self {
+            router,
+            local_port: port,
+            external_addr: SocketAddr::new(public_ip, external_port),
+            renewal,
+        })
+    }
+
+    /// Address the server can be reached at from the internet
+    pub fn external_addr(&self) -> SocketAddr {
+        self.external_addr
+    }
+
+    /// Asks the router to stop forwarding the port
+    pub async fn remove(self) {
+        self.renewal.abort();
+        let external_port = self.external_addr.port();
+        if let Err(e) = self.router.unmap(self.local_port, external_port).await {
+            warn!("Failed to remove the forwarding of port {external_port}: {e:#}");
+        }
+    }
+}
+
+#[derive(Clone)]
+enum Router {
+    /// Internet gateway device, which forwards ports to `local_ip`
+    Upnp {
+        gateway: Gateway<Tokio>,
+        local_ip: IpAddr,
+    },
+    NatPmp {
+        gateway: Ipv4Addr,
+    },
+}
+
+impl Router {
+    /// Finds a router through UPnP, along with its public IP address
+    async fn discover_upnp() -> Result<(Self, IpAddr)> {
+        let gateway = igd_next::aio::tokio::search_gateway(SearchOptions {
+            timeout: Some(TIMEOUT),
+            ..Default::default()
+        })
+        .await?;
+        // Mappings need the address the router sees us as
+        let local_ip = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))
+            .and_then(|socket| {
+                socket.connect(gateway.addr)?;
+                socket.local_addr()
+            })
+            .context("Failed to find the local address facing the router")?
+            .ip();
+        let public_ip = gateway.get_external_ip().await?;
+        Ok((Self::Upnp { gateway, local_ip }, public_ip))
+    }
+
+    /// Finds the default gateway, and checks that it speaks NAT-PMP by asking for its public IP
+    /// address
+    async fn discover_natpmp() -> Result<(Self, IpAddr)> {
+        let gateway = natpmp::get_default_gateway()?;
+        let mut client = natpmp::new_tokio_natpmp_with(gateway).await?;
+        client.send_public_address_request().await?;
+        match timeout(TIMEOUT, client.read_response_or_retry()).await?? {
+            Response::Gateway(response) => Ok((
+                Self::NatPmp { gateway },
+                IpAddr::V4(*response.public_address()),
+            )),
+            _ => bail!("Unexpected answer from {gateway}"),
+        }
+    }
+
+    /// Forwards a public port to `local_port` for `lease` seconds, preferably the same one, and
+    /// returns it
+    async fn map_any(&self, local_port: u16, lease: u32) -> Result<u16> {
+        match self {
+            Self::Upnp { gateway, local_ip } => match self.map(local_port, local_port, lease).await
+            {
+                Ok(external_port) => Ok(external_port),
+                // The port is likely forwarded to another device already, so let the router pick
+                Err(_) => Ok(gateway
+                    .add_any_port(
+                        PortMappingProtocol::TCP,
+                        SocketAddr::new(*local_ip, local_port),
+                        lease,
+                        "miniserve",
+                    )
+                    .await?),
+            },
+            // Routers already pick another port by themselves
+            Self::NatPmp { .. } => self.map(local_port, local_port, lease).await,
+        }
+    }
+
+    /// Forwards `external_port` to `local_port` for `lease` seconds, returning the public port
+    /// that was actually forwarded
+    async fn map(&self, local_port: u16, external_port: u16, lease: u32) -> Result<u16> {
+        match self {
+            Self::Upnp { gateway, local_ip } => {
+                gateway
+                    .add_port(
+                        PortMappingProtocol::TCP,
+                        external_port,
+                        SocketAddr::new(*local_ip, local_port),
+                        lease,
+                        "miniserve",
+                    )
+                    .await?;
+                Ok(external_port)
+            }
+            Self::NatPmp { gateway } => {
+                let client = natpmp::new_tokio_natpmp_with(*gateway).await?;
+                client
+                    .send_port_mapping_request(Protocol::TCP, local_port, external_port, lease)
+                    .await?;
+                match timeout(TIMEOUT, client.read_response_or_retry()).await?? {
+                    Response::TCP(response) => Ok(response.public_port()),
+                    _ => Err(anyhow!("Unexpected answer from {gateway}")),
+                }
+            }
+        }
+    }
+
+    /// Stops forwarding `external_port` to `local_port`
+    async fn unmap(&self, local_port: u16, external_port: u16) -> Result<()> {
+        match self {
+            Self::Upnp { gateway, .. } => {
+                gateway
+                    .remove_port(PortMappingProtocol::TCP, external_port)
+                    .await?
+            }
+            // NAT-PMP removes mappings that are requested with a lease of zero
+            Self::NatPmp { .. } => {
+                self.map(local_port, 0, 0).await?;
+            }
+        }
+        Ok(())
+    }
+}
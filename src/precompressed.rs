@@ -0,0 +1,93 @@
+//! Serving of precompressed variants of files, like the `.br` and `.gz` files that build tools
+//! emit next to web assets
+use std::path::PathBuf;
+
+use actix_files::NamedFile;
+use actix_web::{
+    HttpMessage,
+    body::{EitherBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    http::{
+        Method,
+        header::{self, AcceptEncoding, ContentEncoding, Encoding, Header, HeaderValue},
+    },
+    middleware::Next,
+    web,
+};
+
+use crate::{MiniserveConfig, content_type, file_utils};
+
+/// Encodings of precompressed variants, with the extension of their files
+const VARIANTS: [(ContentEncoding, &str); 2] = [
+    (ContentEncoding::Brotli, "br"),
+    (ContentEncoding::Gzip, "gz"),
+];
+
+/// Middleware answering requests for a file with its precompressed variant, if there's one the
+/// client accepts
+///
+/// Responses for files with variants always vary on `Accept-Encoding`, so that caches don't hand
+/// a compressed variant to clients that didn't ask for it.
+pub async fn precompressed_middleware(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, actix_web::Error> {
+    let conf = req
+        .app_data::<web::Data<MiniserveConfig>>()
+        .expect("Could not get miniserve config")
+        .clone();
+
+    let file = (conf.precompressed
+        && (req.method() == Method::GET || req.method() == Method::HEAD))
+        .then(|| file_utils::requested_file(&conf, req.match_info().unprocessed()))
+        .flatten();
+    let Some(file) = file else {
+        return Ok(next.call(req).await?.map_into_left_body());
+    };
+
+    let variants: Vec<_> = VARIANTS
+        .into_iter()
+        .filter_map(|(encoding, extension)| {
+            let mut variant = file.clone().into_os_string();
+            variant.push(format!(".{extension}"));
+            let variant = PathBuf::from(variant);
+            let allowed = variant.is_file() && !(conf.no_symlinks && variant.is_symlink());
+            allowed.then_some((encoding, variant))
+        })
+        .collect();
+    if variants.is_empty() {
+        return Ok(next.call(req).await?.map_into_left_body());
+    }
+
+    let accepted = AcceptEncoding::parse(&req).ok().and_then(|accept| {
+        let supported: Vec<_> = variants
+            .iter()
+            .map(|(encoding, _)| Encoding::Known(*encoding))
+            .chain([Encoding::identity()])
+            .collect();
+        accept.negotiate(supported.iter())
+    });
+    let variant = variants
+        .into_iter()
+        .find(|(encoding, _)| accepted == Some(Encoding::Known(*encoding)));
+
+    let mut res = match variant {
+        Some((encoding, variant)) => {
+            // The variant is described like the file it stands for
+            let original = NamedFile::open_async(&file).await?;
+            let named_file = NamedFile::open_async(&variant)
+                .await?
+                .set_content_type(original.content_type().clone())
+                .set_content_disposition(original.content_disposition().clone())
+                .set_content_encoding(encoding);
+            let (req, _) = req.into_parts();
+            let res = named_file.into_response(&req);
+            req.extensions_mut().insert(content_type::FileBody);
+            ServiceResponse::new(req, res).map_into_right_body()
+        }
+        None => next.call(req).await?.map_into_left_body(),
+    };
+    res.headers_mut()
+        .append(header::VARY, HeaderValue::from_static("Accept-Encoding"));
+    Ok(res)
+}
@@ -0,0 +1,116 @@
+//! Dropping root privileges once everything needing them is done
+use std::ffi::CString;
+use std::io;
+
+use anyhow::{Result, anyhow};
+
+/// User and group to switch to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Credentials {
+    uid: Option<libc::uid_t>,
+    gid: libc::gid_t,
+}
+
+impl Credentials {
+    /// Looks up users and groups given by name or ID, the group defaulting to the primary one of
+    /// the user
+    pub fn resolve(user: Option<&str>, group: Option<&str>) -> Result<Option<Self>> {
+        let user = user.map(lookup_user).transpose()?;
+        let gid = match (group, user) {
+            (Some(group), _) => lookup_group(group)?,
+            (None, Some((_, Some(gid)))) => gid,
+            (None, Some((uid, None))) => {
+                return Err(anyhow!(
+                    "User {uid} doesn't exist, so --group must be given"
+                ));
+            }
+            (None, None) => return Ok(None),
+        };
+        Ok(Some(Self {
+            uid: user.map(|(uid, _)| uid),
+            gid,
+        }))
+    }
+
+    /// Switches the process to these credentials for good
+    ///
+    /// Supplementary groups are dropped as well. Groups are changed first, as that isn't allowed
+    /// anymore once the user is changed.
+    pub fn apply(&self) -> io::Result<()> {
+        // SAFETY: these calls are only given plain integers and a pointer to one of them
+        unsafe {
+            check(libc::setgroups(1, &self.gid))?;
+            check(libc::setgid(self.gid))?;
+            if let Some(uid) = self.uid {
+                check(libc::setuid(uid))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Finds the ID and primary group of a user, which may also be given by an ID no name is known for
+fn lookup_user(user: &str) -> Result<(libc::uid_t, Option<libc::gid_t>)> {
+    let name = CString::new(user)?;
+    // SAFETY: the returned entry is read right away, while nothing else looks up users at startup
+    let entry = unsafe {
+        match user.parse() {
+            Ok(uid) => libc::getpwuid(uid),
+            Err(_) => libc::getpwnam(name.as_ptr()),
+        }
+        .as_ref()
+    };
+    match entry {
+        Some(entry) => Ok((entry.pw_uid, Some(entry.pw_gid))),
+        None => user
+            .parse()
+            .map(|uid| (uid, None))
+            .map_err(|_| anyhow!("Unknown user {user:?}")),
+    }
+}
+
+/// Finds the ID of a group, which may also be given by an ID directly
+fn lookup_group(group: &str) -> Result<libc::gid_t> {
+    if let Ok(gid) = group.parse() {
+        return Ok(gid);
+    }
+    let name = CString::new(group)?;
+    // SAFETY: the returned entry is read right away, while nothing else looks up groups at startup
+    unsafe { libc::getgrnam(name.as_ptr()).as_ref() }
+        .map(|entry| entry.gr_gid)
+        .ok_or_else(|| anyhow!("Unknown group {group:?}"))
+}
+
+fn check(result: libc::c_int) -> io::Result<()> {
+    if result == -1 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolve_credentials() {
+        assert_eq!(Credentials::resolve(None, None).unwrap(), None);
+        assert_eq!(
+            Credentials::resolve(Some("root"), None).unwrap(),
+            Some(Credentials {
+                uid: Some(0),
+                gid: 0
+            })
+        );
+        assert_eq!(
+            Credentials::resolve(None, Some("1234")).unwrap(),
+            Some(Credentials {
+                uid: None,
+                gid: 1234
+            })
+        );
+        assert!(Credentials::resolve(Some("no-such-user-here"), None).is_err());
+        assert!(Credentials::resolve(Some("54321"), None).is_err());
+        assert!(Credentials::resolve(Some("54321"), Some("54321")).is_ok());
+    }
+}
@@ -0,0 +1,124 @@
+//! Receiving a single uploaded file with --receive
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use actix_web::{HttpRequest, HttpResponse, web};
+use futures::TryStreamExt;
+use tokio::sync::Notify;
+
+use crate::{
+    config::MiniserveConfig, errors::RuntimeError, file_op::save_file, file_utils::sanitize_path,
+    i18n, renderer,
+};
+
+/// Whether the file has been received yet
+#[derive(Default)]
+pub struct Receiver {
+    /// Set while a file is being uploaded, and for good once one has been
+    taken: AtomicBool,
+    received: Notify,
+}
+
+impl Receiver {
+    /// Waits until the file has been received
+    pub async fn received(&self) {
+        self.received.notified().await
+    }
+}
+
+/// Serves the page asking for the file
+pub async fn receive_page(req: HttpRequest) -> HttpResponse {
+    let conf = req.app_data::<web::Data<MiniserveConfig>>().unwrap();
+    let receiver = req.app_data::<web::Data<Receiver>>().unwrap();
+
+    let upload_action = format!("{}/", conf.route_prefix);
+    let taken = receiver.taken.load(Ordering::SeqCst);
+    HttpResponse::Ok().content_type(mime::TEXT_HTML_UTF_8).body(
+        renderer::receive(
+            conf,
+            (!taken).then_some(upload_action.as_str()),
+            i18n::texts(&req),
+        )
+        .into_string(),
+    )
+}
+
+/// Writes the first file of the multipart form to the destination of --receive
+///
+/// Other uploads are refused once it has been received, or while it is being uploaded.
+pub async fn receive_file(
+    req: HttpRequest,
+    payload: web::Payload,
+) -> Result<HttpResponse, RuntimeError> {
+    let conf = req.app_data::<web::Data<MiniserveConfig>>().unwrap();
+    let receiver = req.app_data::<web::Data<Receiver>>().unwrap();
+    let dest = conf.receive.as_deref().expect("--receive is set");
+
+    if receiver.taken.swap(true, Ordering::SeqCst) {
+        return Err(RuntimeError::AlreadyReceivedError);
+    }
+    match save_first_file(&req, payload, conf, dest).await {
+        Ok(path) => {
+            println!("Received {}", path.display());
+            receiver.received.notify_one();
+            Ok(HttpResponse::Ok()
+                .content_type(mime::TEXT_HTML_UTF_8)
+                .body(renderer::receive(conf, None, i18n::texts(&req)).into_string()))
+        }
+        Err(e) => {
+            // Let the sender try again
+            receiver.taken.store(false, Ordering::SeqCst);
+            Err(e)
+        }
+    }
+}
+
+/// Saves the first file of the form, returning where it was written
+async fn save_first_file(
+    req: &HttpRequest,
+    payload: web::Payload,
+    conf: &MiniserveConfig,
+    dest: &Path,
+) -> Result<PathBuf, RuntimeError> {
+    let mut multipart = actix_multipart::Multipart::new(req.headers(), payload);
+    while let Some(mut field) = multipart
+        .try_next()
+        .await
+        .map_err(|e| RuntimeError::MultipartError(e.to_string()))?
+    {
+        let Some(filename) = field
+            .content_disposition()
+            .and_then(|cd| cd.get_filename())
+            .map(str::to_owned)
+        else {
+            continue;
+        };
+
+        let path = if dest.is_dir() {
+            // Only the name is kept, as the directories it was in are unknown here
+            let filename = Path::new(&filename)
+                .file_name()
+                .and_then(|name| sanitize_path(Path::new(name), conf.show_hidden))
+                .ok_or_else(|| {
+                    RuntimeError::InvalidPathError("Invalid file name to upload".to_string())
+                })?;
+            dest.join(filename)
+        } else {
+            dest.to_path_buf()
+        };
+        save_file(
+            &mut field,
+            path.clone(),
+            conf.overwrite_files,
+            None,
+            conf.temp_upload_directory.as_ref(),
+        )
+        .await?;
+        return Ok(path);
+    }
+
+    Err(RuntimeError::ParseError(
+        "HTTP header".to_string(),
+        "Failed to retrieve the name of the file to upload".to_string(),
+    ))
+}
@@ -1,8 +1,8 @@
-use std::time::SystemTime;
+use std::path::Path;
 
 use actix_web::http::{StatusCode, Uri};
-use chrono::{DateTime, Local};
-use chrono_humanize::Humanize;
+use bytesize::ByteSize;
+use chrono::{DateTime, Utc};
 use clap::{ValueEnum, crate_name, crate_version};
 use fast_qr::{
     QRBuilder,
@@ -10,17 +10,29 @@ use fast_qr::{
     qr::QRCodeError,
 };
 use maud::{DOCTYPE, Markup, PreEscaped, html};
+use percent_encoding::{percent_decode_str, utf8_percent_encode};
 use strum::{Display, IntoEnumIterator};
 
+use crate::admin;
 use crate::auth::CurrentUser;
 use crate::consts;
-use crate::listing::{Breadcrumb, Entry, ListingQueryParameters, SortingMethod, SortingOrder};
+use crate::exif;
+use crate::highlight;
+use crate::i18n::Texts;
+use crate::listing::{
+    Breadcrumb, DateFormat, Entry, ListingQueryParameters, ListingView, SortingMethod,
+    SortingOrder, TimeFormat, Totals, percent_encode_sets::COMPONENT,
+};
+use crate::pdf;
+use crate::player;
+use crate::thumbnail;
 use crate::{MiniserveConfig, archive::ArchiveMethod};
 
 #[allow(clippy::too_many_arguments)]
 /// Renders the file listing
 pub fn page(
     entries: Vec<Entry>,
+    totals: Totals,
     readme: Option<(String, String)>,
     abs_uri: &Uri,
     is_root: bool,
@@ -29,19 +41,76 @@ pub fn page(
     encoded_dir: &str,
     conf: &MiniserveConfig,
     current_user: Option<&CurrentUser>,
+    simple_ui: bool,
+    theme: Option<ThemeSlug>,
+    flat: Option<bool>,
+    texts: &Texts,
 ) -> Markup {
     // If query_params.raw is true, we want render a minimal directory listing
     if query_params.raw.is_some() && query_params.raw.unwrap() {
-        return raw(entries, is_root, conf);
+        return raw(entries, is_root, conf, None, None, texts);
+    }
+
+    if conf.minimal_ui {
+        return autoindex(&entries, is_root, encoded_dir);
     }
 
     let upload_route = format!("{}/upload", &conf.route_prefix);
     let (sort_method, sort_order) = (query_params.sort, query_params.order);
+    let show_all = query_params.show_all.unwrap_or(false);
+    let dirs_first = query_params.dirs_first.unwrap_or(conf.dirs_first);
+    let show_hidden = query_params.hidden.unwrap_or(true);
+    let time_format = query_params.time.unwrap_or(conf.time_format);
+    // Parameters which the links sorting the listing keep
+    let mut kept_params = String::new();
+    if show_all {
+        kept_params.push_str("&show_all=true");
+    }
+    if let Some(dirs_first) = query_params.dirs_first {
+        kept_params.push_str(&format!("&dirs_first={dirs_first}"));
+    }
+    if let Some(hidden) = query_params.hidden {
+        kept_params.push_str(&format!("&hidden={hidden}"));
+    }
+    if let Some(time) = query_params.time {
+        kept_params.push_str(&format!("&time={time}"));
+    }
+    if flat == Some(true) {
+        kept_params.push_str("&flat=true");
+    }
+    let filter = query_params.filter.as_deref().filter(|f| !f.is_empty());
+    if let Some(filter) = filter {
+        kept_params.push_str(&format!(
+            "&filter={}",
+            utf8_percent_encode(filter, COMPONENT)
+        ));
+    }
 
     let upload_action = build_upload_action(&upload_route, encoded_dir, sort_method, sort_order);
     let mkdir_action = build_mkdir_action(&upload_route, encoded_dir);
 
     let title_path = breadcrumbs_to_path_string(breadcrumbs);
+    // Previews and thumbnails are of the files served here, not of those at --file-external-url
+    let preview = conf.preview && conf.file_external_url.is_none();
+    let thumbnails = conf.thumbnails && conf.file_external_url.is_none();
+    let media_player = conf.media_player && conf.file_external_url.is_none();
+    let pdf_viewer = conf.pdf_viewer && conf.file_external_url.is_none();
+    let exif_details = conf.exif_details && conf.file_external_url.is_none();
+    // Torrents are of the files of local directories, whose URLs are their web seeds
+    let torrent = conf.torrent && conf.file_external_url.is_none() && flat.is_some();
+    let view = query_params.view.unwrap_or_default();
+    let gallery = view == ListingView::Gallery;
+    // Entries of archives, repositories and buckets have no permissions
+    let show_permissions = entries.iter().any(|entry| entry.permissions.is_some());
+    let columns = if show_permissions { 6 } else { 3 };
+    // Playlists are made by the listings of local directories only, as players are
+    let playlist = conf.media_player
+        && entries
+            .iter()
+            .any(|entry| entry.is_file() && player::is_playable(&entry.name));
+    let has_media = entries.iter().any(|entry| {
+        entry.is_file() && (thumbnail::is_image(&entry.name) || thumbnail::is_video(&entry.name))
+    });
 
     let upload_allowed = conf.allowed_upload_dir.is_empty()
         || conf
@@ -49,200 +118,887 @@ pub fn page(
             .iter()
             .any(|x| encoded_dir.starts_with(&format!("/{x}")));
 
+    let notice = (entries.len() < totals.entries).then(|| {
+        truncation_notice(
+            entries.len(),
+            totals.entries,
+            sort_method,
+            sort_order,
+            &kept_params,
+            filter,
+            texts,
+        )
+    });
+
+    // Browsers that can't handle the full UI get the minimal listing instead, with a plain
+    // multipart form in place of the upload widget
+    if simple_ui {
+        let upload_action =
+            (conf.uploads_enabled() && upload_allowed).then_some(upload_action.as_str());
+        return raw(entries, is_root, conf, upload_action, notice, texts);
+    }
+
+    let head = page_header(
+        &title_path,
+        conf.uploads_enabled(),
+        conf.web_upload_concurrency,
+        &conf.api_route,
+        &conf.favicon_route,
+        &conf.css_route,
+        texts,
+    );
+    let drop_forms = html! {
+        div.toolbar_box_group {
+            @if conf.uploads_enabled() {
+                div.drag-form {
+                    div.form_title {
+                        h1 { (texts.drop_to_upload) }
+                    }
+                }
+            }
+
+            @if conf.mkdir_enabled {
+                div.form {
+                    div.form_title {
+                        h1 { (texts.create_new_directory) }
+                    }
+                }
+            }
+        }
+    };
+    let navigation = html! {
+        a.skip-link href="#listing" { (texts.skip_to_listing) }
+        nav {
+            (qr_spoiler(conf.show_qrcode, abs_uri, texts))
+            (color_scheme_selector(conf.hide_theme_selector, texts))
+        }
+    };
+    let header = custom_text(conf.header_text.as_deref(), conf.html_text, "header-text");
+    let title = html! {
+        span #top { }
+        h1.title dir="ltr" {
+            @for el in breadcrumbs {
+                @if el.link == "." {
+                    // wrapped in span so the text doesn't shift slightly when it turns into a link
+                    span { bdi { (el.name) } }
+                } @else {
+                    a href=(parametrized_link(&el.link, sort_method, sort_order, false)) {
+                        bdi { (el.name) }
+                    }
+                }
+                "/"
+            }
+        }
+    };
+    let toolbar = html! {
+        div.toolbar {
+            @if conf.tar_enabled || conf.tar_gz_enabled || conf.zip_enabled {
+                div.download {
+                    @for archive_method in ArchiveMethod::iter() {
+                        @if archive_method.is_enabled(conf.tar_enabled, conf.tar_gz_enabled, conf.zip_enabled) {
+                            (archive_button(archive_method, sort_method, sort_order, texts))
+                        }
+                    }
+                }
+            }
+            div.download {
+                (view_toggle(view, has_media, texts))
+            }
+            div.download {
+                (dirs_first_toggle(dirs_first, sort_method, sort_order, texts))
+            }
+            @if conf.show_hidden {
+                div.download {
+                    (hidden_toggle(show_hidden, sort_method, sort_order, texts))
+                }
+            }
+            @if let Some(flat) = flat {
+                div.download {
+                    (flat_toggle(flat, sort_method, sort_order, texts))
+                }
+            }
+            // Like flat listings, feeds are made of the files of local directories only
+            @if conf.feed && flat.is_some() {
+                div.download {
+                    a href="feed.xml" type="application/atom+xml" title=(texts.feed_help) { (texts.feed) }
+                }
+            }
+            @if conf.checksums && flat.is_some() {
+                div.download {
+                    a href="SHA256SUMS" download title=(texts.checksums_help) { (texts.checksums) }
+                }
+            }
+            div.download {
+                (time_format_toggle(time_format, sort_method, sort_order, texts))
+            }
+            @if playlist {
+                div.download {
+                    a href="?playlist=true" title=(texts.playlist_help) {
+                        (texts.playlist)
+                    }
+                }
+            }
+            div.toolbar_box_group {
+                @if conf.uploads_enabled() && upload_allowed {
+                    div.toolbar_box {
+                        form id="file_submit" action=(upload_action) method="POST" enctype="multipart/form-data" {
+                            p { label for="file-input" { (texts.select_file_to_upload) } }
+                            div {
+                                @match &conf.uploadable_media_type {
+                                    Some(accept) => {input #file-input accept=(accept) type="file" name="file_to_upload" required="" multiple {}},
+                                    None => {input #file-input type="file" name="file_to_upload" required="" multiple {}}
+                                }
+                                button type="submit" { (texts.upload_file) }
+                            }
+                        }
+                    }
+                }
+                @if conf.mkdir_enabled && upload_allowed {
+                    div.toolbar_box {
+                        form id="mkdir" action=(mkdir_action) method="POST" enctype="multipart/form-data" {
+                            p { label for="mkdir-name" { (texts.directory_name_prompt) } }
+                            div.toolbar_box {
+                                input #mkdir-name type="text" name="mkdir" required="" placeholder=(texts.directory_name) {}
+                                button type="submit" { (texts.create_directory) }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    };
+    let listing = html! {
+        @if let Some(notice) = notice {
+            (notice)
+        }
+        @if gallery {
+            (gallery_grid(entries, is_root, thumbnails, texts))
+            (lightbox(texts))
+        } @else {
+            (listing_filter(texts))
+            table #listing {
+                thead {
+                    tr {
+                        th.name scope="col" aria-sort=[aria_sort("name", sort_method, sort_order)] {
+                            (build_link("name", texts.name, sort_method, sort_order, &kept_params, texts))
+                        }
+                        th.size scope="col" aria-sort=[aria_sort("size", sort_method, sort_order)] {
+                            (build_link("size", texts.size, sort_method, sort_order, &kept_params, texts))
+                        }
+                        th.date scope="col" aria-sort=[aria_sort("date", sort_method, sort_order)] {
+                            (build_link("date", texts.last_modification, sort_method, sort_order, &kept_params, texts))
+                        }
+                        @if show_permissions {
+                            (permission_headers(texts))
+                        }
+                    }
+                }
+                tbody {
+                    @if !is_root {
+                        tr {
+                            td colspan=(columns) {
+                                p {
+                                    span.root-chevron { (chevron_left()) }
+                                    a.root href=(parametrized_link("../", sort_method, sort_order, false)) {
+                                        (texts.parent_directory)
+                                    }
+                                }
+                            }
+                        }
+                    }
+                    @for entry in entries {
+                        (entry_row(entry, sort_method, sort_order, &kept_params, false, conf.show_exact_bytes, time_format, &conf.date_format, preview, thumbnails, media_player, pdf_viewer, exif_details, torrent, texts))
+                    }
+                }
+            }
+            @if view == ListingView::Tree {
+                (tree_script(texts))
+            }
+        }
+    };
+    let totals = totals_summary(&totals, conf.show_exact_bytes, texts);
+    let readme = html! {
+        @if let Some(readme) = readme {
+            div id="readme" {
+                h3 id="readme-filename" { (readme.0) }
+                div id="readme-contents" {
+                    (PreEscaped (readme.1))
+                };
+            }
+        }
+    };
+    let footer = html! {
+        a.back href="#top" title=(texts.back_to_top) aria-label=(texts.back_to_top) {
+            (arrow_up())
+        }
+        div.footer {
+            (custom_text(conf.footer_text.as_deref(), conf.html_text, "footer-text"))
+            @if conf.show_wget_footer {
+                (wget_footer(abs_uri, conf.title.as_deref(), current_user.map(|x| &*x.name),
+                    conf.file_external_url.as_deref(), texts))
+            }
+            @if !conf.hide_version_footer {
+                (version_footer())
+            }
+        }
+    };
+    let upload_widget = html! {
+        div.upload_area id="upload_area" {
+            template id="upload_file_item" {
+                li.upload_file_item {
+                    div.upload_file_container {
+                        div.upload_file_text {
+                            span.file_upload_percent { "" }
+                            {" - "}
+                            span.file_size { "" }
+                            {" - "}
+                            span.file_name { "" }
+                        }
+                        button.file_cancel_upload aria-label=(texts.cancel) { "✖" }
+                    }
+                    div.file_progress_bar {}
+                }
+            }
+            div.upload_container {
+                div.upload_header {
+                    h4 style="margin:0px" id="upload_title" {}
+                    svg id="upload-toggle" xmlns="http://www.w3.org/2000/svg" fill="none" viewBox="0 0 24 24" stroke-width="1.5" stroke="currentColor" class="size-6" {
+                      path stroke-linecap="round" stroke-linejoin="round" d="m4.5 15.75 7.5-7.5 7.5 7.5" {}
+                    }
+                }
+                div.upload_action {
+                    p id="upload_action_text" aria-live="polite" { (texts.starting_upload) }
+                    button.upload_cancel id="upload_cancel" { (texts.cancel) }
+                }
+                div.upload_files {
+                    ul.upload_file_list id="upload_file_list" {
+
+                    }
+                }
+            }
+        }
+    };
+    // The theme is also set by the script, but early enough to not flash the default one
+    let theme = theme.map(|theme| theme.to_string());
+
+    if let Some(template) = read_template(conf.template_dir.as_deref(), "listing.html") {
+        let mut body_attributes = r#"id="drop-container""#.to_string();
+        if let Some(theme) = &theme {
+            body_attributes.push_str(&format!(r#" data-theme="{theme}""#));
+        }
+        let uploads = html! { (drop_forms) (upload_widget) };
+        let filled = fill_template(&template, |name| {
+            let value = match name {
+                "lang" => PreEscaped(texts.code.to_string()),
+                "title" => html! { (title_path) },
+                "head" => head.clone(),
+                "body_attributes" => PreEscaped(body_attributes.clone()),
+                "navigation" => navigation.clone(),
+                "header" => header.clone(),
+                "breadcrumbs" => title.clone(),
+                "toolbar" => toolbar.clone(),
+                "listing" => listing.clone(),
+                "totals" => totals.clone(),
+                "readme" => readme.clone(),
+                "footer" => footer.clone(),
+                "uploads" => uploads.clone(),
+                "scripts" => html! {
+                    (live_refresh_script(conf.live_refresh))
+                    (custom_script(conf.custom_js.as_deref()))
+                },
+                _ => return None,
+            };
+            Some(value.into_string())
+        });
+        return PreEscaped(filled);
+    }
+
     html! {
         (DOCTYPE)
-        html {
-            (page_header(&title_path, conf.file_upload, conf.web_upload_concurrency, &conf.api_route, &conf.favicon_route, &conf.css_route))
+        html lang=(texts.code) {
+            (head)
 
-            body #drop-container
+            body #drop-container data-theme=[theme]
             {
-                div.toolbar_box_group {
-                    @if conf.file_upload {
-                        div.drag-form {
-                            div.form_title {
-                                h1 { "Drop your file here to upload it" }
+                (drop_forms)
+                (navigation)
+                div.container {
+                    (header)
+                    (title)
+                    (toolbar)
+                    (listing)
+                    (totals)
+                    (readme)
+                    (footer)
+                }
+                (upload_widget)
+                (live_refresh_script(conf.live_refresh))
+                (custom_script(conf.custom_js.as_deref()))
+            }
+        }
+    }
+}
+
+/// Renders the file listing
+///
+/// If `upload_action` is set, a basic upload form that works without JavaScript is included.
+pub fn raw(
+    entries: Vec<Entry>,
+    is_root: bool,
+    conf: &MiniserveConfig,
+    upload_action: Option<&str>,
+    notice: Option<Markup>,
+    texts: &Texts,
+) -> Markup {
+    let show_permissions = entries.iter().any(|entry| entry.permissions.is_some());
+    let columns = if show_permissions { 6 } else { 3 };
+    html! {
+        (DOCTYPE)
+        html lang=(texts.code) {
+            body {
+                @if let Some(notice) = notice {
+                    (notice)
+                }
+                @if let Some(upload_action) = upload_action {
+                    form action=(upload_action) method="POST" enctype="multipart/form-data" {
+                        input type="file" name="file_to_upload" required="" multiple {}
+                        button type="submit" { (texts.upload_file) }
+                    }
+                }
+                table {
+                    thead {
+                        tr {
+                            th.name scope="col" { (texts.name) }
+                            th.size scope="col" { (texts.size) }
+                            th.date scope="col" { (texts.last_modification) }
+                            @if show_permissions {
+                                (permission_headers(texts))
                             }
                         }
                     }
-
-                    @if conf.mkdir_enabled {
-                        div.form {
-                            div.form_title {
-                                h1 { "Create a new directory" }
+                    tbody {
+                        @if !is_root {
+                            tr {
+                                td colspan=(columns) {
+                                    p {
+                                        a.root href=(parametrized_link("../", None, None, true)) {
+                                            ".."
+                                        }
+                                    }
+                                }
                             }
                         }
+                        @for entry in entries {
+                            (entry_row(entry, None, None, "", true, conf.show_exact_bytes, conf.time_format, &conf.date_format, false, false, false, false, false, false, texts))
+                        }
                     }
                 }
+            }
+        }
+    }
+}
+
+/// Width of the column of names in indexes of --minimal-ui, as in those of nginx
+const AUTOINDEX_NAME_WIDTH: usize = 50;
+
+/// Renders the file listing as a bare index like those of nginx, for --minimal-ui
+///
+/// `encoded_dir` is the percent-encoded path of the directory, relative to the route prefix.
+pub fn autoindex(entries: &[Entry], is_root: bool, encoded_dir: &str) -> Markup {
+    let title = format!(
+        "Index of {}",
+        make_link_with_trailing_slash(&percent_decode_str(encoded_dir).decode_utf8_lossy())
+    );
+    html! {
+        (DOCTYPE)
+        html {
+            head {
+                title { (title) }
+            }
+            body {
+                h1 { (title) }
+                hr;
+                pre {
+                    @if !is_root {
+                        a href="../" { "../" }
+                        "\n"
+                    }
+                    @for entry in entries {
+                        (autoindex_row(entry))
+                    }
+                }
+                hr;
+            }
+        }
+    }
+}
+
+/// Line of an entry in an index of --minimal-ui: its link, then its last modification and size
+/// aligned in columns
+fn autoindex_row(entry: &Entry) -> Markup {
+    let (name, link) = if entry.is_dir() {
+        (
+            format!("{}/", entry.name),
+            make_link_with_trailing_slash(&entry.link),
+        )
+    } else {
+        (entry.name.clone(), entry.link.clone())
+    };
+    // Names too long for their column are cut, with a mark telling so
+    let shown_name = if name.chars().count() > AUTOINDEX_NAME_WIDTH {
+        let cut: String = name.chars().take(AUTOINDEX_NAME_WIDTH - 3).collect();
+        format!("{cut}..>")
+    } else {
+        name.clone()
+    };
+    let padding = " ".repeat(AUTOINDEX_NAME_WIDTH + 1 - shown_name.chars().count());
+    let modified = entry
+        .last_modification_date
+        .map(|date| {
+            DateTime::<Utc>::from(date)
+                .format("%d-%b-%Y %H:%M")
+                .to_string()
+        })
+        .unwrap_or_else(|| "-".to_owned());
+    let size = match entry.size {
+        Some(size) if !entry.is_dir() => size.as_u64().to_string(),
+        _ => "-".to_owned(),
+    };
+    html! {
+        a href=(link) { (shown_name) }
+        (padding) (format!("{modified:<17} {size:>19}")) "\n"
+    }
+}
+
+/// Renders a Markdown file already converted to `html`, with a link to its raw content
+pub fn markdown(file_name: &str, html: &str, conf: &MiniserveConfig, texts: &Texts) -> Markup {
+    html! {
+        (DOCTYPE)
+        html lang=(texts.code) {
+            (page_header(file_name, false, conf.web_upload_concurrency, &conf.api_route, &conf.favicon_route, &conf.css_route, texts))
+
+            body {
                 nav {
-                    (qr_spoiler(conf.show_qrcode, abs_uri))
-                    (color_scheme_selector(conf.hide_theme_selector))
+                    (color_scheme_selector(conf.hide_theme_selector, texts))
                 }
                 div.container {
-                    span #top { }
-                    h1.title dir="ltr" {
-                        @for el in breadcrumbs {
-                            @if el.link == "." {
-                                // wrapped in span so the text doesn't shift slightly when it turns into a link
-                                span { bdi { (el.name) } }
-                            } @else {
-                                a href=(parametrized_link(&el.link, sort_method, sort_order, false)) {
-                                    bdi { (el.name) }
-                                }
-                            }
-                            "/"
+                    h1.title dir="ltr" { bdi { (file_name) } }
+                    div.toolbar {
+                        div.download {
+                            a href="?raw=true" { (texts.raw) }
+                        }
+                    }
+                    div #readme {
+                        div #readme-contents {
+                            (PreEscaped(html))
                         }
                     }
+                    @if !conf.hide_version_footer {
+                        (version_footer())
+                    }
+                }
+                (custom_script(conf.custom_js.as_deref()))
+            }
+        }
+    }
+}
+
+/// Renders the preview of a text file, given the highlighted HTML of its lines
+pub fn preview(file_name: &str, lines: &[String], conf: &MiniserveConfig, texts: &Texts) -> Markup {
+    html! {
+        (DOCTYPE)
+        html lang=(texts.code) {
+            (page_header(file_name, false, conf.web_upload_concurrency, &conf.api_route, &conf.favicon_route, &conf.css_route, texts))
+
+            body {
+                nav {
+                    (color_scheme_selector(conf.hide_theme_selector, texts))
+                }
+                div.container {
+                    h1.title dir="ltr" { bdi { (file_name) } }
                     div.toolbar {
-                        @if conf.tar_enabled || conf.tar_gz_enabled || conf.zip_enabled {
-                            div.download {
-                                @for archive_method in ArchiveMethod::iter() {
-                                    @if archive_method.is_enabled(conf.tar_enabled, conf.tar_gz_enabled, conf.zip_enabled) {
-                                        (archive_button(archive_method, sort_method, sort_order))
-                                    }
-                                }
+                        div.download {
+                            a href="?raw=true" { (texts.raw) }
+                        }
+                    }
+                    pre.preview {
+                        code {
+                            @for (i, line) in lines.iter().enumerate() {
+                                @let number = i + 1;
+                                a.line-number id=(format!("L{number}")) href=(format!("#L{number}")) { (number) }
+                                (PreEscaped(line)) "\n"
                             }
                         }
-                        div.toolbar_box_group {
-                            @if conf.file_upload && upload_allowed {
-                                div.toolbar_box {
-                                    form id="file_submit" action=(upload_action) method="POST" enctype="multipart/form-data" {
-                                        p { "Select a file to upload or drag it anywhere into the window" }
-                                        div {
-                                            @match &conf.uploadable_media_type {
-                                                Some(accept) => {input #file-input accept=(accept) type="file" name="file_to_upload" required="" multiple {}},
-                                                None => {input #file-input type="file" name="file_to_upload" required="" multiple {}}
-                                            }
-                                            button type="submit" { "Upload file" }
-                                        }
-                                    }
-                                }
+                    }
+                    @if !conf.hide_version_footer {
+                        (version_footer())
+                    }
+                }
+                (custom_script(conf.custom_js.as_deref()))
+            }
+        }
+    }
+}
+
+/// Renders the page playing an audio or video file
+///
+/// `previous` and `next` link to the pages of the media files around it in its directory, and the
+/// next one starts once the file has played to its end.
+pub fn player(
+    file_name: &str,
+    link: &str,
+    video: bool,
+    previous: Option<&str>,
+    next: Option<&str>,
+    conf: &MiniserveConfig,
+    texts: &Texts,
+) -> Markup {
+    html! {
+        (DOCTYPE)
+        html lang=(texts.code) {
+            (page_header(file_name, false, conf.web_upload_concurrency, &conf.api_route, &conf.favicon_route, &conf.css_route, texts))
+
+            body {
+                nav {
+                    (color_scheme_selector(conf.hide_theme_selector, texts))
+                }
+                div.container {
+                    h1.title dir="ltr" { bdi { (file_name) } }
+                    div.toolbar {
+                        div.download {
+                            a href="./" { (texts.directory) }
+                            @if let Some(previous) = previous {
+                                a.player-previous href=(previous) { (texts.previous) }
                             }
-                            @if conf.mkdir_enabled && upload_allowed {
-                                div.toolbar_box {
-                                    form id="mkdir" action=(mkdir_action) method="POST" enctype="multipart/form-data" {
-                                        p { "Specify a directory name to create" }
-                                        div.toolbar_box {
-                                            input type="text" name="mkdir" required="" placeholder="Directory name" {}
-                                            button type="submit" { "Create directory" }
-                                        }
-                                    }
-                                }
+                            @if let Some(next) = next {
+                                a.player-next href=(next) { (texts.next) }
                             }
+                            a href=(link) download=(file_name) { (texts.download) }
+                        }
+                    }
+                    @if video {
+                        video.player #player src=(link) data-next=[next] controls autoplay {}
+                    } @else {
+                        audio.player #player src=(link) data-next=[next] controls autoplay {}
+                    }
+                    script {
+                        (PreEscaped(r#"
+                            const player = document.getElementById('player');
+                            player.addEventListener('ended', () => {
+                                if (player.dataset.next) {
+                                    window.location = player.dataset.next;
+                                }
+                            });
+                        "#))
+                    }
+                    @if !conf.hide_version_footer {
+                        (version_footer())
+                    }
+                }
+                (custom_script(conf.custom_js.as_deref()))
+            }
+        }
+    }
+}
+
+/// Renders the page showing a PDF file with the viewer of the browser
+pub fn pdf(file_name: &str, link: &str, conf: &MiniserveConfig, texts: &Texts) -> Markup {
+    html! {
+        (DOCTYPE)
+        html lang=(texts.code) {
+            (page_header(file_name, false, conf.web_upload_concurrency, &conf.api_route, &conf.favicon_route, &conf.css_route, texts))
+
+            body {
+                nav {
+                    (color_scheme_selector(conf.hide_theme_selector, texts))
+                }
+                div.container {
+                    h1.title dir="ltr" { bdi { (file_name) } }
+                    div.toolbar {
+                        div.download {
+                            a href="./" { (texts.directory) }
+                            a href=(link) { (texts.open) }
+                            a href=(link) download=(file_name) { (texts.download) }
                         }
                     }
-                    table {
-                        thead {
-                            th.name { (build_link("name", "Name", sort_method, sort_order)) }
-                            th.size { (build_link("size", "Size", sort_method, sort_order)) }
-                            th.date { (build_link("date", "Last modification", sort_method, sort_order)) }
+                    iframe.pdf src=(link) title=(file_name) {
+                        // Browsers without a viewer get the link instead
+                        a href=(link) download=(file_name) { ((texts.download_file)(file_name)) }
+                    }
+                    @if !conf.hide_version_footer {
+                        (version_footer())
+                    }
+                }
+                (custom_script(conf.custom_js.as_deref()))
+            }
+        }
+    }
+}
+
+/// Renders the preview of a CSV or TSV file as a table, whose columns sort when clicked
+///
+/// The first row is taken as the header.
+pub fn table(
+    file_name: &str,
+    rows: &[Vec<String>],
+    conf: &MiniserveConfig,
+    texts: &Texts,
+) -> Markup {
+    let (header, body) = rows
+        .split_first()
+        .map_or((&[][..], &[][..]), |(header, body)| {
+            (header.as_slice(), body)
+        });
+    html! {
+        (DOCTYPE)
+        html lang=(texts.code) {
+            (page_header(file_name, false, conf.web_upload_concurrency, &conf.api_route, &conf.favicon_route, &conf.css_route, texts))
+
+            body {
+                nav {
+                    (color_scheme_selector(conf.hide_theme_selector, texts))
+                }
+                div.container {
+                    h1.title dir="ltr" { bdi { (file_name) } }
+                    div.toolbar {
+                        div.download {
+                            a href="?raw=true" { (texts.raw) }
                         }
-                        tbody {
-                            @if !is_root {
+                    }
+                    div.data-table {
+                        table #data {
+                            thead {
                                 tr {
-                                    td colspan="3" {
-                                        p {
-                                            span.root-chevron { (chevron_left()) }
-                                            a.root href=(parametrized_link("../", sort_method, sort_order, false)) {
-                                                "Parent directory"
-                                            }
-                                        }
+                                    @for cell in header {
+                                        th title=(texts.sort) { (cell) }
                                     }
                                 }
                             }
-                            @for entry in entries {
-                                (entry_row(entry, sort_method, sort_order, false, conf.show_exact_bytes))
+                            tbody {
+                                @for row in body {
+                                    tr {
+                                        @for cell in row {
+                                            td { (cell) }
+                                        }
+                                    }
+                                }
                             }
                         }
                     }
-                    @if let Some(readme) = readme {
-                        div id="readme" {
-                            h3 id="readme-filename" { (readme.0) }
-                            div id="readme-contents" {
-                                (PreEscaped (readme.1))
-                            };
-                        }
+                    script {
+                        (PreEscaped(r#"
+                            const table = document.getElementById('data');
+                            table.querySelectorAll('th').forEach((th, column) => th.addEventListener('click', () => {
+                                const ascending = th.dataset.order !== 'asc';
+                                table.querySelectorAll('th').forEach(other => delete other.dataset.order);
+                                th.dataset.order = ascending ? 'asc' : 'desc';
+                                const cell = row => row.cells[column] ? row.cells[column].textContent : '';
+                                const compare = (a, b) => {
+                                    const [x, y] = [cell(a), cell(b)];
+                                    const [m, n] = [Number(x), Number(y)];
+                                    if (x !== '' && y !== '' && !isNaN(m) && !isNaN(n)) {
+                                        return m - n;
+                                    }
+                                    return x.localeCompare(y, undefined, { numeric: true });
+                                };
+                                const body = table.tBodies[0];
+                                Array.from(body.rows)
+                                    .sort((a, b) => ascending ? compare(a, b) : compare(b, a))
+                                    .forEach(row => body.appendChild(row));
+                            }));
+                        "#))
                     }
-                    a.back href="#top" {
-                        (arrow_up())
+                    @if !conf.hide_version_footer {
+                        (version_footer())
                     }
-                    div.footer {
-                        @if conf.show_wget_footer {
-                            (wget_footer(abs_uri, conf.title.as_deref(), current_user.map(|x| &*x.name),
-                                conf.file_external_url.as_deref()))
-                        }
-                        @if !conf.hide_version_footer {
-                            (version_footer())
+                }
+                (custom_script(conf.custom_js.as_deref()))
+            }
+        }
+    }
+}
+
+/// Renders the details page of a photo, showing its EXIF data
+pub fn exif(
+    file_name: &str,
+    link: &str,
+    fields: &[(&str, String)],
+    conf: &MiniserveConfig,
+    texts: &Texts,
+) -> Markup {
+    html! {
+        (DOCTYPE)
+        html lang=(texts.code) {
+            (page_header(file_name, false, conf.web_upload_concurrency, &conf.api_route, &conf.favicon_route, &conf.css_route, texts))
+
+            body {
+                nav {
+                    (color_scheme_selector(conf.hide_theme_selector, texts))
+                }
+                div.container {
+                    h1.title dir="ltr" { bdi { (file_name) } }
+                    div.toolbar {
+                        div.download {
+                            a href="./" { (texts.directory) }
+                            a href=(link) { (texts.open) }
                         }
                     }
-                }
-                div.upload_area id="upload_area" {
-                    template id="upload_file_item" {
-                        li.upload_file_item {
-                            div.upload_file_container {
-                                div.upload_file_text {
-                                    span.file_upload_percent { "" }
-                                    {" - "}
-                                    span.file_size { "" }
-                                    {" - "}
-                                    span.file_name { "" }
+                    div.exif {
+                        a href=(link) {
+                            img.exif-image src=(link) alt=(file_name);
+                        }
+                        @if fields.is_empty() {
+                            p { (texts.no_exif_data) }
+                        } @else {
+                            table {
+                                tbody {
+                                    @for (name, value) in fields {
+                                        tr {
+                                            th { (name) }
+                                            td { (value) }
+                                        }
+                                    }
                                 }
-                                button.file_cancel_upload { "✖" }
                             }
-                            div.file_progress_bar {}
                         }
                     }
-                    div.upload_container {
-                        div.upload_header {
-                            h4 style="margin:0px" id="upload_title" {}
-                            svg id="upload-toggle" xmlns="http://www.w3.org/2000/svg" fill="none" viewBox="0 0 24 24" stroke-width="1.5" stroke="currentColor" class="size-6" {
-                              path stroke-linecap="round" stroke-linejoin="round" d="m4.5 15.75 7.5-7.5 7.5 7.5" {}
-                            }
-                        }
-                        div.upload_action {
-                            p id="upload_action_text" { "Starting upload..." }
-                            button.upload_cancel id="upload_cancel" { "CANCEL" }
-                        }
-                        div.upload_files {
-                            ul.upload_file_list id="upload_file_list" {
+                    @if !conf.hide_version_footer {
+                        (version_footer())
+                    }
+                }
+                (custom_script(conf.custom_js.as_deref()))
+            }
+        }
+    }
+}
 
+/// Renders the page of --receive
+///
+/// If `upload_action` is set, it asks for the file, otherwise it tells that it has been received.
+pub fn receive(conf: &MiniserveConfig, upload_action: Option<&str>, texts: &Texts) -> Markup {
+    let title = conf.title.as_deref().unwrap_or(texts.send_a_file);
+    html! {
+        (DOCTYPE)
+        html lang=(texts.code) {
+            (page_header(title, false, conf.web_upload_concurrency, &conf.api_route, &conf.favicon_route, &conf.css_route, texts))
+
+            body {
+                div.container {
+                    h1.title { (title) }
+                    @if let Some(upload_action) = upload_action {
+                        div.toolbar_box {
+                            form action=(upload_action) method="POST" enctype="multipart/form-data" {
+                                input type="file" name="file_to_upload" required="" {}
+                                button type="submit" { (texts.send_file) }
                             }
                         }
+                    } @else {
+                        p { (texts.file_received) }
+                    }
+                    @if !conf.hide_version_footer {
+                        (version_footer())
                     }
                 }
+                (custom_script(conf.custom_js.as_deref()))
             }
         }
     }
 }
 
-/// Renders the file listing
-pub fn raw(entries: Vec<Entry>, is_root: bool, conf: &MiniserveConfig) -> Markup {
+/// Renders the admin page, with the transfers in progress and recent events
+pub fn admin(
+    conf: &MiniserveConfig,
+    transfers: &[admin::Transfer],
+    events: &[admin::Event],
+    texts: &Texts,
+) -> Markup {
     html! {
         (DOCTYPE)
-        html {
+        html lang=(texts.code) {
+            (page_header(texts.administration, false, conf.web_upload_concurrency, &conf.api_route, &conf.favicon_route, &conf.css_route, texts))
+
             body {
-                table {
-                    thead {
-                        th.name { "Name" }
-                        th.size { "Size" }
-                        th.date { "Last modification" }
+                div.container {
+                    h1.title { (texts.administration) }
+                    div.toolbar_box_group {
+                        @if conf.file_upload {
+                            div.toolbar_box {
+                                form method="POST" {
+                                    @if conf.uploads_enabled() {
+                                        p { (texts.uploads_enabled) }
+                                        button type="submit" name="action" value="pause_uploads" { (texts.pause_uploads) }
+                                    } @else {
+                                        p { (texts.uploads_paused) }
+                                        button type="submit" name="action" value="resume_uploads" { (texts.resume_uploads) }
+                                    }
+                                }
+                            }
+                        }
+                        div.toolbar_box {
+                            form method="POST" {
+                                button type="submit" name="action" value="clear_caches" { (texts.clear_caches) }
+                            }
+                        }
                     }
-                    tbody {
-                        @if !is_root {
-                            tr {
-                                td colspan="3" {
-                                    p {
-                                        a.root href=(parametrized_link("../", None, None, true)) {
-                                            ".."
+                    h2 { (texts.transfers_in_progress) }
+                    @if transfers.is_empty() {
+                        p { (texts.no_transfers) }
+                    } @else {
+                        table {
+                            thead {
+                                tr {
+                                    th { (texts.path) }
+                                    th { (texts.user) }
+                                    th { (texts.client) }
+                                    th { (texts.progress) }
+                                }
+                            }
+                            tbody {
+                                @for transfer in transfers {
+                                    tr {
+                                        td { @if transfer.upload { "↑ " } @else { "↓ " } (transfer.path) }
+                                        td { (transfer.user.as_deref().unwrap_or_default()) }
+                                        td { (transfer.client) }
+                                        td {
+                                            (ByteSize::b(transfer.transferred))
+                                            @if let Some(length) = transfer.length {
+                                                " / " (ByteSize::b(length))
+                                            }
+                                            " (" (transfer.elapsed.as_secs()) " s)"
                                         }
                                     }
                                 }
                             }
                         }
-                        @for entry in entries {
-                            (entry_row(entry, None, None, true, conf.show_exact_bytes))
+                    }
+                    h2 { (texts.recent_events) }
+                    @if events.is_empty() {
+                        p { (texts.no_events) }
+                    } @else {
+                        table {
+                            thead {
+                                tr {
+                                    th { (texts.time) }
+                                    th { (texts.user) }
+                                    th { (texts.event) }
+                                }
+                            }
+                            tbody {
+                                @for event in events {
+                                    tr {
+                                        td { (event.time.format("%Y-%m-%d %H:%M:%S")) }
+                                        td { (event.user.as_deref().unwrap_or_default()) }
+                                        td { (event.description) }
+                                    }
+                                }
+                            }
                         }
                     }
+                    @if !conf.hide_version_footer {
+                        (version_footer())
+                    }
                 }
+                (custom_script(conf.custom_js.as_deref()))
             }
         }
     }
@@ -284,6 +1040,7 @@ fn wget_footer(
     root_dir_name: Option<&str>,
     current_user: Option<&str>,
     file_external_url: Option<&str>,
+    texts: &Texts,
 ) -> Markup {
     fn escape_apostrophes(x: &str) -> String {
         x.replace('\'', "'\"'\"'")
@@ -324,8 +1081,8 @@ fn wget_footer(
 
     html! {
         div.downloadDirectory {
-            p { "Download folder:" }
-            a.cmd title="Click to copy!" style="cursor: pointer;" onclick=(click_to_copy) { (command) }
+            p { (texts.download_folder) }
+            a.cmd title=(texts.click_to_copy) style="cursor: pointer;" onclick=(click_to_copy) { (command) }
         }
     }
 }
@@ -353,12 +1110,13 @@ fn build_mkdir_action(mkdir_route: &str, encoded_dir: &str) -> String {
     format!("{mkdir_route}?path={encoded_dir}")
 }
 
-const THEME_PICKER_CHOICES: &[(&str, &str)] = &[
-    ("Default (light/dark)", "default"),
-    ("Squirrel (light)", "squirrel"),
-    ("Arch Linux (dark)", "archlinux"),
-    ("Zenburn (dark)", "zenburn"),
-    ("Monokai (dark)", "monokai"),
+/// Names of the themes offered besides the default one, whether they're dark, and their slugs
+const THEME_PICKER_CHOICES: &[(&str, bool, &str)] = &[
+    ("Squirrel", false, "squirrel"),
+    ("Arch Linux", true, "archlinux"),
+    ("Zenburn", true, "zenburn"),
+    ("Monokai", true, "monokai"),
+    ("High contrast", true, "highcontrast"),
 ];
 
 #[derive(Debug, Clone, ValueEnum, Display)]
@@ -371,6 +1129,8 @@ pub enum ThemeSlug {
     Zenburn,
     #[strum(serialize = "monokai")]
     Monokai,
+    #[strum(serialize = "highcontrast")]
+    Highcontrast,
 }
 
 impl ThemeSlug {
@@ -380,6 +1140,7 @@ impl ThemeSlug {
             Self::Archlinux => grass::include!("data/themes/archlinux.scss"),
             Self::Zenburn => grass::include!("data/themes/zenburn.scss"),
             Self::Monokai => grass::include!("data/themes/monokai.scss"),
+            Self::Highcontrast => grass::include!("data/themes/highcontrast.scss"),
         }
     }
 
@@ -388,13 +1149,86 @@ impl ThemeSlug {
     }
 }
 
+/// Color scheme made of the colors given to --color-scheme-custom
+///
+/// Unlike the built-in themes, its colors apply to both light and dark mode, and the colors
+/// left out keep those of the default themes.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CustomColorScheme {
+    /// Background of the pages and the listing
+    pub background: Option<String>,
+
+    /// Text, and background of the table header and the menus
+    pub foreground: Option<String>,
+
+    /// Directory links, buttons and the highlighted theme
+    pub accent: Option<String>,
+}
+
+impl CustomColorScheme {
+    /// CSS variables of the themes which each color overrides
+    const BACKGROUND_VARIABLES: &[&str] = &[
+        "background",
+        "table_background",
+        "upload_form_background",
+        "table_header_text_color",
+        "table_header_active_color",
+        "switch_theme_link_color",
+        "change_theme_link_color",
+        "change_theme_link_color_hover",
+        "size_text_color",
+        "upload_modal_header_color",
+    ];
+    const FOREGROUND_VARIABLES: &[&str] = &[
+        "text_color",
+        "table_text_color",
+        "root_link_color",
+        "upload_text_color",
+        "table_header_background",
+        "switch_theme_background",
+        "size_background_color",
+        "upload_modal_header_background",
+    ];
+    const ACCENT_VARIABLES: &[&str] = &[
+        "directory_link_color",
+        "directory_link_color_visited",
+        "download_button_background",
+        "download_button_background_hover",
+        "back_button_background",
+        "back_button_background_hover",
+        "switch_theme_active",
+        "upload_button_background",
+        "progress_bar_background",
+    ];
+
+    /// Rules overriding the colors of the default themes
+    ///
+    /// Themes picked in the theme menu still take precedence.
+    pub fn css(&self) -> String {
+        let mut css = String::from("body:not([data-theme]) {\n");
+        for (color, variables) in [
+            (&self.background, Self::BACKGROUND_VARIABLES),
+            (&self.foreground, Self::FOREGROUND_VARIABLES),
+            (&self.accent, Self::ACCENT_VARIABLES),
+        ] {
+            if let Some(color) = color {
+                for variable in variables {
+                    css.push_str(&format!("  --{variable}: {color};\n"));
+                }
+            }
+        }
+        css.push_str("}\n");
+        css
+    }
+}
+
 /// Partial: qr code spoiler
-fn qr_spoiler(show_qrcode: bool, content: &Uri) -> Markup {
+fn qr_spoiler(show_qrcode: bool, content: &Uri, texts: &Texts) -> Markup {
     html! {
         @if show_qrcode {
             div {
-                p {
-                    "QR code"
+                p tabindex="0" {
+                    (texts.qr_code)
                 }
                 div.qrcode #qrcode title=(PreEscaped(content.to_string())) {
                     @match qr_code_svg(content, consts::SVG_QR_MARGIN) {
@@ -407,122 +1241,640 @@ fn qr_spoiler(show_qrcode: bool, content: &Uri) -> Markup {
     }
 }
 
-/// Partial: color scheme selector
-fn color_scheme_selector(hide_theme_selector: bool) -> Markup {
+/// Partial: color scheme selector
+fn color_scheme_selector(hide_theme_selector: bool, texts: &Texts) -> Markup {
+    let default = format!("{} ({}/{})", texts.default_theme, texts.light, texts.dark);
+    html! {
+        @if !hide_theme_selector {
+            div {
+                p tabindex="0" {
+                    (texts.change_theme)
+                }
+                ul.theme {
+                    li data-theme="default" {
+                        (color_scheme_link(&default, "default", texts))
+                    }
+                    @for (name, dark, slug) in THEME_PICKER_CHOICES {
+                        li data-theme=(slug) {
+                            @let shade = if *dark { texts.dark } else { texts.light };
+                            (color_scheme_link(&format!("{name} ({shade})"), slug, texts))
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+// /// Partial: color scheme link
+fn color_scheme_link(name: &str, slug: &str, texts: &Texts) -> Markup {
+    let title = (texts.switch_theme)(name);
+
+    html! {
+        a href=(format!("javascript:updateColorScheme(\"{slug}\")")) title=(title) {
+            (name)
+        }
+    }
+}
+
+/// Partial: archive button
+fn archive_button(
+    archive_method: ArchiveMethod,
+    sort_method: Option<SortingMethod>,
+    sort_order: Option<SortingOrder>,
+    texts: &Texts,
+) -> Markup {
+    let link = if sort_method.is_none() && sort_order.is_none() {
+        format!("?download={archive_method}")
+    } else {
+        format!(
+            "{}&download={}",
+            parametrized_link("", sort_method, sort_order, false),
+            archive_method
+        )
+    };
+
+    let text = (texts.download_archive)(&archive_method.extension());
+
+    html! {
+        a href=(link) {
+            (text)
+        }
+    }
+}
+
+/// Partial: numbers of files and directories, and the size of the files, of a listing
+fn totals_summary(totals: &Totals, show_exact_bytes: bool, texts: &Texts) -> Markup {
+    let size = if show_exact_bytes {
+        format!("{} B", totals.size.as_u64())
+    } else {
+        totals.size.to_string()
+    };
+    html! {
+        p.totals {
+            ((texts.totals)(totals.files as u64, totals.directories as u64, &size))
+        }
+    }
+}
+
+/// Partial: box filtering the entries of the listing by name, and keyboard navigation of its rows
+///
+/// Arrow keys select rows, Enter opens the selected one and `/` focuses the box.
+fn listing_filter(texts: &Texts) -> Markup {
+    html! {
+        div.filter {
+            input #filter type="search" placeholder=(texts.filter) title=(texts.filter_help)
+                aria-label=(texts.filter) aria-controls="listing" autocomplete="off";
+        }
+        script {
+            (PreEscaped(r#"
+                (function () {
+                    const filter = document.getElementById('filter');
+                    // Rows of entries, leaving out the one of the parent directory
+                    const rows = () => Array.from(document.querySelectorAll('#listing tbody tr[class^="entry-type-"]'));
+                    const visibleRows = () => rows().filter(row => !row.hidden);
+
+                    document.addEventListener('listingrefresh', () => filter.dispatchEvent(new Event('input')));
+                    filter.addEventListener('input', () => {
+                        const text = filter.value.toLowerCase();
+                        rows().forEach(row => {
+                            const name = row.querySelector('td a, td span.broken-symlink');
+                            row.hidden = text !== '' && !(name && name.textContent.toLowerCase().includes(text));
+                            if (row.hidden) {
+                                row.classList.remove('selected');
+                            }
+                        });
+                    });
+
+                    function select(row) {
+                        document.querySelectorAll('#listing tr.selected').forEach(selected => {
+                            selected.classList.remove('selected');
+                            selected.removeAttribute('aria-selected');
+                        });
+                        if (row) {
+                            row.classList.add('selected');
+                            row.setAttribute('aria-selected', 'true');
+                            row.scrollIntoView({ block: 'nearest' });
+                        }
+                    }
+
+                    document.addEventListener('keydown', event => {
+                        if (event.ctrlKey || event.metaKey || event.altKey) {
+                            return;
+                        }
+                        const target = event.target;
+                        const typing = target.isContentEditable || ['INPUT', 'TEXTAREA', 'SELECT'].includes(target.tagName);
+                        if (typing && target !== filter) {
+                            return;
+                        }
+
+                        const visible = visibleRows();
+                        const current = visible.findIndex(row => row.classList.contains('selected'));
+                        if (event.key === '/' && !typing) {
+                            event.preventDefault();
+                            filter.focus();
+                        } else if (event.key === 'ArrowDown' || event.key === 'ArrowUp') {
+                            if (visible.length === 0) {
+                                return;
+                            }
+                            event.preventDefault();
+                            filter.blur();
+                            const step = event.key === 'ArrowDown' ? 1 : -1;
+                            const next = current === -1
+                                ? (step === 1 ? 0 : visible.length - 1)
+                                : Math.min(Math.max(current + step, 0), visible.length - 1);
+                            select(visible[next]);
+                        } else if (event.key === 'Enter' && current !== -1 && !typing) {
+                            const link = visible[current].querySelector('td a');
+                            if (link) {
+                                event.preventDefault();
+                                link.click();
+                            }
+                        } else if (event.key === 'Enter' && typing && visible.length > 0) {
+                            // Enter in the box opens the first entry left
+                            const link = visible[0].querySelector('td a');
+                            if (link) {
+                                event.preventDefault();
+                                link.click();
+                            }
+                        } else if (event.key === 'Escape') {
+                            if (typing) {
+                                filter.value = '';
+                                filter.dispatchEvent(new Event('input'));
+                                filter.blur();
+                            } else {
+                                select(null);
+                            }
+                        }
+                    });
+                })();
+            "#))
+        }
+    }
+}
+
+/// Partial: notice shown above a truncated listing, with a form narrowing it down by name
+fn truncation_notice(
+    shown: usize,
+    total: usize,
+    sort_method: Option<SortingMethod>,
+    sort_order: Option<SortingOrder>,
+    kept_params: &str,
+    filter: Option<&str>,
+    texts: &Texts,
+) -> Markup {
+    let mut link = if sort_method.is_none() && sort_order.is_none() {
+        "?show_all=true".to_string()
+    } else {
+        format!(
+            "{}&show_all=true",
+            parametrized_link("", sort_method, sort_order, false)
+        )
+    };
+    link.push_str(kept_params);
+
+    html! {
+        div.listing-notice {
+            p {
+                ((texts.entries_shown)(shown, total))
+                a href=(link) { (texts.show_all) }
+                ((texts.to_list_remaining)(total - shown))
+            }
+            // A plain form, so that it works in the simple listings of legacy browsers too
+            form method="get" {
+                @if let Some(method) = sort_method {
+                    input type="hidden" name="sort" value=(method);
+                }
+                @if let Some(order) = sort_order {
+                    input type="hidden" name="order" value=(order);
+                }
+                @for (name, value) in kept_params.split('&').filter_map(|param| param.split_once('=')) {
+                    @if name != "filter" && name != "show_all" {
+                        input type="hidden" name=(name) value=(value);
+                    }
+                }
+                label for="refine" { (texts.refine_prompt) }
+                " "
+                input #refine type="search" name="filter" value=[filter];
+                " "
+                button type="submit" { (texts.refine) }
+            }
+        }
+    }
+}
+
+/// Ensure that there's always a trailing slash behind the `link`.
+fn make_link_with_trailing_slash(link: &str) -> String {
+    if link.is_empty() || link.ends_with('/') {
+        link.to_string()
+    } else {
+        format!("{link}/")
+    }
+}
+
+/// If they are set, adds query parameters to links to keep them across pages
+fn parametrized_link(
+    link: &str,
+    sort_method: Option<SortingMethod>,
+    sort_order: Option<SortingOrder>,
+    raw: bool,
+) -> String {
+    if raw {
+        return format!("{}?raw=true", make_link_with_trailing_slash(link));
+    }
+
+    if let Some(method) = sort_method
+        && let Some(order) = sort_order
+    {
+        let parametrized_link = format!(
+            "{}?sort={}&order={}",
+            make_link_with_trailing_slash(link),
+            method,
+            order,
+        );
+
+        return parametrized_link;
+    }
+
+    make_link_with_trailing_slash(link)
+}
+
+/// Partial: link switching between the list and the gallery
+fn view_toggle(view: ListingView, has_media: bool, texts: &Texts) -> Markup {
+    let views = [
+        (ListingView::List, texts.list_view),
+        (ListingView::Tree, texts.tree_view),
+        (ListingView::Gallery, texts.gallery_view),
+    ];
+    html! {
+        @for (other, text) in views {
+            // The gallery is only offered where there's something to show in it
+            @if other != view && (other != ListingView::Gallery || has_media) {
+                a href=(format!("?view={other}")) { (text) }
+            }
+        }
+    }
+}
+
+/// Partial: script of the tree view, expanding directories in place
+///
+/// Their entries are fetched as JSON, sorted and filtered like those of the listing.
+fn tree_script(texts: &Texts) -> Markup {
     html! {
-        @if !hide_theme_selector {
-            div {
-                p {
-                    "Change theme..."
-                }
-                ul.theme {
-                    @for color_scheme in THEME_PICKER_CHOICES {
-                        li data-theme=(color_scheme.1) {
-                            (color_scheme_link(color_scheme))
+        script {
+            (PreEscaped(format!(
+                "const TREE_TEXTS = {{ expand: {:?}, collapse: {:?} }};",
+                texts.expand, texts.collapse,
+            )))
+            (PreEscaped(r#"
+                (function () {
+                    const columns = document.querySelectorAll('#listing thead th').length;
+                    // Entries are asked for like the listing was, but as JSON
+                    const params = new URLSearchParams(location.search);
+                    ['view', 'show_all', 'raw'].forEach(name => params.delete(name));
+                    params.set('json', 'true');
+
+                    function formatSize(bytes) {
+                        const units = ['B', 'KiB', 'MiB', 'GiB', 'TiB'];
+                        let unit = 0;
+                        while (bytes >= 1024 && unit < units.length - 1) {
+                            bytes /= 1024;
+                            unit++;
                         }
+                        return (unit === 0 ? bytes : bytes.toFixed(1)) + ' ' + units[unit];
                     }
-                }
-            }
+
+                    function createRow(entry, depth) {
+                        const row = document.createElement('tr');
+                        row.className = 'entry-type-' + entry.type;
+                        row.dataset.depth = depth;
+                        row.style.setProperty('--depth', depth);
+
+                        const name = document.createElement(entry.type === 'broken_symlink' ? 'span' : 'a');
+                        if (entry.type === 'broken_symlink') {
+                            name.className = 'broken-symlink';
+                        } else {
+                            name.className = entry.symlink_target ? 'symlink' : entry.type;
+                            name.href = entry.link;
+                        }
+                        name.textContent = entry.type === 'directory' ? entry.name + '/' : entry.name;
+                        const paragraph = document.createElement('p');
+                        paragraph.append(name);
+                        const nameCell = document.createElement('td');
+                        nameCell.append(paragraph);
+
+                        const sizeCell = document.createElement('td');
+                        sizeCell.className = 'size-cell';
+                        if (entry.size !== null) {
+                            sizeCell.textContent = formatSize(entry.size);
+                        }
+                        const dateCell = document.createElement('td');
+                        dateCell.className = 'date-cell';
+                        if (entry.modified !== null) {
+                            dateCell.textContent = new Date(entry.modified * 1000).toLocaleString();
+                        }
+                        row.append(nameCell, sizeCell, dateCell);
+                        for (let column = 3; column < columns; column++) {
+                            row.append(document.createElement('td'));
+                        }
+
+                        if (entry.type === 'directory') {
+                            addToggle(row);
+                        }
+                        return row;
+                    }
+
+                    // Rows below a directory, which are those deeper than it up to the next one that isn't
+                    function descendants(row) {
+                        const depth = Number(row.dataset.depth);
+                        const rows = [];
+                        for (let next = row.nextElementSibling; next && Number(next.dataset.depth) > depth; next = next.nextElementSibling) {
+                            rows.push(next);
+                        }
+                        return rows;
+                    }
+
+                    function toggle(row, button) {
+                        if (button.getAttribute('aria-expanded') === 'true') {
+                            descendants(row).forEach(child => child.remove());
+                            button.setAttribute('aria-expanded', 'false');
+                            button.title = TREE_TEXTS.expand;
+                            return;
+                        }
+
+                        const url = new URL(row.querySelector('td a').getAttribute('href'), location.href);
+                        url.search = params.toString();
+                        button.disabled = true;
+                        fetch(url, { headers: { 'Accept': 'application/json' } })
+                            .then(resp => resp.ok ? resp.json() : Promise.reject(resp.status))
+                            .then(entries => {
+                                const depth = Number(row.dataset.depth) + 1;
+                                let previous = row;
+                                entries.forEach(entry => {
+                                    const child = createRow(entry, depth);
+                                    previous.after(child);
+                                    previous = child;
+                                });
+                                button.setAttribute('aria-expanded', 'true');
+                                button.title = TREE_TEXTS.collapse;
+                            })
+                            .catch(error => console.error("Error fetching the directory:", error))
+                            .finally(() => button.disabled = false);
+                    }
+
+                    function addToggle(row) {
+                        const button = document.createElement('button');
+                        button.type = 'button';
+                        button.className = 'tree-toggle';
+                        button.title = TREE_TEXTS.expand;
+                        button.setAttribute('aria-expanded', 'false');
+                        button.addEventListener('click', () => toggle(row, button));
+                        row.querySelector('td p').prepend(button);
+                    }
+
+                    function addToggles() {
+                        document.querySelectorAll('#listing tbody tr.entry-type-directory').forEach(row => {
+                            row.dataset.depth = 0;
+                            addToggle(row);
+                        });
+                    }
+                    addToggles();
+                    document.addEventListener('listingrefresh', addToggles);
+                })();
+            "#))
         }
     }
 }
 
-// /// Partial: color scheme link
-fn color_scheme_link(color_scheme: &(&str, &str)) -> Markup {
-    let title = format!("Switch to {} theme", color_scheme.0);
-
+/// Partial: link switching between listing directories before files and among them
+fn dirs_first_toggle(
+    dirs_first: bool,
+    sort_method: Option<SortingMethod>,
+    sort_order: Option<SortingOrder>,
+    texts: &Texts,
+) -> Markup {
+    let (value, text) = if dirs_first {
+        (false, texts.mix_directories)
+    } else {
+        (true, texts.directories_first)
+    };
+    let mut link = format!("?dirs_first={value}");
+    if let Some(method) = sort_method
+        && let Some(order) = sort_order
+    {
+        link.push_str(&format!("&sort={method}&order={order}"));
+    }
     html! {
-        a href=(format!("javascript:updateColorScheme(\"{}\")", color_scheme.1)) title=(title) {
-            (color_scheme.0)
-        }
+        a href=(link) { (text) }
     }
 }
 
-/// Partial: archive button
-fn archive_button(
-    archive_method: ArchiveMethod,
+/// Partial: link switching between showing hidden files and leaving them out, with --hidden
+fn hidden_toggle(
+    show_hidden: bool,
     sort_method: Option<SortingMethod>,
     sort_order: Option<SortingOrder>,
+    texts: &Texts,
 ) -> Markup {
-    let link = if sort_method.is_none() && sort_order.is_none() {
-        format!("?download={archive_method}")
+    let (value, text) = if show_hidden {
+        (false, texts.hide_hidden_files)
     } else {
-        format!(
-            "{}&download={}",
-            parametrized_link("", sort_method, sort_order, false),
-            archive_method
-        )
+        (true, texts.show_hidden_files)
     };
-
-    let text = format!("Download .{}", archive_method.extension());
-
+    let mut link = format!("?hidden={value}");
+    if let Some(method) = sort_method
+        && let Some(order) = sort_order
+    {
+        link.push_str(&format!("&sort={method}&order={order}"));
+    }
     html! {
-        a href=(link) {
-            (text)
-        }
+        a href=(link) { (text) }
     }
 }
 
-/// Ensure that there's always a trailing slash behind the `link`.
-fn make_link_with_trailing_slash(link: &str) -> String {
-    if link.is_empty() || link.ends_with('/') {
-        link.to_string()
+/// Partial: link switching between listing the files of subdirectories too and leaving them out
+fn flat_toggle(
+    flat: bool,
+    sort_method: Option<SortingMethod>,
+    sort_order: Option<SortingOrder>,
+    texts: &Texts,
+) -> Markup {
+    let (value, text) = if flat {
+        (false, texts.nested_listing)
     } else {
-        format!("{link}/")
+        (true, texts.flat_listing)
+    };
+    let mut link = format!("?flat={value}");
+    if let Some(method) = sort_method
+        && let Some(order) = sort_order
+    {
+        link.push_str(&format!("&sort={method}&order={order}"));
+    }
+    html! {
+        a href=(link) title=(if flat { texts.nested_listing_help } else { texts.flat_listing_help }) { (text) }
     }
 }
 
-/// If they are set, adds query parameters to links to keep them across pages
-fn parametrized_link(
-    link: &str,
+/// Partial: link switching to the next way of showing when entries were last modified
+fn time_format_toggle(
+    time_format: TimeFormat,
     sort_method: Option<SortingMethod>,
     sort_order: Option<SortingOrder>,
-    raw: bool,
-) -> String {
-    if raw {
-        return format!("{}?raw=true", make_link_with_trailing_slash(link));
+    texts: &Texts,
+) -> Markup {
+    let next = time_format.next();
+    let text = match next {
+        TimeFormat::Both => texts.relative_and_absolute_times,
+        TimeFormat::Relative => texts.relative_times,
+        TimeFormat::Absolute => texts.absolute_times,
+    };
+    let mut link = format!("?time={next}");
+    if let Some(method) = sort_method
+        && let Some(order) = sort_order
+    {
+        link.push_str(&format!("&sort={method}&order={order}"));
     }
+    html! {
+        a href=(link) { (text) }
+    }
+}
 
-    if let Some(method) = sort_method {
-        if let Some(order) = sort_order {
-            let parametrized_link = format!(
-                "{}?sort={}&order={}",
-                make_link_with_trailing_slash(link),
-                method,
-                order,
-            );
-
-            return parametrized_link;
+/// Partial: grid of tiles showing the entries, for the gallery view
+///
+/// Images and videos open in the lightbox, and directories keep being shown as galleries.
+fn gallery_grid(entries: Vec<Entry>, is_root: bool, thumbnails: bool, texts: &Texts) -> Markup {
+    html! {
+        div.gallery {
+            @if !is_root {
+                a.gallery-item href="../?view=gallery" {
+                    span.gallery-icon { (chevron_left()) }
+                    span.gallery-name { (texts.parent_directory) }
+                }
+            }
+            @for entry in entries.into_iter().filter(|entry| !entry.is_broken_symlink()) {
+                @if entry.is_dir() {
+                    a.gallery-item.directory href=(format!("{}?view=gallery", make_link_with_trailing_slash(&entry.link))) {
+                        span.gallery-icon { "📁" }
+                        span.gallery-name { (entry.name) }
+                    }
+                } @else if thumbnail::is_image(&entry.name) {
+                    a.gallery-item.gallery-media href=(entry.link) title=(entry.name) {
+                        @if thumbnails {
+                            // Images without a thumbnail are shown in full
+                            img src=(format!("{}?thumbnail=true", entry.link)) loading="lazy" alt=(entry.name) onerror="this.onerror=null;this.src=this.parentNode.href";
+                        } @else {
+                            img src=(entry.link) loading="lazy" alt=(entry.name);
+                        }
+                        span.gallery-name { (entry.name) }
+                    }
+                } @else if thumbnail::is_video(&entry.name) {
+                    a.gallery-item.gallery-media data-video="" href=(entry.link) title=(entry.name) {
+                        video src=(entry.link) preload="metadata" muted {}
+                        span.gallery-name { (entry.name) }
+                    }
+                } @else {
+                    a.gallery-item.file href=(entry.link) title=(entry.name) {
+                        span.gallery-icon { "📃" }
+                        span.gallery-name { (entry.name) }
+                    }
+                }
+            }
         }
     }
+}
 
-    make_link_with_trailing_slash(link)
+/// Partial: overlay showing the images and videos of a gallery one by one
+fn lightbox(texts: &Texts) -> Markup {
+    html! {
+        div #lightbox hidden {
+            button.lightbox-close title=(format!("{} (Esc)", texts.close)) { "✖" }
+            button.lightbox-prev title=(format!("{} (←)", texts.previous)) { "‹" }
+            div.lightbox-content {}
+            button.lightbox-next title=(format!("{} (→)", texts.next)) { "›" }
+        }
+        script {
+            (PreEscaped(r#"
+                (function () {
+                    const items = Array.from(document.querySelectorAll('.gallery-media'));
+                    const lightbox = document.getElementById('lightbox');
+                    const content = lightbox.querySelector('.lightbox-content');
+                    let current = 0;
+
+                    function show(index) {
+                        current = (index + items.length) % items.length;
+                        const item = items[current];
+                        const isVideo = item.dataset.video !== undefined;
+                        const media = document.createElement(isVideo ? 'video' : 'img');
+                        media.src = item.href;
+                        if (isVideo) {
+                            media.controls = true;
+                            media.autoplay = true;
+                        } else {
+                            media.alt = item.title;
+                        }
+                        content.replaceChildren(media);
+                        lightbox.hidden = false;
+                    }
+
+                    function close() {
+                        lightbox.hidden = true;
+                        content.replaceChildren();
+                    }
+
+                    items.forEach((item, index) => item.addEventListener('click', event => {
+                        event.preventDefault();
+                        show(index);
+                    }));
+                    lightbox.querySelector('.lightbox-prev').addEventListener('click', () => show(current - 1));
+                    lightbox.querySelector('.lightbox-next').addEventListener('click', () => show(current + 1));
+                    lightbox.querySelector('.lightbox-close').addEventListener('click', close);
+                    document.addEventListener('keydown', event => {
+                        if (lightbox.hidden) {
+                            return;
+                        }
+                        if (event.key === 'ArrowLeft') {
+                            show(current - 1);
+                        } else if (event.key === 'ArrowRight') {
+                            show(current + 1);
+                        } else if (event.key === 'Escape') {
+                            close();
+                        }
+                    });
+                })();
+            "#))
+        }
+    }
 }
 
 /// Partial: table header link
+///
+/// Listings shown in full stay so when sorted differently.
 fn build_link(
     name: &str,
     title: &str,
     sort_method: Option<SortingMethod>,
     sort_order: Option<SortingOrder>,
+    kept_params: &str,
+    texts: &Texts,
 ) -> Markup {
     let mut link = format!("?sort={name}&order=asc");
-    let mut help = format!("Sort by {name} in ascending order");
+    let mut help = (texts.sort_ascending)(title);
     let mut chevron = chevron_down();
     let mut class = "";
 
-    if let Some(method) = sort_method {
-        if method.to_string() == name {
-            class = "active";
-            if let Some(order) = sort_order {
-                if order.to_string() == "asc" {
-                    link = format!("?sort={name}&order=desc");
-                    help = format!("Sort by {name} in descending order");
-                    chevron = chevron_up();
-                }
-            }
+    if let Some(method) = sort_method
+        && method.to_string() == name
+    {
+        class = "active";
+        if let Some(order) = sort_order
+            && order.to_string() == "asc"
+        {
+            link = format!("?sort={name}&order=desc");
+            help = (texts.sort_descending)(title);
+            chevron = chevron_up();
         }
     };
+    link.push_str(kept_params);
 
     html! {
         span class=(class) {
@@ -532,43 +1884,115 @@ fn build_link(
     }
 }
 
+/// Value of the `aria-sort` attribute of the header of a column, set when the listing is sorted by it
+fn aria_sort(
+    name: &str,
+    sort_method: Option<SortingMethod>,
+    sort_order: Option<SortingOrder>,
+) -> Option<&'static str> {
+    let method = sort_method?;
+    if method.to_string() != name {
+        return None;
+    }
+    // Like the sorting links, a missing order stands for the descending one
+    match sort_order {
+        Some(SortingOrder::Asc) => Some("ascending"),
+        _ => Some("descending"),
+    }
+}
+
 /// Partial: row for an entry
+#[allow(clippy::too_many_arguments)]
 fn entry_row(
     entry: Entry,
     sort_method: Option<SortingMethod>,
     sort_order: Option<SortingOrder>,
+    kept_params: &str,
     raw: bool,
     show_exact_bytes: bool,
+    time_format: TimeFormat,
+    date_format: &DateFormat,
+    preview: bool,
+    thumbnails: bool,
+    media_player: bool,
+    pdf_viewer: bool,
+    exif_details: bool,
+    torrent: bool,
+    texts: &Texts,
 ) -> Markup {
+    let thumbnail = thumbnails && thumbnail::is_image(&entry.name);
+    let file_link = if media_player && player::is_playable(&entry.name) {
+        format!("{}?play=true", entry.link)
+    } else if pdf_viewer && pdf::is_pdf(&entry.name) {
+        format!("{}?viewer=true", entry.link)
+    } else {
+        entry.link.clone()
+    };
+    let preview = preview
+        && highlight::is_previewable(&entry.name)
+        && entry
+            .size
+            .is_some_and(|size| size.as_u64() <= highlight::MAX_PREVIEW_SIZE);
     html! {
         @let entry_type = entry.entry_type.clone();
         tr .{ "entry-type-" (entry_type) } {
             td {
                 p {
                     @if entry.is_dir() {
+                        @if !raw {
+                            (entry_icon(&entry))
+                        }
                         @if let Some(ref symlink_dest) = entry.symlink_info {
                             a.symlink href=(parametrized_link(&entry.link, sort_method, sort_order, raw)) {
                                 (entry.name) "/"
-                                span.symlink-symbol { }
-                                a.directory {(symlink_dest) "/"}
                             }
+                            span.symlink-symbol { }
+                            span.symlink-target { (symlink_dest) "/" }
                         }@else {
                             a.directory href=(parametrized_link(&entry.link, sort_method, sort_order, raw)) {
                                 (entry.name) "/"
                             }
                         }
+                        @if torrent {
+                            (torrent_link(&entry.link, texts))
+                        }
+                        @if !raw {
+                            (copy_link_button(&entry.link, texts))
+                        }
                     } @else if entry.is_file() {
+                        @if thumbnail {
+                            // Images without a thumbnail answer with an error
+                            img.thumbnail src=(format!("{}?thumbnail=true", entry.link)) loading="lazy" alt="" onerror="this.remove()";
+                        } @else if !raw {
+                            (entry_icon(&entry))
+                        }
                         @if let Some(ref symlink_dest) = entry.symlink_info {
-                            a.symlink href=(&entry.link) {
+                            a.symlink href=(&file_link) {
                                 (entry.name)
-                                span.symlink-symbol { }
-                                a.file {(symlink_dest)}
                             }
+                            span.symlink-symbol { }
+                            span.symlink-target { (symlink_dest) }
                         }@else {
-                            a.file href=(&entry.link) {
+                            a.file href=(&file_link) {
                                 (entry.name)
                             }
                         }
+                        @if preview {
+                            a.preview href=(format!("{}?preview=true", entry.link)) title=(texts.preview_help) {
+                                (texts.preview)
+                            }
+                        }
+                        @if exif_details && exif::is_jpeg(&entry.name) {
+                            a.preview href=(format!("{}?exif=true", entry.link)) title=(texts.details_help) {
+                                (texts.details)
+                            }
+                        }
+                        @if torrent {
+                            (torrent_link(&entry.link, texts))
+                        }
+                        @if !raw {
+                            (copy_link_button(&entry.link, texts))
+                        }
 
                         @if !raw {
                             @if let Some(size) = entry.size {
@@ -578,16 +2002,30 @@ fn entry_row(
                                     }
                                 }@else {
                                     span.mobile-info.size {
-                                        (build_link("size", &format!("{}", size), sort_method, sort_order))
+                                        (build_link("size", &format!("{}", size), sort_method, sort_order, kept_params, texts))
                                 }
                             }
-                            @if let Some(modification_timer) = humanize_systemtime(entry.last_modification_date) {
+                            @let modification_time = if time_format.shows_relative() {
+                                entry.last_modification_date.map(texts.relative_time)
+                            } else {
+                                entry.last_modification_date.map(|time| date_format.format(time))
+                            };
+                            @if let Some(modification_time) = modification_time {
                                 span.mobile-info.history {
-                                    (build_link("date", &modification_timer, sort_method, sort_order))
+                                    (build_link("date", &modification_time, sort_method, sort_order, kept_params, texts))
                                     }
                                 }
                             }
                         }
+                    } @else if entry.is_broken_symlink() {
+                        @if !raw {
+                            (entry_icon(&entry))
+                        }
+                        span.broken-symlink title=(texts.broken_link) {
+                            (entry.name)
+                        }
+                        span.symlink-symbol { }
+                        span.symlink-target { (entry.symlink_info.as_deref().unwrap_or_default()) }
                     }
                 }
             }
@@ -601,17 +2039,172 @@ fn entry_row(
                 }
             }
             td.date-cell {
-                @if let Some(modification_date) = convert_to_local(entry.last_modification_date) {
-                    span {
-                        (modification_date) " "
+                @if time_format.shows_absolute() {
+                    @if let Some(modification_date) = entry.last_modification_date.map(|time| date_format.format(time)) {
+                        span {
+                            (modification_date) " "
+                        }
                     }
                 }
-                @if let Some(modification_timer) = humanize_systemtime(entry.last_modification_date) {
-                    span.history {
-                        (modification_timer)
+                @if time_format.shows_relative() {
+                    @if let Some(modification_timer) = entry.last_modification_date.map(texts.relative_time) {
+                        span.history {
+                            (modification_timer)
+                        }
                     }
                 }
             }
+            @if let Some(permissions) = &entry.permissions {
+                td.permissions-cell { (permissions.mode) }
+                td.owner-cell { (permissions.owner) }
+                td.group-cell { (permissions.group) }
+            }
+        }
+    }
+}
+
+/// Extensions of archives, which get an icon of their own
+const ARCHIVE_EXTENSIONS: &[&str] = &[
+    "zip", "tar", "gz", "tgz", "bz2", "xz", "zst", "7z", "rar", "deb", "rpm", "iso",
+];
+
+/// Partial: icon of the type of an entry, as an inline SVG
+fn entry_icon(entry: &Entry) -> Markup {
+    let extension = std::path::Path::new(&entry.name)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_lowercase)
+        .unwrap_or_default();
+    let (kind, path) = if entry.is_broken_symlink() {
+        (
+            "broken",
+            "M9 15l-2 2a3 3 0 0 1-4-4l3-3a3 3 0 0 1 4 0M15 9l2-2a3 3 0 0 1 4 4l-3 3a3 3 0 0 1-4 0M4 4l16 16",
+        )
+    } else if entry.is_dir() {
+        (
+            "folder",
+            "M3 6a1 1 0 0 1 1-1h5l2 2h9a1 1 0 0 1 1 1v10a1 1 0 0 1-1 1H4a1 1 0 0 1-1-1z",
+        )
+    } else if thumbnail::is_image(&entry.name) {
+        (
+            "image",
+            "M4 4h16v16H4zM4 16l5-5 4 4 2-2 5 5M14 9a1.5 1.5 0 1 0 3 0a1.5 1.5 0 1 0-3 0",
+        )
+    } else if player::is_audio(&entry.name) {
+        (
+            "audio",
+            "M9 18V5l11-2v13M9 18a3 3 0 1 1-6 0a3 3 0 1 1 6 0M20 16a3 3 0 1 1-6 0a3 3 0 1 1 6 0",
+        )
+    } else if thumbnail::is_video(&entry.name) {
+        ("video", "M3 6h13v12H3zM16 10l5-3v10l-5-3")
+    } else if ARCHIVE_EXTENSIONS.contains(&extension.as_str()) {
+        ("archive", "M4 4h16v4H4zM5 8v12h14V8M10 12h4")
+    } else if highlight::is_code(&entry.name) {
+        ("code", "M8 7l-5 5 5 5M16 7l5 5-5 5M14 4l-4 16")
+    } else if highlight::is_previewable(&entry.name) {
+        (
+            "text",
+            "M6 2h8l5 5v14a1 1 0 0 1-1 1H6a1 1 0 0 1-1-1V3a1 1 0 0 1 1-1zM14 2v5h5M8 13h8M8 17h8",
+        )
+    } else {
+        (
+            "file",
+            "M6 2h8l5 5v14a1 1 0 0 1-1 1H6a1 1 0 0 1-1-1V3a1 1 0 0 1 1-1zM14 2v5h5",
+        )
+    };
+    html! {
+        svg.icon.{ "icon-" (kind) } viewBox="0 0 24 24" aria-hidden="true" {
+            path d=(path) {}
+        }
+    }
+}
+
+/// Partial: link to the torrent of an entry
+fn torrent_link(link: &str, texts: &Texts) -> Markup {
+    html! {
+        a.preview href=(format!("{link}?torrent=true")) download title=(texts.torrent_help) {
+            (texts.torrent)
+        }
+    }
+}
+
+/// Partial: button copying the absolute URL of an entry to the clipboard
+fn copy_link_button(link: &str, texts: &Texts) -> Markup {
+    html! {
+        button.copy-link type="button" data-link=(link) title=(texts.copy_link_help) {
+            (texts.copy_link)
+        }
+    }
+}
+
+/// Partial: headers of the columns shown with --show-permissions
+fn permission_headers(texts: &Texts) -> Markup {
+    html! {
+        th.permissions scope="col" { (texts.permissions) }
+        th.owner scope="col" { (texts.owner) }
+        th.group scope="col" { (texts.group) }
+    }
+}
+
+/// Partial: text of --header-text or --footer-text, which is already HTML with --html-text
+fn custom_text(text: Option<&str>, html: bool, class: &str) -> Markup {
+    html! {
+        @if let Some(text) = text {
+            div class=(class) {
+                @if html {
+                    (PreEscaped(text))
+                } @else {
+                    (text)
+                }
+            }
+        }
+    }
+}
+
+/// Partial: script of --live-refresh, replacing the listing when its directory changes
+///
+/// Other scripts of the listing redo their part on the `listingrefresh` event it then fires.
+fn live_refresh_script(live_refresh: bool) -> Markup {
+    html! {
+        @if live_refresh {
+            script {
+                (PreEscaped(r#"
+                    (function () {
+                        const events = new EventSource(location.pathname + '?events=true');
+                        let refreshing = false;
+                        events.addEventListener('change', () => {
+                            if (refreshing) {
+                                return;
+                            }
+                            refreshing = true;
+                            fetch(location.href)
+                                .then(resp => resp.ok ? resp.text() : Promise.reject(resp.status))
+                                .then(html => {
+                                    const page = new DOMParser().parseFromString(html, 'text/html');
+                                    ['#listing', '.gallery', '.totals', '.listing-notice'].forEach(selector => {
+                                        const current = document.querySelector(selector);
+                                        const updated = page.querySelector(selector);
+                                        if (current && updated) {
+                                            current.replaceWith(document.adoptNode(updated));
+                                        }
+                                    });
+                                    document.dispatchEvent(new Event('listingrefresh'));
+                                })
+                                .catch(error => console.error("Error refreshing the listing:", error))
+                                .finally(() => refreshing = false);
+                        });
+                    })();
+                "#))
+            }
+        }
+    }
+}
+
+/// Partial: script of --custom-js, added at the end of pages
+fn custom_script(custom_js: Option<&str>) -> Markup {
+    html! {
+        @if let Some(custom_js) = custom_js {
+            script { (PreEscaped(custom_js)) }
         }
     }
 }
@@ -644,6 +2237,7 @@ fn page_header(
     api_route: &str,
     favicon_route: &str,
     css_route: &str,
+    texts: &Texts,
 ) -> Markup {
     html! {
         head {
@@ -652,7 +2246,7 @@ fn page_header(
             meta name="viewport" content="width=device-width, initial-scale=1";
             meta name="color-scheme" content="dark light";
 
-            link rel="icon" type="image/svg+xml" href={ (favicon_route) };
+            link rel="icon" href={ (favicon_route) };
             link rel="stylesheet" href={ (css_route) };
 
             title { (title) }
@@ -660,13 +2254,16 @@ fn page_header(
             script {
                 (PreEscaped(r#"
                     // updates the color scheme by setting the theme data attribute
-                    // on body and saving the new theme to local storage
+                    // on body and saving the new theme to local storage, and to a cookie
+                    // for listings to be rendered with it
                     function updateColorScheme(name) {
                         if (name && name != "default") {
                             localStorage.setItem('theme', name);
+                            document.cookie = "miniserve_theme=" + name + "; path=/; max-age=31536000; samesite=lax";
                             document.body.setAttribute("data-theme", name)
                         } else {
                             localStorage.removeItem('theme');
+                            document.cookie = "miniserve_theme=; path=/; max-age=0; samesite=lax";
                             document.body.removeAttribute("data-theme")
                         }
                     }
@@ -711,27 +2308,73 @@ fn page_header(
                             const href = cell.parentNode.querySelector('a').href;
                             const target = new URL(href).pathname;
 
-                            // First check our local cache
-                            if (target in dirSizeCache) {
-                                cell.dataset.size = dirSizeCache[target];
-                            } else {
-                                fetchDirSize(target).then(dir_size => {
-                                    cell.dataset.size = dir_size;
-                                    dirSizeCache[target] = dir_size;
-                                })
-                                .catch(error => console.error("Error fetching dir size:", error));
+                            // Sizes are only asked for once, even while they're still being calculated
+                            if (!(target in dirSizeCache)) {
+                                dirSizeCache[target] = fetchDirSize(target).catch(error => {
+                                    console.error("Error fetching dir size:", error);
+                                    return "~";
+                                });
                             }
+                            dirSizeCache[target].then(dir_size => {
+                                cell.dataset.size = dir_size;
+                            });
                         })
                     }
                     setInterval(updateSizeCells, 1000);
                 "#))
             }
 
+            script {
+                (PreEscaped(format!("const LINK_COPIED = {:?};", texts.link_copied)))
+                (PreEscaped(r#"
+                    // Copies the absolute URL of entries, also where the clipboard API is unavailable,
+                    // as it is on pages served over plain HTTP to other machines
+                    function copyText(text) {
+                        if (navigator.clipboard && window.isSecureContext) {
+                            return navigator.clipboard.writeText(text);
+                        }
+                        const field = document.createElement('textarea');
+                        field.value = text;
+                        field.style.position = 'fixed';
+                        field.style.opacity = '0';
+                        document.body.appendChild(field);
+                        field.select();
+                        const copied = document.execCommand('copy');
+                        field.remove();
+                        return copied ? Promise.resolve() : Promise.reject();
+                    }
+
+                    document.addEventListener('click', event => {
+                        const button = event.target.closest('button.copy-link');
+                        if (!button) {
+                            return;
+                        }
+                        const label = button.dataset.label || button.textContent;
+                        button.dataset.label = label;
+                        copyText(new URL(button.dataset.link, location.href).href).then(() => {
+                            button.textContent = LINK_COPIED;
+                            setTimeout(() => button.textContent = label, 1500);
+                        }).catch(error => console.error("Error copying the link:", error));
+                    });
+                "#))
+            }
+
             @if file_upload {
                 script {
                     (format!("const CONCURRENCY = {web_file_concurrency};"))
+                    // Debug formatting quotes and escapes the texts as JavaScript strings too
+                    (PreEscaped(format!(
+                        "const UPLOAD_TEXTS = {{ remaining: {:?}, complete: {:?}, uploading: {:?}, pending: {:?}, completed: {:?}, cancelled: {:?}, failed: {:?} }};",
+                        texts.uploads_remaining, texts.uploads_complete, texts.uploading_count, texts.pending_count,
+                        texts.complete_count, texts.cancelled_count, texts.failed_count,
+                    )))
                     (PreEscaped(r#"
                     window.onload = function() {
+                        // Without these the regular form submission is used as-is
+                        if (!window.FormData || !window.XMLHttpRequest || !window.Promise) {
+                            return;
+                        }
+
                         // Constants
                         const UPLOADING = 'uploading', PENDING = 'pending', COMPLETE = 'complete', CANCELLED = 'cancelled', FAILED = 'failed'
                         const UPLOAD_ITEM_ORDER = { UPLOADING: 0, PENDING: 1, COMPLETE: 2, CANCELLED: 3, FAILED: 4 }
@@ -820,18 +2463,19 @@ fn page_header(
                             const allCompleted = completed + cancelled + failed;
 
                             // Update header text based on remaining uploads
-                            let headerText = `${total - allCompleted} uploads remaining...`;
+                            const count = (text, number) => text.replace('{count}', number);
+                            let headerText = count(UPLOAD_TEXTS.remaining, total - allCompleted);
                             if (total === allCompleted) {
-                                headerText = `Complete! Reloading Page!`
+                                headerText = UPLOAD_TEXTS.complete
                             }
 
                             // Build a summary of statuses for sub header
                             const statuses = []
-                            if (uploads > 0) { statuses.push(`Uploading ${uploads}`) }
-                            if (pending > 0) { statuses.push(`Pending ${pending}`) }
-                            if (completed > 0) { statuses.push(`Complete ${completed}`) }
-                            if (cancelled > 0) { statuses.push(`Cancelled ${cancelled}`) }
-                            if (failed > 0) { statuses.push(`Failed ${failed}`) }
+                            if (uploads > 0) { statuses.push(count(UPLOAD_TEXTS.uploading, uploads)) }
+                            if (pending > 0) { statuses.push(count(UPLOAD_TEXTS.pending, pending)) }
+                            if (completed > 0) { statuses.push(count(UPLOAD_TEXTS.completed, completed)) }
+                            if (cancelled > 0) { statuses.push(count(UPLOAD_TEXTS.cancelled, cancelled)) }
+                            if (failed > 0) { statuses.push(count(UPLOAD_TEXTS.failed, failed)) }
 
                             uploadTitle.textContent = headerText
                             uploadActionText.textContent = statuses.join(', ')
@@ -947,7 +2591,9 @@ fn page_header(
 
                                 // Upload the single file in a multipart request.
                                 return new Promise(async (resolve, reject) => {
-                                    const fileHash = await get256FileHash(file);
+                                    // crypto.subtle is only available in secure contexts, so
+                                    // plain HTTP uploads have to go without the checksum
+                                    const fileHash = window.crypto && crypto.subtle ? await get256FileHash(file) : null;
                                     const xhr = new XMLHttpRequest();
                                     const formData = new FormData();
                                     formData.append('file', file);
@@ -1026,8 +2672,10 @@ fn page_header(
                                         xhr.addEventListener("abort", onAbort);
                                         xhr.upload.addEventListener('progress', onProgress);
                                         xhr.open('post', form.getAttribute("action"), true);
-                                        xhr.setRequestHeader('X-File-Hash', fileHash);
-                                        xhr.setRequestHeader('X-File-Hash-Function', 'SHA256');
+                                        if (fileHash) {
+                                            xhr.setRequestHeader('X-File-Hash', fileHash);
+                                            xhr.setRequestHeader('X-File-Hash-Function', 'SHA256');
+                                        }
                                         xhr.send(formData);
                                     }
                                 })
@@ -1041,30 +2689,104 @@ fn page_header(
     }
 }
 
-/// Converts a SystemTime object to a strings tuple (date, time)
-fn convert_to_local(src_time: Option<SystemTime>) -> Option<String> {
-    src_time
-        .map(DateTime::<Local>::from)
-        .map(|date_time| date_time.format("%Y-%m-%d %H:%M:%S %:z").to_string())
+/// Renders an error page, from the template given for this status code if there is one
+///
+/// Templates of --error-pages come first, then the error template of --template-dir. The page of
+/// --error-404 is served before either is looked at.
+pub fn render_error(
+    error_description: &str,
+    error_code: StatusCode,
+    conf: &MiniserveConfig,
+    return_address: &str,
+    texts: &Texts,
+) -> String {
+    let template = read_template(
+        conf.error_pages.as_deref(),
+        &format!("{}.html", error_code.as_str()),
+    )
+    .or_else(|| read_template(conf.template_dir.as_deref(), "error.html"));
+    if let Some(template) = template {
+        return fill_error_template(&template, error_description, error_code, return_address);
+    }
+
+    builtin_error(error_description, error_code, conf, return_address, texts).into_string()
+}
+
+/// Reads the template of this name from a directory of templates, if there's one
+///
+/// Templates are read again for every page, so they can be edited while miniserve is running.
+fn read_template(dir: Option<&Path>, name: &str) -> Option<String> {
+    let template_path = dir?.join(name);
+    match std::fs::read_to_string(&template_path) {
+        Ok(template) => Some(template),
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => None,
+        Err(err) => {
+            log::error!("Failed to read template {template_path:?}: {err}");
+            None
+        }
+    }
+}
+
+/// Substitutes the `{{variable}}` placeholders of an error page template
+///
+/// Values are HTML-escaped, as the error description can contain parts of the request.
+fn fill_error_template(
+    template: &str,
+    error_description: &str,
+    error_code: StatusCode,
+    return_address: &str,
+) -> String {
+    fill_template(template, |name| {
+        let raw = match name {
+            "status_code" => error_code.as_str(),
+            "reason" => error_code.canonical_reason().unwrap_or_default(),
+            "message" => error_description,
+            "return_address" => return_address,
+            _ => return None,
+        };
+        Some(html! { (raw) }.into_string())
+    })
 }
 
-/// Converts a SystemTime to a string readable by a human,
-/// and gives a rough approximation of the elapsed time since
-fn humanize_systemtime(time: Option<SystemTime>) -> Option<String> {
-    time.map(|time| time.humanize())
+/// Substitutes the `{{variable}}` placeholders of a template with the HTML `value` gives for them
+///
+/// Unknown placeholders are kept as they are.
+fn fill_template(template: &str, value: impl Fn(&str) -> Option<String>) -> String {
+    let mut filled = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        filled.push_str(&rest[..start]);
+        let placeholder = &rest[start..];
+        let substituted = placeholder.find("}}").and_then(|end| {
+            value(placeholder[2..end].trim()).map(|value| (value, &placeholder[end + 2..]))
+        });
+        match substituted {
+            Some((value, after)) => {
+                filled.push_str(&value);
+                rest = after;
+            }
+            None => {
+                filled.push_str("{{");
+                rest = &placeholder[2..];
+            }
+        }
+    }
+    filled.push_str(rest);
+    filled
 }
 
-/// Renders an error on the webpage
-pub fn render_error(
+/// Renders the built-in error page
+fn builtin_error(
     error_description: &str,
     error_code: StatusCode,
     conf: &MiniserveConfig,
     return_address: &str,
+    texts: &Texts,
 ) -> Markup {
     html! {
         (DOCTYPE)
-        html {
-            (page_header(&error_code.to_string(), false, conf.web_upload_concurrency, &conf.api_route, &conf.favicon_route, &conf.css_route))
+        html lang=(texts.code) {
+            (page_header(&error_code.to_string(), false, conf.web_upload_concurrency, &conf.api_route, &conf.favicon_route, &conf.css_route, texts))
 
             body
             {
@@ -1077,7 +2799,7 @@ pub fn render_error(
                     @if conf.route_prefix.is_empty() && !conf.disable_indexing {
                         div.error-nav {
                             a.error-back href=(return_address) {
-                                "Go back to file listing"
+                                (texts.back_to_listing)
                             }
                         }
                     }
@@ -1088,6 +2810,7 @@ pub fn render_error(
 
                     }
                 }
+                (custom_script(conf.custom_js.as_deref()))
             }
         }
     }
@@ -1096,6 +2819,7 @@ pub fn render_error(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::i18n::Lang;
     use pretty_assertions::assert_eq;
 
     fn to_html(wget_part: &str) -> String {
@@ -1104,14 +2828,34 @@ mod tests {
         )
     }
 
+    #[test]
+    fn test_fill_error_template() {
+        let filled = fill_error_template(
+            "<h1>{{ status_code }} {{reason}}</h1><p>{{message}}</p>{{unknown}}{{",
+            "Route <script> could not be found",
+            StatusCode::NOT_FOUND,
+            "/",
+        );
+        assert_eq!(
+            filled,
+            "<h1>404 Not Found</h1><p>Route &lt;script&gt; could not be found</p>{{unknown}}{{"
+        );
+    }
+
     fn uri(x: &str) -> Uri {
         Uri::try_from(x).unwrap()
     }
 
     #[test]
     fn test_wget_footer_trivial() {
-        let to_be_tested: String =
-            wget_footer(&uri("https://github.com/"), None, None, None).into();
+        let to_be_tested: String = wget_footer(
+            &uri("https://github.com/"),
+            None,
+            None,
+            None,
+            Lang::En.texts(),
+        )
+        .into();
         let expected = to_html("-nH -P 'github.com' 'https://github.com");
         assert_eq!(to_be_tested, expected);
     }
@@ -1123,6 +2867,7 @@ mod tests {
             Some("Miniserve"),
             None,
             None,
+            Lang::En.texts(),
         )
         .into();
         let expected = to_html("-nH --cut-dirs=1 'https://github.com/svenstaro/miniserve");
@@ -1136,6 +2881,7 @@ mod tests {
             Some("1&1 - Willkommen!!!"),
             Some("Marcell D'Avis"),
             None,
+            Lang::En.texts(),
         )
         .into();
         let expected = to_html(
@@ -1151,6 +2897,7 @@ mod tests {
             Some("Streng Geheim!!!"),
             Some("uøý`¶'7ÅÛé"),
             None,
+            Lang::En.texts(),
         )
         .into();
         let expected = to_html(
@@ -1161,8 +2908,14 @@ mod tests {
 
     #[test]
     fn test_wget_footer_ip() {
-        let to_be_tested: String =
-            wget_footer(&uri("http://127.0.0.1:420/"), None, None, None).into();
+        let to_be_tested: String = wget_footer(
+            &uri("http://127.0.0.1:420/"),
+            None,
+            None,
+            None,
+            Lang::En.texts(),
+        )
+        .into();
         let expected = to_html("-nH -P '127.0.0.1:420' 'http://127.0.0.1:420");
         assert_eq!(to_be_tested, expected);
     }
@@ -1174,6 +2927,7 @@ mod tests {
             None,
             None,
             Some("https://gitlab.com"),
+            Lang::En.texts(),
         )
         .into();
         let expected = to_html("-H -P 'github.com' 'https://github.com");
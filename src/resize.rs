@@ -0,0 +1,416 @@
+//! Scaled down variants of images, served for '?w=<width>' and '?thumb=1' with --resize-images
+//!
+//! JPEG images are served as JPEG images, and PNG images as PNG images. Images that can't be
+//! decoded are served as they are. With --cache-dir, scaled down images are also written to a
+//! directory, so that they outlive restarts.
+use std::collections::VecDeque;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use actix_web::{
+    HttpResponse,
+    body::{EitherBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    http::{Method, header},
+    middleware::Next,
+    web::{self, Bytes},
+};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use crate::{MiniserveConfig, exif, file_utils, jpeg, png};
+
+/// Width of the images served for '?thumb=1'
+pub const THUMBNAIL_WIDTH: usize = 320;
+
+/// Widths can't be requested beyond this
+const MAX_WIDTH: usize = 4096;
+
+/// Images with more pixels than this are served as they are, rather than decoded in memory
+///
+/// The decoders refuse them as soon as they've read their size, before allocating anything.
+const MAX_PIXELS: usize = 64 * 1024 * 1024;
+
+/// Quality of the JPEG images made
+const JPEG_QUALITY: u8 = 85;
+
+/// Decoded pixels, with 1 to 4 channels of a byte: gray, gray and alpha, RGB, or RGBA
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub channels: usize,
+    pub pixels: Vec<u8>,
+}
+
+impl Image {
+    /// Scales the image down to `width`, averaging the pixels each pixel covers
+    fn resize(&self, width: usize) -> Image {
+        let height = (self.height * width).div_ceil(self.width).max(1);
+        let channels = self.channels;
+        let mut pixels = vec![0; width * height * channels];
+        let mut sums = vec![0u32; width * channels];
+        let mut counts = vec![0u32; width];
+        for y in 0..height {
+            let (sy0, sy1) = span(y, height, self.height);
+            sums.iter_mut().for_each(|sum| *sum = 0);
+            counts.iter_mut().for_each(|count| *count = 0);
+            for sy in sy0..sy1 {
+                let row = &self.pixels[sy * self.width * channels..][..self.width * channels];
+                for x in 0..width {
+                    let (sx0, sx1) = span(x, width, self.width);
+                    for pixel in row[sx0 * channels..sx1 * channels].chunks_exact(channels) {
+                        for (sum, &value) in sums[x * channels..].iter_mut().zip(pixel) {
+                            *sum += value as u32;
+                        }
+                    }
+                    counts[x] += (sx1 - sx0) as u32;
+                }
+            }
+            let out = &mut pixels[y * width * channels..][..width * channels];
+            for (i, value) in out.iter_mut().enumerate() {
+                let count = counts[i / channels];
+                *value = ((sums[i] + count / 2) / count) as u8;
+            }
+        }
+        Image {
+            width,
+            height,
+            channels,
+            pixels,
+        }
+    }
+
+    /// Turns the image as described by an EXIF orientation, which photos are usually taken with
+    fn orient(self, orientation: u16) -> Image {
+        if !(2..=8).contains(&orientation) {
+            return self;
+        }
+        let (w, h) = (self.width, self.height);
+        let (width, height) = if orientation >= 5 { (h, w) } else { (w, h) };
+        let mut pixels = Vec::with_capacity(self.pixels.len());
+        for y in 0..height {
+            for x in 0..width {
+                let (sx, sy) = match orientation {
+                    2 => (w - 1 - x, y),
+                    3 => (w - 1 - x, h - 1 - y),
+                    4 => (x, h - 1 - y),
+                    5 => (y, x),
+                    6 => (y, h - 1 - x),
+                    7 => (w - 1 - y, h - 1 - x),
+                    _ => (w - 1 - y, x),
+                };
+                let i = (sy * w + sx) * self.channels;
+                pixels.extend_from_slice(&self.pixels[i..i + self.channels]);
+            }
+        }
+        Image {
+            width,
+            height,
+            channels: self.channels,
+            pixels,
+        }
+    }
+}
+
+/// The range of source pixels the `i`th of `count` pixels covers, out of `source` pixels
+fn span(i: usize, count: usize, source: usize) -> (usize, usize) {
+    let start = i * source / count;
+    let end = ((i + 1) * source / count).max(start + 1).min(source);
+    (start, end)
+}
+
+/// Scales down an image file to `width`, returning its content type and data
+///
+/// `None` is returned for images which can't be decoded, or which are narrower already.
+pub fn resize_file(path: &Path, width: usize) -> Option<(mime::Mime, Vec<u8>)> {
+    let data = std::fs::read(path).ok()?;
+    let (image, jpeg) = if exif::is_jpeg(&path.to_string_lossy()) {
+        let image = jpeg::decode(&data, MAX_PIXELS)?;
+        let orientation = exif::orientation(&data).unwrap_or(1);
+        (image.orient(orientation), true)
+    } else {
+        (png::decode(&data, MAX_PIXELS)?, false)
+    };
+    if width >= image.width {
+        return None;
+    }
+    let resized = image.resize(width);
+    Some(if jpeg {
+        (mime::IMAGE_JPEG, jpeg::encode(&resized, JPEG_QUALITY))
+    } else {
+        (mime::IMAGE_PNG, png::encode(&resized))
+    })
+}
+
+/// Whether a file can be scaled down, going by its name
+pub fn is_resizable(file_name: &str) -> bool {
+    exif::is_jpeg(file_name)
+        || Path::new(file_name)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("png"))
+}
+
+/// An image scaled down to a width, as it was when last modified
+type Key = (PathBuf, Option<SystemTime>, usize);
+
+/// Scaled down images kept in memory, the least recently used of which are dropped first
+///
+/// With a directory, they're also kept there, up to `max_dir_size` bytes. The files used least
+/// recently are removed first there too.
+pub struct ResizeCache {
+    entries: Mutex<VecDeque<(Key, mime::Mime, Bytes)>>,
+    max_size: usize,
+    dir: Option<PathBuf>,
+    max_dir_size: u64,
+}
+
+impl Default for ResizeCache {
+    fn default() -> Self {
+        Self::new(None, 0)
+    }
+}
+
+impl ResizeCache {
+    pub fn new(dir: Option<PathBuf>, max_dir_size: u64) -> Self {
+        Self {
+            entries: Mutex::default(),
+            max_size: 64 * 1024 * 1024,
+            dir,
+            max_dir_size,
+        }
+    }
+
+    /// Forgets the images kept in memory, leaving the cache directory alone
+    pub fn clear(&self) {
+        self.entries.lock().expect("Resize cache poisoned").clear();
+    }
+
+    fn get(&self, key: &Key) -> Option<(mime::Mime, Bytes)> {
+        let mut entries = self.entries.lock().expect("Resize cache poisoned");
+        let index = entries.iter().position(|(k, ..)| k == key)?;
+        let entry = entries.remove(index)?;
+        let found = (entry.1.clone(), entry.2.clone());
+        entries.push_back(entry);
+        Some(found)
+    }
+
+    fn insert(&self, key: Key, mime: mime::Mime, data: Bytes) {
+        let mut entries = self.entries.lock().expect("Resize cache poisoned");
+        entries.retain(|(k, ..)| *k != key);
+        entries.push_back((key, mime, data));
+        let mut size: usize = entries.iter().map(|(.., data)| data.len()).sum();
+        while size > self.max_size {
+            let Some((.., data)) = entries.pop_front() else {
+                break;
+            };
+            size -= data.len();
+        }
+    }
+
+    /// Gets an image file scaled down to `width`, from the cache if it's been scaled already
+    pub async fn resized(&self, path: PathBuf, width: usize) -> Option<(mime::Mime, Bytes)> {
+        let modified = std::fs::metadata(&path)
+            .and_then(|metadata| metadata.modified())
+            .ok();
+        let key = (path.clone(), modified, width);
+        if let Some(found) = self.get(&key) {
+            return Some(found);
+        }
+        let dir = self.dir.clone();
+        let max_dir_size = self.max_dir_size;
+        let cache_key = key.clone();
+        let (mime, data) = web::block(move || match dir {
+            Some(dir) => resize_file_cached(&dir, max_dir_size, &cache_key),
+            None => resize_file(&path, width),
+        })
+        .await
+        .ok()??;
+        let data = Bytes::from(data);
+        self.insert(key, mime.clone(), data.clone());
+        Some((mime, data))
+    }
+}
+
+/// Gets an image file scaled down from the cache directory, or scales it down and writes it there
+fn resize_file_cached(dir: &Path, max_dir_size: u64, key: &Key) -> Option<(mime::Mime, Vec<u8>)> {
+    let (path, _, width) = key;
+    let (mime, extension) = if exif::is_jpeg(&path.to_string_lossy()) {
+        (mime::IMAGE_JPEG, "jpg")
+    } else {
+        (mime::IMAGE_PNG, "png")
+    };
+    let file = dir.join(format!("{}.{extension}", cache_name(key)));
+    if let Ok(data) = fs::read(&file) {
+        // Files are removed in the order they were last used in
+        let _ = fs::File::options()
+            .write(true)
+            .open(&file)
+            .and_then(|file| file.set_modified(SystemTime::now()));
+        return Some((mime, data));
+    }
+
+    let (mime, data) = resize_file(path, *width)?;
+    if data.len() as u64 <= max_dir_size {
+        // Written aside first, so that no half written files are served when several requests
+        // scale the same image down at once
+        let partial = file.with_extension("part");
+        let stored = fs::write(&partial, &data).and_then(|_| fs::rename(&partial, &file));
+        match stored {
+            Ok(()) => evict(dir, max_dir_size),
+            Err(e) => log::error!("Failed to write {file:?} to the cache directory: {e}"),
+        }
+    }
+    Some((mime, data))
+}
+
+/// Name of the file an image scaled down is kept in, which changes when the image is modified
+fn cache_name((path, modified, width): &Key) -> String {
+    let modified = modified
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .map(|since| since.as_nanos())
+        .unwrap_or_default();
+    let mut hasher = Sha256::new();
+    hasher.update(path.as_os_str().as_encoded_bytes());
+    hasher.update(modified.to_le_bytes());
+    hasher.update(width.to_le_bytes());
+    hex::encode(hasher.finalize())
+}
+
+/// Removes the files of the cache directory used least recently until it's within `max_size`
+fn evict(dir: &Path, max_size: u64) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    let mut files: Vec<_> = entries
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let metadata = entry
+                .metadata()
+                .ok()
+                .filter(|metadata| metadata.is_file())?;
+            Some((metadata.modified().ok()?, metadata.len(), entry.path()))
+        })
+        .collect();
+    let mut size: u64 = files.iter().map(|(_, len, _)| len).sum();
+    files.sort();
+    for (_, len, file) in files {
+        if size <= max_size {
+            break;
+        }
+        if fs::remove_file(&file).is_ok() {
+            size -= len;
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ResizeQuery {
+    w: Option<usize>,
+    thumb: Option<u8>,
+}
+
+/// Middleware answering requests for images with '?w=<width>' or '?thumb=1' with scaled down
+/// variants of them
+pub async fn resize_middleware(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, actix_web::Error> {
+    let conf = req
+        .app_data::<web::Data<MiniserveConfig>>()
+        .expect("Could not get miniserve config")
+        .clone();
+
+    let width = web::Query::<ResizeQuery>::from_query(req.query_string())
+        .ok()
+        .and_then(|query| match (query.w, query.thumb) {
+            (Some(width), _) => Some(width.clamp(1, MAX_WIDTH)),
+            (None, Some(thumb)) if thumb > 0 => Some(THUMBNAIL_WIDTH),
+            _ => None,
+        });
+    let file = (conf.resize_images && req.method() == Method::GET)
+        .then(|| file_utils::requested_file(&conf, req.match_info().unprocessed()))
+        .flatten()
+        .filter(|file| is_resizable(&file.to_string_lossy()));
+    let resized = match (file, width) {
+        (Some(file), Some(width)) => {
+            let cache = req
+                .app_data::<web::Data<ResizeCache>>()
+                .expect("Could not get resize cache")
+                .clone();
+            cache.resized(file, width).await
+        }
+        _ => None,
+    };
+    let Some((mime, data)) = resized else {
+        return Ok(next.call(req).await?.map_into_left_body());
+    };
+
+    let res = HttpResponse::Ok()
+        .content_type(mime)
+        .insert_header((header::CACHE_CONTROL, "max-age=3600"))
+        .body(data);
+    Ok(req.into_response(res).map_into_right_body())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn averages_pixels() {
+        let image = Image {
+            width: 4,
+            height: 2,
+            channels: 1,
+            pixels: vec![0, 100, 200, 200, 100, 200, 0, 0],
+        };
+        let resized = image.resize(2);
+        assert_eq!((resized.width, resized.height), (2, 1));
+        assert_eq!(resized.pixels, [100, 100]);
+    }
+
+    #[test]
+    fn turns_images() {
+        // 1 2 3
+        // 4 5 6
+        let image = || Image {
+            width: 3,
+            height: 2,
+            channels: 1,
+            pixels: vec![1, 2, 3, 4, 5, 6],
+        };
+        let turned = image().orient(6);
+        assert_eq!((turned.width, turned.height), (2, 3));
+        assert_eq!(turned.pixels, [4, 1, 5, 2, 6, 3]);
+        assert_eq!(image().orient(8).pixels, [3, 6, 2, 5, 1, 4]);
+        assert_eq!(image().orient(3).pixels, [6, 5, 4, 3, 2, 1]);
+        assert_eq!(image().orient(1).pixels, [1, 2, 3, 4, 5, 6]);
+    }
+
+    #[test]
+    fn evicts_files_used_least_recently() {
+        let dir = tempfile::tempdir().unwrap();
+        let now = SystemTime::now();
+        for (i, name) in ["a.png", "b.png", "c.png"].iter().enumerate() {
+            let file = dir.path().join(name);
+            fs::write(&file, [0; 10]).unwrap();
+            let used = now - std::time::Duration::from_secs(60 * (3 - i as u64));
+            fs::File::options()
+                .write(true)
+                .open(&file)
+                .and_then(|file| file.set_modified(used))
+                .unwrap();
+        }
+        evict(dir.path(), 25);
+        let mut left: Vec<_> = fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        left.sort();
+        assert_eq!(left, ["b.png", "c.png"]);
+    }
+}
@@ -0,0 +1,102 @@
+//! Restricting file system access to the files being served, using Landlock
+use std::path::PathBuf;
+
+use landlock::{
+    ABI, Access, AccessFs, RestrictionStatus, Ruleset, RulesetAttr, RulesetCreatedAttr,
+    RulesetError, RulesetStatus, path_beneath_rules,
+};
+use log::warn;
+
+use crate::{config::MiniserveConfig, errors::StartupError};
+
+/// Landlock version whose access rights are handled, older kernels handling a subset of them
+const ABI_VERSION: ABI = ABI::V5;
+
+/// Where the local time zone is read from, which dates in listings are shown in
+const TIME_ZONE_PATHS: [&str; 2] = ["/etc/localtime", "/usr/share/zoneinfo"];
+
+/// Restricts the current thread, and the threads it starts from now on, to the files being served
+///
+/// Besides the served path, the files read while serving or reloading the configuration stay
+/// readable. Writing is only allowed within the served path and the temporary upload directory,
+/// when uploads or directory creation are enabled, or within the directory --receive writes to.
+/// The pid file and Unix socket may be removed.
+pub fn restrict(conf: &MiniserveConfig) -> Result<(), StartupError> {
+    let mut readable = vec![conf.path.clone()];
+    readable.extend(conf.aliases.iter().map(|(_, path)| path.clone()));
+    readable.extend(conf.error_404.iter().cloned());
+    readable.extend(conf.error_pages.iter().cloned());
+    readable.extend(conf.template_dir.iter().cloned());
+    readable.extend(conf.reloadable_files().map(PathBuf::from));
+    readable.extend(TIME_ZONE_PATHS.iter().map(PathBuf::from));
+
+    let mut writable = vec![];
+    if conf.file_upload || conf.mkdir_enabled {
+        writable.push(conf.path.clone());
+        writable.push(
+            conf.temp_upload_directory
+                .clone()
+                .unwrap_or_else(std::env::temp_dir),
+        );
+    }
+
+    if let Some(dest) = &conf.receive {
+        let dir = match dest.parent() {
+            _ if dest.is_dir() => dest.clone(),
+            Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+        writable.push(dir);
+        writable.push(
+            conf.temp_upload_directory
+                .clone()
+                .unwrap_or_else(std::env::temp_dir),
+        );
+    }
+
+    // Files created on startup are removed on exit
+    let removable = conf
+        .pid_file
+        .iter()
+        .chain(conf.listen_unix.iter().map(|(path, _)| path))
+        .map(|path| match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+            _ => PathBuf::from("."),
+        })
+        .collect::<Vec<_>>();
+
+    let status = restrict_self(&readable, &writable, &removable)
+        .map_err(|e| StartupError::SandboxError(e.to_string()))?;
+    match status.ruleset {
+        RulesetStatus::FullyEnforced => Ok(()),
+        RulesetStatus::PartiallyEnforced => {
+            warn!(
+                "File system access is only partially restricted, as the kernel lacks some Landlock features"
+            );
+            Ok(())
+        }
+        RulesetStatus::NotEnforced => Err(StartupError::SandboxError(
+            "Landlock isn't supported or enabled by the kernel".to_string(),
+        )),
+    }
+}
+
+fn restrict_self(
+    readable: &[PathBuf],
+    writable: &[PathBuf],
+    removable: &[PathBuf],
+) -> Result<RestrictionStatus, RulesetError> {
+    Ruleset::default()
+        .handle_access(AccessFs::from_all(ABI_VERSION))?
+        .create()?
+        .add_rules(path_beneath_rules(
+            readable,
+            AccessFs::from_read(ABI_VERSION),
+        ))?
+        .add_rules(path_beneath_rules(
+            writable,
+            AccessFs::from_all(ABI_VERSION),
+        ))?
+        .add_rules(path_beneath_rules(removable, AccessFs::RemoveFile))?
+        .restrict_self()
+}
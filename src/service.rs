@@ -0,0 +1,198 @@
+//! Running as a Windows service
+//!
+//! Services are started by the service control manager, which hands them over a thread of their
+//! own to run on and asks them to stop through a callback. The server is run on that thread just
+//! like it would be otherwise, and stopped through its handle.
+use std::ffi::{OsStr, OsString};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use actix_web::dev::ServerHandle;
+use anyhow::{Context, Result};
+use log::{error, info};
+use windows_service::{
+    define_windows_service,
+    service::{
+        ServiceAccess, ServiceControl, ServiceControlAccept, ServiceErrorControl, ServiceExitCode,
+        ServiceInfo, ServiceStartType, ServiceState, ServiceStatus, ServiceType,
+    },
+    service_control_handler::{self, ServiceControlHandlerResult, ServiceStatusHandle},
+    service_dispatcher,
+    service_manager::{ServiceManager, ServiceManagerAccess},
+};
+
+use crate::config::MiniserveConfig;
+
+/// Name of the service, which also is the source of its event log entries
+const SERVICE_NAME: &str = "miniserve";
+
+/// Configuration to serve with, handed over to the thread running the service
+static CONFIG: OnceLock<MiniserveConfig> = OnceLock::new();
+
+/// Handle of the server once it runs, which is how the service gets stopped
+pub static SERVER_HANDLE: OnceLock<ServerHandle> = OnceLock::new();
+
+/// Handle through which the state of the service is reported, once registered
+static STATUS_HANDLE: OnceLock<ServiceStatusHandle> = OnceLock::new();
+
+/// How long stopping may take, which is how long actix-web waits for running requests by default
+const STOP_WAIT_HINT: Duration = Duration::from_secs(30);
+
+/// Registers and starts a service serving with the arguments miniserve was given
+pub fn install() -> Result<()> {
+    let manager = ServiceManager::local_computer(
+        None::<&str>,
+        ServiceManagerAccess::CONNECT | ServiceManagerAccess::CREATE_SERVICE,
+    )
+    .context("Failed to connect to the service control manager")?;
+    let service_info = ServiceInfo {
+        name: SERVICE_NAME.into(),
+        display_name: SERVICE_NAME.into(),
+        service_type: ServiceType::OWN_PROCESS,
+        start_type: ServiceStartType::AutoStart,
+        error_control: ServiceErrorControl::Normal,
+        executable_path: std::env::current_exe()?,
+        launch_arguments: service_arguments(),
+        dependencies: vec![],
+        account_name: None, // LocalSystem
+        account_password: None,
+    };
+    let service = manager
+        .create_service(
+            &service_info,
+            ServiceAccess::CHANGE_CONFIG | ServiceAccess::START,
+        )
+        .context("Failed to install the service")?;
+    service.set_description(env!("CARGO_PKG_DESCRIPTION"))?;
+    eventlog::register(SERVICE_NAME).context("Failed to register the event log source")?;
+    service
+        .start::<&OsStr>(&[])
+        .context("Failed to start the service")?;
+
+    println!("Installed and started the {SERVICE_NAME} service");
+    Ok(())
+}
+
+/// Stops and removes the service
+pub fn uninstall() -> Result<()> {
+    let manager = ServiceManager::local_computer(None::<&str>, ServiceManagerAccess::CONNECT)
+        .context("Failed to connect to the service control manager")?;
+    let service = manager
+        .open_service(
+            SERVICE_NAME,
+            ServiceAccess::QUERY_STATUS | ServiceAccess::STOP | ServiceAccess::DELETE,
+        )
+        .context("Failed to open the service")?;
+    // The service is only removed once stopped
+    service
+        .delete()
+        .context("Failed to uninstall the service")?;
+    if service.query_status()?.current_state != ServiceState::Stopped {
+        service.stop().context("Failed to stop the service")?;
+    }
+    eventlog::deregister(SERVICE_NAME).context("Failed to remove the event log source")?;
+
+    println!("Uninstalled the {SERVICE_NAME} service");
+    Ok(())
+}
+
+/// Serves as the service, returning once it's stopped
+///
+/// This fails unless miniserve was started by the service control manager.
+pub fn run(config: MiniserveConfig) -> Result<()> {
+    let _ = CONFIG.set(config);
+    service_dispatcher::start(SERVICE_NAME, ffi_service_main)
+        .context("Failed to connect to the service control manager")
+}
+
+define_windows_service!(ffi_service_main, service_main);
+
+fn service_main(_arguments: Vec<OsString>) {
+    if let Err(e) = run_service() {
+        error!("{e:#}");
+    }
+}
+
+/// Reports the service as running for as long as the server runs
+fn run_service() -> Result<()> {
+    let config = CONFIG.get().context("Missing configuration")?.clone();
+    let log_level = if config.verbose {
+        log::Level::Info
+    } else {
+        log::Level::Warn
+    };
+    // There's nowhere else to report this to
+    let _ = eventlog::init(SERVICE_NAME, log_level);
+
+    let reload_config = config.clone();
+    let status_handle = service_control_handler::register(SERVICE_NAME, move |control| {
+        match control {
+            ServiceControl::Stop | ServiceControl::Shutdown => {
+                // Stopping waits for running requests, which shouldn't be mistaken for a hang
+                if let Some(status_handle) = STATUS_HANDLE.get() {
+                    let _ = status_handle.set_service_status(ServiceStatus {
+                        wait_hint: STOP_WAIT_HINT,
+                        ..status(ServiceState::StopPending, ServiceControlAccept::empty(), 0)
+                    });
+                }
+                if let Some(handle) = SERVER_HANDLE.get() {
+                    futures::executor::block_on(handle.stop(true));
+                }
+            }
+            ServiceControl::ParamChange => match reload_config.reload() {
+                Ok(()) => info!("Reloaded the configuration"),
+                Err(e) => error!("Failed to reload the configuration: {e:#}"),
+            },
+            ServiceControl::Interrogate => {}
+            _ => return ServiceControlHandlerResult::NotImplemented,
+        }
+        ServiceControlHandlerResult::NoError
+    })?;
+    let _ = STATUS_HANDLE.set(status_handle);
+
+    status_handle.set_service_status(status(
+        ServiceState::Running,
+        ServiceControlAccept::STOP
+            | ServiceControlAccept::SHUTDOWN
+            | ServiceControlAccept::PARAM_CHANGE,
+        0,
+    ))?;
+    let result = crate::run(config);
+    status_handle.set_service_status(status(
+        ServiceState::Stopped,
+        ServiceControlAccept::empty(),
+        u32::from(result.is_err()),
+    ))?;
+
+    Ok(result?)
+}
+
+fn status(state: ServiceState, accepted: ServiceControlAccept, exit_code: u32) -> ServiceStatus {
+    ServiceStatus {
+        service_type: ServiceType::OWN_PROCESS,
+        current_state: state,
+        controls_accepted: accepted,
+        exit_code: ServiceExitCode::Win32(exit_code),
+        checkpoint: 0,
+        wait_hint: Duration::default(),
+        process_id: None,
+    }
+}
+
+/// Arguments miniserve was given, asking the service to run rather than to be installed
+fn service_arguments() -> Vec<OsString> {
+    let mut arguments = vec![];
+    let mut skip_value = false;
+    for argument in std::env::args_os().skip(1) {
+        if std::mem::take(&mut skip_value) {
+            continue;
+        }
+        match argument.to_str() {
+            Some("--service") => skip_value = true,
+            Some(a) if a.starts_with("--service=") => {}
+            _ => arguments.push(argument),
+        }
+    }
+    arguments.extend(["--service".into(), "run".into()]);
+    arguments
+}
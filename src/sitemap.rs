@@ -0,0 +1,86 @@
+//! Sitemap of the served files and directories, served with --sitemap
+//!
+//! It's at `sitemap.xml` under the route prefix, unless the served directory holds a file of
+//! that name, which is served instead. Like every page under a random route, it's only found by
+//! those who know the route.
+use std::time::SystemTime;
+
+use actix_web::{
+    HttpResponse,
+    body::{EitherBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    http::Method,
+    middleware::Next,
+    web,
+};
+use chrono::{DateTime, SecondsFormat, Utc};
+use maud::{Markup, PreEscaped, html};
+
+use crate::{MiniserveConfig, file_utils};
+
+/// Name of the sitemap
+const SITEMAP_FILE: &str = "sitemap.xml";
+
+/// Number of URLs sitemaps may hold at most
+const MAX_SITEMAP_URLS: usize = 50_000;
+
+/// Answers requests of `sitemap.xml` at the root with the sitemap
+pub async fn sitemap_middleware(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, actix_web::Error> {
+    let conf = req
+        .app_data::<web::Data<MiniserveConfig>>()
+        .expect("Could not get miniserve config")
+        .clone();
+
+    let requested = conf.sitemap
+        && req.method() == Method::GET
+        && !conf.disable_indexing
+        && req.match_info().unprocessed() == format!("/{SITEMAP_FILE}")
+        && conf.path.is_dir()
+        && !conf.path.join(SITEMAP_FILE).exists();
+    if !requested {
+        return Ok(next.call(req).await?.map_into_left_body());
+    }
+
+    let entries = web::block({
+        let conf = conf.clone();
+        move || file_utils::served_entries(&conf, &conf.path)
+    })
+    .await?;
+
+    let root_url = {
+        let info = req.connection_info();
+        let path = req.path().strip_suffix(SITEMAP_FILE).unwrap_or_default();
+        format!("{}://{}{}", info.scheme(), info.host(), path)
+    };
+    let res = HttpResponse::Ok()
+        .content_type("application/xml; charset=utf-8")
+        .body(render(&root_url, &entries).into_string());
+    Ok(req.into_response(res).map_into_right_body())
+}
+
+/// W3C date of a time, as sitemaps have them
+fn lastmod(time: SystemTime) -> String {
+    DateTime::<Utc>::from(time).to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// Renders the sitemap of the entries below `root_url`, starting with the root itself
+fn render(root_url: &str, entries: &[file_utils::ServedEntry]) -> Markup {
+    let entries = entries.iter().take(MAX_SITEMAP_URLS - 1);
+    html! {
+        (PreEscaped(r#"<?xml version="1.0" encoding="utf-8"?>"#))
+        urlset xmlns="http://www.sitemaps.org/schemas/sitemap/0.9" {
+            url { loc { (root_url) } }
+            @for entry in entries {
+                url {
+                    loc { (root_url) (file_utils::encode_path(&entry.path)) }
+                    @if let Ok(modified) = entry.metadata.modified() {
+                        lastmod { (lastmod(modified)) }
+                    }
+                }
+            }
+        }
+    }
+}
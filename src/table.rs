@@ -0,0 +1,80 @@
+//! Tables of CSV and TSV files, shown in previews
+use std::path::Path;
+
+/// Files larger than this are previewed as text rather than as a table
+pub const MAX_TABLE_SIZE: u64 = 256 * 1024;
+
+/// The delimiter of the fields of a file, if it's a CSV or TSV file going by its name
+pub fn delimiter(file_name: &str) -> Option<char> {
+    let extension = Path::new(file_name).extension()?.to_str()?.to_lowercase();
+    match extension.as_str() {
+        "csv" => Some(','),
+        "tsv" => Some('\t'),
+        _ => None,
+    }
+}
+
+/// Splits delimited text into rows of fields
+///
+/// Fields may be quoted as in RFC 4180, in which case they can hold delimiters, line breaks and
+/// doubled quotes.
+pub fn parse(contents: &str, delimiter: char) -> Vec<Vec<String>> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = contents.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted => {
+                if chars.peek() == Some(&'"') {
+                    chars.next();
+                    field.push('"');
+                } else {
+                    quoted = false;
+                }
+            }
+            '"' if field.is_empty() => quoted = true,
+            c if quoted => field.push(c),
+            c if c == delimiter => row.push(std::mem::take(&mut field)),
+            '\r' if chars.peek() == Some(&'\n') => {}
+            '\n' => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            c => field.push(c),
+        }
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn parses_quoted_fields() {
+        let csv = "name,note\r\n\"Doe, John\",\"said \"\"hi\"\"\nthen left\"\nJane,\n";
+        assert_eq!(
+            parse(csv, ','),
+            vec![
+                vec!["name", "note"],
+                vec!["Doe, John", "said \"hi\"\nthen left"],
+                vec!["Jane", ""],
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_tabs_without_trailing_newline() {
+        assert_eq!(
+            parse("a\tb\n1\t2", '\t'),
+            vec![vec!["a", "b"], vec!["1", "2"]]
+        );
+    }
+}
@@ -0,0 +1,118 @@
+//! Thumbnails of images shown in listings with --thumbnails
+//!
+//! Photos usually carry a small JPEG thumbnail in their EXIF data, made by the camera, which is
+//! served when there is one. Other images are served as they are if they're small enough, and
+//! browsers scale them down. Larger ones are scaled down here, which takes longer.
+use std::io::Read;
+use std::path::Path;
+
+use actix_files::NamedFile;
+use actix_web::{
+    HttpResponse,
+    body::{EitherBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    http::{Method, header},
+    middleware::Next,
+    web,
+};
+use serde::Deserialize;
+
+use crate::{
+    MiniserveConfig, exif, file_utils,
+    resize::{self, ResizeCache},
+};
+
+/// Images without an embedded thumbnail are only served as their own thumbnail up to this size
+const MAX_FULL_IMAGE_SIZE: u64 = 512 * 1024;
+
+/// Extensions of images which get a thumbnail in listings
+const IMAGE_EXTENSIONS: &[&str] = &["jpg", "jpeg", "png", "gif", "webp", "avif", "bmp", "svg"];
+
+/// Whether a file gets a thumbnail, going by its name
+pub fn is_image(file_name: &str) -> bool {
+    Path::new(file_name)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| IMAGE_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+}
+
+/// Extensions of videos shown in galleries
+const VIDEO_EXTENSIONS: &[&str] = &["mp4", "m4v", "webm", "ogv", "mov"];
+
+/// Whether a file is a video browsers can play, going by its name
+pub fn is_video(file_name: &str) -> bool {
+    Path::new(file_name)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| VIDEO_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+}
+
+#[derive(Deserialize)]
+struct ThumbnailQuery {
+    thumbnail: Option<bool>,
+}
+
+/// Middleware answering requests for images with '?thumbnail=true' with their thumbnail
+///
+/// When an image has no thumbnail and is too large to stand for its own, it's scaled down. If it
+/// can't be, the answer is a 404, which listings handle by leaving the thumbnail out.
+pub async fn thumbnail_middleware(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, actix_web::Error> {
+    let conf = req
+        .app_data::<web::Data<MiniserveConfig>>()
+        .expect("Could not get miniserve config")
+        .clone();
+    let resize_cache = req
+        .app_data::<web::Data<ResizeCache>>()
+        .expect("Could not get resize cache")
+        .clone();
+
+    let thumbnail = web::Query::<ThumbnailQuery>::from_query(req.query_string())
+        .is_ok_and(|query| query.thumbnail == Some(true));
+    let file = (conf.thumbnails && req.method() == Method::GET && thumbnail)
+        .then(|| file_utils::requested_file(&conf, req.match_info().unprocessed()))
+        .flatten()
+        .filter(|file| is_image(&file.to_string_lossy()));
+    let Some(file) = file else {
+        return Ok(next.call(req).await?.map_into_left_body());
+    };
+
+    let embedded = {
+        let file = file.clone();
+        web::block(move || read_embedded_thumbnail(&file)).await?
+    };
+    let (req, _) = req.into_parts();
+    let res = match embedded {
+        Some(thumbnail) => HttpResponse::Ok()
+            .content_type(mime::IMAGE_JPEG)
+            .insert_header((header::CACHE_CONTROL, "max-age=3600"))
+            .body(thumbnail),
+        None if file
+            .metadata()
+            .is_ok_and(|metadata| metadata.len() <= MAX_FULL_IMAGE_SIZE) =>
+        {
+            NamedFile::open_async(&file).await?.into_response(&req)
+        }
+        None => match resize_cache.resized(file, resize::THUMBNAIL_WIDTH).await {
+            Some((mime, data)) => HttpResponse::Ok()
+                .content_type(mime)
+                .insert_header((header::CACHE_CONTROL, "max-age=3600"))
+                .body(data),
+            None => HttpResponse::NotFound().finish(),
+        },
+    };
+    Ok(ServiceResponse::new(req, res).map_into_right_body())
+}
+
+/// Reads the thumbnail embedded in the EXIF data of a JPEG file, if it has one
+fn read_embedded_thumbnail(path: &Path) -> Option<Vec<u8>> {
+    let mut head = Vec::new();
+    std::fs::File::open(path)
+        .ok()?
+        .take(exif::MAX_EXIF_READ)
+        .read_to_end(&mut head)
+        .ok()?;
+    exif::thumbnail(&head).map(<[u8]>::to_vec)
+}
@@ -0,0 +1,360 @@
+//! Torrents of served files and directories, served with --torrent
+//!
+//! They're answers to `?torrent=true` queries, and have the URL of the file or directory as a
+//! web seed (BEP 19), so that peers can always fetch pieces from miniserve. They have no tracker,
+//! peers find each other through the DHT.
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{Hash, Hasher};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use actix_web::{
+    HttpResponse,
+    body::{EitherBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    http::{
+        Method,
+        header::{ContentDisposition, DispositionParam, DispositionType},
+    },
+    middleware::Next,
+    web,
+};
+use serde::Deserialize;
+use sha1::{Digest, Sha1};
+
+use crate::{MiniserveConfig, file_utils};
+
+/// Length of pieces, unless torrents would have more than `MAX_PIECES` pieces
+const MIN_PIECE_LENGTH: u64 = 256 * 1024;
+
+/// Number of pieces torrents have at most, with longer pieces for larger contents
+const MAX_PIECES: u64 = 2048;
+
+/// Number of torrents whose pieces are kept in the cache at most
+const MAX_CACHED_TORRENTS: usize = 64;
+
+#[derive(Deserialize)]
+struct TorrentQuery {
+    torrent: Option<bool>,
+}
+
+/// A file of a torrent, with its path from the directory of the torrent
+struct TorrentFile {
+    path: Vec<String>,
+    file: PathBuf,
+    length: u64,
+    modified: Option<SystemTime>,
+}
+
+/// Hashes of the pieces of torrents made recently, so that their contents aren't read again
+///
+/// They're found by the path of the torrent and a fingerprint of the sizes and modification
+/// times of its files, so that changed contents are hashed again.
+#[derive(Default)]
+pub struct TorrentCache {
+    pieces: Mutex<HashMap<(PathBuf, u64), Vec<u8>>>,
+}
+
+impl TorrentCache {
+    /// Gets the SHA-1 hashes of the pieces of the files of the torrent of `path`, one after the
+    /// other
+    async fn pieces(
+        &self,
+        path: &Path,
+        files: &[TorrentFile],
+        piece_length: u64,
+    ) -> io::Result<Vec<u8>> {
+        let fingerprint = {
+            let mut hasher = DefaultHasher::new();
+            piece_length.hash(&mut hasher);
+            for file in files {
+                (&file.path, file.length, file.modified).hash(&mut hasher);
+            }
+            hasher.finish()
+        };
+        let key = (path.to_path_buf(), fingerprint);
+        let cached = self
+            .pieces
+            .lock()
+            .expect("Torrent cache poisoned")
+            .get(&key)
+            .cloned();
+        if let Some(pieces) = cached {
+            return Ok(pieces);
+        }
+
+        let files: Vec<_> = files
+            .iter()
+            .map(|file| (file.file.clone(), file.length))
+            .collect();
+        let pieces = web::block(move || hash_pieces(&files, piece_length))
+            .await
+            .map_err(io::Error::other)??;
+        let mut cached = self.pieces.lock().expect("Torrent cache poisoned");
+        cached.retain(|(path, _), _| *path != key.0);
+        if cached.len() >= MAX_CACHED_TORRENTS {
+            cached.clear();
+        }
+        cached.insert(key, pieces.clone());
+        Ok(pieces)
+    }
+
+    /// Forgets all torrents, so that their contents are hashed again
+    pub fn clear(&self) {
+        self.pieces.lock().expect("Torrent cache poisoned").clear();
+    }
+}
+
+/// Answers `?torrent=true` queries of files and directories with their torrent
+pub async fn torrent_middleware(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<EitherBody<impl MessageBody>>, actix_web::Error> {
+    let conf = req
+        .app_data::<web::Data<MiniserveConfig>>()
+        .expect("Could not get miniserve config")
+        .clone();
+    let cache = req
+        .app_data::<web::Data<TorrentCache>>()
+        .expect("Could not get torrent cache")
+        .clone();
+
+    let torrent = web::Query::<TorrentQuery>::from_query(req.query_string())
+        .is_ok_and(|query| query.torrent == Some(true));
+    let path = (conf.torrent && req.method() == Method::GET && torrent)
+        .then(|| requested_path(&conf, req.match_info().unprocessed()))
+        .flatten();
+    let Some(path) = path else {
+        return Ok(next.call(req).await?.map_into_left_body());
+    };
+
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let files = web::block({
+        let conf = conf.clone();
+        let path = path.clone();
+        move || torrent_files(&conf, &path)
+    })
+    .await??;
+    let length: u64 = files.iter().map(|file| file.length).sum();
+    let piece_length = piece_length(length);
+    let pieces = match cache.pieces(&path, &files, piece_length).await {
+        Ok(pieces) => pieces,
+        Err(e) => {
+            log::error!("Failed to hash the pieces of {path:?}: {e}");
+            let res = HttpResponse::InternalServerError().finish();
+            return Ok(req.into_response(res).map_into_right_body());
+        }
+    };
+
+    // Web seeds of directories end with a slash, and clients append the name of the torrent
+    let web_seed = {
+        let info = req.connection_info();
+        let path = if path.is_dir() {
+            let dir = req.path().trim_end_matches('/');
+            &dir[..=dir.rfind('/').unwrap_or_default()]
+        } else {
+            req.path()
+        };
+        format!("{}://{}{}", info.scheme(), info.host(), path)
+    };
+    let info = if path.is_dir() {
+        let files = files
+            .into_iter()
+            .map(|file| {
+                Bencode::dict([
+                    ("length", Bencode::Int(file.length)),
+                    (
+                        "path",
+                        Bencode::List(file.path.into_iter().map(Bencode::from).collect()),
+                    ),
+                ])
+            })
+            .collect();
+        Bencode::dict([
+            ("files", Bencode::List(files)),
+            ("name", Bencode::from(name.clone())),
+            ("piece length", Bencode::Int(piece_length)),
+            ("pieces", Bencode::Bytes(pieces)),
+        ])
+    } else {
+        Bencode::dict([
+            ("length", Bencode::Int(length)),
+            ("name", Bencode::from(name.clone())),
+            ("piece length", Bencode::Int(piece_length)),
+            ("pieces", Bencode::Bytes(pieces)),
+        ])
+    };
+    let torrent = Bencode::dict([
+        (
+            "created by",
+            Bencode::from(format!("miniserve/{}", env!("CARGO_PKG_VERSION"))),
+        ),
+        ("info", info),
+        ("url-list", Bencode::List(vec![Bencode::from(web_seed)])),
+    ]);
+
+    let res = HttpResponse::Ok()
+        .content_type("application/x-bittorrent")
+        .insert_header(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters: vec![DispositionParam::Filename(format!("{name}.torrent"))],
+        })
+        .body(torrent.encode());
+    Ok(req.into_response(res).map_into_right_body())
+}
+
+/// Resolves the file or directory a request path asks the torrent of
+///
+/// The served directory itself has none, as web seeds are found by the name of the torrent
+/// below the URL of its parent.
+fn requested_path(conf: &MiniserveConfig, path: &str) -> Option<PathBuf> {
+    if let Some(file) = file_utils::requested_file(conf, path) {
+        return Some(file);
+    }
+    if conf.disable_indexing {
+        return None;
+    }
+    file_utils::requested_dir(conf, path).filter(|dir| *dir != conf.path)
+}
+
+/// Files of the torrent of a file or a directory, the ones of directories sorted by path
+fn torrent_files(conf: &MiniserveConfig, path: &Path) -> io::Result<Vec<TorrentFile>> {
+    if !path.is_dir() {
+        let metadata = path.metadata()?;
+        return Ok(vec![TorrentFile {
+            path: Vec::new(),
+            file: path.to_path_buf(),
+            length: metadata.len(),
+            modified: metadata.modified().ok(),
+        }]);
+    }
+    let mut files: Vec<_> = file_utils::served_entries(conf, path)
+        .into_iter()
+        .filter(|entry| entry.metadata.is_file())
+        .map(|entry| TorrentFile {
+            file: path.join(&entry.path),
+            path: entry.path.split('/').map(str::to_owned).collect(),
+            length: entry.metadata.len(),
+            modified: entry.metadata.modified().ok(),
+        })
+        .collect();
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(files)
+}
+
+/// Length of the pieces of torrents of `length` bytes
+fn piece_length(length: u64) -> u64 {
+    length
+        .div_ceil(MAX_PIECES)
+        .next_power_of_two()
+        .max(MIN_PIECE_LENGTH)
+}
+
+/// Hashes the contents of files, one after the other, in pieces of `piece_length` bytes
+///
+/// Files are read up to the length they had when listed, so that pieces match the lengths of
+/// the torrent even if files grew since.
+fn hash_pieces(files: &[(PathBuf, u64)], piece_length: u64) -> io::Result<Vec<u8>> {
+    let mut contents: Box<dyn Read> = Box::new(io::empty());
+    for (path, length) in files {
+        let file = std::fs::File::open(path)?.take(*length);
+        contents = Box::new(contents.chain(file));
+    }
+    let mut contents = io::BufReader::new(contents);
+    let mut pieces = Vec::new();
+    while !io::BufRead::fill_buf(&mut contents)?.is_empty() {
+        let mut hasher = Sha1::new();
+        io::copy(&mut (&mut contents).take(piece_length), &mut hasher)?;
+        pieces.extend_from_slice(&hasher.finalize());
+    }
+    Ok(pieces)
+}
+
+/// Values of bencode, the encoding of torrents
+enum Bencode {
+    Int(u64),
+    Bytes(Vec<u8>),
+    List(Vec<Bencode>),
+    Dict(BTreeMap<&'static str, Bencode>),
+}
+
+impl Bencode {
+    /// Dictionary of values, whose keys are sorted as bencode wants them
+    fn dict(entries: impl IntoIterator<Item = (&'static str, Bencode)>) -> Self {
+        Self::Dict(entries.into_iter().collect())
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        self.encode_into(&mut out);
+        out
+    }
+
+    fn encode_into(&self, out: &mut Vec<u8>) {
+        match self {
+            Self::Int(int) => out.extend_from_slice(format!("i{int}e").as_bytes()),
+            Self::Bytes(bytes) => {
+                out.extend_from_slice(format!("{}:", bytes.len()).as_bytes());
+                out.extend_from_slice(bytes);
+            }
+            Self::List(list) => {
+                out.push(b'l');
+                for value in list {
+                    value.encode_into(out);
+                }
+                out.push(b'e');
+            }
+            Self::Dict(dict) => {
+                out.push(b'd');
+                for (key, value) in dict {
+                    Self::Bytes(key.as_bytes().to_vec()).encode_into(out);
+                    value.encode_into(out);
+                }
+                out.push(b'e');
+            }
+        }
+    }
+}
+
+impl From<String> for Bencode {
+    fn from(string: String) -> Self {
+        Self::Bytes(string.into_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn encodes_bencode() {
+        let value = Bencode::dict([
+            ("spam", Bencode::List(vec![Bencode::from("a".to_owned())])),
+            ("length", Bencode::Int(42)),
+        ]);
+        assert_eq!(value.encode(), b"d6:lengthi42e4:spaml1:aee");
+    }
+
+    #[test]
+    fn hashes_pieces_across_files() -> io::Result<()> {
+        let dir = tempfile::tempdir()?;
+        std::fs::write(dir.path().join("a"), "abc")?;
+        std::fs::write(dir.path().join("b"), "defgh")?;
+        let files = [(dir.path().join("a"), 3), (dir.path().join("b"), 4)];
+
+        let pieces = hash_pieces(&files, 4)?;
+        let expected: Vec<u8> = [&b"abcd"[..], b"efg"]
+            .iter()
+            .flat_map(|piece| Sha1::digest(piece).to_vec())
+            .collect();
+        assert_eq!(pieces, expected);
+        Ok(())
+    }
+}
@@ -0,0 +1,145 @@
+use std::process::Command;
+
+use assert_cmd::prelude::*;
+use assert_fs::TempDir;
+use predicates::str::contains;
+use reqwest::{
+    StatusCode,
+    blocking::{Client, multipart},
+    redirect,
+};
+use rstest::rstest;
+use select::{document::Document, predicate::Attr};
+
+mod fixtures;
+
+use crate::fixtures::{Error, TestServer, server, tmpdir};
+
+#[rstest]
+#[case("admin", StatusCode::OK)]
+#[case("joe", StatusCode::FORBIDDEN)]
+fn admin_page_is_for_the_admin_only(
+    #[with(&["-a", "admin:pwd", "-a", "joe:pwd", "--admin", "admin"])] server: TestServer,
+    #[case] user: &str,
+    #[case] expected: StatusCode,
+) -> Result<(), Error> {
+    let status = Client::new()
+        .get(server.url().join("__admin")?)
+        .basic_auth(user, Some("pwd"))
+        .send()?
+        .status();
+    assert_eq!(status, expected);
+
+    Ok(())
+}
+
+#[rstest]
+fn admin_page_pauses_uploads(
+    #[with(&["-a", "admin:pwd", "--admin", "admin", "-u"])] server: TestServer,
+) -> Result<(), Error> {
+    let client = Client::builder()
+        .redirect(redirect::Policy::none())
+        .build()?;
+    let res = client
+        .post(server.url().join("__admin")?)
+        .basic_auth("admin", Some("pwd"))
+        .form(&[("action", "pause_uploads")])
+        .send()?;
+    assert_eq!(res.status(), StatusCode::SEE_OTHER);
+
+    let listing = client
+        .get(server.url())
+        .basic_auth("admin", Some("pwd"))
+        .send()?
+        .error_for_status()?;
+    let parsed = Document::from_read(listing)?;
+    assert!(parsed.find(Attr("id", "file_submit")).next().is_none());
+
+    // Connections are left unusable by uploads rejected before their body has been read
+    let part = multipart::Part::text("this should not be uploaded").file_name("paused.txt");
+    let status = Client::new()
+        .post(server.url().join("/upload?path=/")?)
+        .basic_auth("admin", Some("pwd"))
+        .multipart(multipart::Form::new().part("file_to_upload", part))
+        .send()?
+        .status();
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+    assert!(!server.path().join("paused.txt").exists());
+
+    let page = client
+        .get(server.url().join("__admin")?)
+        .basic_auth("admin", Some("pwd"))
+        .send()?
+        .error_for_status()?
+        .text()?;
+    assert!(page.contains("Uploads are paused."));
+    assert!(page.contains("Paused uploads"));
+
+    client
+        .post(server.url().join("__admin")?)
+        .basic_auth("admin", Some("pwd"))
+        .form(&[("action", "resume_uploads")])
+        .send()?
+        .error_for_status()?;
+    let part = multipart::Part::text("this should be uploaded").file_name("resumed.txt");
+    client
+        .post(server.url().join("/upload?path=/")?)
+        .basic_auth("admin", Some("pwd"))
+        .multipart(multipart::Form::new().part("file_to_upload", part))
+        .send()?
+        .error_for_status()?;
+    assert!(server.path().join("resumed.txt").exists());
+
+    Ok(())
+}
+
+#[rstest]
+fn admin_page_shows_downloads(
+    #[with(&["-a", "admin:pwd", "--admin", "admin"])] server: TestServer,
+) -> Result<(), Error> {
+    let client = Client::new();
+    client
+        .get(server.url().join("test.txt")?)
+        .basic_auth("admin", Some("pwd"))
+        .send()?
+        .error_for_status()?
+        .bytes()?;
+
+    let page = client
+        .get(server.url().join("__admin")?)
+        .basic_auth("admin", Some("pwd"))
+        .send()?
+        .error_for_status()?
+        .text()?;
+    assert!(page.contains("Downloaded /test.txt"));
+
+    Ok(())
+}
+
+#[rstest]
+fn admin_page_refuses_other_origins(
+    #[with(&["-a", "admin:pwd", "--admin", "admin", "-u"])] server: TestServer,
+) -> Result<(), Error> {
+    let status = Client::new()
+        .post(server.url().join("__admin")?)
+        .basic_auth("admin", Some("pwd"))
+        .header("Origin", "http://example.com")
+        .form(&[("action", "pause_uploads")])
+        .send()?
+        .status();
+    assert_eq!(status, StatusCode::FORBIDDEN);
+
+    Ok(())
+}
+
+#[rstest]
+fn admin_page_requires_auth(tmpdir: TempDir) -> Result<(), Error> {
+    Command::cargo_bin("miniserve")?
+        .arg(tmpdir.path())
+        .args(["--admin", "admin"])
+        .assert()
+        .failure()
+        .stderr(contains("--admin"));
+
+    Ok(())
+}
@@ -62,3 +62,24 @@ fn api_dir_size_prevent_path_transversal_attacks(
 
     Ok(())
 }
+
+/// Test that directory sizes are reused rather than calculated for every request
+#[rstest]
+fn api_dir_size_is_cached(#[with(&["--dir-size"])] server: TestServer) -> Result<(), Error> {
+    let mut command = HashMap::new();
+    command.insert("DirSize", DIRECTORIES[0]);
+    let dir_size = || -> Result<String, Error> {
+        Ok(Client::new()
+            .post(server.url().join("__miniserve_internal/api")?)
+            .json(&command)
+            .send()?
+            .error_for_status()?
+            .text()?)
+    };
+
+    let size = dir_size()?;
+    std::fs::write(server.path().join(DIRECTORIES[0]).join("large"), [0; 4096])?;
+    assert_eq!(dir_size()?, size);
+
+    Ok(())
+}
@@ -1,10 +1,21 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::thread::sleep;
+use std::time::Duration;
+
+use assert_cmd::prelude::*;
+use assert_fs::fixture::TempDir;
 use reqwest::StatusCode;
 use rstest::rstest;
-use select::{document::Document, predicate::Text};
+use select::{
+    document::Document,
+    predicate::{Name, Text},
+};
+use zip::{ZipWriter, write::SimpleFileOptions};
 
 mod fixtures;
 
-use crate::fixtures::{Error, TestServer, server};
+use crate::fixtures::{Error, TestServer, port, server, tmpdir};
 
 #[rstest]
 fn archives_are_disabled(server: TestServer) -> Result<(), Error> {
@@ -90,3 +101,93 @@ fn archives_are_disabled_when_indexing_disabled(
 
     Ok(())
 }
+
+#[rstest]
+fn browses_zip_archives(tmpdir: TempDir, port: u16) -> Result<(), Error> {
+    let archive_path = tmpdir.path().join("backup.zip");
+    let mut zip = ZipWriter::new(std::fs::File::create(&archive_path)?);
+    let options = SimpleFileOptions::default();
+    zip.start_file("top.txt", options)?;
+    zip.write_all(b"top level")?;
+    zip.start_file("nested/deeper/inner.txt", options)?;
+    zip.write_all(b"nested content")?;
+    zip.finish()?;
+
+    let mut child = Command::cargo_bin("miniserve")?
+        .arg(&archive_path)
+        .arg("--browse-archive")
+        .arg("-p")
+        .arg(port.to_string())
+        .stdout(Stdio::null())
+        .spawn()?;
+
+    sleep(Duration::from_secs(1));
+
+    let url = format!("http://localhost:{port}");
+    let links = |path: &str| -> Result<Vec<String>, Error> {
+        let body = reqwest::blocking::get(format!("{url}{path}"))?.error_for_status()?;
+        let parsed = Document::from_read(body)?;
+        Ok(parsed
+            .find(Name("a"))
+            .filter_map(|a| a.attr("href").map(str::to_string))
+            .collect())
+    };
+
+    let root_links = links("/")?;
+    assert!(root_links.contains(&"/top.txt".to_string()));
+    assert!(root_links.contains(&"/nested/".to_string()));
+
+    let nested_links = links("/nested/")?;
+    assert!(nested_links.contains(&"/nested/deeper/".to_string()));
+    assert!(!nested_links.iter().any(|link| link.ends_with("inner.txt")));
+
+    let resp = reqwest::blocking::get(format!("{url}/nested/deeper/inner.txt"))?;
+    assert_eq!(resp.headers().get("content-length").unwrap(), "14");
+    assert_eq!(resp.text()?, "nested content");
+
+    // Directories without a trailing slash are redirected, like regular ones
+    assert_eq!(links("/nested/deeper")?, links("/nested/deeper/")?);
+
+    let resp = reqwest::blocking::get(format!("{url}/missing.txt"))?;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+    child.kill()?;
+
+    Ok(())
+}
+
+#[rstest]
+#[case(&[], StatusCode::NOT_FOUND)]
+#[case(&["--hidden"], StatusCode::OK)]
+fn hidden_archive_members_need_hidden(
+    tmpdir: TempDir,
+    port: u16,
+    #[case] args: &[&str],
+    #[case] status: StatusCode,
+) -> Result<(), Error> {
+    let archive_path = tmpdir.path().join("repo.zip");
+    let mut zip = ZipWriter::new(std::fs::File::create(&archive_path)?);
+    zip.start_file(".git/config", SimpleFileOptions::default())?;
+    zip.write_all(b"[core]")?;
+    zip.finish()?;
+
+    let mut child = Command::cargo_bin("miniserve")?
+        .arg(&archive_path)
+        .arg("--browse-archive")
+        .args(args)
+        .arg("-p")
+        .arg(port.to_string())
+        .stdout(Stdio::null())
+        .spawn()?;
+
+    sleep(Duration::from_secs(1));
+
+    for path in ["/.git/config", "/.git/"] {
+        let resp = reqwest::blocking::get(format!("http://localhost:{port}{path}"))?;
+        assert_eq!(resp.status(), status, "{path}");
+    }
+
+    child.kill()?;
+
+    Ok(())
+}
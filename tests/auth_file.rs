@@ -53,3 +53,53 @@ fn auth_file_rejects(
 
     Ok(())
 }
+
+/// The auth file is read again on SIGHUP, replacing the previous accounts.
+#[cfg(unix)]
+#[rstest]
+fn auth_file_reloads_on_sighup() -> Result<(), Error> {
+    use std::{process::Command, thread::sleep, time::Duration};
+
+    let auth_dir = assert_fs::TempDir::new()?;
+    let auth_file = auth_dir.path().join("auth.txt");
+    std::fs::write(&auth_file, "joe:123\n")?;
+    let server = server(&[std::ffi::OsStr::new("--auth-file"), auth_file.as_os_str()]);
+
+    let client = Client::new();
+    let status_for = |username: &str, password: &str| {
+        client
+            .get(server.url())
+            .basic_auth(username, Some(password))
+            .send()
+            .map(|res| res.status())
+    };
+    assert_eq!(status_for("joe", "123")?, StatusCode::OK);
+    assert_eq!(status_for("bob", "456")?, StatusCode::UNAUTHORIZED);
+
+    std::fs::write(&auth_file, "bob:456\n")?;
+    Command::new("kill")
+        .args(["-HUP", &server.pid().to_string()])
+        .status()?;
+    sleep(Duration::from_millis(500));
+
+    assert_eq!(status_for("joe", "123")?, StatusCode::UNAUTHORIZED);
+    assert_eq!(status_for("bob", "456")?, StatusCode::OK);
+
+    Ok(())
+}
+
+/// SIGHUP still terminates miniserve when there is nothing to reload.
+#[cfg(unix)]
+#[rstest]
+fn sighup_terminates_without_reloadable_files(server: TestServer) -> Result<(), Error> {
+    use std::{process::Command, thread::sleep, time::Duration};
+
+    Command::new("kill")
+        .args(["-HUP", &server.pid().to_string()])
+        .status()?;
+    sleep(Duration::from_millis(500));
+
+    assert!(Client::new().get(server.url()).send().is_err());
+
+    Ok(())
+}
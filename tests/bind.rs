@@ -26,6 +26,30 @@ fn bind_fails(tmpdir: TempDir, port: u16, #[case] args: &[&str]) -> Result<(), E
     Ok(())
 }
 
+#[rstest]
+fn bind_unknown_interface_fails(tmpdir: TempDir, port: u16) -> Result<(), Error> {
+    Command::cargo_bin("miniserve")?
+        .arg(tmpdir.path())
+        .arg("-p")
+        .arg(port.to_string())
+        .args(["-i", "doesnotexist0"])
+        .assert()
+        .stderr(predicates::str::contains(
+            "No usable addresses on network interface \"doesnotexist0\"",
+        ))
+        .failure();
+
+    Ok(())
+}
+
+#[cfg(target_os = "linux")]
+#[rstest]
+fn bind_interface_by_name(#[with(&["-i", "lo"])] server: TestServer) -> Result<(), Error> {
+    reqwest::blocking::get(format!("http://127.0.0.1:{}", server.port()))?.error_for_status()?;
+
+    Ok(())
+}
+
 #[rstest]
 #[case(server(&[] as &[&str]), true, true)]
 #[case(server(&["-i", "::"]), false, true)]
@@ -89,3 +113,254 @@ fn validate_printed_urls(tmpdir: TempDir, port: u16, #[case] args: &[&str]) -> R
 
     Ok(())
 }
+
+#[cfg(unix)]
+#[rstest]
+fn bind_unix_socket(tmpdir: TempDir) -> Result<(), Error> {
+    use std::io::{Read, Write};
+    use std::os::unix::fs::PermissionsExt;
+    use std::os::unix::net::UnixStream;
+    use std::thread::sleep;
+    use std::time::Duration;
+
+    let socket_dir = TempDir::new()?;
+    let socket = socket_dir.path().join("miniserve.sock");
+    let mut child = Command::cargo_bin("miniserve")?
+        .arg(tmpdir.path())
+        .arg("--listen-unix")
+        .arg(&socket)
+        .arg("--listen-unix-mode")
+        .arg("600")
+        .stdout(Stdio::null())
+        .spawn()?;
+
+    sleep(Duration::from_secs(1));
+
+    assert_eq!(
+        std::fs::metadata(&socket)?.permissions().mode() & 0o777,
+        0o600
+    );
+
+    let mut stream = UnixStream::connect(&socket)?;
+    stream.write_all(b"GET /test.txt HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+    assert!(response.starts_with("HTTP/1.1 200 OK"));
+    assert!(response.ends_with("Test Hello Yes"));
+
+    child.kill()?;
+
+    Ok(())
+}
+
+#[cfg(unix)]
+#[rstest]
+fn bind_free_port_and_print_it(tmpdir: TempDir) -> Result<(), Error> {
+    let mut child = Command::cargo_bin("miniserve")?
+        .arg(tmpdir.path())
+        .args(["-p", "0", "--print-bound-port-fd", "1"])
+        .stdout(Stdio::piped())
+        .spawn()?;
+
+    // Keep stdout open, miniserve fails when it can't print anymore
+    let mut lines = BufReader::new(child.stdout.take().unwrap()).lines();
+    let port = lines
+        .by_ref()
+        .map(|line| line.expect("Error reading stdout"))
+        .find_map(|line| line.parse::<u16>().ok())
+        .expect("No port was printed");
+    assert_ne!(port, 0);
+
+    reqwest::blocking::get(format!("http://localhost:{port}"))?.error_for_status()?;
+
+    child.kill()?;
+
+    Ok(())
+}
+
+/// Unknown users are rejected before binding anything.
+#[cfg(unix)]
+#[rstest]
+fn bind_as_unknown_user_fails(tmpdir: TempDir, port: u16) -> Result<(), Error> {
+    Command::cargo_bin("miniserve")?
+        .arg(tmpdir.path())
+        .arg("-p")
+        .arg(port.to_string())
+        .args(["--user", "no-such-user-here"])
+        .assert()
+        .stderr(predicates::str::contains(
+            "Unknown user \"no-such-user-here\"",
+        ))
+        .failure();
+
+    Ok(())
+}
+
+/// The server switches to the given user once bound, which only root is allowed to do.
+#[cfg(target_os = "linux")]
+#[rstest]
+fn bind_then_switch_user() -> Result<(), Error> {
+    let uid = Command::new("id").arg("-u").output()?.stdout;
+    if String::from_utf8(uid)?.trim() != "0" {
+        return Ok(());
+    }
+
+    let server = server(&["--user", "nobody"]);
+    let status = std::fs::read_to_string(format!("/proc/{}/status", server.pid()))?;
+    let uids = status
+        .lines()
+        .find_map(|line| line.strip_prefix("Uid:"))
+        .expect("No Uid line in the process status");
+    let nobody = Command::new("id").args(["-u", "nobody"]).output()?.stdout;
+    let nobody = String::from_utf8(nobody)?;
+    assert!(uids.split_whitespace().all(|uid| uid == nobody.trim()));
+
+    Ok(())
+}
+
+/// Answers STUN binding requests on a local port, telling clients they come from 203.0.113.7
+fn mock_stun_server() -> Result<u16, Error> {
+    let socket = std::net::UdpSocket::bind("127.0.0.1:0")?;
+    let port = socket.local_addr()?.port();
+    std::thread::spawn(move || {
+        let mut request = [0; 512];
+        while let Ok((len, peer)) = socket.recv_from(&mut request) {
+            if len < 20 {
+                continue;
+            }
+            let cookie = &request[4..8];
+            let mut response = vec![0x01, 0x01, 0x00, 0x0c];
+            response.extend(&request[4..20]);
+            response.extend([0x00, 0x20, 0x00, 0x08, 0x00, 0x01, 0x00, 0x00]);
+            response.extend([203, 0, 113, 7].iter().zip(cookie).map(|(b, m)| b ^ m));
+            let _ = socket.send_to(&response, peer);
+        }
+    });
+    Ok(port)
+}
+
+/// Answers any HTTP request with 203.0.113.7 as plain text
+fn mock_ip_endpoint() -> Result<u16, Error> {
+    use std::io::{Read, Write};
+
+    let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+    let port = listener.local_addr()?.port();
+    std::thread::spawn(move || {
+        for mut stream in listener.incoming().flatten() {
+            let _ = stream.read(&mut [0; 1024]);
+            let _ = stream.write_all(b"HTTP/1.0 200 OK\r\n\r\n203.0.113.7\n");
+        }
+    });
+    Ok(port)
+}
+
+/// The public IP address is printed in a URL when asked for.
+#[rstest]
+#[case::stun(format!("stun:127.0.0.1:{}", mock_stun_server().unwrap()))]
+#[case::http(format!("http://127.0.0.1:{}/", mock_ip_endpoint().unwrap()))]
+fn print_external_ip(tmpdir: TempDir, port: u16, #[case] source: String) -> Result<(), Error> {
+    let mut child = Command::cargo_bin("miniserve")?
+        .arg(tmpdir.path())
+        .arg("-p")
+        .arg(port.to_string())
+        .arg("--external-ip")
+        .arg(source)
+        .stdout(Stdio::piped())
+        .spawn()?;
+
+    let mut lines = BufReader::new(child.stdout.take().unwrap())
+        .lines()
+        .map(|line| line.expect("Error reading stdout"));
+    let printed = lines
+        .by_ref()
+        .skip_while(|line| !line.starts_with("Publicly available at"))
+        .nth(1);
+
+    child.kill()?;
+
+    assert_eq!(
+        printed.as_deref().map(str::trim),
+        Some(format!("http://203.0.113.7:{port}").as_str())
+    );
+
+    Ok(())
+}
+
+/// The browser is opened at the URL of the server on this machine.
+#[cfg(unix)]
+#[rstest]
+fn open_browser(tmpdir: TempDir, port: u16) -> Result<(), Error> {
+    use std::os::unix::fs::PermissionsExt;
+    use std::thread::sleep;
+    use std::time::Duration;
+
+    let browser_dir = TempDir::new()?;
+    let browser = browser_dir.path().join("browser");
+    let opened = browser_dir.path().join("opened");
+    std::fs::write(
+        &browser,
+        format!("#!/bin/sh\necho \"$1\" > '{}'\n", opened.display()),
+    )?;
+    std::fs::set_permissions(&browser, std::fs::Permissions::from_mode(0o755))?;
+
+    let mut child = Command::cargo_bin("miniserve")?
+        .arg(tmpdir.path())
+        .arg("-p")
+        .arg(port.to_string())
+        .args(["-i", "127.0.0.1", "--open"])
+        .env("BROWSER", &browser)
+        .stdout(Stdio::null())
+        .spawn()?;
+
+    sleep(Duration::from_secs(1));
+
+    let url = std::fs::read_to_string(&opened)?;
+    assert_eq!(url.trim(), format!("http://127.0.0.1:{port}"));
+    reqwest::blocking::get(url.trim())?.error_for_status()?;
+
+    child.kill()?;
+
+    Ok(())
+}
+
+/// The served URL is copied to the clipboard.
+#[cfg(target_os = "linux")]
+#[rstest]
+fn copy_url_to_clipboard(tmpdir: TempDir, port: u16) -> Result<(), Error> {
+    use std::os::unix::fs::PermissionsExt;
+    use std::thread::sleep;
+    use std::time::Duration;
+
+    let tools_dir = TempDir::new()?;
+    let copied = tools_dir.path().join("copied");
+    let wl_copy = tools_dir.path().join("wl-copy");
+    std::fs::write(
+        &wl_copy,
+        format!("#!/bin/sh\ncat > '{}'\n", copied.display()),
+    )?;
+    std::fs::set_permissions(&wl_copy, std::fs::Permissions::from_mode(0o755))?;
+    let path = format!(
+        "{}:{}",
+        tools_dir.path().display(),
+        std::env::var("PATH").unwrap_or_default()
+    );
+
+    let mut child = Command::cargo_bin("miniserve")?
+        .arg(tmpdir.path())
+        .arg("-p")
+        .arg(port.to_string())
+        .args(["-i", "127.0.0.1", "--copy-url"])
+        .env("PATH", path)
+        .stdout(Stdio::null())
+        .spawn()?;
+
+    sleep(Duration::from_secs(1));
+    child.kill()?;
+
+    assert_eq!(
+        std::fs::read_to_string(&copied)?,
+        format!("http://127.0.0.1:{port}")
+    );
+
+    Ok(())
+}
@@ -1,12 +1,16 @@
 use std::process::Command;
+use std::time::Duration;
 
 use assert_cmd::prelude::*;
+use assert_fs::TempDir;
 use clap::{ValueEnum, crate_name, crate_version};
 use clap_complete::Shell;
+use predicates::str::contains;
+use rstest::rstest;
 
 mod fixtures;
 
-use crate::fixtures::Error;
+use crate::fixtures::{Error, port, tmpdir};
 
 #[test]
 /// Show help and exit.
@@ -45,6 +49,20 @@ fn print_completions() -> Result<(), Error> {
     Ok(())
 }
 
+#[test]
+/// Print a man page covering every option and exit.
+fn print_manpage() -> Result<(), Error> {
+    Command::cargo_bin("miniserve")?
+        .arg("--print-manpage")
+        .assert()
+        .success()
+        .stdout(contains(".TH miniserve 1"))
+        .stdout(contains(r"\fB\-\-port\fR"))
+        .stdout(contains(r"\fBMINISERVE_PORT\fR"));
+
+    Ok(())
+}
+
 #[test]
 /// Print completions rejects invalid shells.
 fn print_completions_invalid_shell() -> Result<(), Error> {
@@ -56,3 +74,159 @@ fn print_completions_invalid_shell() -> Result<(), Error> {
 
     Ok(())
 }
+
+#[cfg(feature = "s3")]
+#[test]
+/// Object storage can only be browsed, not written to.
+fn s3_rejects_uploads() -> Result<(), Error> {
+    Command::cargo_bin("miniserve")?
+        .arg("s3://bucket/prefix")
+        .arg("-u")
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains(
+            "aren't supported for object storage",
+        ));
+
+    Ok(())
+}
+
+#[test]
+/// At least one worker is needed to handle requests.
+fn workers_must_be_positive() -> Result<(), Error> {
+    Command::cargo_bin("miniserve")?
+        .args(["--workers", "0"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("--workers <N>"));
+
+    Ok(())
+}
+
+#[test]
+/// Listings can't be truncated to no entries at all.
+fn max_listing_entries_must_be_positive() -> Result<(), Error> {
+    Command::cargo_bin("miniserve")?
+        .args(["--max-listing-entries", "0"])
+        .assert()
+        .failure()
+        .stderr(predicates::str::contains("--max-listing-entries"));
+
+    Ok(())
+}
+
+#[test]
+#[cfg(unix)]
+/// Ports can only be forwarded to TCP listeners.
+fn public_conflicts_with_listen_unix() -> Result<(), Error> {
+    Command::cargo_bin("miniserve")?
+        .args(["--public", "--listen-unix", "miniserve.sock"])
+        .assert()
+        .failure()
+        .stderr(contains("cannot be used with"));
+
+    Ok(())
+}
+
+#[test]
+/// The misspelled name of the temporary directory variable is still read.
+fn deprecated_temp_directory_env_is_read() -> Result<(), Error> {
+    Command::cargo_bin("miniserve")?
+        .arg("-u")
+        .env("MINISERVER_TEMP_UPLOAD_DIRECTORY", "/does/not/exist")
+        .assert()
+        .failure()
+        .stderr(contains("Invalid MINISERVER_TEMP_UPLOAD_DIRECTORY"));
+
+    Ok(())
+}
+
+#[rstest]
+/// The server stops by itself once the given duration has elapsed.
+fn duration_stops_server(tmpdir: TempDir, port: u16) -> Result<(), Error> {
+    assert_cmd::Command::cargo_bin("miniserve")?
+        .arg(tmpdir.path())
+        .arg("-p")
+        .arg(port.to_string())
+        .args(["--duration", "1s"])
+        .timeout(Duration::from_secs(10))
+        .assert()
+        .success()
+        .stdout(contains("Stopping automatically at"));
+
+    Ok(())
+}
+
+#[rstest]
+/// The server stops once a file has been downloaded, which listings don't count as.
+fn max_downloads_stops_server(tmpdir: TempDir, port: u16) -> Result<(), Error> {
+    let mut child = Command::cargo_bin("miniserve")?
+        .arg(tmpdir.path())
+        .arg("-p")
+        .arg(port.to_string())
+        .args(["--max-downloads", "1"])
+        .stdout(std::process::Stdio::null())
+        .spawn()?;
+    std::thread::sleep(Duration::from_secs(1));
+
+    let url = format!("http://localhost:{port}");
+    reqwest::blocking::get(&url)?.error_for_status()?;
+    std::thread::sleep(Duration::from_millis(500));
+    assert!(child.try_wait()?.is_none());
+    reqwest::blocking::get(format!("{url}/test.txt"))?.error_for_status()?;
+
+    // Stopping can take a while on a busy machine
+    let mut status = None;
+    for _ in 0..50 {
+        status = child.try_wait()?;
+        if status.is_some() {
+            break;
+        }
+        std::thread::sleep(Duration::from_millis(200));
+    }
+    if status.is_none() {
+        child.kill()?;
+    }
+    assert!(status.is_some_and(|status| status.success()));
+
+    Ok(())
+}
+
+#[rstest]
+/// The server stops once a file has been received, which is written into the destination.
+fn receive_stops_server(port: u16) -> Result<(), Error> {
+    let dest = TempDir::new()?;
+    let child = Command::cargo_bin("miniserve")?
+        .arg("-p")
+        .arg(port.to_string())
+        .arg("--receive")
+        .arg(dest.path())
+        .stdout(std::process::Stdio::piped())
+        .spawn()?;
+    std::thread::sleep(Duration::from_secs(1));
+
+    let url = format!("http://localhost:{port}");
+    reqwest::blocking::get(&url)?.error_for_status()?;
+    // Nothing else is served
+    assert_eq!(
+        reqwest::blocking::get(format!("{url}/test.txt"))?.status(),
+        reqwest::StatusCode::NOT_FOUND
+    );
+    let part = reqwest::blocking::multipart::Part::text("received content").file_name("sent.txt");
+    let form = reqwest::blocking::multipart::Form::new().part("file_to_upload", part);
+    reqwest::blocking::Client::new()
+        .post(&url)
+        .multipart(form)
+        .send()?
+        .error_for_status()?;
+
+    let output = child.wait_with_output()?;
+    let received = dest.path().join("sent.txt");
+    assert!(output.status.success());
+    assert!(
+        String::from_utf8(output.stdout)?.contains(&format!("Received {}", received.display()))
+    );
+    assert_eq!(std::fs::read_to_string(received)?, "received content");
+
+    Ok(())
+}
@@ -0,0 +1,94 @@
+use reqwest::{Method, StatusCode, blocking::Client};
+use rstest::rstest;
+
+mod fixtures;
+
+use crate::fixtures::{Error, FILES, TestServer, server};
+
+#[rstest]
+#[case(server(&["--cors", "https://app.example.com"]), "https://app.example.com")]
+#[case(server(&["--cors", "*"]), "*")]
+fn cors_headers_for_allowed_origins(
+    #[case] server: TestServer,
+    #[case] expected: &str,
+) -> Result<(), Error> {
+    let resp = Client::new()
+        .get(server.url().join(FILES[0])?)
+        .header("Origin", "https://app.example.com")
+        .send()?
+        .error_for_status()?;
+    assert_eq!(
+        resp.headers().get("access-control-allow-origin").unwrap(),
+        expected
+    );
+
+    Ok(())
+}
+
+#[rstest]
+fn no_cors_headers_for_other_origins(
+    #[with(&["--cors", "https://app.example.com"])] server: TestServer,
+) -> Result<(), Error> {
+    let resp = Client::new()
+        .get(server.url())
+        .header("Origin", "https://evil.example.com")
+        .send()?;
+    // The request itself is still served, but browsers won't expose it to the other origin
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert!(resp.headers().get("access-control-allow-origin").is_none());
+    // Caches mustn't hand this response over to allowed origins
+    assert_eq!(resp.headers().get("vary").unwrap(), "Origin");
+
+    let resp = Client::new().get(server.url()).send()?;
+    assert_eq!(resp.headers().get("vary").unwrap(), "Origin");
+
+    Ok(())
+}
+
+#[rstest]
+fn preflight_bypasses_auth(
+    #[with(&["--cors", "https://app.example.com", "-a", "joe:123"])] server: TestServer,
+) -> Result<(), Error> {
+    let resp = Client::new()
+        .request(Method::OPTIONS, server.url())
+        .header("Origin", "https://app.example.com")
+        .header("Access-Control-Request-Method", "GET")
+        .header("Access-Control-Request-Headers", "authorization")
+        .send()?;
+    assert_eq!(resp.status(), StatusCode::NO_CONTENT);
+    let headers = resp.headers();
+    assert_eq!(
+        headers.get("access-control-allow-origin").unwrap(),
+        "https://app.example.com"
+    );
+    assert_eq!(
+        headers.get("access-control-allow-headers").unwrap(),
+        "authorization"
+    );
+    assert_eq!(
+        headers.get("access-control-allow-credentials").unwrap(),
+        "true"
+    );
+
+    Ok(())
+}
+
+#[rstest]
+fn credentials_allowed_with_auth(
+    #[with(&["--cors", "https://app.example.com", "-a", "joe:123"])] server: TestServer,
+) -> Result<(), Error> {
+    let resp = Client::new()
+        .get(server.url())
+        .basic_auth("joe", Some("123"))
+        .header("Origin", "https://app.example.com")
+        .send()?
+        .error_for_status()?;
+    assert_eq!(
+        resp.headers()
+            .get("access-control-allow-credentials")
+            .unwrap(),
+        "true"
+    );
+
+    Ok(())
+}
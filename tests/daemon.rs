@@ -0,0 +1,94 @@
+#![cfg(unix)]
+
+use std::process::Command;
+use std::thread::sleep;
+use std::time::Duration;
+
+use assert_cmd::prelude::*;
+use assert_fs::fixture::TempDir;
+use reqwest::blocking::Client;
+use rstest::rstest;
+
+mod fixtures;
+
+use crate::fixtures::{Error, port, tmpdir};
+
+/// The server keeps running in the background after the command returns.
+#[rstest]
+fn daemon_serves_in_background(tmpdir: TempDir, port: u16) -> Result<(), Error> {
+    let run_dir = TempDir::new()?;
+    let pid_file = run_dir.path().join("miniserve.pid");
+    let log_file = run_dir.path().join("miniserve.log");
+
+    Command::cargo_bin("miniserve")?
+        .arg(tmpdir.path())
+        .arg("-p")
+        .arg(port.to_string())
+        .arg("-v")
+        .arg("--daemon")
+        .arg("--pid-file")
+        .arg(&pid_file)
+        .arg("--log-file")
+        .arg(&log_file)
+        .assert()
+        .success();
+
+    let pid = std::fs::read_to_string(&pid_file)?.trim().to_string();
+    let status = Client::new()
+        .get(format!("http://localhost:{port}/test.txt"))
+        .send()?
+        .status();
+    Command::new("kill").arg(&pid).status()?;
+    sleep(Duration::from_millis(500));
+
+    assert!(status.is_success());
+    assert!(std::fs::read_to_string(&log_file)?.contains("\"GET /test.txt HTTP/1.1\" 200"));
+    assert!(!pid_file.exists());
+
+    Ok(())
+}
+
+/// The log file only makes sense for a daemon.
+#[rstest]
+fn log_file_requires_daemon(tmpdir: TempDir) -> Result<(), Error> {
+    Command::cargo_bin("miniserve")?
+        .arg(tmpdir.path())
+        .arg("--log-file")
+        .arg(tmpdir.path().join("miniserve.log"))
+        .assert()
+        .failure();
+
+    Ok(())
+}
+
+/// The pid file is removed on exit even though the sandbox restricts writing.
+#[cfg(target_os = "linux")]
+#[rstest]
+fn sandboxed_daemon_removes_pid_file(tmpdir: TempDir, port: u16) -> Result<(), Error> {
+    let run_dir = TempDir::new()?;
+    let pid_file = run_dir.path().join("miniserve.pid");
+
+    Command::cargo_bin("miniserve")?
+        .arg(tmpdir.path())
+        .arg("-p")
+        .arg(port.to_string())
+        .arg("--sandbox")
+        .arg("--daemon")
+        .arg("--pid-file")
+        .arg(&pid_file)
+        .assert()
+        .success();
+
+    let pid = std::fs::read_to_string(&pid_file)?.trim().to_string();
+    // Signals are only handled once the server runs
+    Client::new()
+        .get(format!("http://localhost:{port}"))
+        .send()?
+        .error_for_status()?;
+    Command::new("kill").arg(&pid).status()?;
+    sleep(Duration::from_millis(500));
+
+    assert!(!pid_file.exists());
+
+    Ok(())
+}
@@ -0,0 +1,104 @@
+use std::path::Path;
+use std::process::{Command, Stdio};
+use std::thread::sleep;
+use std::time::Duration;
+
+use assert_cmd::prelude::*;
+use assert_fs::fixture::TempDir;
+use reqwest::StatusCode;
+use rstest::rstest;
+use select::{document::Document, node::Node};
+
+mod fixtures;
+
+use crate::fixtures::{DEEPLY_NESTED_FILE, DIRECTORIES, Error, HIDDEN_FILES, port, tmpdir};
+
+fn git(repo: &Path, args: &[&str]) -> Result<(), Error> {
+    Command::new("git")
+        .arg("-C")
+        .arg(repo)
+        .args([
+            "-c",
+            "user.name=miniserve",
+            "-c",
+            "user.email=miniserve@example.com",
+        ])
+        .args(args)
+        .stdout(Stdio::null())
+        .assert()
+        .success();
+    Ok(())
+}
+
+#[rstest]
+fn serves_git_ref_without_checkout(tmpdir: TempDir, port: u16) -> Result<(), Error> {
+    git(tmpdir.path(), &["init", "-q"])?;
+    git(tmpdir.path(), &["add", "-A"])?;
+    git(tmpdir.path(), &["commit", "-q", "-m", "first"])?;
+    git(tmpdir.path(), &["tag", "v1"])?;
+    std::fs::write(tmpdir.path().join("test.txt"), "Changed afterwards")?;
+    std::fs::write(tmpdir.path().join("untracked.txt"), "Not committed")?;
+    git(tmpdir.path(), &["commit", "-q", "-a", "-m", "second"])?;
+
+    let mut child = Command::cargo_bin("miniserve")?
+        .arg("--git")
+        .arg(tmpdir.path())
+        .arg("--ref")
+        .arg("v1")
+        .arg("-p")
+        .arg(port.to_string())
+        .stdout(Stdio::null())
+        .spawn()?;
+
+    sleep(Duration::from_secs(1));
+
+    let url = format!("http://localhost:{port}");
+    let body = reqwest::blocking::get(&url)?.error_for_status()?;
+    let parsed = Document::from_read(body)?;
+    let links = parsed
+        .find(|x: &Node| x.name() == Some("a"))
+        .filter_map(|a| a.attr("href").map(str::to_string))
+        .collect::<Vec<_>>();
+    for directory in DIRECTORIES {
+        assert!(
+            links
+                .iter()
+                .any(|link| link.contains(&directory.replace(' ', "%20")))
+        );
+    }
+    for &file in HIDDEN_FILES {
+        assert!(!links.iter().any(|link| link.contains(file)));
+    }
+    assert!(!links.iter().any(|link| link.contains("untracked.txt")));
+
+    let resp = reqwest::blocking::get(format!("{url}/test.txt"))?;
+    assert_eq!(resp.text()?, "Test Hello Yes");
+
+    let resp = reqwest::blocking::get(format!("{url}/{DEEPLY_NESTED_FILE}"))?;
+    assert_eq!(resp.status(), StatusCode::OK);
+
+    let resp = reqwest::blocking::get(format!("{url}/{}", HIDDEN_FILES[0]))?;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+    let resp = reqwest::blocking::get(format!("{url}/untracked.txt"))?;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+    child.kill()?;
+
+    Ok(())
+}
+
+#[rstest]
+fn unknown_git_ref_fails(tmpdir: TempDir) -> Result<(), Error> {
+    git(tmpdir.path(), &["init", "-q"])?;
+
+    Command::cargo_bin("miniserve")?
+        .arg("--git")
+        .arg(tmpdir.path())
+        .arg("--ref")
+        .arg("does-not-exist")
+        .assert()
+        .failure();
+
+    Ok(())
+}
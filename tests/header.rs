@@ -2,7 +2,7 @@ use rstest::rstest;
 
 mod fixtures;
 
-use crate::fixtures::{Error, server};
+use crate::fixtures::{Error, TestServer, server};
 
 #[rstest]
 #[case(vec!["x-info: 123".to_string()])]
@@ -20,3 +20,163 @@ fn custom_header_set(#[case] headers: Vec<String>) -> Result<(), Error> {
 
     Ok(())
 }
+
+#[rstest]
+fn repeated_custom_header_keeps_all_values() -> Result<(), Error> {
+    let server = server(&["--header", "link: <a>", "--header", "link: <b>"]);
+    let resp = reqwest::blocking::get(server.url())?;
+
+    let values: Vec<_> = resp.headers().get_all("link").iter().collect();
+    assert_eq!(values, ["<a>", "<b>"]);
+
+    Ok(())
+}
+
+#[rstest]
+fn hardened_headers_are_set() -> Result<(), Error> {
+    let server = server(&[
+        "--hardened-headers",
+        "--header",
+        "referrer-policy: no-referrer",
+    ]);
+    let resp = reqwest::blocking::get(server.url())?;
+
+    let headers = resp.headers();
+    assert_eq!(headers.get("x-content-type-options").unwrap(), "nosniff");
+    assert!(
+        headers
+            .get("content-security-policy")
+            .unwrap()
+            .to_str()?
+            .starts_with("default-src 'self'")
+    );
+    // Explicit headers replace the preset, and HSTS is only sent over TLS
+    let referrer_policies: Vec<_> = headers.get_all("referrer-policy").iter().collect();
+    assert_eq!(referrer_policies, ["no-referrer"]);
+    assert!(headers.get("strict-transport-security").is_none());
+
+    Ok(())
+}
+
+#[rstest]
+fn mime_map_overrides_content_type(
+    #[with(&["--mime-map", ".txt=text/x-custom", "--mime-map", "mkv=video/x-matroska"])]
+    server: TestServer,
+) -> Result<(), Error> {
+    for (file, content_type) in [
+        ("test.txt", "text/x-custom"),
+        ("test.mkv", "video/x-matroska"),
+        ("test.html", "text/html; charset=utf-8"),
+    ] {
+        let resp = reqwest::blocking::get(server.url().join(file)?)?.error_for_status()?;
+        assert_eq!(resp.headers().get("content-type").unwrap(), content_type);
+    }
+
+    // Listings aren't affected
+    let resp = reqwest::blocking::get(server.url())?;
+    assert_eq!(
+        resp.headers().get("content-type").unwrap(),
+        "text/html; charset=utf-8"
+    );
+
+    Ok(())
+}
+
+#[rstest]
+fn charset_is_declared_for_text_files(
+    #[with(&["--charset", "*.txt=windows-1252", "--charset", "iso-8859-1"])] server: TestServer,
+) -> Result<(), Error> {
+    for (file, content_type) in [
+        ("test.txt", "text/plain; charset=windows-1252"),
+        ("dira/test.txt", "text/plain; charset=windows-1252"),
+        ("test.html", "text/html; charset=iso-8859-1"),
+        ("test.mkv", "video/x-matroska"),
+    ] {
+        let resp = reqwest::blocking::get(server.url().join(file)?)?.error_for_status()?;
+        assert_eq!(resp.headers().get("content-type").unwrap(), content_type);
+    }
+
+    Ok(())
+}
+
+#[rstest]
+fn download_and_inline_globs_set_disposition(
+    #[with(&["--download", "*.html", "--download", "dira/*", "--inline", "*.txt"])]
+    server: TestServer,
+) -> Result<(), Error> {
+    for (file, disposition) in [
+        ("test.html", Some("attachment; filename=\"test.html\"")),
+        ("dira/test.txt", Some("attachment; filename=\"test.txt\"")),
+        ("test.txt", Some("inline; filename=\"test.txt\"")),
+        ("test.mkv", None),
+    ] {
+        let resp = reqwest::blocking::get(server.url().join(file)?)?.error_for_status()?;
+        let header = resp.headers().get("content-disposition");
+        match disposition {
+            Some(disposition) => assert_eq!(header.unwrap(), disposition),
+            None => {
+                assert!(header.is_none_or(|value| value.to_str().unwrap().starts_with("inline")))
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[rstest]
+fn pages_made_for_files_keep_their_headers(
+    #[with(&["--render-markdown", "--mime-map", ".md=text/plain"])] server: TestServer,
+) -> Result<(), Error> {
+    std::fs::write(server.path().join("readme.md"), "# Title")?;
+
+    let resp = reqwest::blocking::get(server.url().join("readme.md")?)?.error_for_status()?;
+    assert_eq!(
+        resp.headers().get("content-type").unwrap(),
+        "text/html; charset=utf-8"
+    );
+
+    // The file itself is still adjusted
+    let resp = reqwest::blocking::get(server.url().join("readme.md?raw=true")?)?;
+    assert_eq!(resp.headers().get("content-type").unwrap(), "text/plain");
+
+    Ok(())
+}
+
+#[rstest]
+fn charset_isnt_declared_for_pages_made_for_files(
+    #[with(&["--preview", "--charset", "*.txt=windows-1252"])] server: TestServer,
+) -> Result<(), Error> {
+    let resp =
+        reqwest::blocking::get(server.url().join("test.txt?preview=true")?)?.error_for_status()?;
+    assert_eq!(
+        resp.headers().get("content-type").unwrap(),
+        "text/html; charset=utf-8"
+    );
+
+    Ok(())
+}
+
+#[rstest]
+fn pages_made_for_files_arent_downloaded(
+    #[with(&["--render-markdown", "--download", "*.md", "--download", "*.pdf"])] server: TestServer,
+) -> Result<(), Error> {
+    std::fs::write(server.path().join("readme.md"), "# Title")?;
+    std::fs::write(server.path().join("paper.pdf"), "%PDF-1.4")?;
+
+    for page in ["readme.md", "paper.pdf?viewer=true"] {
+        let resp = reqwest::blocking::get(server.url().join(page)?)?.error_for_status()?;
+        assert!(
+            resp.headers().get("content-disposition").is_none(),
+            "{page}"
+        );
+    }
+
+    // The file shown by the viewer is still downloaded
+    let resp = reqwest::blocking::get(server.url().join("paper.pdf")?)?;
+    assert_eq!(
+        resp.headers().get("content-disposition").unwrap(),
+        "attachment; filename=\"paper.pdf\""
+    );
+
+    Ok(())
+}
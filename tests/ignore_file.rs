@@ -0,0 +1,129 @@
+use std::process::{Command, Stdio};
+use std::thread::sleep;
+use std::time::Duration;
+
+use assert_cmd::prelude::*;
+use reqwest::StatusCode;
+use rstest::rstest;
+use select::{document::Document, predicate::Name};
+
+mod fixtures;
+
+use crate::fixtures::{BROKEN_SYMLINK, Error, TestServer, port, server, tmpdir};
+
+/// Runs miniserve on a directory with the given .miniserveignore in it
+fn server_ignoring(patterns: &str, args: &[&str]) -> Result<TestServer, Error> {
+    let port = port();
+    let tmpdir = tmpdir();
+    std::fs::write(tmpdir.path().join(".miniserveignore"), patterns)?;
+    // Archives can't be made of directories with broken symlinks in them
+    std::fs::remove_file(tmpdir.path().join(BROKEN_SYMLINK))?;
+    let child = Command::cargo_bin("miniserve")?
+        .arg(tmpdir.path())
+        .arg("-p")
+        .arg(port.to_string())
+        .args(args)
+        .stdout(Stdio::null())
+        .spawn()?;
+    sleep(Duration::from_secs(1));
+    Ok(TestServer::new(port, tmpdir, child, false))
+}
+
+fn listed_names(server: &TestServer, path: &str) -> Result<Vec<String>, Error> {
+    let body = reqwest::blocking::get(server.url().join(path)?)?.error_for_status()?;
+    let parsed = Document::from_read(body)?;
+    Ok(parsed.find(Name("a")).map(|a| a.text()).collect())
+}
+
+#[rstest]
+fn ignore_file_hides_entries() -> Result<(), Error> {
+    let server = server_ignoring("*.html\ndira/\n!dirb/test.html\n", &["-r"])?;
+
+    let names = listed_names(&server, "")?;
+    assert!(names.contains(&"test.txt".to_string()));
+    assert!(!names.contains(&"test.html".to_string()));
+    assert!(!names.contains(&"dira/".to_string()));
+    assert!(names.contains(&"dirb/".to_string()));
+    assert!(listed_names(&server, "dirb/")?.contains(&"test.html".to_string()));
+
+    for path in ["test.html", "dira/", "dira/test.txt"] {
+        let status = reqwest::blocking::get(server.url().join(path)?)?.status();
+        assert_eq!(status, StatusCode::NOT_FOUND, "{path}");
+    }
+
+    let archive = reqwest::blocking::get(server.url().join("?download=tar")?)?
+        .error_for_status()?
+        .bytes()?;
+    let mut archive = tar::Archive::new(archive.as_ref());
+    let paths: Vec<_> = archive
+        .entries()?
+        .map(|entry| Ok(entry?.path()?.to_string_lossy().into_owned()))
+        .collect::<Result<_, Error>>()?;
+    assert!(paths.iter().any(|path| path.ends_with("/test.txt")));
+    assert!(paths.iter().any(|path| path.ends_with("/dirb/test.html")));
+    assert!(!paths.iter().any(|path| path.contains("/dira/")));
+    assert!(
+        !paths
+            .iter()
+            .any(|path| path.ends_with("/test.html") && !path.contains("/dirb/"))
+    );
+
+    Ok(())
+}
+
+#[rstest]
+fn ignore_flag_hides_entries(
+    #[with(&["--ignore", "*.mkv", "--ignore", "dirb", "-z"])] server: TestServer,
+) -> Result<(), Error> {
+    let names = listed_names(&server, "")?;
+    assert!(names.contains(&"test.txt".to_string()));
+    assert!(!names.contains(&"test.mkv".to_string()));
+    assert!(!names.contains(&"dirb/".to_string()));
+
+    for path in ["test.mkv", "dirb/", "dira/test.mkv"] {
+        let status = reqwest::blocking::get(server.url().join(path)?)?.status();
+        assert_eq!(status, StatusCode::NOT_FOUND, "{path}");
+    }
+
+    let archive = reqwest::blocking::get(server.url().join("dira/?download=zip")?)?
+        .error_for_status()?
+        .bytes()?;
+    let archive = zip::ZipArchive::new(std::io::Cursor::new(archive))?;
+    let names: Vec<_> = archive.file_names().collect();
+    assert!(names.iter().any(|name| name.ends_with("/test.txt")));
+    assert!(!names.iter().any(|name| name.ends_with(".mkv")));
+
+    Ok(())
+}
+
+#[rstest]
+fn ignore_flag_rejects_invalid_patterns() -> Result<(), Error> {
+    Command::cargo_bin("miniserve")?
+        .args(["--ignore", "[ab"])
+        .assert()
+        .failure();
+
+    Ok(())
+}
+
+/// The ignore file is read again on SIGHUP, like other reloadable settings.
+#[cfg(unix)]
+#[rstest]
+fn ignore_file_reloads_on_sighup() -> Result<(), Error> {
+    let server = server_ignoring("*.html\n", &[])?;
+    assert!(!listed_names(&server, "")?.contains(&"test.html".to_string()));
+
+    std::fs::write(server.path().join(".miniserveignore"), "*.txt\n")?;
+    Command::new("kill")
+        .args(["-HUP", &server.pid().to_string()])
+        .status()?;
+    sleep(Duration::from_millis(500));
+
+    let names = listed_names(&server, "")?;
+    assert!(names.contains(&"test.html".to_string()));
+    assert!(!names.contains(&"test.txt".to_string()));
+    let status = reqwest::blocking::get(server.url().join("test.txt")?)?.status();
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    Ok(())
+}
@@ -0,0 +1,111 @@
+use std::process::{Command, Stdio};
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+use assert_cmd::prelude::*;
+use assert_fs::TempDir;
+use predicates::str::contains;
+use reqwest::{StatusCode, blocking::Client};
+use rstest::rstest;
+use select::{document::Document, predicate::Name};
+
+mod fixtures;
+
+use crate::fixtures::{Error, TestServer, port, tmpdir};
+
+#[rstest]
+fn serves_every_instance(tmpdir: TempDir) -> Result<(), Error> {
+    let (first_port, second_port) = (port(), port());
+    let instances = TempDir::new()?;
+    let file = instances.path().join("instances");
+    std::fs::write(
+        &file,
+        format!(
+            "# Shares\n\
+             -p {first_port} --title 'First share' '{path}'\n\
+             \n\
+             -p {second_port} -a joe:pwd '{path}/dir space'\n",
+            path = tmpdir.path().display()
+        ),
+    )?;
+
+    let child = Command::cargo_bin("miniserve")?
+        .arg("--instances")
+        .arg(&file)
+        .stdout(Stdio::null())
+        .spawn()?;
+    let server = TestServer::new(first_port, tmpdir, child, false);
+
+    let start = Instant::now();
+    while ![first_port, second_port]
+        .iter()
+        .all(|port| port_check::is_port_reachable(format!("localhost:{port}")))
+    {
+        assert!(
+            start.elapsed() < Duration::from_secs(2),
+            "Servers didn't start"
+        );
+        sleep(Duration::from_millis(100));
+    }
+
+    let body = reqwest::blocking::get(server.url())?.error_for_status()?;
+    let parsed = Document::from_read(body)?;
+    let title = parsed.find(Name("title")).next().ok_or("No title")?;
+    assert!(title.text().starts_with("First share"));
+
+    let second = format!("http://localhost:{second_port}/");
+    let status = Client::new().get(&second).send()?.status();
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let body = Client::new()
+        .get(&second)
+        .basic_auth("joe", Some("pwd"))
+        .send()?
+        .error_for_status()?;
+    let parsed = Document::from_read(body)?;
+    assert!(parsed.find(Name("a")).any(|a| a.text() == "test.txt"));
+    assert!(!parsed.find(Name("a")).any(|a| a.text() == "dira/"));
+
+    Ok(())
+}
+
+#[rstest]
+#[case::process_option("--daemon /srv", "--daemon")]
+#[case::no_path("-p 8080", "path")]
+#[case::unterminated_quote("'/srv", "quote")]
+fn rejects_invalid_instances(
+    tmpdir: TempDir,
+    #[case] line: &str,
+    #[case] error: &str,
+) -> Result<(), Error> {
+    let file = tmpdir.path().join("instances");
+    std::fs::write(
+        &file,
+        format!("{} -p 8081\n{line}\n", tmpdir.path().display()),
+    )?;
+
+    Command::cargo_bin("miniserve")?
+        .arg("--instances")
+        .arg(&file)
+        .assert()
+        .failure()
+        .stderr(contains("line 2"))
+        .stderr(contains(error));
+
+    Ok(())
+}
+
+#[rstest]
+fn instances_exclude_other_options(tmpdir: TempDir) -> Result<(), Error> {
+    let file = tmpdir.path().join("instances");
+    std::fs::write(&file, format!("{}\n", tmpdir.path().display()))?;
+
+    Command::cargo_bin("miniserve")?
+        .arg("--instances")
+        .arg(&file)
+        .args(["-p", "8080"])
+        .assert()
+        .failure()
+        .stderr(contains("--instances"));
+
+    Ok(())
+}
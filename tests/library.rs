@@ -0,0 +1,48 @@
+use std::net::{IpAddr, Ipv4Addr};
+use std::thread::{self, sleep};
+use std::time::{Duration, Instant};
+
+use assert_fs::TempDir;
+use miniserve::MiniserveConfig;
+use rstest::rstest;
+use select::{document::Document, predicate::Name};
+
+mod fixtures;
+
+use crate::fixtures::{Error, port, tmpdir};
+
+#[rstest]
+fn serves_when_embedded(tmpdir: TempDir, port: u16) -> Result<(), Error> {
+    let config = MiniserveConfig::builder(tmpdir.path())
+        .port(port)
+        .interface(IpAddr::V4(Ipv4Addr::LOCALHOST))
+        .title("Embedded")
+        .arg("--hidden")
+        .build()?;
+    thread::spawn(move || actix_web::rt::System::new().block_on(miniserve::serve(config)));
+
+    let start = Instant::now();
+    while !port_check::is_port_reachable(format!("127.0.0.1:{port}")) {
+        assert!(
+            start.elapsed() < Duration::from_secs(1),
+            "Server didn't start"
+        );
+        sleep(Duration::from_millis(100));
+    }
+
+    let body = reqwest::blocking::get(format!("http://127.0.0.1:{port}"))?.error_for_status()?;
+    let parsed = Document::from_read(body)?;
+    let title = parsed.find(Name("title")).next().ok_or("No title")?;
+    assert!(title.text().starts_with("Embedded"));
+    assert!(parsed.find(Name("a")).any(|a| a.text() == ".hidden_file1"));
+
+    Ok(())
+}
+
+#[rstest]
+#[case::unknown_option(&["--no-such-option"])]
+#[case::invalid_value(&["--port", "not-a-port"])]
+fn rejects_invalid_options(tmpdir: TempDir, #[case] args: &[&str]) {
+    let config = MiniserveConfig::builder(tmpdir.path()).args(args).build();
+    assert!(config.is_err());
+}
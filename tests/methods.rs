@@ -0,0 +1,115 @@
+use std::process::{Command, Stdio};
+use std::thread::sleep;
+use std::time::Duration;
+
+use assert_cmd::prelude::*;
+use assert_fs::TempDir;
+use reqwest::{Method, StatusCode, blocking::Client, header};
+use rstest::rstest;
+
+mod fixtures;
+
+use crate::fixtures::{Error, TestServer, port, server, tmpdir};
+
+#[rstest]
+#[case("")]
+#[case("test.txt")]
+#[case("dira/")]
+#[case("?download=tar")]
+#[case("__miniserve_internal/healthcheck")]
+#[case("__miniserve_internal/favicon.svg")]
+#[case("__miniserve_internal/style.css")]
+fn head_has_the_headers_of_get(
+    #[with(&["-r"])] server: TestServer,
+    #[case] path: &str,
+) -> Result<(), Error> {
+    let client = Client::new();
+    let url = server.url().join(path)?;
+    let get = client.get(url.clone()).send()?.error_for_status()?;
+    let head = client.head(url).send()?.error_for_status()?;
+
+    for name in [header::CONTENT_TYPE, header::CONTENT_DISPOSITION] {
+        assert_eq!(
+            get.headers().get(&name),
+            head.headers().get(&name),
+            "{name}"
+        );
+    }
+    assert_eq!(head.bytes()?.len(), 0);
+
+    Ok(())
+}
+
+#[rstest]
+#[case("", "GET, HEAD, OPTIONS")]
+#[case("test.txt", "GET, HEAD, OPTIONS")]
+#[case("upload", "POST, OPTIONS")]
+#[case("__miniserve_internal/api", "POST, OPTIONS")]
+#[case("__miniserve_internal/healthcheck", "GET, HEAD, OPTIONS")]
+fn options_tells_allowed_methods(
+    #[with(&["-u"])] server: TestServer,
+    #[case] path: &str,
+    #[case] allowed: &str,
+) -> Result<(), Error> {
+    let res = Client::new()
+        .request(Method::OPTIONS, server.url().join(path)?)
+        .send()?;
+    assert_eq!(res.status(), StatusCode::NO_CONTENT);
+    assert_eq!(res.headers()[header::ALLOW], allowed);
+
+    Ok(())
+}
+
+#[rstest]
+fn options_is_left_to_webdav(
+    #[with(&["--enable-webdav"])] server: TestServer,
+) -> Result<(), Error> {
+    let res = Client::new()
+        .request(Method::OPTIONS, server.url())
+        .send()?
+        .error_for_status()?;
+    assert!(res.headers().contains_key("DAV"));
+
+    Ok(())
+}
+
+#[rstest]
+#[case(Method::POST, "test.txt", "GET, HEAD, OPTIONS")]
+#[case(Method::PUT, "dira/", "GET, HEAD, OPTIONS")]
+#[case(Method::DELETE, "test.txt", "GET, HEAD, OPTIONS")]
+#[case(Method::GET, "upload", "POST, OPTIONS")]
+#[case(Method::GET, "__miniserve_internal/api", "POST, OPTIONS")]
+fn unsupported_methods_are_not_allowed(
+    #[with(&["-u"])] server: TestServer,
+    #[case] method: Method,
+    #[case] path: &str,
+    #[case] allowed: &str,
+) -> Result<(), Error> {
+    let res = Client::new()
+        .request(method, server.url().join(path)?)
+        .send()?;
+    assert_eq!(res.status(), StatusCode::METHOD_NOT_ALLOWED);
+    assert_eq!(res.headers()[header::ALLOW], allowed);
+
+    Ok(())
+}
+
+#[rstest]
+fn single_files_only_allow_reading(tmpdir: TempDir, port: u16) -> Result<(), Error> {
+    let child = Command::cargo_bin("miniserve")?
+        .arg(tmpdir.path().join("test.txt"))
+        .arg("-p")
+        .arg(port.to_string())
+        .stdout(Stdio::null())
+        .spawn()?;
+    sleep(Duration::from_secs(1));
+    let server = TestServer::new(port, tmpdir, child, false);
+
+    let client = Client::new();
+    client.get(server.url()).send()?.error_for_status()?;
+    let res = client.post(server.url()).send()?;
+    assert_eq!(res.status(), StatusCode::METHOD_NOT_ALLOWED);
+    assert_eq!(res.headers()[header::ALLOW], "GET, HEAD, OPTIONS");
+
+    Ok(())
+}
@@ -3,6 +3,7 @@ use std::process::{Command, Stdio};
 use pretty_assertions::{assert_eq, assert_ne};
 use rstest::rstest;
 use select::document::Document;
+use select::predicate::Predicate;
 
 mod fixtures;
 mod utils;
@@ -188,3 +189,94 @@ fn can_specify_default_sorting_order(
 
     Ok(())
 }
+
+#[rstest]
+/// Listings are truncated to the configured maximum, with a link to show all entries.
+fn truncates_large_listings(
+    #[with(&["--max-listing-entries", "2"])] server: TestServer,
+) -> Result<(), Error> {
+    use select::predicate::{Class, Name};
+
+    let count_rows = |parsed: &Document| parsed.find(Name("tbody").descendant(Name("tr"))).count();
+
+    let body = reqwest::blocking::get(server.url())?.error_for_status()?;
+    let parsed = Document::from_read(body)?;
+    assert_eq!(count_rows(&parsed), 2);
+    let notice = parsed.find(Class("listing-notice")).next().unwrap();
+    assert!(notice.text().starts_with("2 of "));
+
+    let show_all = get_link_from_text(&parsed, "Show all").expect("Show all link not found.");
+    let body = reqwest::blocking::get(server.url().join(&show_all)?)?.error_for_status()?;
+    let parsed = Document::from_read(body)?;
+    assert!(count_rows(&parsed) > 2);
+    assert!(parsed.find(Class("listing-notice")).next().is_none());
+
+    // Sorting a full listing keeps it full
+    let sort_by_size = parsed
+        .find(Class("size").descendant(Name("a")))
+        .next()
+        .and_then(|a| a.attr("href"))
+        .expect("Sort link not found.");
+    assert!(sort_by_size.contains("show_all=true"));
+    let body = reqwest::blocking::get(server.url().join(sort_by_size)?)?.error_for_status()?;
+    let parsed = Document::from_read(body)?;
+    assert!(count_rows(&parsed) > 2);
+
+    Ok(())
+}
+
+#[rstest]
+/// Truncated listings can be narrowed down to the names containing a text.
+fn truncated_listings_can_be_refined(
+    #[with(&["--max-listing-entries", "2"])] server: TestServer,
+) -> Result<(), Error> {
+    use select::predicate::{Attr, Class, Name};
+
+    let body = reqwest::blocking::get(server.url())?.error_for_status()?;
+    let parsed = Document::from_read(body)?;
+    let notice = parsed.find(Class("listing-notice")).next().unwrap();
+    assert!(notice.find(Attr("name", "filter")).next().is_some());
+
+    let body = reqwest::blocking::get(server.url().join("?filter=DIR")?)?.error_for_status()?;
+    let parsed = Document::from_read(body)?;
+    let names: Vec<_> = parsed
+        .find(Name("tbody").descendant(Name("a")))
+        .map(|a| a.text())
+        .collect();
+    assert_eq!(names.len(), 2);
+    assert!(names.iter().all(|name| name.to_lowercase().contains("dir")));
+    let notice = parsed.find(Class("listing-notice")).next().unwrap();
+    assert_eq!(
+        notice
+            .find(Attr("name", "filter"))
+            .next()
+            .unwrap()
+            .attr("value"),
+        Some("DIR")
+    );
+
+    Ok(())
+}
+
+#[rstest]
+/// Legacy browsers, which struggle the most with large listings, get truncated ones too.
+fn truncates_simple_listings(
+    #[with(&["--max-listing-entries", "2"])] server: TestServer,
+) -> Result<(), Error> {
+    use select::predicate::{Class, Name};
+
+    let body = reqwest::blocking::Client::new()
+        .get(server.url())
+        .header(
+            "User-Agent",
+            "Mozilla/4.0 (compatible; MSIE 8.0; Windows NT 6.1; Trident/4.0)",
+        )
+        .send()?
+        .error_for_status()?;
+    let parsed = Document::from_read(body)?;
+    let rows = parsed.find(Name("tbody").descendant(Name("tr"))).count();
+    assert_eq!(rows, 2);
+    assert!(parsed.find(Class("listing-notice")).next().is_some());
+
+    Ok(())
+}
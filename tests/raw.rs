@@ -99,3 +99,107 @@ fn raw_mode_links_to_directories_end_with_raw_true(
 
     Ok(())
 }
+
+/// Legacy browsers are served the raw listing, with a plain upload form if uploads are enabled
+#[rstest]
+#[case(server(&[] as &[&str]), false)]
+#[case(server(&["-u"]), true)]
+fn legacy_browser_gets_simple_ui(
+    #[case] server: TestServer,
+    #[case] upload_form: bool,
+) -> Result<(), Error> {
+    let client = Client::new();
+    let body = client
+        .get(server.url())
+        .header(
+            "User-Agent",
+            "Mozilla/4.0 (compatible; MSIE 8.0; Windows NT 6.1; Trident/4.0)",
+        )
+        .send()?
+        .error_for_status()?;
+    let parsed = Document::from_read(body)?;
+
+    assert!(parsed.find(Class("toolbar")).next().is_none());
+    assert!(parsed.find(Class("directory")).next().is_some());
+
+    let form = parsed.find(Name("form")).next();
+    assert_eq!(form.is_some(), upload_form);
+    if let Some(form) = form {
+        assert_eq!(form.attr("enctype"), Some("multipart/form-data"));
+        assert!(form.attr("action").unwrap().contains("/upload?path=/"));
+    }
+
+    Ok(())
+}
+
+/// Text-mode browsers are recognized by their product token rather than by any mention of it
+#[rstest]
+#[case("Lynx/2.8.9rel.1 libwww-FM/2.14 SSL-MM/1.4.1", true)]
+#[case("w3m/0.5.3+git20230121", true)]
+#[case("Links (2.29; Linux 6.1.0 x86_64; GNU C 12.2; text)", true)]
+#[case("ELinks/0.16.1 (textmode; Linux 6.1.0 x86_64; 80x24-2)", true)]
+#[case("Mozilla/5.0 (Windows NT 10.0; Trident/7.0; rv:11.0) like Gecko", true)]
+#[case("Mozilla/5.0 (compatible; LinksCrawler/1.0)", false)]
+#[case("Mozilla/5.0 (X11; Linux x86_64) SafeLinks/2.1", false)]
+fn legacy_browser_detection(
+    server: TestServer,
+    #[case] user_agent: &str,
+    #[case] legacy: bool,
+) -> Result<(), Error> {
+    let body = Client::new()
+        .get(server.url())
+        .header("User-Agent", user_agent)
+        .send()?
+        .error_for_status()?;
+    let parsed = Document::from_read(body)?;
+
+    assert_eq!(parsed.find(Class("toolbar")).next().is_none(), legacy);
+
+    Ok(())
+}
+
+#[rstest]
+#[case("")]
+#[case("dira/")]
+fn minimal_ui_renders_bare_index(
+    #[case] dir: &str,
+    #[with(&["--minimal-ui", "-u"])] server: TestServer,
+) -> Result<(), Error> {
+    let body = Client::new()
+        .get(server.url().join(dir)?)
+        .send()?
+        .error_for_status()?
+        .text()?;
+    for tag in ["<script", "<style", "<link", "<form"] {
+        assert!(!body.contains(tag), "{tag}");
+    }
+
+    let parsed = Document::from_read(body.as_bytes())?;
+    let title = parsed.find(Name("title")).next().unwrap().text();
+    assert_eq!(title, format!("Index of /{dir}"));
+
+    let listing = parsed.find(Name("pre")).next().unwrap().text();
+    if !dir.is_empty() {
+        assert!(listing.starts_with("../\n"));
+    }
+    let line = regex::Regex::new(r"^(\S.*?) +\d{2}-\w{3}-\d{4} \d{2}:\d{2} +(\d+|-)$")?;
+    let size = if dir.is_empty() { "14" } else { "21" };
+    let lines: Vec<_> = listing
+        .lines()
+        .filter_map(|text| line.captures(text))
+        .map(|captures| {
+            (
+                captures[0].chars().count(),
+                captures[1].to_owned(),
+                captures[2].to_owned(),
+            )
+        })
+        .collect();
+    // Names, dates and sizes are in columns, as wide as those of nginx
+    assert!(lines.contains(&(88, "test.txt".to_owned(), size.to_owned())));
+    if dir.is_empty() {
+        assert!(lines.contains(&(88, "dira/".to_owned(), "-".to_owned())));
+    }
+
+    Ok(())
+}
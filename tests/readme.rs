@@ -3,7 +3,7 @@ use std::io::Write;
 use std::path::PathBuf;
 
 use rstest::rstest;
-use select::predicate::Attr;
+use select::predicate::{Attr, Name};
 use select::{document::Document, node::Node};
 
 mod fixtures;
@@ -129,3 +129,28 @@ fn show_nested_readme_contents(
     }
     Ok(())
 }
+
+/// Prefer the Markdown readme like code forges do, and show others as plain text
+#[rstest]
+fn prefers_markdown_readme(#[with(&["--readme"])] server: TestServer) -> Result<(), Error> {
+    std::fs::write(server.path().join("README"), "<b>plain</b>")?;
+    let body = reqwest::blocking::get(server.url())?.error_for_status()?;
+    let parsed = Document::from_read(body)?;
+    let contents = parsed.find(Attr("id", "readme-contents")).next().unwrap();
+    assert_eq!(
+        contents.find(Name("pre")).next().unwrap().text(),
+        "<b>plain</b>"
+    );
+    assert!(contents.find(Name("b")).next().is_none());
+
+    write_readme_contents(server.path().to_path_buf(), "README.txt");
+    std::fs::write(server.path().join("README.md"), "| a |\n|---|\n| b |")?;
+    let body = reqwest::blocking::get(server.url())?.error_for_status()?;
+    let parsed = Document::from_read(body)?;
+    let filename = parsed.find(Attr("id", "readme-filename")).next().unwrap();
+    assert_eq!(filename.text(), "README.md");
+    let contents = parsed.find(Attr("id", "readme-contents")).next().unwrap();
+    assert!(contents.find(Name("table")).next().is_some());
+
+    Ok(())
+}
@@ -0,0 +1,189 @@
+#![cfg(feature = "s3")]
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::process::{Command, Stdio};
+use std::thread::{self, sleep};
+use std::time::Duration;
+
+use assert_cmd::prelude::*;
+use reqwest::StatusCode;
+use reqwest::blocking::Client;
+use rstest::rstest;
+use select::{document::Document, node::Node};
+
+mod fixtures;
+
+use crate::fixtures::{Error, port};
+
+/// Content of the only object at the root of the mock bucket
+const OBJECT: &str = "Hello, object storage!";
+
+/// Starts a minimal S3-compatible endpoint serving a bucket named "bucket", which contains
+/// `hello.txt` and `docs/readme.md`, and returns its port
+fn mock_storage() -> Result<u16, Error> {
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let port = listener.local_addr()?.port();
+    thread::spawn(move || {
+        for stream in listener.incoming().flatten() {
+            let _ = answer(stream);
+        }
+    });
+    Ok(port)
+}
+
+fn answer(mut stream: TcpStream) -> std::io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    let mut range = None;
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':')
+            && name.eq_ignore_ascii_case("range")
+        {
+            range = value.trim().strip_prefix("bytes=").map(str::to_owned);
+        }
+    }
+
+    let target = request_line.split(' ').nth(1).unwrap_or_default();
+    let listing = |contents: &str, prefixes: &str| {
+        format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+             <ListBucketResult><IsTruncated>false</IsTruncated>{contents}{prefixes}</ListBucketResult>"
+        )
+    };
+    let (status, content_range, body) = match target {
+        "/bucket/?delimiter=%2F&list-type=2&prefix=" => (
+            "200 OK",
+            None,
+            listing(
+                &format!(
+                    "<Contents><Key>hello.txt</Key><Size>{}</Size>\
+                     <LastModified>2024-01-01T00:00:00.000Z</LastModified></Contents>",
+                    OBJECT.len()
+                ),
+                "<CommonPrefixes><Prefix>docs/</Prefix></CommonPrefixes>",
+            ),
+        ),
+        "/bucket/?delimiter=%2F&list-type=2&prefix=docs%2F" => (
+            "200 OK",
+            None,
+            listing(
+                "<Contents><Key>docs/readme.md</Key><Size>0</Size></Contents>",
+                "",
+            ),
+        ),
+        // Served only if miniserve let such keys through
+        "/bucket/.env" | "/hello.txt" => ("200 OK", None, "secret".to_owned()),
+        "/bucket/hello.txt" => match range.as_deref().and_then(|r| r.split_once('-')) {
+            Some((start, end)) => {
+                let (start, end): (usize, usize) = (start.parse().unwrap(), end.parse().unwrap());
+                (
+                    "206 Partial Content",
+                    Some(format!("bytes {start}-{end}/{}", OBJECT.len())),
+                    OBJECT[start..=end].to_owned(),
+                )
+            }
+            None => ("200 OK", None, OBJECT.to_owned()),
+        },
+        // Listing prefixes that don't exist isn't an error
+        _ if target.starts_with("/bucket/?") => ("200 OK", None, listing("", "")),
+        _ => ("404 Not Found", None, String::new()),
+    };
+
+    write!(stream, "HTTP/1.1 {status}\r\nConnection: close\r\n")?;
+    if let Some(content_range) = content_range {
+        write!(stream, "Content-Range: {content_range}\r\n")?;
+    }
+    write!(stream, "Content-Length: {}\r\n\r\n{body}", body.len())
+}
+
+/// Status of a request for `path`, sent as it is, without the normalization of HTTP clients
+fn raw_status(port: u16, path: &str) -> Result<u16, Error> {
+    let mut stream = TcpStream::connect(("localhost", port))?;
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    write!(
+        stream,
+        "GET {path} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n"
+    )?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+    Ok(response
+        .split(' ')
+        .nth(1)
+        .and_then(|status| status.parse().ok())
+        .unwrap_or_default())
+}
+
+#[rstest]
+fn serves_bucket_from_object_storage(port: u16) -> Result<(), Error> {
+    let storage_port = mock_storage()?;
+    let mut child = Command::cargo_bin("miniserve")?
+        .arg("s3://bucket")
+        .arg("-p")
+        .arg(port.to_string())
+        .env(
+            "AWS_ENDPOINT_URL",
+            format!("http://127.0.0.1:{storage_port}"),
+        )
+        .env_remove("AWS_ACCESS_KEY_ID")
+        .env_remove("AWS_SECRET_ACCESS_KEY")
+        .stdout(Stdio::null())
+        .spawn()?;
+
+    sleep(Duration::from_secs(1));
+
+    let url = format!("http://localhost:{port}");
+    let client = Client::new();
+    let body = client.get(&url).send()?.error_for_status()?;
+    let parsed = Document::from_read(body)?;
+    for name in ["hello.txt", "docs/"] {
+        assert!(parsed.find(|x: &Node| x.text() == name).next().is_some());
+    }
+
+    let resp = client.get(format!("{url}/hello.txt")).send()?;
+    assert_eq!(resp.status(), StatusCode::OK);
+    assert_eq!(resp.text()?, OBJECT);
+
+    let resp = client
+        .get(format!("{url}/hello.txt"))
+        .header("Range", "bytes=7-12")
+        .send()?;
+    assert_eq!(resp.status(), StatusCode::PARTIAL_CONTENT);
+    assert_eq!(
+        resp.headers().get("content-range").unwrap(),
+        &format!("bytes 7-12/{}", OBJECT.len())
+    );
+    assert_eq!(resp.text()?, "object");
+
+    // Prefixes are redirected to with a trailing slash, like directories
+    let body = client
+        .get(format!("{url}/docs"))
+        .send()?
+        .error_for_status()?;
+    let parsed = Document::from_read(body)?;
+    assert!(
+        parsed
+            .find(|x: &Node| x.text() == "readme.md")
+            .next()
+            .is_some()
+    );
+
+    let resp = client.get(format!("{url}/missing.txt")).send()?;
+    assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+    // Neither hidden objects, without --hidden, nor keys outside of the bucket are served
+    for path in ["/.env", "/docs/../.env", "/../hello.txt", "/./hello.txt"] {
+        assert_eq!(raw_status(port, path)?, 404, "{path}");
+    }
+
+    child.kill()?;
+
+    Ok(())
+}
@@ -1,5 +1,6 @@
 use assert_cmd::Command;
 use predicates::str::contains;
+use reqwest::Version;
 use reqwest::blocking::ClientBuilder;
 use rstest::rstest;
 use select::{document::Document, node::Node};
@@ -35,6 +36,185 @@ fn tls_works(#[case] server: TestServer) -> Result<(), Error> {
     Ok(())
 }
 
+/// HTTP/2 is negotiated with clients supporting it.
+#[rstest]
+#[case(server(&[
+        "--tls-cert", "tests/data/cert_rsa.pem",
+        "--tls-key", "tests/data/key_pkcs8.pem",
+]))]
+fn tls_negotiates_http2(#[case] server: TestServer) -> Result<(), Error> {
+    // The native TLS backend doesn't offer HTTP/2 through ALPN
+    let client = ClientBuilder::new()
+        .use_rustls_tls()
+        .danger_accept_invalid_certs(true)
+        .build()?;
+    let resp = client.get(server.url()).send()?.error_for_status()?;
+    assert_eq!(resp.version(), Version::HTTP_2);
+
+    Ok(())
+}
+
+/// Hardened headers include HSTS when TLS is enabled.
+#[rstest]
+#[case(server(&[
+        "--tls-cert", "tests/data/cert_rsa.pem",
+        "--tls-key", "tests/data/key_pkcs8.pem",
+        "--hardened-headers",
+]))]
+fn tls_hardened_headers_include_hsts(#[case] server: TestServer) -> Result<(), Error> {
+    let client = ClientBuilder::new()
+        .danger_accept_invalid_certs(true)
+        .build()?;
+    let resp = client.get(server.url()).send()?.error_for_status()?;
+    assert_eq!(
+        resp.headers().get("strict-transport-security").unwrap(),
+        "max-age=31536000"
+    );
+
+    Ok(())
+}
+
+/// HTTP/3 is advertised to clients when enabled.
+#[cfg(feature = "http3")]
+#[rstest]
+#[case(server(&[
+        "--tls-cert", "tests/data/cert_rsa.pem",
+        "--tls-key", "tests/data/key_pkcs8.pem",
+        "--http3",
+]))]
+fn tls_http3_is_advertised(#[case] server: TestServer) -> Result<(), Error> {
+    let client = ClientBuilder::new()
+        .danger_accept_invalid_certs(true)
+        .build()?;
+    let resp = client.get(server.url()).send()?.error_for_status()?;
+    assert_eq!(
+        resp.headers().get("alt-svc").unwrap().to_str()?,
+        format!("h3=\":{}\"; ma=86400", server.port())
+    );
+
+    Ok(())
+}
+
+/// HTTP/3 requests are answered like any other.
+#[cfg(feature = "http3")]
+#[rstest]
+#[case(server(&[
+        "--tls-cert", "tests/data/cert_rsa.pem",
+        "--tls-key", "tests/data/key_pkcs8.pem",
+        "--http3",
+]))]
+fn http3_serves_listing(#[case] server: TestServer) -> Result<(), Error> {
+    use bytes::Buf;
+    use std::sync::Arc;
+
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()?;
+    let body = runtime.block_on(async {
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let mut tls_config = rustls::ClientConfig::builder_with_provider(provider.clone())
+            .with_protocol_versions(&[&rustls::version::TLS13])?
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(AcceptAnyCert(provider)))
+            .with_no_client_auth();
+        tls_config.alpn_protocols = vec![b"h3".to_vec()];
+        let mut endpoint = quinn::Endpoint::client("127.0.0.1:0".parse()?)?;
+        endpoint.set_default_client_config(quinn::ClientConfig::new(Arc::new(
+            quinn::crypto::rustls::QuicClientConfig::try_from(tls_config)?,
+        )));
+
+        let conn = endpoint
+            .connect(([127, 0, 0, 1], server.port()).into(), "localhost")?
+            .await?;
+        let (mut driver, mut send_request) =
+            h3::client::new(h3_quinn::Connection::new(conn)).await?;
+        tokio::spawn(async move { driver.wait_idle().await });
+
+        let mut stream = send_request
+            .send_request(http::Request::get(server.url().as_str()).body(())?)
+            .await?;
+        stream.finish().await?;
+        let resp = stream.recv_response().await?;
+        assert_eq!(resp.status(), http::StatusCode::OK);
+        let mut body = Vec::new();
+        while let Some(mut chunk) = stream.recv_data().await? {
+            body.extend_from_slice(&chunk.copy_to_bytes(chunk.remaining()));
+        }
+        Ok::<_, Error>(body)
+    })?;
+
+    let parsed = Document::from_read(body.as_slice())?;
+    for &file in FILES {
+        assert!(parsed.find(|x: &Node| x.text() == file).next().is_some());
+    }
+
+    Ok(())
+}
+
+/// Certificate verifier trusting the self-signed test certificates
+#[cfg(feature = "http3")]
+#[derive(Debug)]
+struct AcceptAnyCert(std::sync::Arc<rustls::crypto::CryptoProvider>);
+
+#[cfg(feature = "http3")]
+impl rustls::client::danger::ServerCertVerifier for AcceptAnyCert {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &rustls::pki_types::CertificateDer<'_>,
+        _intermediates: &[rustls::pki_types::CertificateDer<'_>],
+        _server_name: &rustls::pki_types::ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: rustls::pki_types::UnixTime,
+    ) -> Result<rustls::client::danger::ServerCertVerified, rustls::Error> {
+        Ok(rustls::client::danger::ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &rustls::pki_types::CertificateDer<'_>,
+        dss: &rustls::DigitallySignedStruct,
+    ) -> Result<rustls::client::danger::HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &rustls::pki_types::CertificateDer<'_>,
+        dss: &rustls::DigitallySignedStruct,
+    ) -> Result<rustls::client::danger::HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<rustls::SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
+}
+
+/// HTTP/3 can't be served without TLS.
+#[cfg(feature = "http3")]
+#[rstest]
+fn http3_requires_tls() -> Result<(), Error> {
+    Command::cargo_bin("miniserve")?
+        .arg("--http3")
+        .assert()
+        .failure()
+        .stderr(contains("--tls-cert"));
+
+    Ok(())
+}
+
 /// Wrong path for cert throws error.
 #[rstest]
 fn wrong_path_cert() -> Result<(), Error> {